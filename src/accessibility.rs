@@ -0,0 +1,67 @@
+use eframe::egui::{self, accesskit};
+
+// Gives a screen reader a name for a widget without a visible label, e.g. a search box that only
+// shows a hint
+pub fn name(response: &egui::Response, name: &str) {
+    response.ctx.accesskit_node_builder(response.id, |builder| builder.set_name(name));
+}
+
+// Exposes what `add_contents` shows as a log: screen readers can read it as one region and
+// announce new lines
+pub fn log_region(ui: &mut egui::Ui, id: egui::Id, name: &str, add_contents: impl FnOnce(&mut egui::Ui)) {
+    ui.ctx().accesskit_node_builder(id, |builder| {
+        builder.set_role(accesskit::Role::Log);
+        builder.set_name(name);
+        builder.set_live(accesskit::Live::Polite);
+    });
+    let ctx = ui.ctx().clone();
+    ctx.with_accessibility_parent(id, || add_contents(ui));
+}
+
+// Moves keyboard focus to `response` in the frame it appears, so that Tab continues in a dialog
+// that just opened instead of behind it
+pub fn focus_when_shown(response: &egui::Response) {
+    let frame = response.ctx.frame_nr();
+    let key = response.id.with("last_shown");
+    let last = response.ctx.data_mut(|data| {
+        let last = data.get_temp::<u64>(key);
+        data.insert_temp(key, frame);
+        last
+    });
+    if last.is_none_or(|last| last + 1 < frame) {
+        response.request_focus();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i18n::tr;
+
+    #[test]
+    fn focuses_a_dialog_button_once_it_appears() {
+        let ctx = egui::Context::default();
+        let button_id = std::cell::Cell::new(egui::Id::NULL);
+        let frame = |shown: bool| {
+            let _ = ctx.run(egui::RawInput::default(), |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    if shown {
+                        let button = ui.button(tr("Cancel"));
+                        focus_when_shown(&button);
+                        button_id.set(button.id);
+                    }
+                });
+            });
+        };
+
+        frame(true);
+        assert!(ctx.memory(|memory| memory.has_focus(button_id.get())));
+        ctx.memory_mut(|memory| memory.surrender_focus(button_id.get()));
+        frame(true);
+        // Still shown, so focus stays wherever the user moved it
+        assert!(!ctx.memory(|memory| memory.has_focus(button_id.get())));
+        frame(false);
+        frame(true);
+        assert!(ctx.memory(|memory| memory.has_focus(button_id.get())));
+    }
+}
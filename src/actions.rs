@@ -0,0 +1,139 @@
+use crate::accessibility;
+use crate::i18n::tr;
+use eframe::egui::{self, Key, KeyboardShortcut, Modifiers};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Action {
+    FocusSearch,
+    Search,
+    InstallSelected,
+    ClearLog,
+    Refresh,
+    ShowDetails,
+    RefreshMetadata,
+    ShowInstalled,
+    OpenSettings,
+    CommandPalette,
+}
+
+impl Action {
+    pub const ALL: [Action; 10] = [
+        Action::FocusSearch,
+        Action::Search,
+        Action::InstallSelected,
+        Action::ClearLog,
+        Action::Refresh,
+        Action::ShowDetails,
+        Action::RefreshMetadata,
+        Action::ShowInstalled,
+        Action::OpenSettings,
+        Action::CommandPalette,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::FocusSearch => "Focus search box",
+            Action::Search => "Search the AUR",
+            Action::InstallSelected => "Install selected package",
+            Action::ClearLog => "Clear log",
+            Action::Refresh => "Refresh current list",
+            Action::ShowDetails => "Show package details",
+            Action::RefreshMetadata => "Refresh package metadata",
+            Action::ShowInstalled => "Show installed AUR packages",
+            Action::OpenSettings => "Open settings",
+            Action::CommandPalette => "Command palette",
+        }
+    }
+
+    pub fn shortcut(&self) -> Option<KeyboardShortcut> {
+        match self {
+            Action::FocusSearch => Some(KeyboardShortcut::new(Modifiers::CTRL, Key::F)),
+            Action::InstallSelected => Some(KeyboardShortcut::new(Modifiers::CTRL, Key::Enter)),
+            Action::ClearLog => Some(KeyboardShortcut::new(Modifiers::CTRL, Key::L)),
+            Action::Refresh => Some(KeyboardShortcut::new(Modifiers::NONE, Key::F5)),
+            Action::OpenSettings => Some(KeyboardShortcut::new(Modifiers::CTRL, Key::Comma)),
+            Action::CommandPalette => Some(KeyboardShortcut::new(Modifiers::CTRL, Key::P)),
+            Action::Search | Action::ShowDetails | Action::RefreshMetadata | Action::ShowInstalled => None,
+        }
+    }
+}
+
+// Shortcuts are consumed so focused text fields don't also receive them
+pub fn triggered(ctx: &egui::Context) -> Vec<Action> {
+    Action::ALL
+        .into_iter()
+        .filter(|action| {
+            action
+                .shortcut()
+                .is_some_and(|shortcut| ctx.input_mut(|i| i.consume_shortcut(&shortcut)))
+        })
+        .collect()
+}
+
+#[derive(Default)]
+pub struct CommandPalette {
+    query: String,
+    selected: usize,
+}
+
+pub enum PaletteResult {
+    Run(Action),
+    Close,
+}
+
+pub fn show_palette(ctx: &egui::Context, palette: &mut CommandPalette) -> Option<PaletteResult> {
+    let query = palette.query.to_lowercase();
+    let matches: Vec<Action> = Action::ALL
+        .into_iter()
+        .filter(|action| *action != Action::CommandPalette && action.label().to_lowercase().contains(&query))
+        .collect();
+
+    let (up, down, enter, escape) = ctx.input_mut(|i| {
+        (
+            i.consume_key(Modifiers::NONE, Key::ArrowUp),
+            i.consume_key(Modifiers::NONE, Key::ArrowDown),
+            i.key_pressed(Key::Enter),
+            i.consume_key(Modifiers::NONE, Key::Escape),
+        )
+    });
+    if up {
+        palette.selected = palette.selected.saturating_sub(1);
+    }
+    if down {
+        palette.selected += 1;
+    }
+    palette.selected = palette.selected.min(matches.len().saturating_sub(1));
+
+    let mut result = None;
+    egui::Window::new(tr("Command palette"))
+        .collapsible(false)
+        .resizable(false)
+        .title_bar(false)
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 40.0])
+        .show(ctx, |ui| {
+            let input = ui.add(egui::TextEdit::singleline(&mut palette.query).hint_text(tr("Type a command...")));
+            input.request_focus();
+            accessibility::name(&input, tr("Command palette"));
+
+            for (index, action) in matches.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.selectable_label(index == palette.selected, action.label()).clicked() {
+                        result = Some(PaletteResult::Run(*action));
+                    }
+                    if let Some(shortcut) = action.shortcut() {
+                        ui.weak(ctx.format_shortcut(&shortcut));
+                    }
+                });
+            }
+        });
+
+    if enter {
+        if let Some(action) = matches.get(palette.selected) {
+            result = Some(PaletteResult::Run(*action));
+        }
+    }
+    if escape {
+        result = Some(PaletteResult::Close);
+    }
+    result
+}
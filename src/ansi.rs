@@ -0,0 +1,126 @@
+use eframe::egui::{self, text::LayoutJob, Color32, FontId, TextFormat};
+
+#[derive(Clone, Copy, Default, PartialEq)]
+struct Style {
+    foreground: Option<Color32>,
+    bold: bool,
+    underline: bool,
+}
+
+// The standard xterm palette for codes 30-37 and their bright 90-97 variants
+const BASIC_COLORS: [Color32; 16] = [
+    Color32::from_rgb(0, 0, 0),
+    Color32::from_rgb(205, 49, 49),
+    Color32::from_rgb(13, 188, 121),
+    Color32::from_rgb(229, 229, 16),
+    Color32::from_rgb(36, 114, 200),
+    Color32::from_rgb(188, 63, 188),
+    Color32::from_rgb(17, 168, 205),
+    Color32::from_rgb(229, 229, 229),
+    Color32::from_rgb(102, 102, 102),
+    Color32::from_rgb(241, 76, 76),
+    Color32::from_rgb(35, 209, 139),
+    Color32::from_rgb(245, 245, 67),
+    Color32::from_rgb(59, 142, 234),
+    Color32::from_rgb(214, 112, 214),
+    Color32::from_rgb(41, 184, 219),
+    Color32::from_rgb(255, 255, 255),
+];
+
+fn color_256(index: u8) -> Color32 {
+    match index {
+        0..=15 => BASIC_COLORS[index as usize],
+        16..=231 => {
+            let index = index - 16;
+            let level = |value: u8| if value == 0 { 0 } else { 55 + value * 40 };
+            Color32::from_rgb(level(index / 36), level((index / 6) % 6), level(index % 6))
+        }
+        _ => {
+            let gray = 8 + (index - 232) * 10;
+            Color32::from_rgb(gray, gray, gray)
+        }
+    }
+}
+
+fn apply_sgr(style: &mut Style, params: &str) {
+    let codes: Vec<u8> = params.split(';').map(|code| code.parse().unwrap_or(0)).collect();
+    let mut index = 0;
+    while index < codes.len() {
+        match codes[index] {
+            0 => *style = Style::default(),
+            1 => style.bold = true,
+            4 => style.underline = true,
+            22 => style.bold = false,
+            24 => style.underline = false,
+            code @ 30..=37 => style.foreground = Some(BASIC_COLORS[(code - 30) as usize]),
+            code @ 90..=97 => style.foreground = Some(BASIC_COLORS[(code - 90 + 8) as usize]),
+            39 => style.foreground = None,
+            38 => match codes.get(index + 1) {
+                Some(5) => {
+                    style.foreground = codes.get(index + 2).map(|&n| color_256(n));
+                    index += 2;
+                }
+                Some(2) => {
+                    if let [r, g, b] = codes[(index + 2).min(codes.len())..(index + 5).min(codes.len())] {
+                        style.foreground = Some(Color32::from_rgb(r, g, b));
+                    }
+                    index += 4;
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        index += 1;
+    }
+}
+
+// Splits text into styled runs, dropping escape sequences that aren't colors (cursor moves etc.)
+fn parse(text: &str) -> Vec<(Style, String)> {
+    let mut runs: Vec<(Style, String)> = Vec::new();
+    let mut style = Style::default();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            match runs.last_mut() {
+                Some((run_style, run)) if *run_style == style => run.push(c),
+                _ => runs.push((style, c.to_string())),
+            }
+            continue;
+        }
+        if chars.peek() != Some(&'[') {
+            chars.next();
+            continue;
+        }
+        chars.next();
+        let mut params = String::new();
+        for c in chars.by_ref() {
+            if c.is_ascii_alphabetic() {
+                if c == 'm' {
+                    apply_sgr(&mut style, &params);
+                }
+                break;
+            }
+            params.push(c);
+        }
+    }
+    runs
+}
+
+pub fn strip(text: &str) -> String {
+    parse(text).into_iter().map(|(_, run)| run).collect()
+}
+
+// Bold text without an explicit color uses the theme's strong text color
+pub fn layout_job(text: &str, default_color: Color32, strong_color: Color32, font: FontId) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    for (style, run) in parse(text) {
+        let fallback = if style.bold { strong_color } else { default_color };
+        let mut format = TextFormat::simple(font.clone(), style.foreground.unwrap_or(fallback));
+        if style.underline {
+            format.underline = egui::Stroke::new(1.0, format.color);
+        }
+        job.append(&run, 0.0, format);
+    }
+    job
+}
@@ -0,0 +1,65 @@
+use crate::http;
+use serde::Deserialize;
+use std::error::Error;
+
+// The project's latest published release, as GitHub reports it
+pub const RELEASES_URL: &str = "https://api.github.com/repos/OmarDevX/package-manager-ui.rs/releases/latest";
+
+#[derive(Deserialize)]
+struct ReleaseEntry {
+    tag_name: String,
+    html_url: String,
+}
+
+#[derive(Clone)]
+pub struct Release {
+    pub version: String,
+    // Release page with the changelog
+    pub url: String,
+}
+
+// The latest release in `feed` when it is newer than this build
+pub async fn newer_release(feed: &str) -> Result<Option<Release>, Box<dyn Error>> {
+    let entry: ReleaseEntry = http::get_json(feed).await?;
+    let version = entry.tag_name.trim_start_matches('v').to_string();
+    Ok(is_newer(&version, env!("CARGO_PKG_VERSION")).then_some(Release { version, url: entry.html_url }))
+}
+
+// Compares dotted numeric versions; a pre-release suffix such as "-rc1" is ignored
+fn is_newer(candidate: &str, current: &str) -> bool {
+    let parts = |version: &str| -> Vec<u64> {
+        let release = version.split(['-', '+']).next().unwrap_or(version);
+        release.split('.').map(|part| part.parse().unwrap_or(0)).collect()
+    };
+    parts(candidate) > parts(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn compares_release_versions() {
+        assert!(is_newer("0.2.0", "0.1.0"));
+        assert!(is_newer("0.10.0", "0.9.3"));
+        assert!(is_newer("1.0", "0.9.9"));
+        assert!(!is_newer("0.1.0", "0.1.0"));
+        assert!(!is_newer("0.1.0-rc1", "0.1.0"));
+    }
+
+    #[tokio::test]
+    async fn reads_the_latest_release_from_the_feed() {
+        let _isolated = testing::isolate().await;
+        let feed = |tag: &'static str| {
+            axum::Router::new().route(
+                "/latest",
+                axum::routing::get(move || async move { axum::Json(serde_json::json!({ "tag_name": tag, "html_url": format!("https://example.com/releases/{}", tag) })) }),
+            )
+        };
+
+        let newer = newer_release(&format!("{}/latest", testing::serve(feed("v99.0.0")))).await.unwrap().unwrap();
+        assert_eq!((newer.version.as_str(), newer.url.as_str()), ("99.0.0", "https://example.com/releases/v99.0.0"));
+        assert!(newer_release(&format!("{}/latest", testing::serve(feed("v0.0.1")))).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,266 @@
+use flate2::read::GzDecoder;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+// Desktop metadata for a package from the AppStream catalogs, e.g. archlinux-appstream-data or flatpak's
+pub struct Component {
+    pub id: String,
+    // Empty for flatpak catalogs, where the id is what gets installed
+    pub pkgname: String,
+    pub flatpak: bool,
+    pub name: String,
+    pub summary: String,
+    // file:// path of a cached icon or an http(s) URL, ready for egui's image loaders
+    pub icon: Option<String>,
+    pub screenshots: Vec<String>,
+    // freedesktop.org categories such as Development or Game
+    pub categories: Vec<String>,
+}
+
+// Largest cached icon sizes first; catalogs ship a subset of these
+const ICON_SIZES: [&str; 3] = ["128x128", "64x64", "48x48"];
+
+// AUR packages often repackage an app under one of these suffixes
+const AUR_SUFFIXES: [&str; 3] = ["-bin", "-git", "-appimage"];
+
+struct Catalog {
+    xml: PathBuf,
+    // Cached icons live in <icons>/<origin>/<size>/ for system catalogs and <icons>/<size>/ for flatpak
+    icons: PathBuf,
+    per_origin: bool,
+}
+
+fn catalogs() -> Vec<Catalog> {
+    let mut catalogs = Vec::new();
+    for root in ["/usr/share/swcatalog", "/usr/share/app-info"] {
+        let root = Path::new(root);
+        for xml_dir in [root.join("xml"), root.join("xmls")] {
+            for xml in gz_files(&xml_dir) {
+                catalogs.push(Catalog {
+                    xml,
+                    icons: root.join("icons"),
+                    per_origin: true,
+                });
+            }
+        }
+    }
+    let flatpak_roots = [PathBuf::from("/var/lib/flatpak/appstream")]
+        .into_iter()
+        .chain(dirs::data_dir().map(|dir| dir.join("flatpak").join("appstream")));
+    for root in flatpak_roots {
+        // <root>/<remote>/<arch>/active/appstream.xml.gz
+        for remote in subdirs(&root) {
+            for arch in subdirs(&remote) {
+                let active = arch.join("active");
+                let xml = active.join("appstream.xml.gz");
+                if xml.is_file() {
+                    catalogs.push(Catalog {
+                        xml,
+                        icons: active.join("icons"),
+                        per_origin: false,
+                    });
+                }
+            }
+        }
+    }
+    catalogs
+}
+
+fn subdirs(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| entries.filter_map(Result::ok).map(|entry| entry.path()).filter(|path| path.is_dir()).collect())
+        .unwrap_or_default()
+}
+
+fn gz_files(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.to_string_lossy().ends_with(".xml.gz"))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Matches the pkgname, the component id (flatpak app ids) or the name without an AUR suffix.
+// Catalogs can be tens of megabytes, so call this off the UI thread.
+pub fn lookup(package: &str) -> Option<Component> {
+    let stripped = AUR_SUFFIXES.iter().find_map(|suffix| package.strip_suffix(suffix)).unwrap_or(package);
+    let names = [package, stripped];
+    let mut found = None;
+    for catalog in catalogs() {
+        let result = scan(&catalog, &mut |partial, origin| {
+            if partial.matches(&names) {
+                found = Some(partial.into_component(&catalog, origin));
+            }
+            found.is_some()
+        });
+        if let Err(e) = result {
+            tracing::debug!("Could not read AppStream catalog {}: {}", catalog.xml.display(), e);
+        }
+        if found.is_some() {
+            break;
+        }
+    }
+    found
+}
+
+// Every component of every catalog, for browsing by category; slower still than `lookup`
+pub fn all() -> Vec<Component> {
+    let mut components = Vec::new();
+    for catalog in catalogs() {
+        let result = scan(&catalog, &mut |partial, origin| {
+            components.push(partial.into_component(&catalog, origin));
+            false
+        });
+        if let Err(e) = result {
+            tracing::debug!("Could not read AppStream catalog {}: {}", catalog.xml.display(), e);
+        }
+    }
+    components
+}
+
+#[derive(Default)]
+struct Partial {
+    id: String,
+    pkgname: String,
+    name: String,
+    summary: String,
+    cached_icons: Vec<(String, String)>,
+    remote_icon: Option<String>,
+    screenshots: Vec<String>,
+    categories: Vec<String>,
+}
+
+impl Partial {
+    fn matches(&self, names: &[&str]) -> bool {
+        let id = self.id.trim_end_matches(".desktop");
+        names.iter().any(|name| self.pkgname == *name || id == *name)
+    }
+
+    fn into_component(self, catalog: &Catalog, origin: &str) -> Component {
+        let icon_dir = if catalog.per_origin { catalog.icons.join(origin) } else { catalog.icons.clone() };
+        let cached = ICON_SIZES.iter().find_map(|size| {
+            self.cached_icons
+                .iter()
+                .filter(|(icon_size, _)| icon_size == size)
+                .map(|(size, file)| icon_dir.join(size).join(file))
+                .find(|path| path.is_file())
+        });
+        Component {
+            name: if self.name.is_empty() { self.id.clone() } else { self.name },
+            id: self.id,
+            pkgname: self.pkgname,
+            flatpak: !catalog.per_origin,
+            summary: self.summary,
+            icon: cached.map(|path| format!("file://{}", path.display())).or(self.remote_icon),
+            screenshots: self.screenshots,
+            categories: self.categories,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+    Id,
+    Pkgname,
+    Name,
+    Summary,
+    CachedIcon,
+    RemoteIcon,
+    Screenshot,
+    Category,
+}
+
+fn attribute(element: &BytesStart, name: &[u8]) -> Option<String> {
+    element
+        .attributes()
+        .filter_map(Result::ok)
+        .find(|attribute| attribute.key.as_ref() == name)
+        .and_then(|attribute| attribute.unescape_value().ok().map(|value| value.into_owned()))
+}
+
+// Streams through the catalog, handing each component to `visit` with the catalog's origin until
+// it returns true. Translated elements carry xml:lang and are skipped, as are fields nested
+// deeper than the component's children.
+fn scan(catalog: &Catalog, visit: &mut dyn FnMut(Partial, &str) -> bool) -> Result<(), Box<dyn Error>> {
+    let mut reader = Reader::from_reader(BufReader::new(GzDecoder::new(File::open(&catalog.xml)?)));
+    let mut buf = Vec::new();
+    let mut origin = String::new();
+    let mut depth = 0;
+    let mut component: Option<(usize, Partial)> = None;
+    let mut field: Option<Field> = None;
+    let mut icon_size = String::new();
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(element) => {
+                depth += 1;
+                let translated = attribute(&element, b"xml:lang").is_some();
+                match element.name().as_ref() {
+                    b"components" => origin = attribute(&element, b"origin").unwrap_or_default(),
+                    b"component" => component = Some((depth, Partial::default())),
+                    tag => {
+                        let child = component.as_ref().is_some_and(|(component_depth, _)| depth == component_depth + 1);
+                        field = match tag {
+                            b"id" if child => Some(Field::Id),
+                            b"pkgname" if child => Some(Field::Pkgname),
+                            b"name" if child && !translated => Some(Field::Name),
+                            b"summary" if child && !translated => Some(Field::Summary),
+                            b"icon" if child => match attribute(&element, b"type").as_deref() {
+                                Some("cached") => {
+                                    let width = attribute(&element, b"width").unwrap_or_else(|| "64".to_string());
+                                    let height = attribute(&element, b"height").unwrap_or_else(|| width.clone());
+                                    icon_size = format!("{}x{}", width, height);
+                                    Some(Field::CachedIcon)
+                                }
+                                Some("remote") => Some(Field::RemoteIcon),
+                                _ => None,
+                            },
+                            b"image" if component.is_some() && attribute(&element, b"type").as_deref() == Some("source") => {
+                                Some(Field::Screenshot)
+                            }
+                            b"category" if component.is_some() => Some(Field::Category),
+                            _ => None,
+                        };
+                    }
+                }
+            }
+            Event::Text(text) => {
+                if let (Some(field), Some((_, partial))) = (field, component.as_mut()) {
+                    let text = text.unescape()?.trim().to_string();
+                    match field {
+                        Field::Id => partial.id.push_str(&text),
+                        Field::Pkgname => partial.pkgname.push_str(&text),
+                        Field::Name => partial.name.push_str(&text),
+                        Field::Summary => partial.summary.push_str(&text),
+                        Field::CachedIcon => partial.cached_icons.push((icon_size.clone(), text)),
+                        Field::RemoteIcon => partial.remote_icon = partial.remote_icon.take().or(Some(text)),
+                        Field::Screenshot => partial.screenshots.push(text),
+                        Field::Category => partial.categories.push(text),
+                    }
+                }
+            }
+            Event::End(element) => {
+                field = None;
+                if element.name().as_ref() == b"component" {
+                    if let Some((_, partial)) = component.take() {
+                        if visit(partial, &origin) {
+                            return Ok(());
+                        }
+                    }
+                }
+                depth -= 1;
+            }
+            Event::Eof => return Ok(()),
+            _ => {}
+        }
+        buf.clear();
+    }
+}
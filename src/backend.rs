@@ -0,0 +1,661 @@
+use crate::config::{Config, RemoveMode};
+use crate::escalation;
+use crate::exit_codes::{self, ExitCode};
+use crate::remote;
+use crate::runner::Run;
+use std::error::Error;
+use std::future::Future;
+use std::process::Output;
+use std::sync::RwLock;
+
+// (name, installed version, available version)
+pub type Update = (String, String, String);
+
+// Everything the UI needs from a package manager. Installing from the AUR additionally goes
+// through the review and makepkg pipeline, which only the pacman backend supports.
+pub trait PackageBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+    // Shown in the source column of package lists
+    fn label(&self) -> &'static str;
+    // When true, `install` receives built package files; otherwise package names
+    fn builds_from_source(&self) -> bool;
+    fn search(&self, query: &str) -> Result<Vec<String>, Box<dyn Error>>;
+    // Packages the user installed explicitly, as (name, version)
+    fn list_installed(&self) -> Result<Vec<(String, String)>, Box<dyn Error>>;
+    fn is_installed(&self, package: &str) -> Result<bool, Box<dyn Error>>;
+    fn install(&self, targets: &[String], escalation_tool: &str) -> Result<(), Box<dyn Error>>;
+    // `mode` only applies to sources removing through pacman; the others remove the package alone
+    fn remove(&self, package: &str, mode: RemoveMode, escalation_tool: &str) -> Result<(), Box<dyn Error>>;
+    fn check_updates(&self) -> Result<Vec<Update>, Box<dyn Error>>;
+    // Upgrades installed packages by name; reinstalling gets the newest version by default
+    fn update(&self, packages: &[String], escalation_tool: &str) -> Result<(), Box<dyn Error>> {
+        self.install(packages, escalation_tool)
+    }
+    // Every installed package with its size in bytes; sources that cannot tell return nothing
+    fn installed_sizes(&self) -> Result<Vec<(String, u64)>, Box<dyn Error>> {
+        Ok(Vec::new())
+    }
+    fn removes_with_pacman(&self) -> bool {
+        false
+    }
+}
+
+pub const AUTO: &str = "auto";
+pub const BACKENDS: [&str; 2] = ["pacman", "apt"];
+
+static PACMAN: Pacman = Pacman;
+static APT: Apt = Apt;
+static FLATPAK: Flatpak = Flatpak;
+static SNAP: Snap = Snap;
+static REPO: Repo = Repo;
+static ACTIVE: RwLock<&'static dyn PackageBackend> = RwLock::new(&PACMAN);
+// Sources listed next to the active backend, e.g. Flatpak and Snap
+static EXTRA: RwLock<Vec<&'static dyn PackageBackend>> = RwLock::new(Vec::new());
+
+fn found(program: &str) -> bool {
+    remote::command(program, ["--version"]).run().is_ok_and(|output| output.status.success())
+}
+
+// Picks the configured backend, or with "auto" the first package manager found on the active host,
+// plus the enabled extra sources that are installed there
+pub fn select(config: &Config) -> Result<(), Box<dyn Error>> {
+    let name = if config.backend == AUTO {
+        if !found("pacman") && found("apt-get") {
+            "apt"
+        } else {
+            "pacman"
+        }
+    } else {
+        config.backend.as_str()
+    };
+    let backend: &'static dyn PackageBackend = match name {
+        "pacman" => &PACMAN,
+        "apt" => &APT,
+        other => return Err(format!("Unknown backend '{}'; expected one of {}", other, BACKENDS.join(", ")).into()),
+    };
+    *ACTIVE.write().unwrap() = backend;
+
+    let mut extra: Vec<&'static dyn PackageBackend> = Vec::new();
+    if config.flatpak && found("flatpak") {
+        extra.push(&FLATPAK);
+    }
+    if config.snap && found("snap") {
+        extra.push(&SNAP);
+    }
+    *EXTRA.write().unwrap() = extra;
+    Ok(())
+}
+
+pub fn active() -> &'static dyn PackageBackend {
+    *ACTIVE.read().unwrap()
+}
+
+// The active backend followed by the extra sources
+pub fn sources() -> Vec<&'static dyn PackageBackend> {
+    std::iter::once(active()).chain(EXTRA.read().unwrap().iter().copied()).collect()
+}
+
+// Falls back to the active backend for a source that is no longer enabled
+pub fn by_name(name: &str) -> &'static dyn PackageBackend {
+    if name == REPO.name() {
+        return &REPO;
+    }
+    sources().into_iter().find(|source| source.name() == name).unwrap_or_else(active)
+}
+
+// Packages from the pacman sync repositories. They are not one of the `sources`: browsing is the
+// only way to reach them, and their updates go through `system_upgrade`.
+pub fn repo() -> &'static dyn PackageBackend {
+    &REPO
+}
+
+// Upgrades the repository packages with `pacman -Syu`, leaving out `skipped`. Upgrading only some
+// of them with -S instead would be a partial upgrade, which Arch does not support.
+pub fn system_upgrade(skipped: &[String], escalation_tool: &str) -> Result<(), Box<dyn Error>> {
+    let mut args = vec!["-Syu".to_string(), "--noconfirm".to_string()];
+    if !skipped.is_empty() {
+        args.extend(["--ignore".to_string(), skipped.join(",")]);
+    }
+    let output = escalation::privileged(escalation::direct(escalation_tool), "pacman", args)?.run()?;
+    stdout_of("pacman -Syu", output).map(|_| ())
+}
+
+// pkexec exits with 126 when the dialog is dismissed and 127 when authorization fails
+fn failure(command: &str, output: &Output) -> Box<dyn Error> {
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    let denied = matches!(output.status.code(), Some(126 | 127))
+        || ["permission denied", "not authorized", "incorrect password", "not in the sudoers"]
+            .iter()
+            .any(|marker| stderr.to_lowercase().contains(marker));
+    let code = if denied { ExitCode::PermissionDenied } else { ExitCode::Generic };
+    exit_codes::coded(code, format!("{} failed: {}", command, stderr))
+}
+
+fn stdout_of(command: &str, output: Output) -> Result<String, Box<dyn Error>> {
+    if !output.status.success() {
+        return Err(failure(command, &output));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Sizes like "12.34 MiB" from pacman or "1.2 GB" from flatpak, read in the C locale
+pub fn parse_size(text: &str) -> Option<u64> {
+    let (number, unit) = text.trim().split_once(' ')?;
+    let number: f64 = number.parse().ok()?;
+    let factor = match unit.trim() {
+        "B" | "bytes" => 1.0,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        "kB" | "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        _ => return None,
+    };
+    Some((number * factor) as u64)
+}
+
+// The AUR RPC client is async, while backend calls may come from inside a tokio worker,
+// so the request gets a runtime of its own on a separate thread
+fn run_async<T, F>(future: F) -> Result<T, Box<dyn Error>>
+where
+    T: Send + 'static,
+    F: Future<Output = Result<T, Box<dyn Error>>> + Send + 'static,
+{
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().map_err(|e| e.to_string())?;
+        rt.block_on(future).map_err(|e| e.to_string())
+    })
+    .join()
+    .map_err(|_| "Background request panicked".to_string())?
+    .map_err(Into::into)
+}
+
+struct Pacman;
+
+impl Pacman {
+    fn is_newer_version(installed: &str, available: &str) -> bool {
+        vercmp(available, installed).map_or(installed != available, |order| order.is_gt())
+    }
+}
+
+// Orders two pacman versions with vercmp, which prints a negative, zero or positive number
+pub fn vercmp(a: &str, b: &str) -> Option<std::cmp::Ordering> {
+    let output = remote::command("vercmp", [a, b]).run().ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse::<i32>().ok().map(|order| order.cmp(&0))
+}
+
+impl PackageBackend for Pacman {
+    fn name(&self) -> &'static str {
+        "pacman"
+    }
+
+    fn label(&self) -> &'static str {
+        "AUR"
+    }
+
+    fn builds_from_source(&self) -> bool {
+        true
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let query = query.to_string();
+        run_async(async move { crate::search_aur_package(&query).await })
+    }
+
+    // Foreign packages, i.e. those not from a sync repository, which for this helper means the AUR
+    fn list_installed(&self) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+        let stdout = stdout_of("pacman -Qm", remote::command("pacman", ["-Qm"]).run()?)?;
+        Ok(stdout
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(name, version)| (name.to_string(), version.to_string()))
+            .collect())
+    }
+
+    fn is_installed(&self, package: &str) -> Result<bool, Box<dyn Error>> {
+        let output = remote::command("pacman", ["-Q", package]).run()?;
+        Ok(output.status.success())
+    }
+
+    fn install(&self, targets: &[String], escalation_tool: &str) -> Result<(), Box<dyn Error>> {
+        let args = ["-U", "--noconfirm"].iter().map(|arg| arg.to_string()).chain(targets.iter().cloned());
+        let output = escalation::privileged(escalation::direct(escalation_tool), "pacman", args)?.run()?;
+        stdout_of("pacman -U", output).map(|_| ())
+    }
+
+    fn remove(&self, package: &str, mode: RemoveMode, escalation_tool: &str) -> Result<(), Box<dyn Error>> {
+        let output = escalation::privileged(escalation::direct(escalation_tool), "pacman", mode.pacman_args(package))?.run()?;
+        stdout_of(&format!("pacman {}", mode.flag()), output).map(|_| ())
+    }
+
+    fn check_updates(&self) -> Result<Vec<Update>, Box<dyn Error>> {
+        let installed = self.list_installed()?;
+        if installed.is_empty() {
+            return Ok(Vec::new());
+        }
+        let names: Vec<String> = installed.iter().map(|(name, _)| name.clone()).collect();
+        let available = run_async(async move { crate::aur_versions(&names).await })?;
+        Ok(available
+            .into_iter()
+            .filter_map(|(name, available)| {
+                let (_, current) = installed.iter().find(|(installed_name, _)| *installed_name == name)?;
+                Pacman::is_newer_version(current, &available).then(|| (name, current.clone(), available))
+            })
+            .collect())
+    }
+
+    // All packages, not only foreign ones, since the report is about reclaiming space
+    fn installed_sizes(&self) -> Result<Vec<(String, u64)>, Box<dyn Error>> {
+        let stdout = stdout_of("pacman -Qi", remote::command("env", ["LC_ALL=C", "pacman", "-Qi"]).run()?)?;
+        Ok(stdout
+            .split("\n\n")
+            .filter_map(|block| {
+                let field = |key: &str| {
+                    block.lines().find_map(|line| {
+                        let (name, value) = line.split_once(':')?;
+                        (name.trim() == key).then(|| value.trim())
+                    })
+                };
+                Some((field("Name")?.to_string(), parse_size(field("Installed Size")?)?))
+            })
+            .collect())
+    }
+
+    fn removes_with_pacman(&self) -> bool {
+        true
+    }
+}
+
+struct Apt;
+
+impl PackageBackend for Apt {
+    fn name(&self) -> &'static str {
+        "apt"
+    }
+
+    fn label(&self) -> &'static str {
+        "apt"
+    }
+
+    fn builds_from_source(&self) -> bool {
+        false
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let stdout = stdout_of("apt-cache search", remote::command("apt-cache", ["search", "--names-only", query]).run()?)?;
+        Ok(stdout.lines().filter_map(|line| line.split(" - ").next()).map(str::to_string).collect())
+    }
+
+    fn list_installed(&self) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+        let manual = stdout_of("apt-mark showmanual", remote::command("apt-mark", ["showmanual"]).run()?)?;
+        let names: Vec<&str> = manual.lines().collect();
+        if names.is_empty() {
+            return Ok(Vec::new());
+        }
+        let args = ["-W", "-f=${Package} ${Version}\\n"].into_iter().chain(names);
+        let stdout = stdout_of("dpkg-query", remote::command("dpkg-query", args).run()?)?;
+        Ok(stdout
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(name, version)| (name.to_string(), version.to_string()))
+            .collect())
+    }
+
+    fn is_installed(&self, package: &str) -> Result<bool, Box<dyn Error>> {
+        let output = remote::command("dpkg-query", ["-W", "-f=${Status}", package]).run()?;
+        Ok(output.status.success() && String::from_utf8_lossy(&output.stdout).contains("install ok installed"))
+    }
+
+    fn install(&self, targets: &[String], escalation_tool: &str) -> Result<(), Box<dyn Error>> {
+        let args = ["install", "-y"].iter().map(|arg| arg.to_string()).chain(targets.iter().cloned());
+        let output = escalation::privileged(escalation::direct(escalation_tool), "apt-get", args)?.run()?;
+        stdout_of("apt-get install", output).map(|_| ())
+    }
+
+    fn remove(&self, package: &str, _mode: RemoveMode, escalation_tool: &str) -> Result<(), Box<dyn Error>> {
+        let output = escalation::privileged(escalation::direct(escalation_tool), "apt-get", ["remove", "-y", package])?.run()?;
+        stdout_of("apt-get remove", output).map(|_| ())
+    }
+
+    // Lines look like `curl/stable 7.88.1-10+deb12u6 amd64 [upgradable from: 7.88.1-10+deb12u5]`
+    fn check_updates(&self) -> Result<Vec<Update>, Box<dyn Error>> {
+        let stdout = stdout_of("apt list", remote::command("apt", ["list", "--upgradable"]).run()?)?;
+        Ok(stdout
+            .lines()
+            .filter_map(|line| {
+                let (name, rest) = line.split_once('/')?;
+                let available = rest.split_whitespace().nth(1)?;
+                let installed = rest.split("upgradable from: ").nth(1)?.trim_end_matches(']');
+                Some((name.to_string(), installed.to_string(), available.to_string()))
+            })
+            .collect())
+    }
+
+    // dpkg reports Installed-Size in KiB
+    fn installed_sizes(&self) -> Result<Vec<(String, u64)>, Box<dyn Error>> {
+        let output = remote::command("dpkg-query", ["-W", "-f=${Package} ${Installed-Size}\\n"]).run()?;
+        Ok(stdout_of("dpkg-query", output)?
+            .lines()
+            .filter_map(|line| {
+                let (name, size) = line.split_once(' ')?;
+                Some((name.to_string(), size.trim().parse::<u64>().ok()? * 1024))
+            })
+            .collect())
+    }
+}
+
+// Apps from Flathub. flatpak asks polkit itself for system-wide changes, so the escalation tool is unused.
+struct Flatpak;
+
+impl Flatpak {
+    const REMOTE: &'static str = "flathub";
+
+    // `--columns` output is tab separated; the version column may be empty
+    fn columns(stdout: &str) -> Vec<(String, String)> {
+        stdout
+            .lines()
+            .filter_map(|line| {
+                let mut columns = line.split('\t');
+                let application = columns.next()?.trim();
+                application.contains('.').then(|| (application.to_string(), columns.next().unwrap_or("").trim().to_string()))
+            })
+            .collect()
+    }
+}
+
+impl PackageBackend for Flatpak {
+    fn name(&self) -> &'static str {
+        "flatpak"
+    }
+
+    fn label(&self) -> &'static str {
+        "Flatpak"
+    }
+
+    fn builds_from_source(&self) -> bool {
+        false
+    }
+
+    // Prints "No matches found" rather than failing when nothing matches, which `columns` skips
+    fn search(&self, query: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let output = remote::command("flatpak", ["search", "--columns=application,remotes", query]).run()?;
+        let stdout = stdout_of("flatpak search", output)?;
+        Ok(Flatpak::columns(&stdout)
+            .into_iter()
+            .filter(|(_, remotes)| remotes.split(',').any(|remote| remote == Flatpak::REMOTE))
+            .map(|(application, _)| application)
+            .collect())
+    }
+
+    fn list_installed(&self) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+        let output = remote::command("flatpak", ["list", "--app", "--columns=application,version"]).run()?;
+        Ok(Flatpak::columns(&stdout_of("flatpak list", output)?))
+    }
+
+    fn is_installed(&self, package: &str) -> Result<bool, Box<dyn Error>> {
+        let output = remote::command("flatpak", ["info", package]).run()?;
+        Ok(output.status.success())
+    }
+
+    fn install(&self, targets: &[String], _escalation_tool: &str) -> Result<(), Box<dyn Error>> {
+        let args = ["install", "-y", "--noninteractive", Flatpak::REMOTE].iter().map(|arg| arg.to_string()).chain(targets.iter().cloned());
+        stdout_of("flatpak install", remote::command("flatpak", args).run()?).map(|_| ())
+    }
+
+    fn remove(&self, package: &str, _mode: RemoveMode, _escalation_tool: &str) -> Result<(), Box<dyn Error>> {
+        let output = remote::command("flatpak", ["uninstall", "-y", "--noninteractive", package]).run()?;
+        stdout_of("flatpak uninstall", output).map(|_| ())
+    }
+
+    fn check_updates(&self) -> Result<Vec<Update>, Box<dyn Error>> {
+        let output = remote::command("flatpak", ["remote-ls", "--updates", "--app", "--columns=application,version"]).run()?;
+        let available = Flatpak::columns(&stdout_of("flatpak remote-ls", output)?);
+        let installed = self.list_installed()?;
+        Ok(available
+            .into_iter()
+            .map(|(name, version)| {
+                let current = installed.iter().find(|(installed_name, _)| *installed_name == name).map(|(_, version)| version.clone());
+                (name, current.unwrap_or_default(), version)
+            })
+            .collect())
+    }
+
+    fn update(&self, packages: &[String], _escalation_tool: &str) -> Result<(), Box<dyn Error>> {
+        let args = ["update", "-y", "--noninteractive"].iter().map(|arg| arg.to_string()).chain(packages.iter().cloned());
+        stdout_of("flatpak update", remote::command("flatpak", args).run()?).map(|_| ())
+    }
+
+    fn installed_sizes(&self) -> Result<Vec<(String, u64)>, Box<dyn Error>> {
+        let output = remote::command("env", ["LC_ALL=C", "flatpak", "list", "--app", "--columns=application,size"]).run()?;
+        Ok(Flatpak::columns(&stdout_of("flatpak list", output)?)
+            .into_iter()
+            .filter_map(|(application, size)| Some((application, parse_size(&size.replace('\u{a0}', " "))?)))
+            .collect())
+    }
+}
+
+// Snaps from the Snap Store; needs a running snapd
+struct Snap;
+
+impl Snap {
+    // snap prints aligned tables with a header row; the first columns are name and version
+    fn table(stdout: &str) -> Vec<Vec<String>> {
+        stdout
+            .lines()
+            .skip(1)
+            .map(|line| line.split_whitespace().map(str::to_string).collect::<Vec<String>>())
+            .filter(|row| row.len() >= 2)
+            .collect()
+    }
+}
+
+impl PackageBackend for Snap {
+    fn name(&self) -> &'static str {
+        "snap"
+    }
+
+    fn label(&self) -> &'static str {
+        "Snap"
+    }
+
+    fn builds_from_source(&self) -> bool {
+        false
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let output = remote::command("snap", ["find", query]).run()?;
+        // "No matching snaps" is reported as a failure
+        if !output.status.success() && String::from_utf8_lossy(&output.stderr).contains("No matching snaps") {
+            return Ok(Vec::new());
+        }
+        Ok(Snap::table(&stdout_of("snap find", output)?).into_iter().map(|row| row[0].clone()).collect())
+    }
+
+    // Bases and snapd itself are installed as dependencies and are left out
+    fn list_installed(&self) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+        let stdout = stdout_of("snap list", remote::command("snap", ["list"]).run()?)?;
+        Ok(Snap::table(&stdout)
+            .into_iter()
+            .filter(|row| row.get(5).is_none_or(|notes| !["base", "core", "snapd"].iter().any(|kind| notes.contains(kind))))
+            .map(|row| (row[0].clone(), row[1].clone()))
+            .collect())
+    }
+
+    fn is_installed(&self, package: &str) -> Result<bool, Box<dyn Error>> {
+        let output = remote::command("snap", ["list", package]).run()?;
+        Ok(output.status.success())
+    }
+
+    fn install(&self, targets: &[String], escalation_tool: &str) -> Result<(), Box<dyn Error>> {
+        let args = std::iter::once("install".to_string()).chain(targets.iter().cloned());
+        stdout_of("snap install", escalation::privileged(escalation::direct(escalation_tool), "snap", args)?.run()?).map(|_| ())
+    }
+
+    fn remove(&self, package: &str, _mode: RemoveMode, escalation_tool: &str) -> Result<(), Box<dyn Error>> {
+        let output = escalation::privileged(escalation::direct(escalation_tool), "snap", ["remove", package])?.run()?;
+        stdout_of("snap remove", output).map(|_| ())
+    }
+
+    // `snap refresh --list` shows the available version; "All snaps up to date" goes to stderr
+    fn check_updates(&self) -> Result<Vec<Update>, Box<dyn Error>> {
+        let available = Snap::table(&stdout_of("snap refresh --list", remote::command("snap", ["refresh", "--list"]).run()?)?);
+        let installed = self.list_installed()?;
+        Ok(available
+            .into_iter()
+            .map(|row| {
+                let current = installed.iter().find(|(name, _)| *name == row[0]).map(|(_, version)| version.clone());
+                (row[0].clone(), current.unwrap_or_default(), row[1].clone())
+            })
+            .collect())
+    }
+
+    fn update(&self, packages: &[String], escalation_tool: &str) -> Result<(), Box<dyn Error>> {
+        let args = std::iter::once("refresh".to_string()).chain(packages.iter().cloned());
+        stdout_of("snap refresh", escalation::privileged(escalation::direct(escalation_tool), "snap", args)?.run()?).map(|_| ())
+    }
+}
+
+// Lines of `checkupdates` and `pacman -Qu`: "name installed -> available", with " [ignored]" after
+// packages in IgnorePkg
+pub fn read_updates(stdout: &str) -> Vec<Update> {
+    stdout
+        .lines()
+        .filter_map(|line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+            [name, installed, "->", available, ..] => Some((name.to_string(), installed.to_string(), available.to_string())),
+            _ => None,
+        })
+        .collect()
+}
+
+struct Repo;
+
+impl PackageBackend for Repo {
+    fn name(&self) -> &'static str {
+        "repo"
+    }
+
+    fn label(&self) -> &'static str {
+        "Repo"
+    }
+
+    fn builds_from_source(&self) -> bool {
+        false
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let stdout = stdout_of("pacman -Ssq", remote::command("pacman", ["-Ssq", query]).run()?)?;
+        Ok(stdout.lines().map(str::to_string).collect())
+    }
+
+    // Explicitly installed packages from the sync repositories
+    fn list_installed(&self) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+        let stdout = stdout_of("pacman -Qen", remote::command("pacman", ["-Qen"]).run()?)?;
+        Ok(stdout
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(name, version)| (name.to_string(), version.to_string()))
+            .collect())
+    }
+
+    fn is_installed(&self, package: &str) -> Result<bool, Box<dyn Error>> {
+        let output = remote::command("pacman", ["-Q", package]).run()?;
+        Ok(output.status.success())
+    }
+
+    fn install(&self, targets: &[String], escalation_tool: &str) -> Result<(), Box<dyn Error>> {
+        let tool = escalation::direct(escalation_tool);
+        let args = ["-S", "--needed", "--noconfirm"].iter().map(|arg| arg.to_string()).chain(targets.iter().cloned());
+        let output = escalation::privileged(tool, "pacman", args)?.run()?;
+        stdout_of("pacman -S", output).map(|_| ())
+    }
+
+    fn remove(&self, package: &str, mode: RemoveMode, escalation_tool: &str) -> Result<(), Box<dyn Error>> {
+        Pacman.remove(package, mode, escalation::direct(escalation_tool))
+    }
+
+    // checkupdates compares against a fresh copy of the sync databases; without pacman-contrib
+    // this falls back to `pacman -Qu` and the databases as last synced. Both exit with an error
+    // when nothing is outdated, which only counts as a failure with something on stderr.
+    fn check_updates(&self) -> Result<Vec<Update>, Box<dyn Error>> {
+        let (command, output) = match remote::command("checkupdates", Vec::<&str>::new()).run() {
+            Ok(output) => ("checkupdates", output),
+            Err(_) => ("pacman -Qu", remote::command("pacman", ["-Qu"]).run()?),
+        };
+        if !output.status.success() && !output.stderr.trim_ascii().is_empty() {
+            return Err(failure(command, &output));
+        }
+        Ok(read_updates(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    fn removes_with_pacman(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Scripted};
+
+    #[test]
+    fn reads_pacman_and_flatpak_sizes() {
+        assert_eq!(parse_size("1.50 MiB"), Some(1_572_864));
+        assert_eq!(parse_size("12 B"), Some(12));
+        assert_eq!(parse_size("1.2 GB"), Some(1_200_000_000));
+        assert_eq!(parse_size("3 parsecs"), None);
+    }
+
+    #[test]
+    fn compares_versions_with_vercmp() {
+        let _isolated = testing::isolate_blocking();
+        Scripted::default().answer("vercmp 2.0-1 1:1.0-1", "-1\n").answer("vercmp 1.0-2 1.0-1", "1\n").install();
+
+        assert_eq!(vercmp("2.0-1", "1:1.0-1"), Some(std::cmp::Ordering::Less));
+        assert!(Pacman::is_newer_version("1.0-1", "1.0-2"));
+        // The epoch outweighs the version
+        assert!(!Pacman::is_newer_version("1:1.0-1", "2.0-1"));
+
+        // Without vercmp any other version counts as newer
+        Scripted::default().install();
+        assert!(!Pacman::is_newer_version("3.0-1", "3.0-1"));
+        assert!(Pacman::is_newer_version("3.0-1", "2.0-1"));
+    }
+
+    #[test]
+    fn finds_aur_updates() {
+        let _isolated = testing::isolate_blocking();
+        let router = axum::Router::new().route(
+            "/rpc/",
+            axum::routing::get(|| async {
+                axum::Json(serde_json::json!({ "results": [
+                    { "Name": "foo", "Version": "1.1-1" },
+                    { "Name": "bar", "Version": "2.0-1" },
+                ] }))
+            }),
+        );
+        testing::use_aur(&testing::serve(router), |_| {});
+        let scripted = Scripted::default()
+            .answer("pacman -Qm", "foo 1.0-1\nbar 2.0-1\ngone 0.1-1\n")
+            .answer("vercmp 1.1-1 1.0-1", "1\n")
+            .answer("vercmp 2.0-1 2.0-1", "0\n")
+            .install();
+
+        let updates = PACMAN.check_updates().unwrap();
+        assert_eq!(updates, vec![("foo".to_string(), "1.0-1".to_string(), "1.1-1".to_string())]);
+        assert_eq!(scripted.calls(), ["pacman -Qm", "vercmp 1.1-1 1.0-1", "vercmp 2.0-1 2.0-1"]);
+    }
+
+    #[test]
+    fn finds_repo_updates_with_or_without_checkupdates() {
+        let _isolated = testing::isolate_blocking();
+        Scripted::default().answer("checkupdates", "linux 6.9.1-1 -> 6.9.2-1\nmesa 1:24.1-1 -> 1:24.2-1\n").install();
+        let updates = REPO.check_updates().unwrap();
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[1], ("mesa".to_string(), "1:24.1-1".to_string(), "1:24.2-1".to_string()));
+
+        let scripted = Scripted::default().answer("pacman -Qu", "vim 9.1-1 -> 9.1-2 [ignored]\n").install();
+        assert_eq!(REPO.check_updates().unwrap(), vec![("vim".to_string(), "9.1-1".to_string(), "9.1-2".to_string())]);
+        assert_eq!(scripted.calls(), ["checkupdates", "pacman -Qu"]);
+    }
+}
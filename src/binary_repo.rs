@@ -0,0 +1,38 @@
+use crate::{backend, remote};
+use crate::runner::Run;
+use std::error::Error;
+
+// An AUR package that a binary repository such as chaotic-aur already ships prebuilt
+#[derive(Clone)]
+pub struct Prebuilt {
+    pub repo: String,
+    pub package: String,
+    pub version: String,
+}
+
+// Repositories must already be set up in pacman.conf; ones that are not are skipped like
+// repositories that lack the package
+pub fn find(package: &str, repos: &[String]) -> Option<Prebuilt> {
+    repos.iter().find_map(|repo| {
+        let output = remote::command("pacman", ["-Si", &format!("{}/{}", repo, package)]).run().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        // `pacman -Si` prints aligned `Key : Value` lines
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let version = stdout.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == "Version").then(|| value.trim().to_string())
+        })?;
+        Some(Prebuilt {
+            repo: repo.clone(),
+            package: package.to_string(),
+            version,
+        })
+    })
+}
+
+pub fn install(prebuilt: &Prebuilt, escalation_tool: &str) -> Result<(), Box<dyn Error>> {
+    tracing::info!("Installing {} {} from {}", prebuilt.package, prebuilt.version, prebuilt.repo);
+    backend::repo().install(&[format!("{}/{}", prebuilt.repo, prebuilt.package)], escalation_tool)
+}
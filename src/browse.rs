@@ -0,0 +1,102 @@
+use crate::{appstream, backend, remote};
+use crate::runner::Run;
+
+// freedesktop.org main categories and the names shown for them
+pub const CATEGORIES: [(&str, &str); 11] = [
+    ("AudioVideo", "Multimedia"),
+    ("Development", "Development"),
+    ("Education", "Education"),
+    ("Game", "Games"),
+    ("Graphics", "Graphics"),
+    ("Network", "Internet"),
+    ("Office", "Office"),
+    ("Science", "Science"),
+    ("Settings", "Settings"),
+    ("System", "System"),
+    ("Utility", "Utilities"),
+];
+
+// pacman groups are listed after the AppStream categories under this prefix
+const GROUP_PREFIX: &str = "group:";
+
+#[derive(Clone)]
+pub struct BrowseEntry {
+    // Backend name, as in the search results
+    pub source: &'static str,
+    pub package: String,
+    pub title: String,
+    pub categories: Vec<String>,
+}
+
+// AppStream applications plus pacman groups; catalogs are read locally even when a remote host is active
+pub fn load() -> Vec<BrowseEntry> {
+    // System catalogs describe repository packages, which the AUR backend cannot install itself
+    let system_source = if backend::active().builds_from_source() { backend::repo().name() } else { backend::active().name() };
+    let flatpak_enabled = backend::sources().iter().any(|source| source.name() == "flatpak");
+
+    let mut entries: Vec<BrowseEntry> = appstream::all()
+        .into_iter()
+        .filter(|component| component.flatpak && flatpak_enabled || !component.flatpak && !component.pkgname.is_empty())
+        .map(|component| BrowseEntry {
+            source: if component.flatpak { "flatpak" } else { system_source },
+            package: if component.flatpak { component.id.trim_end_matches(".desktop").to_string() } else { component.pkgname },
+            title: if component.summary.is_empty() { component.name } else { format!("{} - {}", component.name, component.summary) },
+            categories: component.categories,
+        })
+        .collect();
+
+    if backend::active().builds_from_source() {
+        entries.extend(pacman_groups());
+    }
+    entries.sort_by_key(|entry| entry.title.to_lowercase());
+    // A package can ship several components, e.g. an application and its plugins
+    let mut seen = std::collections::HashSet::new();
+    entries.retain(|entry| entry.categories.iter().any(|c| c.starts_with(GROUP_PREFIX)) || seen.insert((entry.source, entry.package.clone())));
+    entries
+}
+
+// `pacman -Sg` prints one `group package` pair per line
+fn pacman_groups() -> Vec<BrowseEntry> {
+    let Ok(output) = remote::command("pacman", ["-Sg"]).run() else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(group, package)| BrowseEntry {
+            source: backend::repo().name(),
+            package: package.to_string(),
+            title: package.to_string(),
+            categories: vec![format!("{}{}", GROUP_PREFIX, group)],
+        })
+        .collect()
+}
+
+// (key, label, entry count) for every category and group that has entries
+pub fn categories(entries: &[BrowseEntry]) -> Vec<(String, String, usize)> {
+    let count = |key: &str| entries.iter().filter(|entry| entry.categories.iter().any(|category| category == key)).count();
+    let mut categories: Vec<(String, String, usize)> = CATEGORIES
+        .iter()
+        .map(|(key, label)| (key.to_string(), label.to_string(), count(key)))
+        .filter(|(_, _, count)| *count > 0)
+        .collect();
+
+    let mut groups: Vec<String> = entries
+        .iter()
+        .flat_map(|entry| &entry.categories)
+        .filter(|category| category.starts_with(GROUP_PREFIX))
+        .cloned()
+        .collect();
+    groups.sort();
+    groups.dedup();
+    categories.extend(groups.into_iter().map(|key| {
+        let label = format!("{} (group)", &key[GROUP_PREFIX.len()..]);
+        let count = count(&key);
+        (key, label, count)
+    }));
+    categories
+}
+
+pub fn in_category<'a>(entries: &'a [BrowseEntry], category: &'a str) -> impl Iterator<Item = &'a BrowseEntry> {
+    entries.iter().filter(move |entry| entry.categories.iter().any(|c| c == category))
+}
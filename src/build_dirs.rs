@@ -0,0 +1,189 @@
+use crate::config::Cleanup;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DAY: i64 = 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+enum Outcome {
+    // Downloaded, and being reviewed, built or installed; or interrupted and waiting to be resumed
+    Pending,
+    Installed,
+    Failed,
+}
+
+// A build directory this app extracted a snapshot to, on this machine
+#[derive(Serialize, Deserialize, Clone)]
+struct Entry {
+    package: String,
+    path: String,
+    outcome: Outcome,
+    // Unix time of the last change of outcome
+    updated: i64,
+}
+
+// Space taken by the build directories of finished installs
+#[derive(Clone, Copy, Default)]
+pub struct Usage {
+    pub directories: usize,
+    pub bytes: u64,
+}
+
+fn registry_path() -> Result<PathBuf, Box<dyn Error>> {
+    let data_dir = dirs::data_dir().ok_or("Could not determine data directory")?;
+    Ok(data_dir.join("aur-helper").join("build-dirs.json"))
+}
+
+// Directories that were deleted by hand are forgotten
+fn load() -> Vec<Entry> {
+    let entries: Vec<Entry> = registry_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    entries.into_iter().filter(|entry| Path::new(&entry.path).exists()).collect()
+}
+
+fn store(entries: &[Entry]) -> Result<(), Box<dyn Error>> {
+    let path = registry_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string(entries)?)?;
+    Ok(())
+}
+
+// Records that the snapshot of `package` was extracted to `path`
+pub fn track(package: &str, path: &str) -> Result<(), Box<dyn Error>> {
+    let mut entries = load();
+    entries.retain(|entry| entry.path != path);
+    entries.push(Entry {
+        package: package.to_string(),
+        path: path.to_string(),
+        outcome: Outcome::Pending,
+        updated: chrono::Utc::now().timestamp(),
+    });
+    store(&entries)
+}
+
+// Records how the install of `package` ended and deletes the build directories `policy` no longer
+// keeps. Returns the deleted directories.
+pub fn finish(package: &str, installed: bool, policy: &Cleanup) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut entries = load();
+    let now = chrono::Utc::now().timestamp();
+    // Later entries count as newer when finished within the same second
+    entries.sort_by_key(|entry| entry.package == package);
+    for entry in entries.iter_mut().filter(|entry| entry.package == package) {
+        entry.outcome = if installed { Outcome::Installed } else { Outcome::Failed };
+        entry.updated = now;
+    }
+    let (expired, kept) = expire(entries, policy, now);
+    let removed = remove(&expired);
+    store(&kept)?;
+    Ok(removed)
+}
+
+// Splits `entries` into those `policy` deletes at `now` and those it keeps. Pending directories
+// age out like failed ones, since an install left pending that long is not coming back.
+fn expire(mut entries: Vec<Entry>, policy: &Cleanup, now: i64) -> (Vec<Entry>, Vec<Entry>) {
+    entries.sort_by_key(|entry| entry.updated);
+    entries.reverse();
+    let mut installed = 0;
+    entries.into_iter().partition(|entry| match entry.outcome {
+        Outcome::Installed => {
+            installed += 1;
+            policy.after_success || installed > policy.keep_recent
+        }
+        Outcome::Failed | Outcome::Pending => now - entry.updated > i64::from(policy.keep_failed_days) * DAY,
+    })
+}
+
+// Deletes the directories of `entries`, returning those that are gone now
+fn remove(entries: &[Entry]) -> Vec<String> {
+    entries
+        .iter()
+        .filter(|entry| match fs::remove_dir_all(&entry.path) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!("Could not delete build directory {}: {}", entry.path, e);
+                false
+            }
+        })
+        .map(|entry| entry.path.clone())
+        .collect()
+}
+
+// Space the build directories of finished installs take, whether or not the policy keeps them
+pub fn usage() -> Usage {
+    let finished: Vec<Entry> = load().into_iter().filter(|entry| entry.outcome != Outcome::Pending).collect();
+    Usage {
+        directories: finished.len(),
+        bytes: finished.iter().map(|entry| size(Path::new(&entry.path))).sum(),
+    }
+}
+
+// Deletes the build directories of every finished install, leaving pending ones for resuming
+pub fn remove_finished() -> Result<Vec<String>, Box<dyn Error>> {
+    let (finished, pending): (Vec<Entry>, Vec<Entry>) = load().into_iter().partition(|entry| entry.outcome != Outcome::Pending);
+    let removed = remove(&finished);
+    let kept: Vec<Entry> = finished.into_iter().filter(|entry| !removed.contains(&entry.path)).chain(pending).collect();
+    store(&kept)?;
+    Ok(removed)
+}
+
+// Size of the files below `path`; symbolic links are not followed
+fn size(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path).map(|entries| entries.flatten().map(|entry| size(&entry.path())).sum()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn deletes_what_the_policy_does_not_keep() {
+        let _isolated = testing::isolate_blocking();
+        let root = testing::temp_dir("build-dirs");
+        let dir = |name: &str| {
+            let path = root.join(name);
+            fs::create_dir_all(&path).unwrap();
+            fs::write(path.join("PKGBUILD"), "pkgname=foo\n").unwrap();
+            path.to_string_lossy().into_owned()
+        };
+        let policy = Cleanup {
+            after_success: false,
+            keep_recent: 1,
+            keep_failed_days: 7,
+        };
+        let (old, new, failed, building) = (dir("old"), dir("new"), dir("failed"), dir("building"));
+        for (package, path) in [("old", &old), ("new", &new), ("failed", &failed), ("building", &building)] {
+            track(package, path).unwrap();
+        }
+
+        finish("old", true, &policy).unwrap();
+        finish("failed", false, &policy).unwrap();
+        assert!(Path::new(&old).exists() && Path::new(&failed).exists());
+        // Only the newest installed directory is kept
+        assert_eq!(finish("new", true, &policy).unwrap(), [old.as_str()]);
+        assert_eq!(usage().directories, 2);
+        assert_eq!(usage().bytes, 2 * "pkgname=foo\n".len() as u64);
+
+        let mut entries = load();
+        entries.iter_mut().find(|entry| entry.package == "failed").unwrap().updated -= 8 * DAY;
+        let (expired, _) = expire(entries, &policy, chrono::Utc::now().timestamp());
+        assert_eq!(expired.iter().map(|entry| entry.path.as_str()).collect::<Vec<_>>(), [failed.as_str()]);
+
+        assert_eq!(remove_finished().unwrap().len(), 2);
+        assert!(Path::new(&building).exists());
+        assert_eq!(usage().directories, 0);
+    }
+}
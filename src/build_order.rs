@@ -0,0 +1,161 @@
+// A package to build, with what it needs to build and run and the names it also goes by
+#[derive(Clone)]
+pub struct Node {
+    pub name: String,
+    pub version: String,
+    pub depends: Vec<String>,
+    pub provides: Vec<String>,
+}
+
+// "foo" for "foo>=1.2" or "foo=1.2-1"
+pub fn dependency_name(dependency: &str) -> &str {
+    dependency.split(['<', '>', '=']).next().unwrap_or(dependency)
+}
+
+// For each node, the other nodes it depends on, by name or by something they provide
+fn prerequisites(nodes: &[Node]) -> Vec<Vec<usize>> {
+    nodes
+        .iter()
+        .enumerate()
+        .map(|(index, node)| {
+            let mut found: Vec<usize> = node
+                .depends
+                .iter()
+                .map(|dependency| dependency_name(dependency))
+                .filter_map(|dependency| {
+                    nodes.iter().enumerate().position(|(other, candidate)| {
+                        other != index && (candidate.name == dependency || candidate.provides.iter().any(|provided| dependency_name(provided) == dependency))
+                    })
+                })
+                .collect();
+            found.sort();
+            found.dedup();
+            found
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mark {
+    New,
+    OnPath,
+    Done,
+}
+
+// Packages of `nodes` that each depend on the next, ending with the first again, e.g.
+// [a, b, a]; None when they can be ordered
+pub fn cycle(nodes: &[Node]) -> Option<Vec<String>> {
+    let prerequisites = prerequisites(nodes);
+    let mut marks = vec![Mark::New; nodes.len()];
+    let mut path = Vec::new();
+    (0..nodes.len())
+        .find_map(|start| visit(start, &prerequisites, &mut marks, &mut path))
+        .map(|cycle| cycle.into_iter().map(|index| nodes[index].name.clone()).collect())
+}
+
+// Depth-first search from `index`; a node met again while still on the path closes a cycle
+fn visit(index: usize, prerequisites: &[Vec<usize>], marks: &mut [Mark], path: &mut Vec<usize>) -> Option<Vec<usize>> {
+    match marks[index] {
+        Mark::Done => return None,
+        Mark::OnPath => {
+            let start = path.iter().position(|&on_path| on_path == index).unwrap_or_default();
+            return Some(path[start..].iter().copied().chain([index]).collect());
+        }
+        Mark::New => {}
+    }
+    marks[index] = Mark::OnPath;
+    path.push(index);
+    for &next in &prerequisites[index] {
+        if let Some(cycle) = visit(next, prerequisites, marks, path) {
+            return Some(cycle);
+        }
+    }
+    path.pop();
+    marks[index] = Mark::Done;
+    None
+}
+
+// Lets `package` of `cycle` be built first by dropping its dependencies on the package after it.
+// Returns them as "name=version", for pacman's --assume-installed.
+pub fn break_cycle(nodes: &mut [Node], cycle: &[String], package: &str) -> Vec<String> {
+    let next = cycle.iter().position(|name| name == package).and_then(|position| cycle.get(position + 1));
+    let Some(next) = next.and_then(|next| nodes.iter().find(|node| node.name == *next)) else {
+        return Vec::new();
+    };
+    // The names the next package answers to, with the version each stands for
+    let mut names = vec![(next.name.clone(), next.version.clone())];
+    names.extend(next.provides.iter().map(|provided| match provided.split_once('=') {
+        Some((name, version)) => (name.to_string(), version.to_string()),
+        None => (provided.clone(), next.version.clone()),
+    }));
+    let mut assumed = Vec::new();
+    if let Some(node) = nodes.iter_mut().find(|node| node.name == package) {
+        node.depends.retain(|dependency| match names.iter().find(|(name, _)| name == dependency_name(dependency)) {
+            Some((name, version)) => {
+                assumed.push(format!("{}={}", name, version));
+                false
+            }
+            None => true,
+        });
+    }
+    assumed
+}
+
+// The names of `nodes` with every package after the ones it depends on, otherwise in the order
+// given. Packages in a dependency cycle, which `cycle` finds, keep their order at the end.
+pub fn order(nodes: &[Node]) -> Vec<String> {
+    let prerequisites = prerequisites(nodes);
+    let mut placed = vec![false; nodes.len()];
+    let mut order = Vec::new();
+    while let Some(next) = (0..nodes.len()).find(|&index| !placed[index] && prerequisites[index].iter().all(|&prerequisite| placed[prerequisite])) {
+        placed[next] = true;
+        order.push(nodes[next].name.clone());
+    }
+    order.extend(nodes.iter().zip(&placed).filter(|(_, placed)| !**placed).map(|(node, _)| node.name.clone()));
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, depends: &[&str], provides: &[&str]) -> Node {
+        Node {
+            name: name.to_string(),
+            version: "1.0-1".to_string(),
+            depends: depends.iter().map(|name| name.to_string()).collect(),
+            provides: provides.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn builds_prerequisites_first_and_keeps_the_rest_in_order() {
+        let nodes = [
+            node("app", &["libfoo>=2", "glibc"], &[]),
+            node("other", &[], &[]),
+            node("libfoo-git", &["libbar"], &["libfoo=2.1"]),
+            node("libbar", &[], &[]),
+        ];
+        assert_eq!(order(&nodes), ["other", "libbar", "libfoo-git", "app"]);
+
+        let independent = [node("b", &[], &[]), node("a", &[], &[])];
+        assert_eq!(order(&independent), ["b", "a"]);
+        assert!(cycle(&nodes).is_none());
+    }
+
+    #[test]
+    fn finds_a_cycle_and_breaks_it_at_the_chosen_package() {
+        let mut nodes = vec![
+            node("other", &[], &[]),
+            node("a", &["b"], &[]),
+            node("b", &["c>=2"], &[]),
+            node("c-git", &["a", "other"], &["c=2.5"]),
+        ];
+        let found = cycle(&nodes).unwrap();
+        assert_eq!(found, ["a", "b", "c-git", "a"]);
+
+        assert_eq!(break_cycle(&mut nodes, &found, "b"), ["c=2.5"]);
+        assert!(cycle(&nodes).is_none());
+        assert_eq!(order(&nodes), ["other", "b", "a", "c-git"]);
+    }
+}
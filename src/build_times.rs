@@ -0,0 +1,65 @@
+use crate::state;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::time::Duration;
+
+// Builds kept per package; the estimate is their median, so one odd build does not skew it
+const KEPT_BUILDS: usize = 5;
+
+// A past successful build, kept in the state file by package name
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Build {
+    version: String,
+    seconds: u64,
+    // Unix time the build finished
+    finished: i64,
+}
+
+pub fn record(package: &str, version: &str, duration: Duration) -> Result<(), Box<dyn Error>> {
+    state::update(|state| {
+        let builds = state.builds.entry(package.to_string()).or_default();
+        builds.push(Build {
+            version: version.to_string(),
+            seconds: duration.as_secs(),
+            finished: chrono::Utc::now().timestamp(),
+        });
+        builds.drain(..builds.len().saturating_sub(KEPT_BUILDS));
+    })
+}
+
+// How long building `package` usually takes; None before its first recorded build
+pub fn estimate(package: &str) -> Option<Duration> {
+    let mut seconds: Vec<u64> = state::read().builds.get(package)?.iter().map(|build| build.seconds).collect();
+    seconds.sort_unstable();
+    seconds.get(seconds.len() / 2).map(|median| Duration::from_secs(*median))
+}
+
+// "Building for 2 min 10 s, about 3 min 05 s left", or how much longer than usual it takes
+pub fn describe(elapsed: Duration, estimate: Option<Duration>) -> String {
+    let building = format!("Building for {}", crate::format_duration(elapsed));
+    match estimate {
+        Some(estimate) if elapsed < estimate => format!("{}, about {} left", building, crate::format_duration(estimate - elapsed)),
+        Some(estimate) => format!("{}, longer than the usual {}", building, crate::format_duration(estimate)),
+        None => building,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn estimates_from_recent_builds() {
+        let _isolated = testing::isolate_blocking();
+        assert!(estimate("foo").is_none());
+        for seconds in [600, 90, 100, 120, 110, 95] {
+            record("foo", "1.0-1", Duration::from_secs(seconds)).unwrap();
+        }
+        // The first build fell out of the history, and the median ignores the slowest
+        assert_eq!(estimate("foo"), Some(Duration::from_secs(100)));
+
+        assert_eq!(describe(Duration::from_secs(40), Some(Duration::from_secs(100))), "Building for 40 s, about 1 min 00 s left");
+        assert_eq!(describe(Duration::from_secs(130), Some(Duration::from_secs(100))), "Building for 2 min 10 s, longer than the usual 1 min 40 s");
+    }
+}
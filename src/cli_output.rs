@@ -0,0 +1,108 @@
+use crate::troubleshoot::Hint;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::io::{self, IsTerminal};
+use std::time::Duration;
+
+pub struct SummaryRow {
+    pub package: String,
+    pub ok: bool,
+    pub detail: String,
+    pub elapsed: Duration,
+}
+
+// Terminal output for CLI mode: progress bars and colors only when stdout is a TTY
+pub struct CliOutput {
+    interactive: bool,
+    quiet: bool,
+}
+
+impl CliOutput {
+    pub fn new(quiet: bool) -> CliOutput {
+        CliOutput {
+            interactive: io::stdout().is_terminal(),
+            quiet,
+        }
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.interactive {
+            format!("\x1b[{}m{}\x1b[0m", color, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    pub fn ok(&self, message: &str) {
+        if !self.quiet {
+            println!("{} {}", self.paint("32", "ok"), message);
+        }
+    }
+
+    // Plain information, such as the order packages are built in
+    pub fn note(&self, message: &str) {
+        if !self.quiet {
+            println!("{}", message);
+        }
+    }
+
+    pub fn fail(&self, message: &str) {
+        eprintln!("{} {}", self.paint("31", "fail"), message);
+    }
+
+    // Printed below a failure, even with --quiet, since they say how to fix it
+    pub fn hints(&self, hints: &[Hint]) {
+        for hint in hints {
+            eprintln!("  {} {}", self.paint("33", "hint"), hint.problem);
+            eprintln!("       {}", hint.fix);
+        }
+    }
+
+    pub fn progress_bar(&self) -> Option<ProgressBar> {
+        if !self.interactive || self.quiet {
+            return None;
+        }
+        let bar = ProgressBar::new_spinner();
+        bar.enable_steady_tick(Duration::from_millis(120));
+        Some(bar)
+    }
+
+    pub fn summary(&self, rows: &[SummaryRow]) {
+        if self.quiet || rows.is_empty() {
+            return;
+        }
+        let width = rows.iter().map(|row| row.package.len()).max().unwrap_or(0).max("Package".len());
+        println!();
+        println!("{:<width$}  {:<6}  {:>8}  Detail", "Package", "Status", "Time", width = width);
+        for row in rows {
+            let status = if row.ok { self.paint("32", "ok    ") } else { self.paint("31", "failed") };
+            println!(
+                "{:<width$}  {}  {:>7.1}s  {}",
+                row.package,
+                status,
+                row.elapsed.as_secs_f32(),
+                row.detail,
+                width = width
+            );
+        }
+    }
+}
+
+pub fn start_download(bar: &ProgressBar, total: Option<u64>) {
+    match total {
+        Some(total) => {
+            bar.set_style(
+                ProgressStyle::with_template("{msg} [{bar:30}] {bytes}/{total_bytes} ({bytes_per_sec})")
+                    .unwrap()
+                    .progress_chars("=> "),
+            );
+            bar.set_length(total);
+        }
+        None => bar.set_style(ProgressStyle::with_template("{spinner} {msg} {bytes}").unwrap()),
+    }
+    bar.set_position(0);
+}
+
+pub fn start_spinner(bar: &ProgressBar, message: &str) {
+    bar.set_style(ProgressStyle::with_template("{spinner} {elapsed:>4} {msg}").unwrap());
+    bar.set_message(message.to_string());
+}
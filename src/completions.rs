@@ -0,0 +1,75 @@
+use clap_complete::engine::CompletionCandidate;
+use clap_complete::env::Shells;
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process::Command as StdCommand;
+
+// Environment variable the generated scripts set when calling back into the binary for candidates
+pub const COMPLETE_VAR: &str = "COMPLETE";
+
+pub const SHELLS: [&str; 3] = ["bash", "zsh", "fish"];
+
+// AUR names seen in search results; completing against the full AUR would need a network call per keypress
+fn aur_cache_path() -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("aur-helper").join("aur-packages"))
+}
+
+pub fn remember_aur_names(names: &[String]) -> Result<(), Box<dyn Error>> {
+    let path = aur_cache_path().ok_or("Could not determine cache directory")?;
+    let mut cached: BTreeSet<String> = fs::read_to_string(&path)
+        .unwrap_or_default()
+        .lines()
+        .map(str::to_string)
+        .collect();
+    let before = cached.len();
+    cached.extend(names.iter().filter(|name| !name.is_empty()).cloned());
+    if cached.len() == before {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, cached.into_iter().map(|name| name + "\n").collect::<String>())?;
+    Ok(())
+}
+
+pub fn cached_aur_names() -> Vec<String> {
+    let Some(path) = aur_cache_path() else {
+        return Vec::new();
+    };
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+pub fn aur_packages() -> Vec<CompletionCandidate> {
+    cached_aur_names().into_iter().map(CompletionCandidate::new).collect()
+}
+
+pub fn installed_packages() -> Vec<CompletionCandidate> {
+    let Ok(output) = StdCommand::new("pacman").arg("-Qmq").output() else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+// Prints a script that asks this binary for candidates at completion time, so package names stay current
+pub fn print_registration(shell: &str) -> Result<(), Box<dyn Error>> {
+    let shells = Shells::builtins();
+    let completer = shells.completer(shell).ok_or(format!("Unsupported shell: {}", shell))?;
+    let exe = std::env::current_exe()?;
+    let bin = std::env::args()
+        .next()
+        .and_then(|arg0| PathBuf::from(arg0).file_name().map(|name| name.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "aur-helper".to_string());
+    completer.write_registration(COMPLETE_VAR, &bin.replace('-', "_"), &bin, &exe.to_string_lossy(), &mut io::stdout())?;
+    Ok(())
+}
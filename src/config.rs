@@ -0,0 +1,350 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+// Shell commands run around package operations. Each receives the package name as $1;
+// a failing pre_* hook aborts the operation.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hooks {
+    pub pre_install: Option<String>,
+    pub post_install: Option<String>,
+    pub pre_remove: Option<String>,
+    pub post_update: Option<String>,
+}
+
+// Which build directories of finished installs are deleted
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Cleanup {
+    // Delete a build directory as soon as its packages are installed
+    pub after_success: bool,
+    // Otherwise the directories of this many installed packages are kept, newest first
+    pub keep_recent: u32,
+    // Days the directory of a failed install is kept for looking into the failure
+    pub keep_failed_days: u32,
+}
+
+impl Default for Cleanup {
+    fn default() -> Cleanup {
+        Cleanup {
+            after_success: false,
+            keep_recent: 5,
+            keep_failed_days: 7,
+        }
+    }
+}
+
+// Steps that ask before continuing; all are skipped when `no_confirm` is set
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Confirmations {
+    // Show what an AUR package pulls in before downloading it
+    pub dependencies: bool,
+    // Offer a full upgrade before installing from sync databases newer than the installed packages
+    pub partial_upgrade: bool,
+    // Show the PKGBUILD review before building
+    pub review: bool,
+    // Show the list of packages about to be installed or upgraded
+    pub transaction: bool,
+    pub removal: bool,
+}
+
+impl Default for Confirmations {
+    fn default() -> Confirmations {
+        Confirmations {
+            dependencies: true,
+            partial_upgrade: true,
+            review: true,
+            transaction: true,
+            removal: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum ConfirmStep {
+    Dependencies,
+    PartialUpgrade,
+    Review,
+    Transaction,
+    Removal,
+}
+
+// An Arch machine managed over SSH; key-based login is required since ssh runs without a terminal
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HostProfile {
+    // user@host as passed to ssh
+    pub address: String,
+    pub port: Option<u16>,
+    pub identity_file: Option<String>,
+    pub build_dir: String,
+    // Must not ask for a password, e.g. sudo with NOPASSWD for pacman
+    pub escalation_tool: String,
+}
+
+impl Default for HostProfile {
+    fn default() -> HostProfile {
+        HostProfile {
+            address: String::new(),
+            port: None,
+            identity_file: None,
+            build_dir: "/tmp/aur-helper".to_string(),
+            escalation_tool: "sudo".to_string(),
+        }
+    }
+}
+
+// A package kept at the version it had when it was held: update checks and "Update all" skip it
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hold {
+    pub version: String,
+    pub reason: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemePreference {
+    System,
+    Dark,
+    Light,
+}
+
+// How pacman removes a package; each mode also removes what the previous ones do
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RemoveMode {
+    // -R: only the package; changed config files are saved as .pacsave
+    Package,
+    // -Rs: also dependencies nothing else needs
+    Dependencies,
+    // -Rns: also the saved config files
+    Purge,
+    // -Rnsc: also every package that depends on it
+    Cascade,
+}
+
+impl RemoveMode {
+    pub const ALL: [RemoveMode; 4] = [RemoveMode::Package, RemoveMode::Dependencies, RemoveMode::Purge, RemoveMode::Cascade];
+
+    pub fn flag(self) -> &'static str {
+        match self {
+            RemoveMode::Package => "-R",
+            RemoveMode::Dependencies => "-Rs",
+            RemoveMode::Purge => "-Rns",
+            RemoveMode::Cascade => "-Rnsc",
+        }
+    }
+
+    // Value used in the config file and on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            RemoveMode::Package => "package",
+            RemoveMode::Dependencies => "dependencies",
+            RemoveMode::Purge => "purge",
+            RemoveMode::Cascade => "cascade",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<RemoveMode> {
+        RemoveMode::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RemoveMode::Package => "Package only",
+            RemoveMode::Dependencies => "With unneeded dependencies",
+            RemoveMode::Purge => "With dependencies and configs",
+            RemoveMode::Cascade => "With packages that depend on it",
+        }
+    }
+
+    pub fn explanation(self) -> &'static str {
+        match self {
+            RemoveMode::Package => "Removes just this package. Dependencies stay installed and modified config files are kept as .pacsave.",
+            RemoveMode::Dependencies => "Also removes dependencies that no other package needs. Modified config files are kept as .pacsave.",
+            RemoveMode::Purge => "Also removes unneeded dependencies and deletes the saved config files.",
+            RemoveMode::Cascade => "Also removes every package that depends on this one, with their unneeded dependencies and configs.",
+        }
+    }
+
+    // pacman's arguments to remove `package`
+    pub fn pacman_args(self, package: &str) -> Vec<String> {
+        vec![self.flag().to_string(), package.to_string(), "--noconfirm".to_string()]
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    // Snapshots are extracted to <build_dir>/<package>
+    pub build_dir: String,
+    pub cleanup: Cleanup,
+    pub makepkg_flags: Vec<String>,
+    // pacman repositories with prebuilt AUR packages, offered before building from source
+    pub binary_repos: Vec<String>,
+    // Build with one make job per core when makepkg.conf's MAKEFLAGS has no -j
+    pub parallel_builds: bool,
+    // Upper bound on those jobs; 0 uses every core
+    pub max_build_jobs: u32,
+    // Queued packages built at once after their review; 1 builds one after another
+    pub concurrent_builds: u32,
+    // Run makepkg under nice and ionice
+    pub background_builds: bool,
+    // CPUQuota in percent for background builds, applied through systemd-run; 0 for no limit
+    pub background_cpu_quota: u32,
+    // podman or docker to build in a throwaway container; empty builds on the host
+    pub container: String,
+    pub container_image: String,
+    // Terminal emulator for "Open terminal here"; empty tries $TERMINAL, then common ones
+    pub terminal: String,
+    // Command used to run pacman as root: sudo, doas, pkexec or "auto"
+    pub escalation_tool: String,
+    // Preselected in the uninstall confirmation and used by `remove` on the command line
+    pub remove_mode: RemoveMode,
+    pub theme: ThemePreference,
+    // Hex colors like "#3584e4"; unset keeps the theme's defaults
+    pub accent_color: Option<String>,
+    pub background_color: Option<String>,
+    // Zoom factor on top of the display's native scaling
+    pub ui_scale: f32,
+    pub font_size: f32,
+    // j/k, /, Enter and dd in package lists
+    pub vim_mode: bool,
+    pub notifications: bool,
+    pub update_interval_hours: u32,
+    // Off by default: looking for a newer release of the app contacts GitHub at every start
+    pub check_app_updates: bool,
+    // How long fetched AUR metadata is shown before it is fetched again
+    pub metadata_ttl_minutes: u32,
+    // Limits for network requests: establishing a connection, and waiting between bytes of a reply
+    pub connect_timeout_secs: u64,
+    pub read_timeout_secs: u64,
+    // The AUR's web and RPC address, for a mirror, a private aurweb instance or a test server
+    pub aur_url: String,
+    // Where snapshots are downloaded from when that is another server; empty uses aur_url
+    pub snapshot_url: String,
+    // Tried in turn when a snapshot cannot be downloaded: servers with the same snapshot paths, or
+    // git repositories ending in .git with a branch per package base
+    pub snapshot_mirrors: Vec<String>,
+    // Where the PGP keys in a PKGBUILD's validpgpkeys are imported from
+    pub keyserver: String,
+    // Ask the servers of a PKGBUILD's sources whether they are there before it is reviewed
+    pub check_sources: bool,
+    // Download the sources and compare them with the PKGBUILD's checksums before the review
+    pub verify_sources: bool,
+    // Bearer token `cookin serve` requires; empty generates one at every start. Required for
+    // listening on an address other than loopback.
+    pub api_token: String,
+    pub hooks: Hooks,
+    pub no_confirm: bool,
+    pub confirm: Confirmations,
+    // pacman, apt or "auto" to detect the package manager of the active host
+    pub backend: String,
+    // List Flathub apps next to the backend's packages when flatpak is installed
+    pub flatpak: bool,
+    // Off by default since most Arch systems do not run snapd
+    pub snap: bool,
+    // Held packages by name
+    pub holds: BTreeMap<String, Hold>,
+    // Also list held packages in IgnorePkg, so plain `pacman -Syu` keeps them too
+    pub holds_in_ignore_pkg: bool,
+    // Name of the entry in `hosts` to manage; unset manages this machine
+    pub active_host: Option<String>,
+    pub hosts: BTreeMap<String, HostProfile>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            build_dir: "/tmp".to_string(),
+            cleanup: Cleanup::default(),
+            makepkg_flags: vec!["-s".to_string(), "--noconfirm".to_string()],
+            binary_repos: vec!["chaotic-aur".to_string()],
+            parallel_builds: true,
+            max_build_jobs: 0,
+            concurrent_builds: 1,
+            background_builds: false,
+            background_cpu_quota: 0,
+            container: String::new(),
+            container_image: "docker.io/library/archlinux:base-devel".to_string(),
+            terminal: String::new(),
+            escalation_tool: crate::escalation::AUTO.to_string(),
+            remove_mode: RemoveMode::Purge,
+            theme: ThemePreference::System,
+            accent_color: None,
+            background_color: None,
+            ui_scale: 1.0,
+            font_size: 12.5,
+            vim_mode: false,
+            notifications: true,
+            update_interval_hours: 6,
+            check_app_updates: false,
+            metadata_ttl_minutes: 60,
+            connect_timeout_secs: 10,
+            read_timeout_secs: 30,
+            aur_url: crate::http::DEFAULT_AUR_URL.to_string(),
+            snapshot_url: String::new(),
+            snapshot_mirrors: Vec::new(),
+            keyserver: crate::pgp_keys::DEFAULT_KEYSERVER.to_string(),
+            check_sources: true,
+            verify_sources: true,
+            api_token: String::new(),
+            hooks: Hooks::default(),
+            no_confirm: false,
+            confirm: Confirmations::default(),
+            backend: crate::backend::AUTO.to_string(),
+            flatpak: true,
+            snap: false,
+            holds: BTreeMap::new(),
+            holds_in_ignore_pkg: false,
+            active_host: None,
+            hosts: BTreeMap::new(),
+        }
+    }
+}
+
+pub fn config_path() -> Result<PathBuf, Box<dyn Error>> {
+    let config_dir = dirs::config_dir().ok_or("Could not determine config directory")?;
+    Ok(config_dir.join("aur-helper").join("config.toml"))
+}
+
+impl Config {
+    // A missing file is not an error; the defaults are used instead
+    pub fn load() -> Result<Config, Box<dyn Error>> {
+        let path = config_path()?;
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        let content = fs::read_to_string(&path)?;
+        toml::from_str(&content).map_err(|e| format!("Invalid config file {}: {}", path.display(), e).into())
+    }
+
+    pub fn confirms(&self, step: ConfirmStep) -> bool {
+        !self.no_confirm
+            && match step {
+                ConfirmStep::Dependencies => self.confirm.dependencies,
+                ConfirmStep::PartialUpgrade => self.confirm.partial_upgrade,
+                ConfirmStep::Review => self.confirm.review,
+                ConfirmStep::Transaction => self.confirm.transaction,
+                ConfirmStep::Removal => self.confirm.removal,
+            }
+    }
+
+    pub fn is_held(&self, package: &str) -> bool {
+        self.holds.contains_key(package)
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = config_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
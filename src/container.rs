@@ -0,0 +1,55 @@
+use crate::config::Config;
+use crate::remote;
+use crate::runner::Run;
+use std::error::Error;
+
+pub const TOOLS: [&str; 2] = ["podman", "docker"];
+
+// Where the build directory is mounted in the container
+const MOUNT: &str = "/build";
+
+// Runs as root in a fresh container: installs the toolchain, builds as an unprivileged user
+// (makepkg refuses to run as root) and hands the directory back to the host user.
+// Packages land in the mounted build directory since the image's makepkg.conf sets no PKGDEST.
+const SCRIPT: &str = r#"
+set -e
+pacman -Syu --noconfirm --needed base-devel sudo >/dev/null
+useradd -m builder
+echo 'builder ALL=(ALL) NOPASSWD: ALL' > /etc/sudoers.d/builder
+chown -R builder: /build
+trap 'chown -R "$OWNER" /build' EXIT
+su builder -c "cd /build && $BUILD"
+"#;
+
+// The container tool's command line that builds `build_dir` with `makepkg` (the makepkg command
+// and its arguments, run as the unprivileged user)
+pub fn command(config: &Config, build_dir: &str, makepkg: &[String]) -> Result<Vec<String>, Box<dyn Error>> {
+    let tool = config.container.as_str();
+    let mut command: Vec<String> = vec![tool.to_string(), "run".to_string(), "--rm".to_string()];
+    command.extend(["-v".to_string(), format!("{}:{}", build_dir, MOUNT)]);
+    command.extend(["-e".to_string(), format!("OWNER={}", owner(tool, build_dir)?)]);
+    command.extend(["-e".to_string(), format!("BUILD={}", makepkg.iter().map(|arg| shell_quote(arg)).collect::<Vec<_>>().join(" "))]);
+    if config.background_builds && config.background_cpu_quota > 0 {
+        command.push(format!("--cpus={:.2}", config.background_cpu_quota as f64 / 100.0));
+    }
+    command.push(config.container_image.clone());
+    command.extend(["bash".to_string(), "-c".to_string(), SCRIPT.to_string()]);
+    Ok(command)
+}
+
+// Rootless podman maps the container's root to the host user; docker's root is the host's, so
+// files are given back to whoever owns the build directory
+fn owner(tool: &str, build_dir: &str) -> Result<String, Box<dyn Error>> {
+    if tool == "podman" {
+        return Ok("0:0".to_string());
+    }
+    let output = remote::command("stat", ["-c", "%u:%g", build_dir]).run()?;
+    if !output.status.success() {
+        return Err(format!("Could not read the owner of {}: {}", build_dir, String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
@@ -0,0 +1,126 @@
+use crate::diagnostics;
+use chrono::Local;
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fs;
+use std::panic::{self, PanicHookInfo};
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError, TryLockError};
+use std::thread;
+
+// Operations listed in a crash report, most recent last
+const KEPT_OPERATIONS: usize = 20;
+// Names the report of a crash the GUI has not shown yet
+const PENDING_FILE: &str = "last-crash";
+
+static OPERATIONS: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+// A panic in a background task, which the GUI reports instead of spinning forever
+static BACKGROUND_PANIC: Mutex<Option<String>> = Mutex::new(None);
+
+fn crash_dir() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(diagnostics::state_dir()?.join("crashes"))
+}
+
+// Called for every operation that starts, so a report tells what led up to a crash
+pub fn note_operation(title: &str) {
+    let mut operations = OPERATIONS.lock().unwrap_or_else(PoisonError::into_inner);
+    if operations.len() == KEPT_OPERATIONS {
+        operations.pop_front();
+    }
+    operations.push_back(format!("{} {}", Local::now().format("%Y-%m-%d %H:%M:%S"), title));
+}
+
+// Writes a crash report for every panic, after the usual message on stderr. A panic on the main
+// thread ends the program and is shown on the next launch; one in a background task is reported
+// by the running GUI.
+pub fn install() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        let message = describe(info);
+        let saved = write_report(&report(&message));
+        match &saved {
+            Ok(path) => eprintln!("A crash report was saved to {}", path.display()),
+            Err(e) => eprintln!("Could not save a crash report: {}", e),
+        }
+        if thread::current().name() == Some("main") {
+            if let Ok(path) = &saved {
+                let _ = crash_dir().and_then(|dir| Ok(fs::write(dir.join(PENDING_FILE), path.to_string_lossy().as_bytes())?));
+            }
+        } else {
+            let report = saved.map(|path| format!("; report saved to {}", path.display())).unwrap_or_default();
+            *BACKGROUND_PANIC.lock().unwrap_or_else(PoisonError::into_inner) = Some(message + &report);
+        }
+    }));
+}
+
+// "<message> at <file>:<line>:<column>"
+fn describe(info: &PanicHookInfo) -> String {
+    let message = info.payload_as_str().unwrap_or("unknown panic");
+    match info.location() {
+        Some(location) => format!("{} at {}", message, location),
+        None => message.to_string(),
+    }
+}
+
+fn report(message: &str) -> String {
+    let operations: Vec<String> = match OPERATIONS.try_lock() {
+        Ok(operations) => operations.iter().cloned().collect(),
+        Err(TryLockError::Poisoned(operations)) => operations.into_inner().iter().cloned().collect(),
+        // Held by the thread that panicked
+        Err(TryLockError::WouldBlock) => Vec::new(),
+    };
+    let mut report = format!("{} crashed at {}\n", env!("CARGO_PKG_NAME"), Local::now().format("%Y-%m-%d %H:%M:%S"));
+    report.push_str(&format!("Version: {} ({} {})\n", env!("CARGO_PKG_VERSION"), std::env::consts::OS, std::env::consts::ARCH));
+    report.push_str(&format!("Thread: {}\n", thread::current().name().unwrap_or("unnamed")));
+    report.push_str(&format!("Panic: {}\n", message));
+    report.push_str("\nRecent operations:\n");
+    if operations.is_empty() {
+        report.push_str("  none\n");
+    }
+    for operation in &operations {
+        report.push_str(&format!("  {}\n", operation));
+    }
+    report.push_str(&format!("\nBacktrace:\n{}\n", Backtrace::force_capture()));
+    report
+}
+
+fn write_report(report: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let dir = crash_dir()?;
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("crash-{}.log", Local::now().format("%Y%m%d-%H%M%S")));
+    fs::write(&path, report)?;
+    Ok(path)
+}
+
+// The report of a crash in the last session, once; the GUI shows it at startup
+pub fn take_pending() -> Option<PathBuf> {
+    let marker = crash_dir().ok()?.join(PENDING_FILE);
+    let path = fs::read_to_string(&marker).ok()?;
+    let _ = fs::remove_file(&marker);
+    Some(PathBuf::from(path)).filter(|path| path.is_file())
+}
+
+// What panicked in a background task since the last call
+pub fn take_background_panic() -> Option<String> {
+    BACKGROUND_PANIC.lock().unwrap_or_else(PoisonError::into_inner).take()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_list_the_latest_operations() {
+        for index in 0..KEPT_OPERATIONS + 5 {
+            note_operation(&format!("Install package-{}", index));
+        }
+        let report = report("index out of bounds at src/main.rs:1:1");
+        assert!(report.contains("Panic: index out of bounds at src/main.rs:1:1\n"), "{}", report);
+        assert!(report.contains(&format!("Version: {}", env!("CARGO_PKG_VERSION"))));
+        assert!(!report.contains("Install package-4\n"));
+        assert!(report.contains("Install package-5\n") && report.contains("Install package-24\n"));
+        assert!(report.contains("\nBacktrace:\n"));
+    }
+}
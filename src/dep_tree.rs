@@ -0,0 +1,183 @@
+use crate::build_order::dependency_name;
+use crate::remote;
+use crate::runner::Run;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Origin {
+    Installed,
+    Repo,
+    Aur,
+    // Neither installed, in a sync repository nor in the AUR
+    Missing,
+}
+
+impl Origin {
+    pub fn label(self) -> &'static str {
+        match self {
+            Origin::Installed => "installed",
+            Origin::Repo => "repo",
+            Origin::Aur => "AUR",
+            Origin::Missing => "not found",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Row {
+    pub depth: usize,
+    pub name: String,
+    // Empty for installed packages
+    pub version: String,
+    pub origin: Origin,
+}
+
+// Everything installing `package` brings along, as rows in the order they are shown, each
+// dependency under the package that needs it
+pub struct Tree {
+    pub package: String,
+    pub rows: Vec<Row>,
+}
+
+impl Tree {
+    pub fn count(&self, origin: Origin) -> usize {
+        self.rows.iter().filter(|row| row.origin == origin).count()
+    }
+
+    // True when installing needs nothing that is not installed yet
+    pub fn is_satisfied(&self) -> bool {
+        self.rows.iter().all(|row| row.origin == Origin::Installed)
+    }
+
+    // Plain-text form for the terminal, indented by depth
+    pub fn lines(&self) -> Vec<String> {
+        self.rows
+            .iter()
+            .map(|row| {
+                let version = if row.version.is_empty() { String::new() } else { format!(" {}", row.version) };
+                format!("{}{}{} ({})", "  ".repeat(row.depth), row.name, version, row.origin.label())
+            })
+            .collect()
+    }
+}
+
+// Resolves the dependencies of the AUR package `name`, whose build and run dependencies are
+// `depends`: installed ones and repository packages end a branch, AUR packages are followed
+pub async fn resolve(name: &str, depends: &[String]) -> Result<Tree, Box<dyn Error>> {
+    let mut seen: HashSet<String> = HashSet::from([name.to_string()]);
+    let mut children: HashMap<String, Vec<Row>> = HashMap::new();
+    let mut pending = vec![(name.to_string(), depends.to_vec())];
+    while !pending.is_empty() {
+        let mut aur = Vec::new();
+        for (parent, depends) in pending.drain(..) {
+            let rows = classify(&depends, &mut seen);
+            aur.extend(rows.iter().filter(|row| row.origin == Origin::Aur).map(|row| row.name.clone()));
+            children.insert(parent, rows);
+        }
+        if aur.is_empty() {
+            break;
+        }
+        let found = crate::fetch_metadata_batch(&aur).await?;
+        for rows in children.values_mut() {
+            for row in rows.iter_mut().filter(|row| row.origin == Origin::Aur && row.version.is_empty()) {
+                match found.iter().find(|package| package.name == row.name) {
+                    Some(package) => row.version = package.version.clone(),
+                    None => row.origin = Origin::Missing,
+                }
+            }
+        }
+        pending = found.into_iter().map(|package| (package.name, package.depends)).collect();
+    }
+    let mut rows = Vec::new();
+    flatten(name, 0, &children, &mut rows);
+    Ok(Tree { package: name.to_string(), rows })
+}
+
+fn flatten(parent: &str, depth: usize, children: &HashMap<String, Vec<Row>>, rows: &mut Vec<Row>) {
+    for row in children.get(parent).into_iter().flatten() {
+        rows.push(Row { depth: depth + row.depth, ..row.clone() });
+        if row.origin == Origin::Aur {
+            flatten(&row.name, depth + 1, children, rows);
+        }
+    }
+}
+
+// One level of the tree: each of `depends` not listed before, as installed, from the repositories
+// along with what pacman would pull in for it (one level deeper), or as an AUR package whose
+// version is filled in later
+fn classify(depends: &[String], seen: &mut HashSet<String>) -> Vec<Row> {
+    let depends: Vec<&String> = depends.iter().filter(|dependency| seen.insert(dependency_name(dependency).to_string())).collect();
+    if depends.is_empty() {
+        return Vec::new();
+    }
+    // `pacman -T` prints the dependencies that are not satisfied, minding versions and provides
+    let missing: Vec<String> = remote::command("pacman", std::iter::once("-T").chain(depends.iter().map(|dependency| dependency.as_str())))
+        .run()
+        .map(|output| String::from_utf8_lossy(&output.stdout).lines().map(str::to_string).collect())
+        .unwrap_or_default();
+    let mut rows = Vec::new();
+    for dependency in depends {
+        let name = dependency_name(dependency).to_string();
+        if !missing.contains(dependency) {
+            rows.push(Row { depth: 0, name, version: String::new(), origin: Origin::Installed });
+            continue;
+        }
+        match from_repos(dependency) {
+            // pacman lists what a target needs before the target itself
+            Some(mut packages) => {
+                let (name, version) = packages.pop().unwrap_or((name, String::new()));
+                seen.insert(name.clone());
+                rows.push(Row { depth: 0, name, version, origin: Origin::Repo });
+                for (name, version) in packages.into_iter().filter(|(name, _)| seen.insert(name.clone())) {
+                    rows.push(Row { depth: 1, name, version, origin: Origin::Repo });
+                }
+            }
+            None => rows.push(Row { depth: 0, name, version: String::new(), origin: Origin::Aur }),
+        }
+    }
+    rows
+}
+
+// What `pacman -S` would install for `dependency`, as (name, version); None when no sync
+// repository has it
+fn from_repos(dependency: &str) -> Option<Vec<(String, String)>> {
+    let output = remote::command("pacman", ["-Sp", "--print-format", "%n %v", dependency]).run().ok().filter(|output| output.status.success())?;
+    let packages: Vec<(String, String)> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(name, version)| (name.to_string(), version.to_string()))
+        .collect();
+    (!packages.is_empty()).then_some(packages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Scripted};
+
+    #[test]
+    fn sorts_dependencies_by_where_they_come_from() {
+        let _isolated = testing::isolate_blocking();
+        Scripted::default()
+            .answer("pacman -T glibc qt6-base>=6.5 libfoo", "qt6-base>=6.5\nlibfoo\n")
+            .answer("pacman -Sp --print-format %n %v qt6-base>=6.5", "double-conversion 3.3.0-1\nqt6-base 6.7.2-1\n")
+            .fail("pacman -Sp --print-format %n %v libfoo", "error: target not found: libfoo")
+            .install();
+
+        let depends = ["glibc", "qt6-base>=6.5", "libfoo", "glibc"].map(str::to_string);
+        let mut seen = HashSet::from(["app".to_string()]);
+        let rows = classify(&depends, &mut seen);
+        let children = HashMap::from([("app".to_string(), rows), ("libfoo".to_string(), Vec::new())]);
+        let mut rows = Vec::new();
+        flatten("app", 0, &children, &mut rows);
+        let tree = Tree { package: "app".to_string(), rows };
+
+        assert_eq!(
+            tree.lines(),
+            ["glibc (installed)", "qt6-base 6.7.2-1 (repo)", "  double-conversion 3.3.0-1 (repo)", "libfoo (AUR)"]
+        );
+        assert_eq!(tree.count(Origin::Repo), 2);
+        assert!(!tree.is_satisfied());
+    }
+}
@@ -0,0 +1,63 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command as StdCommand;
+
+// The desktop file's name, which is also the window's app id so that launchers match the two
+pub const APP_ID: &str = "cookin";
+
+const DESKTOP_FILE: &str = include_str!("../data/cookin.desktop");
+pub const ICON_PNG: &[u8] = include_bytes!("../data/cookin.png");
+const ICON_SVG: &[u8] = include_bytes!("../data/cookin.svg");
+
+// The packaged desktop file launches `cookin` from PATH; a user's entry launches `exec`
+fn contents(exec: &Path) -> String {
+    // The Exec key splits its value at spaces unless quoted
+    let exec = exec.display().to_string();
+    let exec = if exec.contains(' ') { format!("\"{}\"", exec) } else { exec };
+    DESKTOP_FILE.replace("\nExec=cookin\n", &format!("\nExec={}\n", exec))
+}
+
+// Writes the desktop file and icons to the user's XDG data directory, launching the running
+// executable. Returns the files written.
+pub fn install() -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let data_dir = dirs::data_dir().ok_or("Could not determine data directory")?;
+    let exec = std::env::current_exe()?;
+    let applications = data_dir.join("applications");
+    let icons = data_dir.join("icons").join("hicolor");
+    let files = [
+        (applications.join(format!("{}.desktop", APP_ID)), contents(&exec).into_bytes()),
+        (icons.join("256x256").join("apps").join(format!("{}.png", APP_ID)), ICON_PNG.to_vec()),
+        (icons.join("scalable").join("apps").join(format!("{}.svg", APP_ID)), ICON_SVG.to_vec()),
+    ];
+    for (path, content) in &files {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)?;
+    }
+    // Launchers that cache entries pick up the new one sooner; neither tool is required
+    let _ = StdCommand::new("update-desktop-database").arg(&applications).status();
+    let _ = StdCommand::new("gtk-update-icon-cache").args(["--quiet", "--ignore-theme-index"]).arg(&icons).status();
+    Ok(files.into_iter().map(|(path, _)| path).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn installs_the_entry_and_icons_for_the_user() {
+        let _isolated = testing::isolate_blocking();
+        let files = install().unwrap();
+        let data_dir = dirs::data_dir().unwrap();
+        assert!(files.iter().all(|file| file.starts_with(&data_dir) && file.is_file()));
+
+        let entry = fs::read_to_string(data_dir.join("applications/cookin.desktop")).unwrap();
+        let exec = std::env::current_exe().unwrap();
+        assert!(entry.contains(&format!("\nExec={}\n", exec.display())));
+        assert!(entry.contains("\nIcon=cookin\n"));
+        assert!(eframe::icon_data::from_png_bytes(ICON_PNG).is_ok());
+    }
+}
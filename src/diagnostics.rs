@@ -0,0 +1,124 @@
+use crate::logging::LogLevel;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+// makepkg output is already written to the GUI log line by line, so the mirror skips it
+pub const BUILD_OUTPUT_TARGET: &str = "build_output";
+
+const KEPT_LOG_FILES: usize = 7;
+
+// Forwards tracing events to the GUI over a channel; locking the app state here could deadlock
+// when an event is emitted while the state is already held
+struct GuiLayer {
+    sender: Mutex<Sender<(LogLevel, String)>>,
+}
+
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            self.fields.push_str(&format!(" {}={:?}", field.name(), value));
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for GuiLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if event.metadata().target() == BUILD_OUTPUT_TARGET {
+            return;
+        }
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let level = match *event.metadata().level() {
+            Level::ERROR => LogLevel::Error,
+            Level::WARN => LogLevel::Warning,
+            Level::INFO => LogLevel::Info,
+            _ => LogLevel::Debug,
+        };
+        let _ = self.sender.lock().unwrap().send((level, visitor.message + &visitor.fields));
+    }
+}
+
+pub struct Diagnostics {
+    // Dropping the guard flushes the file writer, so it must live until exit
+    _guard: WorkerGuard,
+    pub gui_events: Option<Receiver<(LogLevel, String)>>,
+}
+
+// Maps -q/-v/-vv onto a filter for terminal output; makepkg output is only shown from -vv
+pub fn cli_filter(verbose: u8, quiet: bool) -> String {
+    let krate = env!("CARGO_CRATE_NAME");
+    match (quiet, verbose) {
+        (true, _) => "error".to_string(),
+        (false, 0) => format!("warn,{}=info,{}=off", krate, BUILD_OUTPUT_TARGET),
+        (false, 1) => format!("warn,{}=debug,{}=off", krate, BUILD_OUTPUT_TARGET),
+        (false, _) => format!("info,{}=trace,{}=info", krate, BUILD_OUTPUT_TARGET),
+    }
+}
+
+// $XDG_STATE_HOME/aur-helper, for log files and crash reports
+pub fn state_dir() -> Result<PathBuf, Box<dyn Error>> {
+    let state_dir = dirs::state_dir().or_else(dirs::data_local_dir).ok_or("Could not determine state directory")?;
+    Ok(state_dir.join("aur-helper"))
+}
+
+// Log files rotate daily in the state directory; RUST_LOG, when set, overrides `stderr_filter`
+pub fn init(mirror_to_gui: bool, stderr_filter: &str) -> Result<Diagnostics, Box<dyn Error>> {
+    let state_dir = state_dir()?;
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("aur-helper")
+        .filename_suffix("log")
+        .max_log_files(KEPT_LOG_FILES)
+        .build(state_dir)?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    let crate_debug = || EnvFilter::new(format!("{}=debug,info", env!("CARGO_CRATE_NAME")));
+    let file_layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(false)
+        .with_filter(crate_debug());
+    let stderr_layer = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .without_time()
+        .with_target(false)
+        .with_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(stderr_filter)));
+
+    let (gui_layer, gui_events) = if mirror_to_gui {
+        let (sender, receiver) = mpsc::channel();
+        let layer = GuiLayer {
+            sender: Mutex::new(sender),
+        };
+        (Some(layer.with_filter(crate_debug())), Some(receiver))
+    } else {
+        (None, None)
+    };
+
+    tracing_subscriber::registry()
+        .with(file_layer)
+        .with(stderr_layer)
+        .with(gui_layer)
+        .try_init()?;
+
+    Ok(Diagnostics {
+        _guard: guard,
+        gui_events,
+    })
+}
@@ -0,0 +1,109 @@
+use crate::http;
+use chrono::Utc;
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+
+// The AUR publishes metadata for every package once a day; the RPC has no "sort by votes"
+const METADATA_PATH: &str = "/packages-meta-v1.json.gz";
+const MAX_AGE_SECONDS: i64 = 24 * 60 * 60;
+const LIST_LENGTH: usize = 100;
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Ranking {
+    #[default]
+    MostVoted,
+    // The AUR's popularity score decays with the age of each vote, so it favors recent interest
+    Rising,
+}
+
+impl Ranking {
+    pub const ALL: [Ranking; 2] = [Ranking::MostVoted, Ranking::Rising];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Ranking::MostVoted => "Most voted",
+            Ranking::Rising => "Rising",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DiscoverEntry {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "NumVotes")]
+    pub votes: u64,
+    #[serde(rename = "Popularity")]
+    pub popularity: f64,
+}
+
+// Only the top of each ranking is kept on disk, not the whole metadata dump
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Discover {
+    // Unix timestamp
+    pub fetched: i64,
+    pub most_voted: Vec<DiscoverEntry>,
+    pub rising: Vec<DiscoverEntry>,
+}
+
+impl Discover {
+    pub fn entries(&self, ranking: Ranking) -> &[DiscoverEntry] {
+        match ranking {
+            Ranking::MostVoted => &self.most_voted,
+            Ranking::Rising => &self.rising,
+        }
+    }
+}
+
+fn cache_path() -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("aur-helper").join("discover.json"))
+}
+
+// Uses the cached rankings while they are fresh, unless `refresh` is set
+pub async fn load(refresh: bool) -> Result<Discover, Box<dyn Error>> {
+    let cached = cache_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str::<Discover>(&content).ok());
+    if let Some(cached) = cached.filter(|cached| !refresh && Utc::now().timestamp() - cached.fetched < MAX_AGE_SECONDS) {
+        return Ok(cached);
+    }
+
+    let url = http::aur_url(METADATA_PATH);
+    tracing::debug!("Downloading {}", url);
+    let response = http::get(&url).await?;
+    let bytes = response.bytes().await.map_err(http::readable)?;
+    let discover = rank(&bytes)?;
+    if let Some(path) = cache_path() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string(&discover)?)?;
+    }
+    Ok(discover)
+}
+
+fn rank(bytes: &[u8]) -> Result<Discover, Box<dyn Error>> {
+    // The file may arrive already decompressed, depending on how the server encodes it
+    let json = if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut json = Vec::new();
+        GzDecoder::new(bytes).read_to_end(&mut json)?;
+        json
+    } else {
+        bytes.to_vec()
+    };
+    let mut packages: Vec<DiscoverEntry> = serde_json::from_slice(&json)?;
+
+    packages.sort_by_key(|package| std::cmp::Reverse(package.votes));
+    let most_voted = packages.iter().take(LIST_LENGTH).cloned().collect();
+    packages.sort_by(|a, b| b.popularity.total_cmp(&a.popularity));
+    let rising = packages.into_iter().take(LIST_LENGTH).collect();
+    Ok(Discover {
+        fetched: Utc::now().timestamp(),
+        most_voted,
+        rising,
+    })
+}
@@ -0,0 +1,68 @@
+use crate::remote;
+use crate::runner::Run;
+use std::error::Error;
+use std::io::{self, IsTerminal};
+use std::process::Command as StdCommand;
+
+// `escalation_tool = "auto"` picks the first available entry of TOOLS
+pub const AUTO: &str = "auto";
+pub const TOOLS: [&str; 3] = ["sudo", "doas", "pkexec"];
+
+pub fn available() -> Vec<&'static str> {
+    TOOLS.into_iter().filter(|tool| crate::command_exists(tool)).collect()
+}
+
+fn is_root() -> bool {
+    StdCommand::new("id")
+        .arg("-u")
+        .run()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).trim() == "0")
+}
+
+// Without a terminal sudo and doas cannot ask for a password, so pkexec is preferred there
+pub fn resolve(configured: &str) -> Result<String, Box<dyn Error>> {
+    if configured != AUTO {
+        if !crate::command_exists(configured) {
+            return Err(format!("Escalation tool '{}' was not found in PATH", configured).into());
+        }
+        return Ok(configured.to_string());
+    }
+    let available = available();
+    let preferred = if io::stdin().is_terminal() {
+        available.first()
+    } else {
+        available.iter().find(|tool| **tool == "pkexec").or(available.first())
+    };
+    preferred
+        .map(|tool| tool.to_string())
+        .ok_or_else(|| "No privilege escalation tool found; install sudo, doas or pkexec".into())
+}
+
+// The root helper only runs pacman transactions on package files and AUR names; anything else it
+// would be asked for escalates per call instead
+pub fn direct(configured: &str) -> &str {
+    if configured == crate::helper::ESCALATION_TOOL {
+        AUTO
+    } else {
+        configured
+    }
+}
+
+// Every privileged package manager call goes through here; when already root the program is run
+// directly. On a remote host the profile's own escalation tool is used.
+pub fn privileged<I, S>(configured: &str, program: &str, args: I) -> Result<StdCommand, Box<dyn Error>>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let args: Vec<String> = args.into_iter().map(|arg| arg.as_ref().to_string()).collect();
+    let tool = match remote::active() {
+        Some((_, host)) => Some(host.escalation_tool).filter(|tool| !tool.is_empty()),
+        None if is_root() => None,
+        None => Some(resolve(configured)?),
+    };
+    Ok(match tool {
+        Some(tool) => remote::command(&tool, std::iter::once(program.to_string()).chain(args)),
+        None => remote::command(program, args),
+    })
+}
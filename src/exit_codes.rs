@@ -0,0 +1,53 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+// Process exit codes in CLI mode; scripts branch on these, so existing values must not change
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExitCode {
+    Success = 0,
+    Generic = 1,
+    Network = 2,
+    BuildFailure = 3,
+    NotFound = 4,
+    PermissionDenied = 5,
+    // Another instance or pacman itself is in a transaction
+    Locked = 6,
+    // `update --check` found outdated packages
+    UpdatesAvailable = 10,
+}
+
+// An error that knows which exit code it maps to
+#[derive(Debug)]
+pub struct CodedError {
+    pub code: ExitCode,
+    pub message: String,
+}
+
+impl fmt::Display for CodedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for CodedError {}
+
+pub fn coded(code: ExitCode, message: impl Into<String>) -> Box<dyn Error> {
+    Box::new(CodedError {
+        code,
+        message: message.into(),
+    })
+}
+
+pub fn classify(error: &(dyn Error + 'static)) -> ExitCode {
+    if let Some(error) = error.downcast_ref::<CodedError>() {
+        return error.code;
+    }
+    if error.is::<reqwest::Error>() {
+        return ExitCode::Network;
+    }
+    match error.downcast_ref::<io::Error>().map(io::Error::kind) {
+        Some(io::ErrorKind::PermissionDenied) => ExitCode::PermissionDenied,
+        _ => ExitCode::Generic,
+    }
+}
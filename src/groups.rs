@@ -0,0 +1,70 @@
+use crate::remote;
+use crate::runner::Run;
+
+// A pacman group such as base-devel, with the members picked for installing
+#[derive(Clone)]
+pub struct Group {
+    pub name: String,
+    pub members: Vec<Member>,
+}
+
+#[derive(Clone)]
+pub struct Member {
+    pub name: String,
+    pub installed: bool,
+    pub picked: bool,
+}
+
+impl Group {
+    pub fn picked(&self) -> Vec<String> {
+        self.members.iter().filter(|member| member.picked).map(|member| member.name.clone()).collect()
+    }
+}
+
+// The group called `name` in the sync repositories, or None for any other name. Members that are
+// already installed start out unpicked, since installing them again changes nothing.
+pub fn find(name: &str) -> Option<Group> {
+    // Both exit with an error for a name that is not a group, or one with no installed members
+    let output = remote::command("pacman", ["-Sgq", name]).run().ok().filter(|output| output.status.success())?;
+    let members = lines(&output.stdout);
+    if members.is_empty() {
+        return None;
+    }
+    let installed = remote::command("pacman", ["-Qgq", name]).run().ok().filter(|output| output.status.success()).map(|output| lines(&output.stdout)).unwrap_or_default();
+    let members = members
+        .into_iter()
+        .map(|member| {
+            let installed = installed.contains(&member);
+            Member { name: member, installed, picked: !installed }
+        })
+        .collect();
+    Some(Group { name: name.to_string(), members })
+}
+
+fn lines(stdout: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(stdout).lines().map(str::trim).filter(|line| !line.is_empty()).map(str::to_string).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Scripted};
+
+    #[test]
+    fn lists_members_and_leaves_installed_ones_unpicked() {
+        let _isolated = testing::isolate_blocking();
+        Scripted::default()
+            .answer("pacman -Sgq base-devel", "base-devel\nmake\nfakeroot\n")
+            .answer("pacman -Qgq base-devel", "make\n")
+            .fail("pacman -Sgq firefox", "error: group 'firefox' was not found")
+            .install();
+
+        let mut group = find("base-devel").unwrap();
+        assert_eq!(group.members.len(), 3);
+        assert!(group.members[1].installed);
+        assert_eq!(group.picked(), ["base-devel", "fakeroot"]);
+        group.members[0].picked = false;
+        assert_eq!(group.picked(), ["fakeroot"]);
+        assert!(find("firefox").is_none());
+    }
+}
@@ -0,0 +1,257 @@
+use crate::config::RemoveMode;
+use crate::exit_codes::{self, ExitCode};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::process::{Command as StdCommand, Stdio};
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// `escalation_tool = "helper"` runs pacman through this root helper instead of escalating per call
+pub const ESCALATION_TOOL: &str = "helper";
+
+// Long enough for the user to type a password into the polkit dialog
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(120);
+
+// Requests and events are sent as one JSON object per line
+#[derive(Serialize, Deserialize)]
+enum Request {
+    Install { files: Vec<String> },
+    Remove { package: String, mode: RemoveMode },
+}
+
+#[derive(Serialize, Deserialize)]
+enum Event {
+    Output(String),
+    Done { ok: bool, error: Option<String> },
+}
+
+struct Connection {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+// One helper per session, so polkit only asks once
+static CONNECTION: Mutex<Option<Connection>> = Mutex::new(None);
+
+fn socket_path() -> Result<PathBuf, Box<dyn Error>> {
+    let runtime_dir = dirs::runtime_dir().ok_or("XDG_RUNTIME_DIR is not set")?;
+    Ok(runtime_dir.join("aur-helper.sock"))
+}
+
+fn connect() -> Result<Connection, Box<dyn Error>> {
+    let path = socket_path()?;
+    let exe = std::env::current_exe()?;
+    tracing::info!("Starting privileged helper");
+    let mut child = StdCommand::new("pkexec")
+        .arg(exe)
+        .arg("helper")
+        .arg("--socket")
+        .arg(&path)
+        .stdin(Stdio::null())
+        .spawn()?;
+
+    let started = Instant::now();
+    loop {
+        if let Ok(stream) = UnixStream::connect(&path) {
+            return Ok(Connection {
+                reader: BufReader::new(stream.try_clone()?),
+                writer: stream,
+            });
+        }
+        if let Some(status) = child.try_wait()? {
+            // pkexec exits with 126/127 when authorization is dismissed or denied
+            let code = if matches!(status.code(), Some(126 | 127)) { ExitCode::PermissionDenied } else { ExitCode::Generic };
+            return Err(exit_codes::coded(code, format!("Privileged helper exited with {}", status)));
+        }
+        if started.elapsed() > CONNECT_TIMEOUT {
+            let _ = child.kill();
+            return Err("Timed out waiting for the privileged helper".into());
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+fn send(request: &Request) -> Result<(), Box<dyn Error>> {
+    let mut connection = CONNECTION.lock().unwrap();
+    // A helper that went away (e.g. killed) is restarted once
+    for attempt in 0..2 {
+        if connection.is_none() {
+            *connection = Some(connect()?);
+        }
+        match exchange(connection.as_mut().unwrap(), request) {
+            Ok(result) => return result,
+            Err(e) if attempt == 0 => {
+                tracing::debug!("Lost connection to the privileged helper: {}", e);
+                *connection = None;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    unreachable!()
+}
+
+// The outer error is a broken connection; the inner one is the helper's report
+fn exchange(connection: &mut Connection, request: &Request) -> Result<Result<(), Box<dyn Error>>, std::io::Error> {
+    writeln!(connection.writer, "{}", serde_json::to_string(request)?)?;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if connection.reader.read_line(&mut line)? == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        match serde_json::from_str::<Event>(&line)? {
+            Event::Output(output) => tracing::info!("{}", output),
+            Event::Done { ok: true, .. } => return Ok(Ok(())),
+            Event::Done { ok: false, error } => {
+                return Ok(Err(error.unwrap_or_else(|| "pacman failed".to_string()).into()));
+            }
+        }
+    }
+}
+
+pub fn install(files: &[String]) -> Result<(), Box<dyn Error>> {
+    send(&Request::Install { files: files.to_vec() })
+}
+
+pub fn remove(package: &str, mode: RemoveMode) -> Result<(), Box<dyn Error>> {
+    send(&Request::Remove {
+        package: package.to_string(),
+        mode,
+    })
+}
+
+// Only package files and plain package names are accepted, so the socket cannot be used
+// to run arbitrary pacman operations
+fn pacman_args(request: &Request) -> Result<Vec<String>, String> {
+    match request {
+        Request::Install { files } => {
+            if files.is_empty() {
+                return Err("No package files given".to_string());
+            }
+            for file in files {
+                let path = Path::new(file);
+                if !path.is_absolute() || !path.is_file() || !file.contains(".pkg.tar") {
+                    return Err(format!("Not a package file: {}", file));
+                }
+            }
+            Ok(["-U", "--noconfirm"].iter().map(|arg| arg.to_string()).chain(files.iter().cloned()).collect())
+        }
+        // The mode can only be one of the known flag sets, as anything else fails to deserialize
+        Request::Remove { package, mode } => {
+            let valid_name = Regex::new(r"^[a-z0-9@_+][a-z0-9@._+-]*$").unwrap();
+            if !valid_name.is_match(package) {
+                return Err(format!("Invalid package name: {}", package));
+            }
+            Ok(mode.pacman_args(package))
+        }
+    }
+}
+
+fn reply(stream: &mut UnixStream, event: &Event) -> std::io::Result<()> {
+    writeln!(stream, "{}", serde_json::to_string(event)?)
+}
+
+// Errors are only returned when the client can no longer be written to
+fn run_pacman(args: &[String], stream: &mut UnixStream) -> std::io::Result<Event> {
+    let spawned = StdCommand::new("pacman")
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => {
+            return Ok(Event::Done {
+                ok: false,
+                error: Some(format!("Could not run pacman: {}", e)),
+            })
+        }
+    };
+    let (sender, receiver) = mpsc::channel();
+    let readers: Vec<_> = [
+        Box::new(child.stdout.take().unwrap()) as Box<dyn std::io::Read + Send>,
+        Box::new(child.stderr.take().unwrap()),
+    ]
+    .into_iter()
+    .map(|pipe| {
+        let sender = sender.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(pipe).lines().map_while(Result::ok) {
+                let _ = sender.send(line);
+            }
+        })
+    })
+    .collect();
+    drop(sender);
+
+    let mut last_line = String::new();
+    for line in receiver {
+        reply(stream, &Event::Output(line.clone()))?;
+        last_line = line;
+    }
+    for reader in readers {
+        let _ = reader.join();
+    }
+    let status = match child.wait() {
+        Ok(status) => status,
+        Err(e) => {
+            return Ok(Event::Done {
+                ok: false,
+                error: Some(format!("Could not wait for pacman: {}", e)),
+            })
+        }
+    };
+    Ok(Event::Done {
+        ok: status.success(),
+        error: (!status.success()).then(|| format!("pacman exited with {}: {}", status, last_line)),
+    })
+}
+
+fn serve_connection(stream: UnixStream) -> std::io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let event = match serde_json::from_str::<Request>(&line?) {
+            Ok(request) => match pacman_args(&request) {
+                Ok(args) => run_pacman(&args, &mut writer)?,
+                Err(error) => Event::Done { ok: false, error: Some(error) },
+            },
+            Err(e) => Event::Done {
+                ok: false,
+                error: Some(format!("Malformed request: {}", e)),
+            },
+        };
+        reply(&mut writer, &event)?;
+    }
+    Ok(())
+}
+
+// Runs as root under pkexec. The socket belongs to the calling user and the helper exits
+// once that user's session closes the connection.
+pub fn serve(socket: &Path) -> Result<(), Box<dyn Error>> {
+    let uid: u32 = std::env::var("PKEXEC_UID")
+        .map_err(|_| "The helper must be started through pkexec")?
+        .parse()?;
+    if let Ok(metadata) = fs::symlink_metadata(socket) {
+        if !metadata.file_type().is_socket() {
+            return Err(format!("{} exists and is not a socket", socket.display()).into());
+        }
+        fs::remove_file(socket)?;
+    }
+    let listener = UnixListener::bind(socket)?;
+    std::os::unix::fs::chown(socket, Some(uid), None)?;
+    fs::set_permissions(socket, fs::Permissions::from_mode(0o600))?;
+
+    let result = listener.accept().map(|(stream, _)| stream);
+    let _ = fs::remove_file(socket);
+    serve_connection(result?)?;
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use crate::runner::{self, Run};
+use std::error::Error;
+use std::process::Command as StdCommand;
+
+pub fn run_hook(name: &str, command: Option<&str>, package_name: &str) -> Result<(), Box<dyn Error>> {
+    let Some(command) = command else {
+        return Ok(());
+    };
+    tracing::info!("Running {} hook for {}: {}", name, package_name, command);
+
+    // `sh -c <command> sh <package>` makes the package available as $1
+    let mut hook = StdCommand::new("sh");
+    hook.args(["-c", command, "sh", package_name]);
+    if runner::simulate(&hook) {
+        return Ok(());
+    }
+    let output = hook.run()?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} hook failed for {}: {}",
+            name,
+            package_name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(())
+}
@@ -0,0 +1,230 @@
+use crate::config::Config;
+use crate::exit_codes::{self, ExitCode};
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, ClientBuilder, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use std::error::Error;
+use std::sync::RwLock;
+use std::time::Duration;
+
+pub const DEFAULT_AUR_URL: &str = "https://aur.archlinux.org";
+
+const USER_AGENT: &str = concat!("aur-helper-ui/", env!("CARGO_PKG_VERSION"));
+
+// How often a rate limited request is sent again, and the longest Retry-After that is waited out
+const RATE_LIMIT_RETRIES: u32 = 3;
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+// When a 429 reply does not say how long to wait
+const DEFAULT_RATE_LIMIT_WAIT: Duration = Duration::from_secs(5);
+
+// Network settings from the config, shared by every request
+struct Settings {
+    connect_timeout_secs: u64,
+    read_timeout_secs: u64,
+    // Empty until configured, which means the official AUR
+    aur_url: String,
+    // Empty to download snapshots from `aur_url`
+    snapshot_url: String,
+}
+
+static SETTINGS: RwLock<Settings> = RwLock::new(Settings {
+    connect_timeout_secs: 10,
+    read_timeout_secs: 30,
+    aur_url: String::new(),
+    snapshot_url: String::new(),
+});
+
+pub fn configure(config: &Config) {
+    *SETTINGS.write().unwrap() = Settings {
+        connect_timeout_secs: config.connect_timeout_secs,
+        read_timeout_secs: config.read_timeout_secs,
+        aur_url: config.aur_url.trim_end_matches('/').to_string(),
+        snapshot_url: config.snapshot_url.trim_end_matches('/').to_string(),
+    };
+}
+
+// `path` on the configured AUR, e.g. the metadata dump
+pub fn aur_url(path: &str) -> String {
+    let settings = SETTINGS.read().unwrap();
+    let base = if settings.aur_url.is_empty() { DEFAULT_AUR_URL } else { settings.aur_url.as_str() };
+    format!("{}{}", base, path)
+}
+
+// A snapshot's URLPath as the RPC reports it, on the server that hands out snapshots
+pub fn snapshot_url(path: &str) -> String {
+    let snapshot_url = SETTINGS.read().unwrap().snapshot_url.clone();
+    if snapshot_url.is_empty() {
+        return aur_url(path);
+    }
+    format!("{}{}", snapshot_url, path)
+}
+
+// An RPC v5 call with each of `params` percent-encoded, so names such as "libc++" arrive as they
+// are; a key may repeat, e.g. ("arg[]", name) for every package of an info request
+pub fn rpc_url(params: &[(&str, &str)]) -> String {
+    let base = aur_url("/rpc/");
+    // A malformed aur_url from the config file is left for the request to report
+    let Ok(mut url) = reqwest::Url::parse(&base) else {
+        return base;
+    };
+    url.query_pairs_mut().append_pair("v", "5").extend_pairs(params);
+    url.to_string()
+}
+
+// Client settings shared by every request; callers may add their own before building
+pub fn builder() -> ClientBuilder {
+    let settings = SETTINGS.read().unwrap();
+    Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(Duration::from_secs(settings.connect_timeout_secs))
+        .read_timeout(Duration::from_secs(settings.read_timeout_secs))
+}
+
+pub fn client() -> Result<Client, Box<dyn Error>> {
+    Ok(builder().build()?)
+}
+
+// Sends `request`, waiting as long as the server asks when it answers 429 Too Many Requests
+pub async fn send(mut request: RequestBuilder) -> Result<Response, Box<dyn Error>> {
+    let mut retries = 0;
+    loop {
+        let retry = request.try_clone();
+        let response = request.send().await.map_err(readable)?;
+        if response.status() != StatusCode::TOO_MANY_REQUESTS {
+            return Ok(response);
+        }
+        let host = response.url().host_str().unwrap_or("the server").to_string();
+        let wait = retry_after(&response).unwrap_or(DEFAULT_RATE_LIMIT_WAIT);
+        let retry = retry.filter(|_| retries < RATE_LIMIT_RETRIES && wait <= MAX_RATE_LIMIT_WAIT);
+        let Some(retry) = retry else {
+            return Err(exit_codes::coded(ExitCode::Network, format!("{} is limiting requests; try again in {} s", host, wait.as_secs().max(1))));
+        };
+        tracing::info!("{} is limiting requests; retrying in {} s", host, wait.as_secs());
+        tokio::time::sleep(wait).await;
+        retries += 1;
+        request = retry;
+    }
+}
+
+// Retry-After is either a number of seconds or an HTTP date
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some((date.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().unwrap_or(Duration::ZERO))
+}
+
+// GET that fails on error statuses
+pub async fn get(url: &str) -> Result<Response, Box<dyn Error>> {
+    // Results holding a `Box<dyn Error>` must not live across an await, or the future is not Send
+    let client = client()?;
+    let response = send(client.get(url)).await?;
+    Ok(response.error_for_status()?)
+}
+
+pub async fn get_json<T: DeserializeOwned>(url: &str) -> Result<T, Box<dyn Error>> {
+    let response = get(url).await?;
+    response.json::<T>().await.map_err(readable)
+}
+
+// Timeouts say which limit was hit and which setting raises it; other errors are kept as they are
+pub fn readable(error: reqwest::Error) -> Box<dyn Error> {
+    if !error.is_timeout() {
+        return Box::new(error);
+    }
+    let host = error.url().and_then(|url| url.host_str()).unwrap_or("the server").to_string();
+    let settings = SETTINGS.read().unwrap();
+    let message = if error.is_connect() {
+        format!("Timed out connecting to {} after {} s (connect_timeout_secs)", host, settings.connect_timeout_secs)
+    } else {
+        format!("{} stopped responding for {} s (read_timeout_secs)", host, settings.read_timeout_secs)
+    };
+    exit_codes::coded(ExitCode::Network, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use axum::response::IntoResponse;
+    use axum::routing;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // Answers 429 with `retry_after` for the first `limited` requests, then an empty RPC reply
+    fn rate_limited(limited: usize, retry_after: &'static str) -> (axum::Router, Arc<AtomicUsize>) {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&hits);
+        let router = axum::Router::new().route(
+            "/rpc/",
+            routing::get(move || {
+                let counter = Arc::clone(&counter);
+                async move {
+                    if counter.fetch_add(1, Ordering::SeqCst) < limited {
+                        (StatusCode::TOO_MANY_REQUESTS, [(RETRY_AFTER, retry_after)], "slow down").into_response()
+                    } else {
+                        axum::Json(serde_json::json!({ "results": [] })).into_response()
+                    }
+                }
+            }),
+        );
+        (router, hits)
+    }
+
+    #[tokio::test]
+    async fn waits_out_rate_limits() {
+        let _isolated = testing::isolate().await;
+        let (router, hits) = rate_limited(2, "0");
+        testing::use_aur(&testing::serve(router), |_| {});
+
+        let reply: serde_json::Value = get_json(&rpc_url(&[("type", "info"), ("arg[]", "yay")])).await.unwrap();
+        assert_eq!(reply["results"], serde_json::json!([]));
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_when_asked_to_wait_too_long() {
+        let _isolated = testing::isolate().await;
+        let (router, hits) = rate_limited(usize::MAX, "3600");
+        testing::use_aur(&testing::serve(router), |_| {});
+
+        let error = get(&rpc_url(&[("type", "info"), ("arg[]", "yay")])).await.unwrap_err();
+        assert!(error.to_string().contains("is limiting requests; try again in 3600 s"), "{}", error);
+        assert_eq!(exit_codes::classify(error.as_ref()), ExitCode::Network);
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn names_the_timeout_that_expired() {
+        let _isolated = testing::isolate().await;
+        let router = axum::Router::new().route(
+            "/rpc/",
+            routing::get(|| async {
+                tokio::time::sleep(Duration::from_secs(3)).await;
+                "too late"
+            }),
+        );
+        testing::use_aur(&testing::serve(router), |config| config.read_timeout_secs = 1);
+
+        let error = get(&rpc_url(&[("type", "info")])).await.unwrap_err();
+        assert!(error.to_string().ends_with("stopped responding for 1 s (read_timeout_secs)"), "{}", error);
+        assert_eq!(exit_codes::classify(error.as_ref()), ExitCode::Network);
+    }
+
+    #[test]
+    fn builds_urls_from_the_configured_servers() {
+        let _isolated = testing::isolate_blocking();
+        testing::use_aur("https://aur.example.com/", |_| {});
+        assert_eq!(rpc_url(&[("type", "suggest"), ("arg", "ya")]), "https://aur.example.com/rpc/?v=5&type=suggest&arg=ya");
+        assert_eq!(
+            rpc_url(&[("type", "info"), ("arg[]", "libc++"), ("arg[]", "a&b")]),
+            "https://aur.example.com/rpc/?v=5&type=info&arg%5B%5D=libc%2B%2B&arg%5B%5D=a%26b"
+        );
+        assert_eq!(snapshot_url("/cgit/aur.git/snapshot/yay.tar.gz"), "https://aur.example.com/cgit/aur.git/snapshot/yay.tar.gz");
+
+        testing::use_aur("https://aur.example.com", |config| config.snapshot_url = "https://cgit.example.com/".to_string());
+        assert_eq!(snapshot_url("/cgit/aur.git/snapshot/yay.tar.gz"), "https://cgit.example.com/cgit/aur.git/snapshot/yay.tar.gz");
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+// Translations in the gettext .po format, by language code. An msgid is the English text itself,
+// so strings without a translation stay in English.
+const CATALOGS: &[(&str, &str)] = &[("de", include_str!("../po/de.po")), ("es", include_str!("../po/es.po"))];
+
+// Translations for the system's language; empty for English or a language without a catalog
+static CATALOG: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+// Picks the catalog of the system locale; called once at startup, before any text is shown
+pub fn init() {
+    let catalog = language()
+        .and_then(|language| CATALOGS.iter().find(|(code, _)| *code == language))
+        .map(|(_, po)| parse(po))
+        .unwrap_or_default();
+    let _ = CATALOG.set(catalog);
+}
+
+// The language of messages as the C library picks it: LC_ALL, then LC_MESSAGES, then LANG
+fn language() -> Option<String> {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"].iter().filter_map(|name| env::var(name).ok()).find(|value| !value.is_empty())?;
+    language_of(&locale)
+}
+
+// "de" for "de_DE.UTF-8" or "de_AT@euro"; None for the C locale
+fn language_of(locale: &str) -> Option<String> {
+    let language = locale.split(['_', '.', '@']).next().unwrap_or_default();
+    match language {
+        "" | "C" | "POSIX" => None,
+        language => Some(language.to_lowercase()),
+    }
+}
+
+// `text` in the system's language
+pub fn tr(text: &'static str) -> &'static str {
+    CATALOG.get().and_then(|catalog| catalog.get(text)).map_or(text, String::as_str)
+}
+
+// A translated `template` with each {} replaced by the next of `args`, for text with values in it
+pub fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut parts = template.split("{}");
+    let mut filled = parts.next().unwrap_or_default().to_string();
+    for (index, part) in parts.enumerate() {
+        if let Some(arg) = args.get(index) {
+            filled.push_str(&arg.to_string());
+        }
+        filled.push_str(part);
+    }
+    filled
+}
+
+// msgid/msgstr pairs on one line each; entries with an empty msgstr are left out
+fn parse(po: &str) -> HashMap<String, String> {
+    let mut catalog = HashMap::new();
+    let mut msgid = None;
+    for line in po.lines().map(str::trim) {
+        if let Some(text) = line.strip_prefix("msgid ") {
+            msgid = Some(unquote(text));
+        } else if let Some(text) = line.strip_prefix("msgstr ") {
+            let msgstr = unquote(text);
+            if let Some(msgid) = msgid.take().filter(|msgid| !msgid.is_empty() && !msgstr.is_empty()) {
+                catalog.insert(msgid, msgstr);
+            }
+        }
+    }
+    catalog
+}
+
+fn unquote(text: &str) -> String {
+    let text = text.strip_prefix('"').and_then(|text| text.strip_suffix('"')).unwrap_or(text);
+    let mut unquoted = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unquoted.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unquoted.push('\n'),
+            Some('t') => unquoted.push('\t'),
+            Some(other) => unquoted.push(other),
+            None => {}
+        }
+    }
+    unquoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translations_keep_their_placeholders() {
+        assert_eq!(language_of("de_DE.UTF-8").as_deref(), Some("de"));
+        assert_eq!(language_of("C.UTF-8"), None);
+        assert_eq!(fill("Remove {} ({})?", &[&"foo", &"purge"]), "Remove foo (purge)?");
+
+        for (language, po) in CATALOGS {
+            let catalog = parse(po);
+            assert!(catalog.len() > 100, "{} has only {} translations", language, catalog.len());
+            for (msgid, msgstr) in &catalog {
+                assert_eq!(msgid.matches("{}").count(), msgstr.matches("{}").count(), "{}: {}", language, msgid);
+            }
+        }
+        assert_eq!(parse("msgid \"Say \\\"hi\\\"\"\nmsgstr \"Sag \\\"hallo\\\"\"\n")["Say \"hi\""], "Sag \"hallo\"");
+    }
+}
@@ -0,0 +1,136 @@
+use crate::exit_codes::{self, ExitCode};
+use crate::remote;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+use std::thread;
+
+const PACMAN_DB_LOCK: &str = "/var/lib/pacman/db.lck";
+
+// Sent by a later instance to the running GUI, one JSON object per line
+#[derive(Serialize, Deserialize)]
+enum Message {
+    Focus,
+    // Packages from `install` on the command line, for the GUI to queue
+    Install { packages: Vec<String> },
+}
+
+fn runtime_path(name: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let runtime_dir = dirs::runtime_dir().ok_or("XDG_RUNTIME_DIR is not set")?;
+    Ok(runtime_dir.join(name))
+}
+
+// false when no GUI is running to receive the message
+fn send(message: &Message) -> bool {
+    let Ok(path) = runtime_path("aur-helper-gui.sock") else {
+        return false;
+    };
+    let Ok(mut stream) = UnixStream::connect(path) else {
+        return false;
+    };
+    serde_json::to_string(message).is_ok_and(|message| writeln!(stream, "{}", message).is_ok())
+}
+
+// Asks a GUI that is already running to come to the front; false when there is none
+pub fn focus_running() -> bool {
+    send(&Message::Focus)
+}
+
+// Queues `packages` in a running GUI instead of building them here; false when there is none
+pub fn hand_off_install(packages: &[String]) -> bool {
+    send(&Message::Install { packages: packages.to_vec() })
+}
+
+// Accepts messages from later instances for as long as the GUI runs; packages to install come
+// out of the returned channel
+pub fn listen(ctx: egui::Context) -> Result<Receiver<Vec<String>>, Box<dyn Error>> {
+    let path = runtime_path("aur-helper-gui.sock")?;
+    // focus_running found nobody listening, so a leftover socket is from an instance that crashed
+    if fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        fs::remove_file(&path)?;
+    }
+    let listener = UnixListener::bind(&path)?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                match serde_json::from_str::<Message>(&line) {
+                    Ok(Message::Focus) => {
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                        ctx.request_repaint();
+                    }
+                    Ok(Message::Install { packages }) => {
+                        if sender.send(packages).is_err() {
+                            return;
+                        }
+                        ctx.request_repaint();
+                    }
+                    Err(e) => tracing::debug!("Ignoring malformed instance message: {}", e),
+                }
+            }
+        }
+    });
+    Ok(receiver)
+}
+
+// Open lock file and how many transactions of this process hold it; flock locks conflict even
+// within one process, so nested transactions (a build installing its packages) share the file
+static TRANSACTIONS: Mutex<(usize, Option<File>)> = Mutex::new((0, None));
+
+// Held while pacman or makepkg runs; released when dropped
+pub struct TransactionLock(());
+
+impl Drop for TransactionLock {
+    fn drop(&mut self) {
+        let mut transactions = TRANSACTIONS.lock().unwrap();
+        transactions.0 -= 1;
+        if transactions.0 == 0 {
+            // Closing the file releases the lock
+            transactions.1 = None;
+        }
+    }
+}
+
+// Fails when another aur-helper process, GUI or CLI, is in a transaction, or pacman's database
+// is locked by some other pacman
+pub fn lock_transactions() -> Result<TransactionLock, Box<dyn Error>> {
+    let mut transactions = TRANSACTIONS.lock().unwrap();
+    if transactions.0 == 0 {
+        let path = runtime_path("aur-helper.lock")?;
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let pid = fs::read_to_string(&path).unwrap_or_default();
+                let owner = if pid.trim().is_empty() { String::new() } else { format!(" (pid {})", pid.trim()) };
+                return Err(exit_codes::coded(ExitCode::Locked, format!("Another aur-helper instance{} is installing or removing packages; try again when it is done", owner)));
+            }
+            Err(TryLockError::Error(e)) => return Err(format!("Could not lock {}: {}", path.display(), e).into()),
+        }
+        if pacman_db_locked() {
+            return Err(exit_codes::coded(
+                ExitCode::Locked,
+                format!("pacman's database is locked by another pacman process. Wait for it to finish; if none is running, remove {}", PACMAN_DB_LOCK),
+            ));
+        }
+        file.set_len(0)?;
+        write!(file, "{}", std::process::id())?;
+        transactions.1 = Some(file);
+    }
+    transactions.0 += 1;
+    Ok(TransactionLock(()))
+}
+
+// On the active host, since that is where pacman runs
+fn pacman_db_locked() -> bool {
+    crate::backend::active().removes_with_pacman() && remote::command("test", ["-e", PACMAN_DB_LOCK]).status().is_ok_and(|status| status.success())
+}
@@ -0,0 +1,44 @@
+use crate::backend;
+use crate::escalation;
+use crate::remote;
+use crate::runner::Run;
+use std::error::Error;
+
+// Signs the official packages; when it is older than the packages being installed, pacman fails
+// with "signature is unknown trust" or "invalid or corrupted package (PGP signature)"
+pub const PACKAGE: &str = "archlinux-keyring";
+
+// (installed, available) when the sync databases have a newer keyring than the installed one
+pub fn outdated() -> Option<(String, String)> {
+    // Exits with an error when the keyring is up to date
+    let output = remote::command("pacman", ["-Qu", PACKAGE]).run().ok()?;
+    let (_, installed, available) = backend::read_updates(&String::from_utf8_lossy(&output.stdout)).into_iter().find(|(name, _, _)| name == PACKAGE)?;
+    Some((installed, available))
+}
+
+// Syncs the databases and installs the newest keyring on its own. This is the one partial upgrade
+// Arch recommends, since the packages of the following upgrade are checked against the new keys.
+pub fn update(escalation_tool: &str) -> Result<(), Box<dyn Error>> {
+    let args = ["-Sy", "--needed", "--noconfirm", PACKAGE];
+    let output = escalation::privileged(escalation::direct(escalation_tool), "pacman", args)?.run()?;
+    if !output.status.success() {
+        return Err(format!("Updating {} failed: {}", PACKAGE, String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Scripted};
+
+    #[test]
+    fn tells_whether_the_keyring_is_behind() {
+        let _isolated = testing::isolate_blocking();
+        Scripted::default().answer("pacman -Qu archlinux-keyring", "archlinux-keyring 20240520-1 -> 20240709-1\n").install();
+        assert_eq!(outdated(), Some(("20240520-1".to_string(), "20240709-1".to_string())));
+
+        Scripted::default().fail("pacman -Qu archlinux-keyring", "").install();
+        assert_eq!(outdated(), None);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{http, remote};
+use crate::runner::Run;
+use reqwest::Client;
+use std::error::Error;
+use std::process::Command as StdCommand;
+use tracing::debug;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Tool {
+    Cargo,
+    Pipx,
+}
+
+impl Tool {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Tool::Cargo => "cargo",
+            Tool::Pipx => "pipx",
+        }
+    }
+
+    // Both commands leave the package alone when it is already up to date
+    pub fn update_command(&self, name: &str) -> StdCommand {
+        match self {
+            Tool::Cargo => remote::command("cargo", ["install", "--locked", name]),
+            Tool::Pipx => remote::command("pipx", ["upgrade", name]),
+        }
+    }
+}
+
+// (name, version, installed from git or a path)
+type Installed = (String, String, bool);
+
+// A tool installed with `cargo install` or pipx rather than the package manager
+#[derive(Clone)]
+pub struct LanguagePackage {
+    pub tool: Tool,
+    pub name: String,
+    pub version: String,
+    // Newest version on crates.io or PyPI, once checked
+    pub latest: Option<String>,
+    // Installed from git or a local path, so there is no registry version to compare with
+    pub local: bool,
+    // The package manager has a package of the same name installed too
+    pub duplicate: bool,
+}
+
+impl LanguagePackage {
+    pub fn is_outdated(&self) -> bool {
+        self.latest.as_ref().is_some_and(|latest| *latest != self.version)
+    }
+}
+
+// Tools that are not installed contribute nothing
+pub fn list_installed() -> Vec<LanguagePackage> {
+    let mut packages = Vec::new();
+    for (tool, result) in [(Tool::Cargo, cargo_installed()), (Tool::Pipx, pipx_installed())] {
+        match result {
+            Ok(found) => packages.extend(found.into_iter().map(|(name, version, local)| LanguagePackage {
+                tool,
+                name,
+                version,
+                latest: None,
+                local,
+                duplicate: false,
+            })),
+            Err(e) => debug!("Could not list {} packages: {}", tool.label(), e),
+        }
+    }
+    packages
+}
+
+// `cargo install --list` prints `ripgrep v14.1.0:` followed by the indented binaries, and
+// `foo v0.1.0 (https://github.com/...#abc123):` for crates installed from git or a path
+fn cargo_installed() -> Result<Vec<Installed>, Box<dyn Error>> {
+    let output = remote::command("cargo", ["install", "--list"]).run()?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string().into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.starts_with(char::is_whitespace))
+        .filter_map(|line| {
+            let mut words = line.trim_end_matches(':').split_whitespace();
+            let name = words.next()?.to_string();
+            let version = words.next()?.trim_start_matches('v').to_string();
+            Some((name, version, words.next().is_some()))
+        })
+        .collect())
+}
+
+fn pipx_installed() -> Result<Vec<Installed>, Box<dyn Error>> {
+    let output = remote::command("pipx", ["list", "--json"]).run()?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string().into());
+    }
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let venvs = json["venvs"].as_object().ok_or("Unexpected pipx output")?;
+    Ok(venvs
+        .iter()
+        .map(|(name, venv)| {
+            let main = &venv["metadata"]["main_package"];
+            let version = main["package_version"].as_str().unwrap_or("").to_string();
+            // pipx keeps the install spec; anything other than the plain name is a URL or path
+            let local = main["package_or_url"].as_str().is_some_and(|spec| spec != name);
+            (name.clone(), version, local)
+        })
+        .collect())
+}
+
+// Failed lookups leave `latest` unset
+pub async fn fetch_latest(packages: &mut [LanguagePackage]) {
+    // crates.io rejects requests without a user agent, which every client from http has
+    let client = match http::client() {
+        Ok(client) => client,
+        Err(e) => {
+            debug!("Could not create HTTP client: {}", e);
+            return;
+        }
+    };
+    for package in packages.iter_mut().filter(|package| !package.local) {
+        match latest_version(&client, package.tool, &package.name).await {
+            Ok(latest) => package.latest = Some(latest),
+            Err(e) => debug!("Could not check {} for updates: {}", package.name, e),
+        }
+    }
+}
+
+async fn latest_version(client: &Client, tool: Tool, name: &str) -> Result<String, Box<dyn Error>> {
+    let (url, pointer) = match tool {
+        Tool::Cargo => (format!("https://crates.io/api/v1/crates/{}", name), "/crate/max_stable_version"),
+        Tool::Pipx => (format!("https://pypi.org/pypi/{}/json", name), "/info/version"),
+    };
+    let response = http::send(client.get(&url)).await?.error_for_status()?;
+    let json = response.json::<serde_json::Value>().await?;
+    Ok(json.pointer(pointer).and_then(|version| version.as_str()).ok_or("No version in response")?.to_string())
+}
@@ -0,0 +1,67 @@
+use std::process::Command as StdCommand;
+
+// Progress of the install in progress on the app's dock or taskbar entry, through the Unity
+// LauncherEntry D-Bus API that Plasma, Dash to Dock, Plank and others also follow. The entry is
+// found by the name of the desktop file.
+const APP_URI: &str = "application://cookin.desktop";
+const OBJECT_PATH: &str = "/com/canonical/unity/launcherentry/cookin";
+
+// Shows `progress` between 0 and 1 on the launcher entry, or hides it for None
+pub fn show(progress: Option<f32>) {
+    emit(properties(progress));
+}
+
+// Shows the number of updates found as a badge on the launcher entry, or hides it for none
+pub fn show_count(count: usize) {
+    emit(count_properties(count));
+}
+
+fn emit(properties: String) {
+    // Best-effort like notifications; gdbus comes with glib2 but no launcher may be listening
+    let _ = StdCommand::new("gdbus")
+        .args(["emit", "--session", "--object-path", OBJECT_PATH, "--signal", "com.canonical.Unity.LauncherEntry.Update", APP_URI])
+        .arg(properties)
+        .spawn();
+}
+
+// The signal's a{sv} argument in GVariant text format
+fn properties(progress: Option<f32>) -> String {
+    match progress {
+        Some(progress) => format!("{{'progress': <{:.2}>, 'progress-visible': <true>}}", progress.clamp(0.0, 1.0)),
+        None => "{'progress-visible': <false>}".to_string(),
+    }
+}
+
+fn count_properties(count: usize) -> String {
+    match count {
+        0 => "{'count-visible': <false>}".to_string(),
+        count => format!("{{'count': <int64 {}>, 'count-visible': <true>}}", count),
+    }
+}
+
+// Window title with the percentage in front, for taskbars without launcher progress
+pub fn title(base: &str, progress: Option<f32>) -> String {
+    match progress {
+        Some(progress) => format!("{}% – {}", percent(progress), base),
+        None => base.to_string(),
+    }
+}
+
+pub fn percent(progress: f32) -> u32 {
+    (progress.clamp(0.0, 1.0) * 100.0).round() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_progress_for_the_launcher_and_the_title() {
+        assert_eq!(properties(Some(0.4213)), "{'progress': <0.42>, 'progress-visible': <true>}");
+        assert_eq!(properties(None), "{'progress-visible': <false>}");
+        assert_eq!(count_properties(3), "{'count': <int64 3>, 'count-visible': <true>}");
+        assert_eq!(count_properties(0), "{'count-visible': <false>}");
+        assert_eq!(title("Rust AUR Helper GUI", Some(0.4213)), "42% – Rust AUR Helper GUI");
+        assert_eq!(title("Rust AUR Helper GUI", None), "Rust AUR Helper GUI");
+    }
+}
@@ -0,0 +1,97 @@
+use crate::remote;
+use crate::runner::Run;
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+use std::process::{Command as StdCommand, Stdio};
+
+// A package file from disk, e.g. one downloaded or built elsewhere, to be installed like `pacman -U`
+#[derive(Clone)]
+pub struct LocalPackage {
+    pub path: String,
+    pub name: String,
+    pub version: String,
+    pub description: String,
+    pub arch: String,
+    // Installed size in bytes
+    pub size: u64,
+    pub depends: Vec<String>,
+}
+
+pub fn is_package_file(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name.to_string_lossy().contains(".pkg.tar"))
+}
+
+// Reads the .PKGINFO at the root of the archive; bsdtar ships with pacman and handles every compression
+pub fn read(path: &Path) -> Result<LocalPackage, Box<dyn Error>> {
+    let output = StdCommand::new("bsdtar").arg("-xOf").arg(path).arg(".PKGINFO").run()?;
+    if !output.status.success() {
+        return Err(format!("{} is not a package file: {}", path.display(), String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    let mut package = LocalPackage {
+        path: path.to_string_lossy().into_owned(),
+        name: String::new(),
+        version: String::new(),
+        description: String::new(),
+        arch: String::new(),
+        size: 0,
+        depends: Vec::new(),
+    };
+    // `key = value` lines; keys such as depend repeat once per value
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let Some((key, value)) = line.split_once(" = ") else {
+            continue;
+        };
+        let value = value.trim().to_string();
+        match key.trim() {
+            "pkgname" => package.name = value,
+            "pkgver" => package.version = value,
+            "pkgdesc" => package.description = value,
+            "arch" => package.arch = value,
+            "size" => package.size = value.parse().unwrap_or(0),
+            "depend" => package.depends.push(value),
+            _ => {}
+        }
+    }
+    if package.name.is_empty() {
+        return Err(format!("{} has no package name in its .PKGINFO", path.display()).into());
+    }
+    Ok(package)
+}
+
+// Path the package can be installed from on the active host; files are copied to the host's
+// build directory first when it is remote
+pub fn stage(package: &LocalPackage) -> Result<String, Box<dyn Error>> {
+    let Some((name, host)) = remote::active() else {
+        return Ok(package.path.clone());
+    };
+    let file_name = Path::new(&package.path).file_name().ok_or("Package path has no file name")?.to_string_lossy().into_owned();
+    let remote_path = format!("{}/{}", host.build_dir.trim_end_matches('/'), file_name);
+    let mkdir = remote::command("mkdir", ["-p", &host.build_dir]).run()?;
+    if !mkdir.status.success() {
+        return Err(format!("Could not create {} on {}: {}", host.build_dir, name, String::from_utf8_lossy(&mkdir.stderr).trim()).into());
+    }
+    let output = remote::command("tee", [remote_path.as_str()]).stdin(File::open(&package.path)?).stdout(Stdio::null()).run()?;
+    if !output.status.success() {
+        return Err(format!("Could not copy {} to {}: {}", file_name, name, String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(remote_path)
+}
+
+// zenity or kdialog, whichever is installed; None when neither is or the dialog was cancelled
+pub fn pick_file() -> Option<String> {
+    let mut dialog = if crate::command_exists("zenity") {
+        let mut zenity = StdCommand::new("zenity");
+        zenity.args(["--file-selection", "--title=Install package file", "--file-filter=Packages | *.pkg.tar.*"]);
+        zenity
+    } else if crate::command_exists("kdialog") {
+        let mut kdialog = StdCommand::new("kdialog");
+        kdialog.args(["--getopenfilename", ".", "*.pkg.tar.*"]);
+        kdialog
+    } else {
+        return None;
+    };
+    let output = dialog.output().ok()?;
+    let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !path.is_empty()).then_some(path)
+}
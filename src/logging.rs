@@ -0,0 +1,295 @@
+use crate::accessibility;
+use crate::ansi;
+use crate::i18n::tr;
+use chrono::{DateTime, Local};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub enum LogLevel {
+    Debug,
+    #[default]
+    Info,
+    Warning,
+    Error,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 4] = [LogLevel::Debug, LogLevel::Info, LogLevel::Warning, LogLevel::Error];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warning => "WARN",
+            LogLevel::Error => "ERROR",
+        }
+    }
+
+    fn color(&self, ui: &egui::Ui) -> egui::Color32 {
+        match self {
+            LogLevel::Debug => ui.visuals().weak_text_color(),
+            LogLevel::Info => ui.visuals().text_color(),
+            LogLevel::Warning => egui::Color32::YELLOW,
+            LogLevel::Error => egui::Color32::RED,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct LogEntry {
+    pub timestamp: DateTime<Local>,
+    pub level: LogLevel,
+    pub package: Option<String>,
+    // Index into `Log::operations`; entries logged outside an operation have none
+    pub operation: Option<usize>,
+    pub message: String,
+}
+
+pub struct Operation {
+    pub title: String,
+    pub package: Option<String>,
+    pub started: DateTime<Local>,
+}
+
+pub struct Log {
+    pub entries: Vec<LogEntry>,
+    pub operations: Vec<Operation>,
+    current_operation: Option<usize>,
+    // Both are kept between sessions
+    pub min_level: LogLevel,
+    search: String,
+    pub auto_scroll: bool,
+    // Package whose entries "Save log" writes; None saves everything
+    export_package: Option<String>,
+    // Operation to open and scroll to on the next frame
+    reveal: Option<usize>,
+}
+
+impl Default for Log {
+    fn default() -> Log {
+        Log {
+            entries: Vec::new(),
+            operations: Vec::new(),
+            current_operation: None,
+            min_level: LogLevel::Info,
+            search: String::new(),
+            auto_scroll: true,
+            export_package: None,
+            reveal: None,
+        }
+    }
+}
+
+impl Log {
+    // Starts a new collapsible section; following entries are grouped under it
+    pub fn begin_operation(&mut self, title: &str, package: Option<&str>) {
+        crate::crash::note_operation(title);
+        self.operations.push(Operation {
+            title: title.to_string(),
+            package: package.map(str::to_string),
+            started: Local::now(),
+        });
+        self.current_operation = Some(self.operations.len() - 1);
+    }
+
+    pub fn push(&mut self, level: LogLevel, message: &str) {
+        let package = self
+            .current_operation
+            .and_then(|index| self.operations[index].package.clone());
+        self.entries.push(LogEntry {
+            timestamp: Local::now(),
+            level,
+            package,
+            operation: self.current_operation,
+            message: message.to_string(),
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.operations.clear();
+        self.current_operation = None;
+    }
+
+    // Adds the sections of a log kept elsewhere, e.g. by a build beside the current operation,
+    // without changing where new entries go
+    pub fn append(&mut self, other: Log) {
+        let offset = self.operations.len();
+        self.operations.extend(other.operations);
+        self.entries.extend(other.entries.into_iter().map(|mut entry| {
+            entry.operation = entry.operation.map(|index| index + offset);
+            entry
+        }));
+    }
+
+    // Opens the last section of `package` and scrolls to it; false when it has none
+    pub fn reveal(&mut self, package: &str) -> bool {
+        self.reveal = self.operations.iter().rposition(|operation| operation.package.as_deref() == Some(package));
+        if self.reveal.is_some() {
+            // Sticking to the bottom would scroll away from it again
+            self.auto_scroll = false;
+        }
+        self.reveal.is_some()
+    }
+
+    fn packages(&self) -> Vec<String> {
+        let mut packages: Vec<String> = self.operations.iter().filter_map(|operation| operation.package.clone()).collect();
+        packages.sort();
+        packages.dedup();
+        packages
+    }
+
+    // Writes the log (or only one package's entries) to the logs directory and returns the file path
+    pub fn save(&self, package: Option<&str>) -> Result<PathBuf, Box<dyn Error>> {
+        let entries = self.entries.iter().filter(|entry| package.is_none() || entry.package.as_deref() == package);
+        write_log_file(package.unwrap_or("aur-helper"), entries)
+    }
+
+    // Persists the entries of the current operation, so a failed build can be attached to a bug report
+    pub fn save_failed_build(&self) -> Result<PathBuf, Box<dyn Error>> {
+        let index = self.current_operation.ok_or("No operation in progress")?;
+        let name = self.operations[index].package.as_deref().unwrap_or("build");
+        let entries = self.entries.iter().filter(|entry| entry.operation == Some(index));
+        write_log_file(&format!("{}-failed", name), entries)
+    }
+
+    // Messages of the operation in progress, oldest first
+    pub fn current_messages(&self) -> impl Iterator<Item = &str> {
+        let index = self.current_operation;
+        self.entries.iter().filter(move |entry| index.is_some() && entry.operation == index).map(|entry| entry.message.as_str())
+    }
+
+    fn matches(&self, entry: &LogEntry) -> bool {
+        let search = self.search.to_lowercase();
+        entry.level >= self.min_level
+            && (search.is_empty()
+                || ansi::strip(&entry.message).to_lowercase().contains(&search)
+                || entry.package.as_deref().is_some_and(|package| package.to_lowercase().contains(&search)))
+    }
+}
+
+pub fn logs_dir() -> Result<PathBuf, Box<dyn Error>> {
+    let data_dir = dirs::data_dir().ok_or("Could not determine data directory")?;
+    Ok(data_dir.join("aur-helper").join("logs"))
+}
+
+// Plain-text form used for log files and the clipboard
+fn format_entry(entry: &LogEntry) -> String {
+    format!(
+        "{} [{}] {}{}",
+        entry.timestamp.format("%Y-%m-%d %H:%M:%S"),
+        entry.level.label(),
+        entry.package.as_ref().map(|package| format!("[{}] ", package)).unwrap_or_default(),
+        ansi::strip(&entry.message)
+    )
+}
+
+fn format_entries<'a>(entries: impl Iterator<Item = &'a LogEntry>) -> String {
+    entries.map(|entry| format_entry(entry) + "\n").collect()
+}
+
+fn write_log_file<'a>(name: &str, entries: impl Iterator<Item = &'a LogEntry>) -> Result<PathBuf, Box<dyn Error>> {
+    let dir = logs_dir()?;
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}-{}.log", name, Local::now().format("%Y%m%d-%H%M%S")));
+
+    fs::write(&path, format_entries(entries))?;
+    Ok(path)
+}
+
+fn show_entry(ui: &mut egui::Ui, entry: &LogEntry) {
+    ui.horizontal_wrapped(|ui| {
+        ui.weak(entry.timestamp.format("%H:%M:%S").to_string());
+        ui.colored_label(entry.level.color(ui), entry.level.label());
+        if let Some(package) = &entry.package {
+            ui.weak(format!("[{}]", package));
+        }
+        let font = egui::TextStyle::Body.resolve(ui.style());
+        let job = ansi::layout_job(&entry.message, entry.level.color(ui), ui.visuals().strong_text_color(), font);
+        ui.label(job).context_menu(|ui| {
+            if ui.button(tr("Copy line")).clicked() {
+                ui.ctx().copy_text(format_entry(entry));
+                ui.close_menu();
+            }
+        });
+    });
+}
+
+pub fn show(ui: &mut egui::Ui, log: &mut Log) {
+    ui.horizontal(|ui| {
+        ui.label(tr("Log:"));
+        egui::ComboBox::from_id_source("log_level")
+            .selected_text(log.min_level.label())
+            .show_ui(ui, |ui| {
+                for level in LogLevel::ALL {
+                    ui.selectable_value(&mut log.min_level, level, level.label());
+                }
+            });
+        let search = ui.add(egui::TextEdit::singleline(&mut log.search).hint_text(tr("Search log")).desired_width(160.0));
+        accessibility::name(&search, tr("Search log"));
+        ui.checkbox(&mut log.auto_scroll, tr("Auto-scroll"));
+    });
+
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_source("log_export_package")
+            .selected_text(log.export_package.as_deref().unwrap_or(tr("All packages")))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut log.export_package, None, tr("All packages"));
+                for package in log.packages() {
+                    ui.selectable_value(&mut log.export_package, Some(package.clone()), package);
+                }
+            });
+        // Copies follow the current level filter and search
+        if ui.button(tr("Copy all")).clicked() {
+            ui.ctx().copy_text(format_entries(log.entries.iter().filter(|entry| log.matches(entry))));
+        }
+        if ui.button(tr("Copy errors only")).clicked() {
+            let errors = log.entries.iter().filter(|entry| entry.level == LogLevel::Error && log.matches(entry));
+            ui.ctx().copy_text(format_entries(errors));
+        }
+        if ui.button(tr("Save log")).clicked() {
+            match log.save(log.export_package.as_deref()) {
+                Ok(path) => log.push(LogLevel::Info, &format!("Log saved to {}", path.display())),
+                Err(e) => log.push(LogLevel::Error, &format!("Could not save log: {}", e)),
+            }
+        }
+    });
+
+    let reveal = log.reveal.take();
+    accessibility::log_region(ui, egui::Id::new("log_region"), tr("Log"), |ui| {
+        egui::ScrollArea::vertical()
+            .max_height(240.0)
+            .stick_to_bottom(log.auto_scroll)
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                for entry in log.entries.iter().filter(|entry| entry.operation.is_none() && log.matches(entry)) {
+                    show_entry(ui, entry);
+                }
+
+                let last = log.operations.len().saturating_sub(1);
+                for (index, operation) in log.operations.iter().enumerate() {
+                    let entries: Vec<&LogEntry> = log
+                        .entries
+                        .iter()
+                        .filter(|entry| entry.operation == Some(index) && log.matches(entry))
+                        .collect();
+                    let section = egui::CollapsingHeader::new(format!("{} {}", operation.started.format("%H:%M:%S"), operation.title))
+                        .id_source(("log_operation", index))
+                        .default_open(index == last)
+                        .open((reveal == Some(index)).then_some(true))
+                        .show(ui, |ui| {
+                            for entry in entries {
+                                show_entry(ui, entry);
+                            }
+                        });
+                    if reveal == Some(index) {
+                        section.header_response.scroll_to_me(Some(egui::Align::TOP));
+                    }
+                }
+            });
+    });
+}
@@ -1,448 +1,5802 @@
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, Command};
+use clap_complete::engine::ArgValueCandidates;
+use clap_complete::CompleteEnv;
 use reqwest::Client;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fs;
-use std::process::Command as StdCommand;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command as StdCommand, ExitStatus, Stdio};
+use std::sync::mpsc::Receiver;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tar::Archive;
 use flate2::read::GzDecoder;
 use reqwest::header::CONTENT_TYPE;
+use tokio::io::AsyncWriteExt;
 use tokio::runtime::Runtime;
 use eframe::egui;
+use tracing::{debug, error, info, warn};
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Write};
 
-#[derive(Deserialize)]
+mod accessibility;
+mod actions;
+mod ansi;
+mod app_update;
+mod appstream;
+mod backend;
+mod binary_repo;
+mod browse;
+mod build_dirs;
+mod build_order;
+mod build_times;
+mod cli_output;
+mod completions;
+mod config;
+mod container;
+mod crash;
+mod dep_tree;
+mod desktop_entry;
+mod diagnostics;
+mod discover;
+mod escalation;
+mod exit_codes;
+mod groups;
+mod helper;
+mod hooks;
+mod i18n;
+mod http;
+mod instance;
+mod keyring;
+mod language;
+mod launcher;
+mod local_package;
+mod logging;
+mod makepkg_conf;
+mod metapackage;
+mod mirrors;
+mod namcap;
+mod notifications;
+mod pacman_conf;
+mod partial_upgrade;
+mod pgp_keys;
+mod pipeline;
+mod prerequisites;
+mod queue;
+mod remote;
+mod removal;
+mod review;
+mod runner;
+mod scan;
+mod selection;
+mod server;
+mod settings;
+mod snapshot_mirrors;
+mod source_check;
+mod source_hashes;
+mod srcinfo;
+mod state;
+mod terminal;
+#[cfg(test)]
+mod testing;
+#[cfg(test)]
+mod tests;
+mod theme;
+mod timer;
+mod transaction;
+mod troubleshoot;
+mod updates;
+
+use actions::{Action, CommandPalette, PaletteResult};
+use binary_repo::Prebuilt;
+use browse::BrowseEntry;
+use cli_output::{CliOutput, SummaryRow};
+use config::{Config, ConfirmStep, Hold, HostProfile, RemoveMode};
+use diagnostics::BUILD_OUTPUT_TARGET;
+use discover::{Discover, Ranking};
+use exit_codes::ExitCode;
+use language::LanguagePackage;
+use local_package::LocalPackage;
+use i18n::{fill, tr};
+use logging::{Log, LogLevel};
+use makepkg_conf::MakepkgConf;
+use mirrors::{MirrorsAction, MirrorsView};
+use namcap::{NamcapLevel, NamcapMessage};
+use review::{DiffKind, PendingReview};
+use runner::Run;
+use scan::Severity;
+use settings::{SettingsAction, SettingsDraft};
+
+#[derive(Clone, Serialize, Deserialize)]
 struct Package {
     name: String,
+    package_base: String,
     version: String,
     description: String,
     urlpath: String,
+    // None for orphaned packages
+    maintainer: Option<String>,
+    votes: u64,
+    popularity: f64,
+    // Depends, MakeDepends and CheckDepends, with version constraints
+    #[serde(default)]
+    depends: Vec<String>,
+    #[serde(default)]
+    provides: Vec<String>,
+}
+
+impl Package {
+    // One entry of the `results` array of an RPC info reply
+    fn from_rpc(pkg: &serde_json::Value) -> Package {
+        Package {
+            name: pkg["Name"].as_str().unwrap_or("").to_string(),
+            package_base: pkg["PackageBase"].as_str().unwrap_or("").to_string(),
+            version: pkg["Version"].as_str().unwrap_or("").to_string(),
+            description: pkg["Description"].as_str().unwrap_or("").to_string(),
+            urlpath: pkg["URLPath"].as_str().unwrap_or("").to_string(),
+            maintainer: pkg["Maintainer"].as_str().map(str::to_string),
+            votes: pkg["NumVotes"].as_u64().unwrap_or(0),
+            popularity: pkg["Popularity"].as_f64().unwrap_or(0.0),
+            depends: ["Depends", "MakeDepends", "CheckDepends"].iter().flat_map(|key| strings(&pkg[*key])).collect(),
+            provides: strings(&pkg["Provides"]),
+        }
+    }
+}
+
+// The strings of a JSON array; nothing for a missing field
+fn strings(value: &serde_json::Value) -> Vec<String> {
+    value.as_array().into_iter().flatten().filter_map(|item| item.as_str()).map(str::to_string).collect()
+}
+
+// How an installed search result relates to the newest version its source offers
+#[derive(Clone, Copy, PartialEq)]
+enum VersionState {
+    UpToDate,
+    Outdated,
+    // Installed is newer, or a VCS package whose AUR version only records the last PKGBUILD change
+    LocalNewer,
+    // The source does not report its newest version
+    Unknown,
+}
+
+struct ResultVersion {
+    installed: String,
+    latest: Option<String>,
+    state: VersionState,
+}
+
+const VCS_SUFFIXES: [&str; 6] = ["-git", "-svn", "-hg", "-bzr", "-cvs", "-darcs"];
+
+// How the query is matched; regexes filter the results already fetched instead of searching again
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+enum SearchMatch {
+    #[default]
+    Substring,
+    Exact,
+    Regex,
+}
+
+impl SearchMatch {
+    const ALL: [SearchMatch; 3] = [SearchMatch::Substring, SearchMatch::Exact, SearchMatch::Regex];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SearchMatch::Substring => "Contains",
+            SearchMatch::Exact => "Exact name",
+            SearchMatch::Regex => "Regex (filter results)",
+        }
+    }
+}
+
+// AUR RPC search fields; other sources always search by name
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+enum SearchBy {
+    #[default]
+    NameDesc,
+    Maintainer,
+}
+
+impl SearchBy {
+    const ALL: [SearchBy; 2] = [SearchBy::NameDesc, SearchBy::Maintainer];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SearchBy::NameDesc => "Name and description",
+            SearchBy::Maintainer => "Maintainer",
+        }
+    }
+
+    // `by` parameter of the RPC
+    fn rpc_field(&self) -> &'static str {
+        match self {
+            SearchBy::NameDesc => "name-desc",
+            SearchBy::Maintainer => "maintainer",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+enum Tab {
+    #[default]
+    Search,
+    Installed,
+    // Tools installed with cargo install and pipx
+    Languages,
+    // AppStream categories and pacman groups
+    Browse,
+    // Most voted and rising AUR packages
+    Discover,
+    // Installed packages by size
+    DiskUsage,
+    // Packages kept out of updates
+    Held,
+    // Outdated packages of every source, ticked to be updated
+    Updates,
+    // PGP keys imported for building packages
+    Keys,
+}
+
+// UI state restored on the next launch; window geometry is persisted by eframe itself
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct UiState {
+    tab: Tab,
+    package_name: String,
+    run_namcap: bool,
+    search_by: SearchBy,
+    search_match: SearchMatch,
+    discover_ranking: Ranking,
+    log_level: LogLevel,
+    log_auto_scroll: bool,
+}
+
+// What a first launch, or one saved before a field existed, starts with
+impl Default for UiState {
+    fn default() -> UiState {
+        let log = Log::default();
+        UiState {
+            tab: Tab::default(),
+            package_name: String::new(),
+            run_namcap: false,
+            search_by: SearchBy::default(),
+            search_match: SearchMatch::default(),
+            discover_ranking: Ranking::default(),
+            log_level: log.min_level,
+            log_auto_scroll: log.auto_scroll,
+        }
+    }
 }
 
 #[derive(Default)]
 struct AppState {
-    log: Vec<String>,
+    config: Config,
+    log: Log,
     package_name: String,
     is_running: bool,
     progress: Option<String>,
     error: Option<String>,
-    search_results: Vec<String>,
+    // (source, package), where source is a backend name
+    search_results: Vec<(&'static str, String)>,
+    search_by: SearchBy,
+    search_match: SearchMatch,
+    // Installed and newest versions of the installed search results, keyed by (source, name)
+    search_versions: HashMap<(&'static str, String), ResultVersion>,
+    // Package base of AUR split packages whose name differs from it
+    search_bases: HashMap<String, String>,
+    // The pacman group named by the last search, offered above the results
+    search_group: Option<groups::Group>,
+    // The group whose members are being picked for installing
+    group_install: Option<groups::Group>,
+    // The queued AUR packages in the order worked out from their dependencies, shown in the
+    // transaction summary
+    build_order: Vec<String>,
+    // A metapackage being made from the selection
+    metapackage_draft: Option<metapackage::Draft>,
+    // Found by the last check in the Updates tab
+    updates: Option<Vec<updates::Outdated>>,
+    // Read when the Keys tab is opened
+    pgp_keys: Vec<pgp_keys::ImportedKey>,
+    // Bases whose split packages are folded in the results
+    collapsed_bases: HashSet<String>,
+    // Name completions for the search box from the AUR, with the one picked by keyboard
+    suggestions: Vec<String>,
+    selected_suggestion: Option<usize>,
     selected_package: Option<String>,
+    // Backend the selected package comes from
+    selected_source: &'static str,
+    pending_review: Option<PendingReview>,
+    run_namcap: bool,
+    namcap_messages: Vec<NamcapMessage>,
+    hidden_namcap_levels: Vec<NamcapLevel>,
+    settings: Option<SettingsDraft>,
+    command_palette: Option<CommandPalette>,
+    tab: Tab,
+    // Explicitly installed packages of every source as (source, name, version)
+    installed_packages: Vec<(&'static str, String, String)>,
+    // Rows picked with Ctrl or Shift in the package list
+    selection: selection::Selection,
+    language_packages: Vec<LanguagePackage>,
+    // Loaded once, the first time the Browse tab is opened
+    browse_entries: Option<Vec<BrowseEntry>>,
+    browse_category: Option<String>,
+    discover: Option<Discover>,
+    discover_ranking: Ranking,
+    // (source, package, bytes), largest first
+    package_sizes: Vec<(&'static str, String, u64)>,
+    // Build directories of finished installs, measured with the package sizes
+    build_leftovers: Option<build_dirs::Usage>,
+    details: Option<Details>,
+    // AUR metadata of search results with when it was fetched; fetched in the background once
+    // they are shown, and again when older than the configured TTL
+    metadata_cache: HashMap<String, (Instant, Package)>,
+    // Names whose metadata is being fetched, so each is only asked for once
+    metadata_pending: HashSet<String>,
+    confirm_uninstall: Option<(&'static str, String)>,
+    // Several packages to remove at once, from the selection
+    confirm_bulk_uninstall: Option<Vec<selection::Key>>,
+    // Chosen in the uninstall confirmation for the next removal; the configured mode otherwise
+    remove_mode: Option<RemoveMode>,
+    // What the confirmed removal would take with it
+    removal_impact: Option<removal::Preview>,
+    // Step of the install in progress, recorded in `failure` when it fails
+    step: Option<Step>,
+    // Build directory of the current install, with the host it is built on
+    build_dir: Option<(String, Option<HostProfile>)>,
+    // Package files of the current build, so a failed install can be retried without rebuilding
+    built_files: Vec<String>,
+    // When the running makepkg started, with how long builds of the package usually take
+    build_started: Option<(Instant, Option<Duration>)>,
+    // The last install that failed, until it is retried or skipped
+    failure: Option<Failure>,
+    // Package about to be held, while its reason is typed
+    hold_draft: Option<(String, Hold)>,
+    // Packages installed after the current operation, one after another
+    queue: queue::Queue,
+    // Reviewed queued packages building beside the current operation, with `concurrent_builds`
+    build_jobs: Vec<BuildJob>,
+    // First `d` of a vim-style `dd`
+    pending_delete: bool,
+    // Terminal progress bar, only set in CLI mode on a TTY
+    progress_bar: Option<indicatif::ProgressBar>,
+    // Exit code for the failure recorded in `error`, used by the CLI
+    error_code: Option<ExitCode>,
+    // Confirmations are asked on the terminal instead of in GUI dialogs
+    cli_mode: bool,
+    pending_transaction: Option<PendingTransaction>,
+    dependency_cycle: Option<DependencyCycle>,
+    pending_dependencies: Option<PendingDependencies>,
+    pending_partial_upgrade: Option<PendingPartialUpgrade>,
+    // (installed, available) archlinux-keyring, offered to be updated before the transaction
+    pending_keyring: Option<((String, String), tokio::sync::oneshot::Sender<bool>)>,
+    // Packages built first to break a dependency cycle, with the dependencies pacman is told to
+    // assume installed, as "name=version"
+    cycle_breaks: HashMap<String, Vec<String>>,
+    // Offered instead of building the selected package from source
+    prebuilt_offer: Option<Prebuilt>,
+    // Package file dropped on the window or picked in a file dialog, waiting for confirmation
+    local_package: Option<LocalPackage>,
+    // Open mirrors window; its work runs beside package operations
+    mirrors: Option<MirrorsView>,
+    // Build prerequisites missing on the active host, while the offer to install them is shown
+    missing_prerequisites: Option<Vec<String>>,
+    // Report of a crash in the last session, until its dialog is closed
+    crash_report: Option<PathBuf>,
+    // Installs interrupted in an earlier session, offered for resuming at startup
+    resumable: Vec<pipeline::Checkpoint>,
+    // A newer release of the app, found at startup when `check_app_updates` is on
+    app_update: Option<app_update::Release>,
+    // Kept in the state file, as is the search history, newest first
+    favorites: Vec<state::Favorite>,
+    search_history: Vec<String>,
+}
+
+// Contents of the details window; AUR metadata only exists for AUR packages and AppStream
+// data only for desktop applications found in the local catalogs
+struct Details {
+    name: String,
+    aur: Option<Package>,
+    // When `aur` was fetched
+    refreshed: Instant,
+    appstream: Option<appstream::Component>,
+}
+
+// The steps an AUR install goes through; other sources only have Install
+#[derive(Clone, Copy, PartialEq)]
+enum Step {
+    Fetch,
+    Download,
+    Review,
+    Build,
+    Install,
+}
+
+impl Step {
+    fn label(self) -> &'static str {
+        match self {
+            Step::Fetch => "Fetching metadata",
+            Step::Download => "Downloading the snapshot",
+            Step::Review => "Reading the PKGBUILD for review",
+            Step::Build => "Building",
+            Step::Install => "Installing",
+        }
+    }
+
+    // Share of an install done when the step starts; building takes longest by far
+    fn progress(self) -> f32 {
+        match self {
+            Step::Fetch => 0.0,
+            Step::Download => 0.05,
+            Step::Review => 0.1,
+            Step::Build => 0.15,
+            Step::Install => 0.9,
+        }
+    }
+}
+
+// An install that stopped at `step`, with what is needed to run that step again
+struct Failure {
+    source: &'static str,
+    package: String,
+    step: Step,
+    error: String,
+    // The operation's last log lines
+    output: Vec<String>,
+    hints: Vec<troubleshoot::Hint>,
+    build_dir: Option<(String, Option<HostProfile>)>,
+    // Set once the package was reviewed, so a build or install is retried without another review
+    review: Option<PendingReview>,
+    built_files: Vec<String>,
+}
+
+// A built package waiting for the user to confirm installation
+// A build of a queued package in a state of its own, so the queue can move on; its log is added
+// to the window's when it is done
+struct BuildJob {
+    package: String,
+    state: Arc<Mutex<AppState>>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+struct PendingTransaction {
+    package: String,
+    summary: transaction::Summary,
+    reply: tokio::sync::oneshot::Sender<bool>,
+}
+
+struct PendingDependencies {
+    tree: dep_tree::Tree,
+    reply: tokio::sync::oneshot::Sender<bool>,
+}
+
+struct PendingPartialUpgrade {
+    // Installed packages the sync databases have newer versions of
+    outdated: Vec<backend::Update>,
+    reply: tokio::sync::oneshot::Sender<partial_upgrade::Choice>,
+}
+
+// Queued packages that depend on each other, waiting for the user to pick where to break the cycle
+struct DependencyCycle {
+    cycle: Vec<String>,
+    nodes: Vec<build_order::Node>,
 }
 
 impl AppState {
     fn log(&mut self, message: &str) {
-        self.log.push(message.to_string());
+        self.log.push(LogLevel::Info, message);
+    }
+
+    fn log_warning(&mut self, message: &str) {
+        self.log.push(LogLevel::Warning, message);
+    }
+
+    fn log_error(&mut self, message: &str) {
+        self.log.push(LogLevel::Error, message);
     }
 
     fn clear_log(&mut self) {
         self.log.clear();
     }
 
-    fn add_search_results(&mut self, results: Vec<String>) {
+    // Turns the recorded error into a failure of the current step, keeping what a retry needs
+    fn record_failure(&mut self, source: &'static str, package: &str, review: Option<PendingReview>) {
+        let Some(error) = self.error.clone() else {
+            return;
+        };
+        let mut output: Vec<String> = self.log.current_messages().map(ansi::strip).collect();
+        let hints = troubleshoot::diagnose(&error, output.iter().map(String::as_str));
+        output.drain(..output.len().saturating_sub(FAILURE_OUTPUT_LINES));
+        // Before the build starts, the sources are where they were reviewed
+        let build_dir = self.build_dir.take().or_else(|| review.as_ref().map(|review| (review.build_dir.clone(), None)));
+        self.failure = Some(Failure {
+            source,
+            package: package.to_string(),
+            step: self.step.take().unwrap_or(Step::Install),
+            error,
+            output,
+            hints,
+            build_dir,
+            review,
+            built_files: std::mem::take(&mut self.built_files),
+        });
+    }
+
+    // Queues `package` again ahead of the other waiting ones. Its failure holds the queue, so it
+    // is dismissed.
+    fn retry_queued(&mut self, package: &str) {
+        if self.failure.as_ref().is_some_and(|failure| failure.package == package) {
+            self.failure = None;
+        }
+        self.queue.retry(package);
+    }
+
+    // Overall progress of the install in progress and the queued ones, between 0 and 1; None
+    // when no install is under way. Waiting for the review counts as under way.
+    fn transaction_progress(&self) -> Option<f32> {
+        let step = self.step.filter(|_| self.is_running || self.pending_review.is_some())?;
+        Some(self.queue.progress(step.progress()))
+    }
+
+    // Metadata of `name` with when it was fetched, unless that is longer ago than the TTL
+    fn cached_metadata(&self, name: &str) -> Option<&(Instant, Package)> {
+        let ttl = Duration::from_secs(u64::from(self.config.metadata_ttl_minutes) * 60);
+        self.metadata_cache.get(name).filter(|(fetched, _)| fetched.elapsed() < ttl)
+    }
+
+    fn add_search_results(&mut self, results: Vec<(&'static str, String)>) {
         self.search_results = results;
     }
 
-    fn select_package(&mut self, package: Option<String>) {
+    // Search results narrowed by the regex in the search box; an invalid regex filters nothing.
+    // Split packages are moved next to the first result of their base.
+    fn search_rows(&self) -> Vec<(&'static str, String)> {
+        let regex = (self.search_match == SearchMatch::Regex).then(|| regex::Regex::new(&self.package_name).ok()).flatten();
+        let rows: Vec<(&'static str, String)> =
+            self.search_results.iter().filter(|(_, name)| regex.as_ref().is_none_or(|regex| regex.is_match(name))).cloned().collect();
+        let mut bases: Vec<(&'static str, &str)> = Vec::new();
+        for (source, name) in &rows {
+            let base = self.search_base(name);
+            if !bases.contains(&(*source, base)) {
+                bases.push((*source, base));
+            }
+        }
+        bases
+            .iter()
+            .flat_map(|(source, base)| rows.iter().filter(move |(row_source, name)| row_source == source && self.search_base(name) == *base))
+            .cloned()
+            .collect()
+    }
+
+    fn search_base<'a>(&'a self, name: &'a str) -> &'a str {
+        self.search_bases.get(name).map_or(name, String::as_str)
+    }
+
+    fn select_package(&mut self, package: Option<(&'static str, String)>) {
+        let (source, package) = package.unzip();
+        self.selected_source = source.unwrap_or_default();
+        self.selection.reset(package.clone().map(|package| (self.selected_source, package)));
         self.selected_package = package;
     }
+
+    // A window waiting for the user's answer
+    fn modal_open(&self) -> bool {
+        self.command_palette.is_some()
+            || self.pending_review.is_some()
+            || self.prebuilt_offer.is_some()
+            || self.missing_prerequisites.is_some()
+            || self.crash_report.is_some()
+            || !self.resumable.is_empty()
+            || self.local_package.is_some()
+            || self.confirm_uninstall.is_some()
+            || self.confirm_bulk_uninstall.is_some()
+            || self.pending_transaction.is_some()
+            || self.group_install.is_some()
+            || self.metapackage_draft.is_some()
+            || self.dependency_cycle.is_some()
+            || self.pending_dependencies.is_some()
+            || self.pending_partial_upgrade.is_some()
+            || self.pending_keyring.is_some()
+    }
+
+    fn selected_backend(&self) -> &'static dyn backend::PackageBackend {
+        backend::by_name(self.selected_source)
+    }
+
+    // Whether a package is installed, from the lists already loaded before asking its backend
+    fn knows_installed(&self, (source, name): &selection::Key) -> bool {
+        self.installed_packages.iter().any(|(installed_source, installed, _)| installed_source == source && installed == name)
+            || self.search_versions.contains_key(&(*source, name.clone()))
+            || backend::by_name(source).is_installed(name).unwrap_or(false)
+    }
+
+    fn visible_packages(&self) -> Vec<(&'static str, String)> {
+        match self.tab {
+            Tab::Search => self.search_rows(),
+            Tab::Installed => self.installed_packages.iter().map(|(source, name, _)| (*source, name.clone())).collect(),
+            Tab::Languages => Vec::new(),
+            Tab::Browse => self.browse_rows().into_iter().map(|(source, name, _)| (source, name)).collect(),
+            Tab::Discover => self.discover_rows().into_iter().map(|(source, name, _)| (source, name)).collect(),
+            Tab::DiskUsage => self.package_sizes.iter().map(|(source, name, _)| (*source, name.clone())).collect(),
+            Tab::Held | Tab::Updates | Tab::Keys => Vec::new(),
+        }
+    }
+
+    // (source, package, label) of the selected category
+    fn browse_rows(&self) -> Vec<(&'static str, String, String)> {
+        match (&self.browse_entries, &self.browse_category) {
+            (Some(entries), Some(category)) => browse::in_category(entries, category)
+                .map(|entry| (entry.source, entry.package.clone(), entry.title.clone()))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    // The rankings only exist for the AUR
+    fn discover_rows(&self) -> Vec<(&'static str, String, String)> {
+        let Some(discover) = self.discover.as_ref().filter(|_| backend::active().builds_from_source()) else {
+            return Vec::new();
+        };
+        let source = backend::active().name();
+        discover
+            .entries(self.discover_ranking)
+            .iter()
+            .map(|entry| {
+                let label = format!("{} ({} votes, popularity {:.2})", entry.name, entry.votes, entry.popularity);
+                (source, entry.name.clone(), label)
+            })
+            .collect()
+    }
+
+    // Moves the selection up or down the current list, as with j/k
+    fn move_selection(&mut self, offset: isize) {
+        let packages = self.visible_packages();
+        if packages.is_empty() {
+            return;
+        }
+        let current = self
+            .selected_package
+            .as_ref()
+            .and_then(|selected| packages.iter().position(|(source, name)| *source == self.selected_source && name == selected));
+        let next = match current {
+            Some(index) => (index as isize + offset).clamp(0, packages.len() as isize - 1) as usize,
+            None => 0,
+        };
+        self.select_package(Some(packages[next].clone()));
+    }
 }
 
 struct MyApp {
     state: Arc<Mutex<AppState>>,
     rt: Runtime,
+    applied_visuals: Option<egui::Visuals>,
+    applied_scale: Option<f32>,
+    applied_font_size: Option<f32>,
+    // Zooming changes the config outside the settings window; it is saved on exit
+    zoom_changed: bool,
+    // tracing events mirrored into the log panel
+    log_events: Option<Receiver<(LogLevel, String)>>,
+    // Packages handed over by `install` on the command line
+    handoffs: Option<Receiver<Vec<String>>>,
+    // Actions clicked in desktop notifications
+    notification_actions: Option<Receiver<notifications::Action>>,
+    // Percentage and number of updates last shown in the title and on the launcher entry
+    shown_progress: (Option<u32>, usize),
 }
-impl eframe::App for MyApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Lock state for mutable access
-        let mut state = self.state.lock().unwrap();
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.label("Rust AUR Helper");
+const WINDOW_TITLE: &str = "Rust AUR Helper GUI";
 
-            // Input for package name
-            ui.horizontal(|ui| {
-                ui.label("Package:");
-                ui.text_edit_singleline(&mut state.package_name);
-            });
+const SEARCH_INPUT_ID: &str = "search_input";
 
-            // Search button
-            if ui.button("Search").clicked() {
-                let package_name = state.package_name.clone();
-                if !package_name.is_empty() && !state.is_running {
-                    state.is_running = true;
-                    state.error = None;
-                    state.progress = Some("Searching...".to_string());
-                    
-                    let state_clone = Arc::clone(&self.state);
-
-                    self.rt.spawn(async move {
-                        match search_aur_package(&package_name).await {
-                            Ok(results) => {
-                                let mut state = state_clone.lock().unwrap();
-                                state.add_search_results(results);
-                                state.is_running = false;
-                                state.progress = None;
-                                state.log.push("Search completed.".to_string());
-                            }
-                            Err(e) => {
-                                let mut state = state_clone.lock().unwrap();
-                                state.error = Some(e.to_string());
-                                state.is_running = false;
-                                state.log.push(format!("Search failed: {}", e));
+// Names per RPC info request when prefetching search results
+const METADATA_BATCH: usize = 100;
+
+// Log lines kept with a failed install step
+const FAILURE_OUTPUT_LINES: usize = 20;
+
+impl MyApp {
+    fn start_search(&self, state: &mut AppState) {
+        let package_name = state.package_name.clone();
+        if !package_name.is_empty() && !state.is_running && state.search_match != SearchMatch::Regex {
+            state.is_running = true;
+            state.error = None;
+            state.progress = Some(tr("Searching...").to_string());
+            state.log.begin_operation(&format!("Search \"{}\"", package_name), None);
+
+            let state_clone = Arc::clone(&self.state);
+            let search_by = state.search_by;
+            let exact = state.search_match == SearchMatch::Exact;
+
+            self.rt.spawn(async move {
+                // A failing source only fails the search when no other source returned anything
+                let mut results = Vec::new();
+                let mut failures = Vec::new();
+                let mut bases = HashMap::new();
+                for source in backend::sources() {
+                    // The AUR is asked directly for package bases; only it knows maintainers
+                    let found = match search_by {
+                        _ if source.builds_from_source() => search_aur_with_bases(&package_name, search_by).await.map_err(|e| e.to_string()),
+                        SearchBy::NameDesc => source
+                            .search(&package_name)
+                            .map(|names| names.into_iter().map(|name| (name, String::new())).collect())
+                            .map_err(|e| e.to_string()),
+                        SearchBy::Maintainer => continue,
+                    };
+                    match found {
+                        Ok(found) => {
+                            for (name, base) in found {
+                                if !base.is_empty() && base != name {
+                                    bases.insert(name.clone(), base);
+                                }
+                                results.push((source.name(), name));
                             }
                         }
-                    });
+                        Err(e) => failures.push(format!("{}: {}", source.label(), e)),
+                    }
+                }
+                // Installing a group such as base-devel goes through pacman, not the AUR
+                let group = (search_by == SearchBy::NameDesc && backend::active().builds_from_source()).then(|| groups::find(&package_name)).flatten();
+                // Short names like `fd` match hundreds of descriptions
+                if exact {
+                    results.retain(|(_, name)| name.eq_ignore_ascii_case(&package_name));
+                }
+                let versions = result_versions(&results).await;
+                let mut state = state_clone.lock().unwrap();
+                state.is_running = false;
+                if results.is_empty() && !failures.is_empty() {
+                    state.error = Some(failures.join("; "));
+                    state.log_error(&format!("Search failed: {}", failures.join("; ")));
+                    return;
+                }
+                for failure in &failures {
+                    state.log_warning(&format!("Search failed for {}", failure));
+                }
+                state.search_versions = versions;
+                state.search_bases = bases;
+                state.search_group = group;
+                state.add_search_results(results);
+                state.progress = None;
+                state.log("Search completed.");
+                match state::remember_search(&package_name) {
+                    Ok(history) => state.search_history = history,
+                    Err(e) => state.log_warning(&format!("Could not save the search history: {}", e)),
+                }
+            });
+        }
+    }
+
+    // Search box with AUR name completions; arrows pick one and Enter searches for it
+    fn search_input(&self, ui: &mut egui::Ui, state: &mut AppState) {
+        let input = ui.add(egui::TextEdit::singleline(&mut state.package_name).id(egui::Id::new(SEARCH_INPUT_ID)));
+        accessibility::name(&input, tr("Search packages"));
+        if input.changed() || (input.gained_focus() && state.package_name.is_empty()) {
+            self.request_suggestions(state);
+        }
+        let popup_id = ui.make_persistent_id("search_suggestions");
+        if input.has_focus() && !state.suggestions.is_empty() {
+            let (up, down, escape) = ui.input_mut(|i| {
+                (
+                    i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                    i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                    i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+                )
+            });
+            let last = state.suggestions.len() - 1;
+            if down {
+                state.selected_suggestion = Some(state.selected_suggestion.map_or(0, |index| (index + 1).min(last)));
+            }
+            if up {
+                state.selected_suggestion = state.selected_suggestion.and_then(|index| index.checked_sub(1));
+            }
+            if escape {
+                state.suggestions.clear();
+            }
+            ui.memory_mut(|m| m.open_popup(popup_id));
+        }
+        // A singleline edit gives up focus when Enter is pressed
+        let entered = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        let mut picked = entered.then(|| state.selected_suggestion.and_then(|index| state.suggestions.get(index).cloned())).flatten();
+
+        let suggestions = state.suggestions.clone();
+        let selected = state.selected_suggestion;
+        egui::popup_below_widget(ui, popup_id, &input, egui::PopupCloseBehavior::CloseOnClickOutside, |ui| {
+            ui.set_min_width(input.rect.width());
+            for (index, name) in suggestions.iter().enumerate() {
+                if ui.selectable_label(selected == Some(index), name).clicked() {
+                    picked = Some(name.clone());
                 }
             }
+        });
 
-            // Immutable borrow for search results
-            let search_results = state.search_results.clone();
-            let selected_package = state.selected_package.clone();
-            drop(state); // End the immutable borrow
+        if let Some(name) = picked {
+            state.package_name = name;
+            state.suggestions.clear();
+            state.selected_suggestion = None;
+            ui.memory_mut(|m| m.close_popup());
+            self.start_search(state);
+        } else if entered {
+            state.suggestions.clear();
+            self.start_search(state);
+        }
+    }
 
-            // Display search results and handle selection
-            for result in search_results {
-                let mut state = self.state.lock().unwrap(); // Mutable borrow
-                if ui.radio(selected_package.as_deref() == Some(&result), &result).clicked() {
-                    state.select_package(Some(result.clone()));
+    // Only AUR names are suggested, and only while searching by name; an empty box offers earlier searches
+    fn request_suggestions(&self, state: &mut AppState) {
+        state.selected_suggestion = None;
+        let query = state.package_name.clone();
+        if query.is_empty() {
+            state.suggestions = state.search_history.clone();
+            return;
+        }
+        if query.len() < 2 || state.search_by != SearchBy::NameDesc || state.search_match == SearchMatch::Regex || !backend::active().builds_from_source() {
+            state.suggestions.clear();
+            return;
+        }
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let result = suggest_aur(&query).await.map_err(|e| e.to_string());
+            let mut state = state_clone.lock().unwrap();
+            // Answers to earlier keystrokes can arrive after later ones
+            if state.package_name != query {
+                return;
+            }
+            match result {
+                Ok(names) => state.suggestions = names.into_iter().filter(|name| *name != query).collect(),
+                Err(e) => debug!("Could not fetch suggestions: {}", e),
+            }
+        });
+    }
 
-                    // Check if the selected package is installed
-                    if is_package_installed(&result).unwrap_or(false) {
-                        state.progress = Some("Package is already installed.".to_string());
-                    } else {
-                        state.progress = None;
+    // Lists what removing `package` in `mode` would remove, computing it the first time it is shown
+    fn show_removal_impact(&self, ui: &mut egui::Ui, state: &mut AppState, package: &str, mode: RemoveMode) {
+        let current = state.removal_impact.as_ref().is_some_and(|(for_package, for_mode, _)| for_package == package && *for_mode == mode);
+        if !current {
+            state.removal_impact = Some((package.to_string(), mode, None));
+            let state_clone = Arc::clone(&self.state);
+            let package = package.to_string();
+            self.rt.spawn(async move {
+                let query = package.clone();
+                let result = tokio::task::spawn_blocking(move || removal::preview(&query, mode).map_err(|e| e.to_string()))
+                    .await
+                    .unwrap_or_else(|e| Err(e.to_string()));
+                let mut state = state_clone.lock().unwrap();
+                // The dialog may have moved on to another package or mode meanwhile
+                if let Some((for_package, for_mode, impact)) = &mut state.removal_impact {
+                    if *for_package == package && *for_mode == mode {
+                        *impact = Some(result);
                     }
                 }
+            });
+        }
+        match state.removal_impact.as_ref().and_then(|(_, _, impact)| impact.as_ref()) {
+            None => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(tr("Working out what would be removed..."));
+                });
             }
+            Some(Err(e)) => {
+                ui.colored_label(egui::Color32::RED, fill(tr("pacman would refuse this removal: {}"), &[&e]));
+            }
+            Some(Ok(impact)) => {
+                let text = format!("Removes {} package(s), freeing {}:", impact.packages.len(), format_size(impact.total_size()));
+                if impact.packages.len() > 1 {
+                    ui.colored_label(ui.visuals().warn_fg_color, text);
+                } else {
+                    ui.label(text);
+                }
+                egui::ScrollArea::vertical().id_source("removal_impact").max_height(160.0).show(ui, |ui| {
+                    egui::Grid::new("removal_impact_grid").num_columns(2).striped(true).show(ui, |ui| {
+                        for (name, size) in &impact.packages {
+                            ui.label(name);
+                            ui.weak(format_size(*size));
+                            ui.end_row();
+                        }
+                    });
+                });
+                if !impact.orphans.is_empty() {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!("Left installed but no longer needed: {}", impact.orphans.join(", ")),
+                    );
+                }
+            }
+        }
+    }
 
-            // Re-lock state after the previous borrow ends
-            let mut state = self.state.lock().unwrap();
-
-            // Install/Uninstall button
-            if let Some(package) = &state.selected_package {
-                if !state.is_running {
-                    let button_text = if is_package_installed(package).unwrap_or(false) {
-                        "Uninstall"
-                    } else {
-                        "Install"
-                    };
-
-                    if ui.button(button_text).clicked() {
-                        let package_clone = package.clone();
-                        state.is_running = true;
-                        state.error = None;
-                        state.progress = Some(format!("{}...", button_text).to_string());
+    // Installs the selected package, or uninstalls it when it is already installed
+    fn start_operation(&self, state: &mut AppState, installed: bool) {
+        self.start_operation_with(state, installed, true);
+    }
 
-                        let state_clone = Arc::clone(&self.state);
+    // With `offer_prebuilt`, an AUR package found in a binary repository is offered prebuilt
+    // instead of being built
+    fn start_operation_with(&self, state: &mut AppState, installed: bool, offer_prebuilt: bool) {
+        let Some(package_clone) = state.selected_package.clone() else {
+            return;
+        };
+        if state.is_running {
+            return;
+        }
+        let source = state.selected_backend();
+        let button_text = if installed { "Uninstall" } else { "Install" };
+        let remove_mode = state.remove_mode.take().unwrap_or(state.config.remove_mode);
+        state.is_running = true;
+        state.error = None;
+        state.failure = None;
+        state.step = (!installed).then_some(Step::Install);
+        state.build_dir = None;
+        state.built_files.clear();
+        state.progress = Some(if installed { tr("Uninstalling...") } else { tr("Installing...") }.to_string());
+        state.log.begin_operation(&format!("{} {}", button_text, package_clone), Some(&package_clone));
 
-                        self.rt.spawn(async move {
-                            let result = if button_text == "Uninstall" {
-                                uninstall_package(&package_clone)
-                            } else {
-                                run_package_management_logic(&package_clone, &state_clone).await
-                            };
+        let state_clone = Arc::clone(&self.state);
 
-                            let mut state = state_clone.lock().unwrap();
-                            if let Err(e) = result {
-                                state.error = Some(e.to_string());
-                                state.is_running = false;
-                                state.log.push(format!("{} failed: {}", button_text, e));
-                            } else {
-                                state.progress = Some(format!("Package {} successfully.", button_text).to_string());
-                                state.is_running = false;
-                                state.log.push(format!("Package {} process completed.", button_text));
-                            }
-                        });
+        self.rt.spawn(async move {
+            let config = state_clone.lock().unwrap().config.clone();
+            let result = if installed {
+                hooks::run_hook("pre_remove", config.hooks.pre_remove.as_deref(), &package_clone)
+                    .and_then(|_| uninstall_package(source, &package_clone, remove_mode, &config.escalation_tool))
+            } else if !source.builds_from_source() {
+                let target = std::slice::from_ref(&package_clone);
+                // Repository packages go through pacman -S, which the summary can size up
+                let confirmed = if source.name() == backend::repo().name() {
+                    confirm_sync_install(target, &package_clone, &config, &state_clone).await
+                } else {
+                    Ok(())
+                };
+                confirmed.and_then(|_| install_package(source, target, &config.escalation_tool))
+            } else if let Some(group) = groups::find(&package_clone) {
+                // e.g. queued from the command line; the search offers picking members instead
+                install_group(&group.name, &group.picked(), &config, &state_clone).await
+            } else if let Some(prebuilt) = binary_repo::find(&package_clone, &config.binary_repos).filter(|_| offer_prebuilt) {
+                let mut state = state_clone.lock().unwrap();
+                state.log(&format!("{} {} is available prebuilt from {}", prebuilt.package, prebuilt.version, prebuilt.repo));
+                state.prebuilt_offer = Some(prebuilt);
+                state.is_running = false;
+                state.progress = Some(tr("Waiting for the choice between prebuilt and source.").to_string());
+                return;
+            } else {
+                match prepare_package(&package_clone, &state_clone).await {
+                    Ok(review) => {
+                        checkpoint(&review, pipeline::Stage::Extracted, &[], &state_clone);
+                        let mut state = state_clone.lock().unwrap();
+                        state.pending_review = Some(review);
+                        state.is_running = false;
+                        state.progress = Some(tr("Waiting for PKGBUILD review.").to_string());
+                        return;
                     }
+                    Err(e) => Err(e),
+                }
+            };
+
+            let mut state = state_clone.lock().unwrap();
+            if let Err(e) = result {
+                state.error = Some(e.to_string());
+                state.is_running = false;
+                state.log_error(&format!("{} failed: {}", button_text, e));
+                if !installed {
+                    state.record_failure(source.name(), &package_clone, None);
+                }
+            } else {
+                state.progress = Some(if installed { tr("Package uninstalled successfully.") } else { tr("Package installed successfully.") }.to_string());
+                state.is_running = false;
+                state.step = None;
+                state.log(&format!("Package {} process completed.", button_text));
+                if installed {
+                    state.package_sizes.retain(|(_, name, _)| *name != package_clone);
                 }
             }
+            notify_finished(&state, &package_clone);
+        });
+    }
 
-            // Display progress or error
-            if let Some(error) = &state.error {
-                ui.colored_label(egui::Color32::RED, error);
+    // Builds and installs the metapackage like a reviewed AUR package; there is nothing to review
+    // in a PKGBUILD made here
+    fn start_metapackage(&self, state: &mut AppState, draft: metapackage::Draft) {
+        if state.is_running {
+            return;
+        }
+        let build_root = state.config.build_dir.clone();
+        let description = if draft.description.is_empty() { format!("Depends on {}", draft.packages.join(" ")) } else { draft.description.clone() };
+        let review = metapackage::create(&build_root, &draft.name, &description, &draft.packages)
+            .and_then(|(build_dir, version)| review::load_review(&draft.name, &draft.name, &version, &build_dir));
+        let review = match review {
+            Ok(review) => review,
+            Err(e) => {
+                state.error = Some(e.to_string());
+                state.log_error(&format!("Could not create the metapackage {}: {}", draft.name, e));
+                return;
             }
+        };
+        if let Err(e) = build_dirs::track(&draft.name, &format!("{}/{}", build_root, draft.name)) {
+            state.log_warning(&format!("Could not record the build directory of {}: {}", draft.name, e));
+        }
+        state.is_running = true;
+        state.error = None;
+        state.failure = None;
+        state.build_dir = None;
+        state.built_files.clear();
+        state.progress = Some(tr("Building...").to_string());
+        state.log.begin_operation(&format!("Build and install {}", review.package), Some(&review.package));
+        state.log(&format!("Depends on: {}", draft.packages.join(" ")));
 
-            if let Some(progress) = &state.progress {
-                ui.label(progress);
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let result = build_and_install_package(&review, None, &state_clone).await;
+            let config = state_clone.lock().unwrap().config.clone();
+            let installed = result.is_ok() && state_clone.lock().unwrap().error.is_none();
+            let marked = if installed && draft.mark_as_dependencies { Some(metapackage::mark_as_dependencies(&draft.packages, &config.escalation_tool)) } else { None };
+            let mut state = state_clone.lock().unwrap();
+            if let Err(e) = result {
+                state.error = Some(e.to_string());
+                state.is_running = false;
+                state.log_error(&format!("Install failed: {}", e));
+            }
+            if let Some(Err(e)) = marked {
+                state.log_warning(&format!("Could not mark the packages as dependencies: {}", e));
             }
+            // Build and install failures are recorded in the state rather than returned
+            let source = backend::active().name();
+            state.record_failure(source, &review.package, Some(review.clone()));
+            notify_finished(&state, &review.package);
+        });
+    }
 
-            // Spinner if running
-            if state.is_running {
-                ui.spinner();
-            } else {
-                if ui.button("Clear Log").clicked() {
-                    state.clear_log();
+    // Sorts the waiting AUR packages so that each is built after the others it depends on. The
+    // queue holds off starting one until the order is known.
+    fn order_queue(&self, state: &mut AppState) {
+        let waiting = state.queue.waiting_packages();
+        if !backend::active().builds_from_source() || waiting.len() < 2 || state.queue.ordering {
+            return;
+        }
+        state.queue.ordering = true;
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let nodes = build_nodes(&waiting).await;
+            let mut state = state_clone.lock().unwrap();
+            match nodes {
+                Ok(nodes) => apply_build_order(&mut state, nodes),
+                Err(e) => {
+                    state.queue.ordering = false;
+                    state.log_warning(&format!("Could not work out the build order, keeping the queue's: {}", e));
                 }
-
-                ui.group(|ui| {
-                    ui.label("Log:");
-                    for log in &state.log {
-                        ui.label(log);
-                    }
-                });
             }
         });
     }
-}
-
 
+    // The picked members of a pacman group, as one pacman -S transaction
+    fn start_group_install(&self, state: &mut AppState, group: &str, members: Vec<String>) {
+        if state.is_running {
+            return;
+        }
+        let group = group.to_string();
+        state.is_running = true;
+        state.error = None;
+        state.failure = None;
+        state.progress = Some(tr("Installing...").to_string());
+        state.log.begin_operation(&format!("Install group {}", group), None);
+        state.log(&format!("Members: {}", members.join(" ")));
 
-async fn search_aur_package(package_name: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    let url = format!("https://aur.archlinux.org/rpc/?v=5&type=search&arg={}", package_name);
-    let response = reqwest::get(&url).await?.json::<serde_json::Value>().await?;
-    
-    let packages = response["results"].as_array().unwrap_or(&vec![]).iter()
-        .map(|pkg| pkg["Name"].as_str().unwrap_or("").to_string())
-        .collect::<Vec<String>>();
-    
-    Ok(packages)
-}
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let config = state_clone.lock().unwrap().config.clone();
+            let result = install_group(&group, &members, &config, &state_clone).await;
+            let mut state = state_clone.lock().unwrap();
+            state.is_running = false;
+            match result {
+                Ok(()) => {
+                    state.progress = Some(fill(tr("Group {} installed successfully."), &[&group]));
+                    state.log("Package Install process completed.");
+                }
+                Err(e) => {
+                    state.error = Some(e.to_string());
+                    state.log_error(&format!("Install failed: {}", e));
+                }
+            }
+            notify_finished(&state, &group);
+        });
+    }
 
-async fn fetch_metadata(package_name: &str) -> Result<Package, Box<dyn Error>> {
-    let client = Client::new();
-    let url = format!("https://aur.archlinux.org/rpc/?v=5&type=info&arg={}", package_name);
-    println!("Fetching metadata from URL: {}", url);
+    // Removes `packages` one after another, stopping at the first that fails
+    fn start_bulk_uninstall(&self, state: &mut AppState, packages: Vec<selection::Key>) {
+        if state.is_running || packages.is_empty() {
+            return;
+        }
+        let remove_mode = state.remove_mode.take().unwrap_or(state.config.remove_mode);
+        let names: Vec<String> = packages.iter().map(|(_, name)| name.clone()).collect();
+        state.select_package(None);
+        state.is_running = true;
+        state.error = None;
+        state.failure = None;
+        state.progress = Some(tr("Uninstalling...").to_string());
+        state.log.begin_operation(&format!("Uninstall {}", names.join(" ")), None);
 
-    let response = client.get(&url).send().await?;
-    
-    let content_type = response.headers().get(CONTENT_TYPE)
-        .ok_or("Missing content-type header")?
-        .to_str()?;
-    if !content_type.contains("application/json") {
-        return Err("Unexpected content type".into());
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let config = state_clone.lock().unwrap().config.clone();
+            for (index, (source, package)) in packages.iter().enumerate() {
+                state_clone.lock().unwrap().progress = Some(fill(tr("Uninstalling {} ({}/{})..."), &[&package, &(index + 1), &packages.len()]));
+                let result = hooks::run_hook("pre_remove", config.hooks.pre_remove.as_deref(), package)
+                    .and_then(|_| uninstall_package(backend::by_name(source), package, remove_mode, &config.escalation_tool));
+                let mut state = state_clone.lock().unwrap();
+                if let Err(e) = result {
+                    state.error = Some(e.to_string());
+                    state.is_running = false;
+                    state.log_error(&format!("Uninstall failed: {}", e));
+                    notify_finished(&state, package);
+                    return;
+                }
+                state.installed_packages.retain(|(installed_source, name, _)| installed_source != source || name != package);
+                state.package_sizes.retain(|(_, name, _)| name != package);
+            }
+            let mut state = state_clone.lock().unwrap();
+            state.progress = Some(fill(tr("{} packages uninstalled successfully."), &[&packages.len()]));
+            state.is_running = false;
+            state.log("Package Uninstall process completed.");
+            notify_finished(&state, &names.join(" "));
+        });
     }
 
-    let body = response.text().await?;
-    println!("Response body: {}", body);
+    // Reading every AppStream catalog takes a few seconds, so it happens once in the background
+    fn load_browse_entries(&self, state: &mut AppState) {
+        if state.is_running {
+            return;
+        }
+        state.is_running = true;
+        state.error = None;
+        state.progress = Some(tr("Loading categories...").to_string());
 
-    let json_response = serde_json::from_str::<serde_json::Value>(&body)?;
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let entries = tokio::task::spawn_blocking(browse::load).await.unwrap_or_default();
+            let mut state = state_clone.lock().unwrap();
+            if state.browse_category.is_none() {
+                state.browse_category = browse::categories(&entries).first().map(|(key, _, _)| key.clone());
+            }
+            if entries.is_empty() {
+                state.progress = Some(tr("No AppStream data or pacman groups found.").to_string());
+            } else {
+                state.progress = None;
+            }
+            state.browse_entries = Some(entries);
+            state.is_running = false;
+        });
+    }
 
-    let package = json_response["results"].as_array().unwrap_or(&vec![]).iter().find_map(|pkg| {
-        Some(Package {
-            name: pkg["Name"].as_str().unwrap_or("").to_string(),
-            version: pkg["Version"].as_str().unwrap_or("").to_string(),
-            description: pkg["Description"].as_str().unwrap_or("").to_string(),
-            urlpath: pkg["URLPath"].as_str().unwrap_or("").to_string(),
-        })
-    }).ok_or("Package not found")?;
+    fn refresh_package_sizes(&self, state: &mut AppState) {
+        if state.is_running {
+            return;
+        }
+        state.is_running = true;
+        state.error = None;
+        state.progress = Some(tr("Measuring installed packages...").to_string());
 
-    Ok(package)
-}
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let mut sizes = Vec::new();
+            let mut failures = Vec::new();
+            for source in backend::sources() {
+                match source.installed_sizes() {
+                    Ok(found) => sizes.extend(found.into_iter().map(|(name, size)| (source.name(), name, size))),
+                    Err(e) => failures.push(format!("{}: {}", source.label(), e)),
+                }
+            }
+            sizes.sort_by_key(|(_, _, size)| std::cmp::Reverse(*size));
+            let leftovers = build_dirs::usage();
+            let mut state = state_clone.lock().unwrap();
+            state.package_sizes = sizes;
+            state.build_leftovers = Some(leftovers);
+            state.is_running = false;
+            state.progress = None;
+            if !failures.is_empty() {
+                state.error = Some(format!("Could not measure installed packages: {}", failures.join("; ")));
+            }
+        });
+    }
 
-async fn download_and_extract_package(urlpath: &str, dest: &str) -> Result<(), Box<dyn Error>> {
-    let client = Client::new();
-    let url = format!("https://aur.archlinux.org{}", urlpath);
-    println!("Downloading package from URL: {}", url);
+    fn delete_build_leftovers(&self, state: &mut AppState) {
+        state.is_running = true;
+        state.error = None;
+        state.progress = Some(tr("Deleting build leftovers...").to_string());
 
-    let response = client.get(&url).send().await?;
-    let content_type = response.headers().get(CONTENT_TYPE)
-        .ok_or("Missing content-type header")?
-        .to_str()?;
-    if !content_type.contains("application/x-gzip") {
-        return Err("Unexpected content type".into());
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let result = build_dirs::remove_finished().map_err(|e| e.to_string());
+            let leftovers = build_dirs::usage();
+            let mut state = state_clone.lock().unwrap();
+            state.is_running = false;
+            state.progress = None;
+            state.build_leftovers = Some(leftovers);
+            match result {
+                Ok(removed) => state.log(&format!("Deleted {} build directories", removed.len())),
+                Err(e) => state.error = Some(format!("Could not delete build leftovers: {}", e)),
+            }
+        });
     }
 
-    // Collect the response bytes into a `Vec<u8>`.
-    let bytes = response.bytes().await?.to_vec();
-    println!("Downloaded {} bytes", bytes.len());
+    fn show_package_sizes(&self, ui: &mut egui::Ui, state: &mut AppState) {
+        let total: u64 = state.package_sizes.iter().map(|(_, _, size)| size).sum();
+        ui.horizontal(|ui| {
+            if ui.button(tr("Refresh")).clicked() {
+                self.refresh_package_sizes(state);
+            }
+            ui.label(fill(tr("{} packages, {} in total"), &[&state.package_sizes.len(), &format_size(total)]));
+        });
+        if let Some(leftovers) = state.build_leftovers {
+            ui.horizontal(|ui| {
+                ui.label(fill(tr("Build leftovers: {} in {} directories"), &[&format_size(leftovers.bytes), &leftovers.directories]));
+                let delete = ui.add_enabled(!state.is_running && leftovers.directories > 0, egui::Button::new(tr("Delete")));
+                if delete.on_hover_text(tr("Directories of interrupted installs are kept for resuming")).clicked() {
+                    self.delete_build_leftovers(state);
+                }
+            });
+        }
 
-    // Use the collected bytes to create the `GzDecoder`.
-    let tarball = GzDecoder::new(&*bytes);
-    let mut archive = Archive::new(tarball);
+        let mut uninstall = None;
+        egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+            egui::Grid::new("package_sizes").num_columns(5).striped(true).show(ui, |ui| {
+                for header in ["Package", "Source", "Size", "Cumulative", ""] {
+                    ui.strong(header);
+                }
+                ui.end_row();
+                let mut cumulative = 0;
+                for (source, name, size) in &state.package_sizes {
+                    cumulative += size;
+                    ui.label(name);
+                    ui.weak(backend::by_name(source).label());
+                    ui.label(format_size(*size));
+                    ui.weak(format!("{} ({:.0}%)", format_size(cumulative), cumulative as f64 * 100.0 / total.max(1) as f64));
+                    if ui.add_enabled(!state.is_running, egui::Button::new(tr("Uninstall"))).clicked() {
+                        uninstall = Some((*source, name.clone()));
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+        if let Some((source, name)) = uninstall {
+            state.select_package(Some((source, name.clone())));
+            if state.config.confirms(ConfirmStep::Removal) {
+                state.confirm_uninstall = Some((source, name));
+            } else {
+                self.start_operation(state, true);
+            }
+        }
+    }
+
+    fn show_holds(&self, ui: &mut egui::Ui, state: &mut AppState) {
+        ui.horizontal(|ui| {
+            if ui.button(tr("Refresh")).clicked() {
+                refresh_installed(state);
+            }
+            let mut in_ignore_pkg = state.config.holds_in_ignore_pkg;
+            if backend::active().removes_with_pacman() && ui.checkbox(&mut in_ignore_pkg, tr("Also list held packages in IgnorePkg")).changed() {
+                self.set_holds_in_ignore_pkg(state, in_ignore_pkg);
+            }
+        });
+
+        let mut release = None;
+        egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+            egui::Grid::new("holds").num_columns(5).striped(true).show(ui, |ui| {
+                for header in ["Package", "Held at", "Installed", "Reason", ""] {
+                    ui.strong(header);
+                }
+                ui.end_row();
+                for (name, hold) in &state.config.holds {
+                    ui.label(name);
+                    ui.label(&hold.version);
+                    match state.installed_packages.iter().find(|(_, installed, _)| installed == name) {
+                        Some((_, _, version)) if *version == hold.version => ui.label(version),
+                        Some((_, _, version)) => ui.colored_label(ui.visuals().warn_fg_color, version),
+                        None => ui.weak(tr("not installed")),
+                    };
+                    if hold.reason.is_empty() {
+                        ui.weak(tr("no reason given"));
+                    } else {
+                        ui.label(&hold.reason);
+                    }
+                    if ui.button(tr("Release")).clicked() {
+                        release = Some(name.clone());
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+        if state.config.holds.is_empty() {
+            ui.weak(tr("No held packages. Select an installed package and press Hold to keep it at its version."));
+        }
+        if let Some(name) = release {
+            self.release_hold(state, &name);
+        }
+    }
+
+    fn check_updates(&self, state: &mut AppState) {
+        if state.is_running {
+            return;
+        }
+        state.is_running = true;
+        state.error = None;
+        state.progress = Some(tr("Checking for updates...").to_string());
+
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let config = state_clone.lock().unwrap().config.clone();
+            let result = tokio::task::spawn_blocking(move || updates::check(&config).map_err(|e| e.to_string())).await;
+            let mut state = state_clone.lock().unwrap();
+            state.is_running = false;
+            state.progress = None;
+            match result.unwrap_or_else(|e| Err(e.to_string())) {
+                Ok(found) => state.updates = Some(found),
+                Err(e) => {
+                    state.error = Some(format!("Could not check for updates: {}", e));
+                    state.log_error(&format!("Could not check for updates: {}", e));
+                }
+            }
+        });
+    }
+
+    fn show_updates(&self, ui: &mut egui::Ui, state: &mut AppState) {
+        let aur = backend::active().builds_from_source().then(|| backend::active().name());
+        let checked = state.updates.iter().flatten().filter(|update| update.checked).count();
+        let mut update = false;
+        ui.horizontal(|ui| {
+            if ui.add_enabled(!state.is_running, egui::Button::new(tr("Check for updates"))).clicked() {
+                self.check_updates(state);
+            }
+            let Some(found) = state.updates.as_mut().filter(|found| !found.is_empty()) else {
+                return;
+            };
+            if ui.button(tr("Select all")).clicked() {
+                updates::check_all(found, true);
+            }
+            if ui.button(tr("Select none")).clicked() {
+                updates::check_all(found, false);
+            }
+            if let Some(aur) = aur {
+                if ui.button(tr("Select AUR only")).clicked() {
+                    updates::check_only(found, aur);
+                }
+            }
+            update = ui.add_enabled(!state.is_running && checked > 0, egui::Button::new(fill(tr("Update selected ({})"), &[&checked]))).clicked();
+        });
+
+        match &mut state.updates {
+            None => {
+                ui.weak(tr("Press Check for updates to look for newer versions."));
+            }
+            Some(found) if found.is_empty() => {
+                ui.weak(tr("Everything is up to date."));
+            }
+            Some(found) => {
+                egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                    egui::Grid::new("updates").num_columns(5).striped(true).show(ui, |ui| {
+                        for header in ["", "Package", "Source", "Installed", "Available"] {
+                            ui.strong(header);
+                        }
+                        ui.end_row();
+                        for outdated in found.iter_mut() {
+                            let checkbox = ui.checkbox(&mut outdated.checked, "");
+                            accessibility::name(&checkbox, &outdated.name);
+                            ui.label(&outdated.name);
+                            ui.label(backend::by_name(outdated.source).label());
+                            ui.label(&outdated.installed);
+                            ui.label(&outdated.available);
+                            ui.end_row();
+                        }
+                    });
+                });
+            }
+        }
+        if update {
+            self.start_selected_updates(state);
+        }
+    }
+
+    // Ticked repository packages are upgraded in one `pacman -Syu` that ignores the unticked and
+    // held ones, ticked AUR packages join the install queue, and other sources update in one call
+    // each
+    fn start_selected_updates(&self, state: &mut AppState) {
+        let Some(found) = state.updates.clone() else {
+            return;
+        };
+        if state.is_running {
+            return;
+        }
+        let aur = backend::active().builds_from_source().then(|| backend::active().name());
+        let mut transactions = Vec::new();
+        for source in updates::checked_sources(&found) {
+            let names = updates::names(&found, source, true);
+            if Some(source) == aur {
+                state.log(&format!("Queued: {}", names.join(" ")));
+                for package in names {
+                    state.queue.push(package);
+                }
+            } else {
+                transactions.push((source, names));
+            }
+        }
+        state.updates = Some(found.iter().filter(|update| !update.checked).cloned().collect());
+        // The unticked ones and held ones, which the list leaves out
+        let mut skipped = updates::names(&found, backend::repo().name(), false);
+        skipped.extend(state.config.holds.keys().cloned());
+        if transactions.is_empty() {
+            self.order_queue(state);
+            return;
+        }
+
+        state.is_running = true;
+        state.error = None;
+        state.failure = None;
+        state.progress = Some(tr("Updating...").to_string());
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let escalation_tool = state_clone.lock().unwrap().config.escalation_tool.clone();
+            for (source, names) in transactions {
+                let source = backend::by_name(source);
+                state_clone.lock().unwrap().log.begin_operation(&format!("Update {} {}", source.label(), names.join(" ")), None);
+                let escalation_tool = escalation_tool.clone();
+                let skipped = skipped.clone();
+                let result = tokio::task::spawn_blocking(move || {
+                    let upgrade = source.name() == backend::repo().name();
+                    let result = instance::lock_transactions().and_then(|_lock| {
+                        if upgrade {
+                            // Its new keys check the rest of the upgrade
+                            if names.iter().any(|name| name == keyring::PACKAGE) {
+                                keyring::update(&escalation_tool)?;
+                            }
+                            backend::system_upgrade(&skipped, &escalation_tool)
+                        } else {
+                            source.update(&names, &escalation_tool)
+                        }
+                    });
+                    record_transaction(if upgrade { state::Action::Upgrade } else { state::Action::Update }, source.name(), &names, &result);
+                    result.map_err(|e| e.to_string())
+                })
+                .await;
+                let mut state = state_clone.lock().unwrap();
+                match result.unwrap_or_else(|e| Err(e.to_string())) {
+                    Ok(()) => state.log(&format!("{} packages updated.", source.label())),
+                    Err(e) => {
+                        state.error = Some(format!("Updating {} packages failed: {}", source.label(), e));
+                        state.log_error(&format!("Update failed: {}", e));
+                    }
+                }
+            }
+            let mut state = state_clone.lock().unwrap();
+            state.is_running = false;
+            state.progress = None;
+            refresh_installed(&mut state);
+        });
+        self.order_queue(state);
+    }
+
+    fn show_pgp_keys(&self, ui: &mut egui::Ui, state: &mut AppState) {
+        ui.horizontal(|ui| {
+            if ui.button(tr("Refresh")).clicked() {
+                state.pgp_keys = pgp_keys::list();
+            }
+            ui.weak(fill(tr("Imported from {}"), &[&state.config.keyserver]));
+        });
+
+        let mut action = None;
+        egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+            egui::Grid::new("pgp_keys").num_columns(5).striped(true).show(ui, |ui| {
+                for header in ["Fingerprint", "Needed by", "Imported", "", ""] {
+                    ui.strong(header);
+                }
+                ui.end_row();
+                for key in &state.pgp_keys {
+                    ui.monospace(&key.fingerprint);
+                    ui.label(key.packages.join(", "));
+                    match chrono::DateTime::from_timestamp(key.imported, 0) {
+                        Some(imported) => ui.label(imported.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string()),
+                        None => ui.weak("?"),
+                    };
+                    if key.revoked {
+                        ui.colored_label(ui.visuals().warn_fg_color, tr("revoked"));
+                    } else if ui.add_enabled(!state.is_running, egui::Button::new(tr("Revoke")))
+                        .on_hover_text(tr("Delete the key and never import it again; builds that need it fail"))
+                        .clicked()
+                    {
+                        action = Some((key.fingerprint.clone(), true));
+                    }
+                    if ui.add_enabled(!state.is_running, egui::Button::new(tr("Remove")))
+                        .on_hover_text(tr("Delete the key; the next build that needs it imports it again"))
+                        .clicked()
+                    {
+                        action = Some((key.fingerprint.clone(), false));
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+        if state.pgp_keys.is_empty() {
+            ui.weak(tr("No keys imported. Keys listed in a PKGBUILD's validpgpkeys are imported before it is built."));
+        }
+        if let Some((fingerprint, revoke)) = action {
+            self.drop_pgp_key(state, fingerprint, revoke);
+        }
+    }
+
+    // Deletes a key from the build user's keyring, which may be on a remote host
+    fn drop_pgp_key(&self, state: &mut AppState, fingerprint: String, revoke: bool) {
+        if state.is_running {
+            return;
+        }
+        state.is_running = true;
+        state.error = None;
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let key = fingerprint.clone();
+            let result = tokio::task::spawn_blocking(move || {
+                let result = if revoke { pgp_keys::revoke(&key) } else { pgp_keys::remove(&key) };
+                result.map_err(|e| e.to_string())
+            })
+            .await;
+            let mut state = state_clone.lock().unwrap();
+            state.is_running = false;
+            match result.unwrap_or_else(|e| Err(e.to_string())) {
+                Ok(()) if revoke => state.log(&format!("Revoked the PGP key {}", fingerprint)),
+                Ok(()) => state.log(&format!("Removed the PGP key {}", fingerprint)),
+                Err(e) => {
+                    state.error = Some(e.clone());
+                    state.log_error(&e);
+                }
+            }
+            state.pgp_keys = pgp_keys::list();
+        });
+    }
+
+    fn open_terminal(&self, state: &mut AppState, dir: &str, host: Option<&HostProfile>) {
+        match terminal::open(dir, host, &state.config.terminal) {
+            Ok(()) => state.log(&format!("Opened a terminal in {}", dir)),
+            Err(e) => state.log_warning(&e.to_string()),
+        }
+    }
+
+    // A file manager cannot reach a remote host, so a terminal is opened there instead
+    fn open_build_dir(&self, state: &mut AppState, dir: &str, host: Option<&HostProfile>) {
+        if host.is_some() {
+            self.open_terminal(state, dir, host);
+            return;
+        }
+        match StdCommand::new("xdg-open").arg(dir).spawn() {
+            Ok(_) => state.log(&format!("Opened {}", dir)),
+            Err(e) => state.log_warning(&format!("Could not open {}: {}", dir, e)),
+        }
+    }
+
+    // Builds and installs a reviewed package; with `built`, only installs those package files again
+    fn build_and_install(&self, state: &mut AppState, review: PendingReview, built: Option<Vec<String>>) {
+        state.is_running = true;
+        state.error = None;
+        state.failure = None;
+        state.build_dir = None;
+        state.built_files = built.clone().unwrap_or_default();
+        let (progress, title) = match built {
+            Some(_) => (tr("Installing..."), "Install"),
+            None => (tr("Building..."), "Build and install"),
+        };
+        state.progress = Some(progress.to_string());
+        state.log.begin_operation(&format!("{} {}", title, review.package), Some(&review.package));
+
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let result = build_and_install_package(&review, built.as_deref(), &state_clone).await;
+            let mut state = state_clone.lock().unwrap();
+            if let Err(e) = result {
+                state.error = Some(e.to_string());
+                state.is_running = false;
+                state.log_error(&format!("Install failed: {}", e));
+            }
+            // Build and install failures are recorded in the state rather than returned
+            let source = backend::active().name();
+            state.record_failure(source, &review.package, Some(review.clone()));
+            notify_finished(&state, &review.package);
+        });
+    }
+
+    // Builds a reviewed queued package in a state of its own, so the queue moves on to the next one
+    fn start_build_job(&self, state: &mut AppState, review: PendingReview) {
+        let job = Arc::new(Mutex::new(AppState {
+            config: state.config.clone(),
+            run_namcap: state.run_namcap,
+            is_running: true,
+            build_order: state.build_order.clone(),
+            cycle_breaks: state.cycle_breaks.clone(),
+            ..Default::default()
+        }));
+        job.lock().unwrap().log.begin_operation(&format!("Build and install {}", review.package), Some(&review.package));
+        state.queue.start_build();
+        state.step = None;
+        state.progress = Some(fill(tr("Building {} beside the queue."), &[&review.package]));
+        state.log(&format!("Building {} beside the queue; its output is added to the log when it is done", review.package));
+
+        let job_state = Arc::clone(&job);
+        let package = review.package.clone();
+        let task = self.rt.spawn(async move {
+            let result = build_and_install_package(&review, None, &job_state).await;
+            let mut job_state = job_state.lock().unwrap();
+            if let Err(e) = result {
+                job_state.error = Some(e.to_string());
+                job_state.log_error(&format!("Install failed: {}", e));
+            }
+            job_state.is_running = false;
+            job_state.record_failure(backend::active().name(), &review.package, Some(review.clone()));
+            notify_finished(&job_state, &review.package);
+        });
+        state.build_jobs.push(BuildJob { package, state: job, task });
+    }
+
+    // Picks an interrupted install up after its last completed step. Sources that changed since
+    // they were reviewed are reviewed again before anything is built.
+    fn resume(&self, state: &mut AppState, checkpoint: pipeline::Checkpoint) {
+        let mut review = match review::load_review(&checkpoint.package, &checkpoint.package_base, &checkpoint.version, &checkpoint.build_dir) {
+            Ok(review) => review,
+            Err(e) => {
+                state.error = Some(format!("Could not resume {}: {}", checkpoint.package, e));
+                pipeline::clear(&checkpoint.package);
+                return;
+            }
+        };
+        state.select_package(Some((backend::active().name(), checkpoint.package.clone())));
+        state.log(&format!("Resuming {} {}: {}", checkpoint.package, checkpoint.version, checkpoint.stage.label()));
+        let selected: Vec<String> = checkpoint.selected_packages.into_iter().filter(|name| review.split_packages.contains(name)).collect();
+        if !selected.is_empty() {
+            review.selected_packages = selected;
+        }
+        // Built files of a remote build are on the host, where they cannot be checked from here
+        let built = checkpoint.built_files.iter().all(|file| checkpoint.host.is_some() || Path::new(file).is_file());
+        match checkpoint.stage {
+            pipeline::Stage::Extracted => state.pending_review = Some(review),
+            _ if review.has_changes() => {
+                state.log_warning(&format!("The sources of {} changed since they were reviewed; review them again", checkpoint.package));
+                state.pending_review = Some(review);
+            }
+            pipeline::Stage::Built if built && !checkpoint.built_files.is_empty() => self.build_and_install(state, review, Some(checkpoint.built_files)),
+            _ => self.build_and_install(state, review, None),
+        }
+    }
+
+    // Runs the failed step again: a build or install reuses the review, anything earlier starts over
+    fn retry_failure(&self, state: &mut AppState) {
+        let Some(failure) = state.failure.take() else {
+            return;
+        };
+        state.queue.resume(&failure.package);
+        state.log(&format!("Retrying: {} {}", failure.step.label().to_lowercase(), failure.package));
+        match (failure.step, failure.review) {
+            (Step::Install, Some(review)) if !failure.built_files.is_empty() => self.build_and_install(state, review, Some(failure.built_files)),
+            (Step::Build | Step::Install, Some(review)) => self.build_and_install(state, review, None),
+            _ => {
+                state.select_package(Some((failure.source, failure.package)));
+                self.start_operation(state, false);
+            }
+        }
+    }
+
+    fn show_failure(&self, ui: &mut egui::Ui, state: &mut AppState) {
+        let Some(failure) = &state.failure else {
+            return;
+        };
+        let (package, step, error, output) = (failure.package.clone(), failure.step, failure.error.clone(), failure.output.clone());
+        let hints: Vec<(String, String)> = failure.hints.iter().map(|hint| (hint.problem.clone(), hint.fix.clone())).collect();
+        let build_dir = failure.build_dir.clone();
+        let mut retry = false;
+        let mut skip = false;
+        ui.group(|ui| {
+            ui.colored_label(egui::Color32::RED, format!("{} {} failed: {}", step.label(), package, error));
+            for (problem, fix) in &hints {
+                show_hint(ui, problem, fix);
+            }
+            if !output.is_empty() {
+                ui.collapsing(tr("Output"), |ui| {
+                    egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                        for line in &output {
+                            ui.label(egui::RichText::new(line).monospace());
+                        }
+                    });
+                });
+            }
+            ui.horizontal(|ui| {
+                ui.add_enabled_ui(!state.is_running, |ui| {
+                    retry = ui.button(tr("Retry step")).on_hover_text(format!("{} again", step.label())).clicked();
+                    if let Some((dir, host)) = &build_dir {
+                        if ui.button(tr("Open build dir")).on_hover_text(dir).clicked() {
+                            self.open_build_dir(state, dir, host.as_ref());
+                        }
+                        if ui.button(tr("Open terminal here")).on_hover_text(format!("Inspect {} or rerun makepkg", dir)).clicked() {
+                            self.open_terminal(state, dir, host.as_ref());
+                        }
+                    }
+                    skip = ui.button(tr("Skip package")).on_hover_text(tr("Go on with the next queued package")).clicked();
+                });
+            });
+        });
+        if retry {
+            self.retry_failure(state);
+        } else if skip {
+            pipeline::clear(&package);
+            state.failure = None;
+            state.error = None;
+            state.progress = None;
+            state.log(&format!("Skipped {}", package));
+        }
+    }
+
+    fn hold(&self, state: &mut AppState, package: &str, hold: Hold) {
+        state.log(&format!("Holding {} at {}", package, hold.version));
+        state.config.holds.insert(package.to_string(), hold);
+        if let Err(e) = state.config.save() {
+            state.log_warning(&format!("Could not save settings: {}", e));
+        }
+        if state.config.holds_in_ignore_pkg {
+            self.sync_ignore_pkg(state, vec![package.to_string()], true);
+        }
+    }
+
+    fn release_hold(&self, state: &mut AppState, package: &str) {
+        if state.config.holds.remove(package).is_none() {
+            return;
+        }
+        state.log(&format!("Released the hold on {}", package));
+        if let Err(e) = state.config.save() {
+            state.log_warning(&format!("Could not save settings: {}", e));
+        }
+        if state.config.holds_in_ignore_pkg {
+            self.sync_ignore_pkg(state, vec![package.to_string()], false);
+        }
+    }
+
+    // Moves every held package into IgnorePkg or back out of it
+    fn set_holds_in_ignore_pkg(&self, state: &mut AppState, enabled: bool) {
+        state.config.holds_in_ignore_pkg = enabled;
+        if let Err(e) = state.config.save() {
+            state.log_warning(&format!("Could not save settings: {}", e));
+        }
+        let held: Vec<String> = state.config.holds.keys().cloned().collect();
+        if !held.is_empty() {
+            self.sync_ignore_pkg(state, held, enabled);
+        }
+    }
+
+    // Only pacman reads IgnorePkg; other backends keep the hold in the config alone
+    fn sync_ignore_pkg(&self, state: &mut AppState, packages: Vec<String>, ignored: bool) {
+        if !backend::active().removes_with_pacman() {
+            return;
+        }
+        let escalation_tool = state.config.escalation_tool.clone();
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let list = packages.join(" ");
+            let result = tokio::task::spawn_blocking(move || pacman_conf::set_ignored(&packages, ignored, &escalation_tool).map_err(|e| e.to_string()))
+                .await
+                .unwrap_or_else(|e| Err(e.to_string()));
+            let mut state = state_clone.lock().unwrap();
+            match result {
+                Ok(()) if ignored => state.log(&format!("Added {} to IgnorePkg", list)),
+                Ok(()) => state.log(&format!("Removed {} from IgnorePkg", list)),
+                Err(e) => state.log_warning(&format!("Could not update IgnorePkg: {}", e)),
+            }
+        });
+    }
+
+    fn check_mirror_status(&self, view: &mut MirrorsView) {
+        view.busy = Some("Fetching mirror status...".to_string());
+        view.error = None;
+
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let result = mirrors::status().await.map_err(|e| e.to_string());
+            let mut state = state_clone.lock().unwrap();
+            let Some(view) = &mut state.mirrors else {
+                return;
+            };
+            match result {
+                Ok(status) => *view = status,
+                Err(e) => {
+                    view.busy = None;
+                    view.error = Some(format!("Could not fetch mirror status: {}", e));
+                }
+            }
+        });
+    }
+
+    // Tests one mirror at a time so they do not compete for bandwidth
+    fn test_mirror_speed(&self, view: &mut MirrorsView) {
+        let candidates = view.speed_test_candidates();
+        view.busy = Some(format!("Testing {} mirrors...", candidates.len()));
+        view.error = None;
+
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            for (index, url) in candidates.iter().enumerate() {
+                let speed = mirrors::measure(url).await.map_err(|e| e.to_string());
+                let mut state = state_clone.lock().unwrap();
+                let Some(view) = &mut state.mirrors else {
+                    return;
+                };
+                view.busy = Some(format!("Testing mirrors ({}/{})...", index + 1, candidates.len()));
+                match speed {
+                    Ok(speed) => {
+                        if let Some(mirror) = view.mirrors.iter_mut().find(|mirror| &mirror.url == url) {
+                            mirror.speed = Some(speed);
+                        }
+                    }
+                    Err(e) => tracing::debug!("Speed test of {} failed: {}", url, e),
+                }
+            }
+            if let Some(view) = &mut state_clone.lock().unwrap().mirrors {
+                view.busy = None;
+            }
+        });
+    }
+
+    fn write_mirrorlist(&self, state: &mut AppState) {
+        let escalation_tool = state.config.escalation_tool.clone();
+        let Some(view) = &mut state.mirrors else {
+            return;
+        };
+        let servers: Vec<String> = view.ranked().iter().map(|mirror| mirror.server()).collect();
+        view.busy = Some("Writing mirrorlist...".to_string());
+        view.error = None;
+
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let result = tokio::task::spawn_blocking(move || mirrors::write_mirrorlist(&servers, &escalation_tool).map_err(|e| e.to_string()))
+                .await
+                .unwrap_or_else(|e| Err(e.to_string()));
+            let mut state = state_clone.lock().unwrap();
+            match &result {
+                Ok(()) => state.log(&format!("Wrote {} (previous list kept as {}.bak).", mirrors::MIRRORLIST, mirrors::MIRRORLIST)),
+                Err(e) => state.log_error(&format!("Could not write mirrorlist: {}", e)),
+            }
+            if let Some(view) = &mut state.mirrors {
+                view.busy = None;
+                match result {
+                    Ok(()) => match mirrors::current_servers() {
+                        Ok(servers) => {
+                            for mirror in &mut view.mirrors {
+                                mirror.in_mirrorlist = servers.iter().any(|server| server.starts_with(&mirror.url));
+                            }
+                        }
+                        Err(e) => view.error = Some(e.to_string()),
+                    },
+                    Err(e) => view.error = Some(e),
+                }
+            }
+        });
+    }
+
+    fn load_discover(&self, state: &mut AppState, refresh: bool) {
+        if state.is_running {
+            return;
+        }
+        state.is_running = true;
+        state.error = None;
+        state.progress = Some(tr("Loading AUR rankings...").to_string());
+
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let result = discover::load(refresh).await.map_err(|e| e.to_string());
+            let mut state = state_clone.lock().unwrap();
+            state.is_running = false;
+            state.progress = None;
+            match result {
+                Ok(discover) => state.discover = Some(discover),
+                Err(e) => {
+                    state.error = Some(format!("Could not load AUR rankings: {}", e));
+                    state.log_error(&format!("Could not load AUR rankings: {}", e));
+                }
+            }
+        });
+    }
+
+    // Lists cargo and pipx packages, then looks up their newest versions
+    fn refresh_language_packages(&self, state: &mut AppState) {
+        if state.is_running {
+            return;
+        }
+        state.is_running = true;
+        state.error = None;
+        state.progress = Some(tr("Checking language packages...").to_string());
+
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let mut packages = language::list_installed();
+            language::fetch_latest(&mut packages).await;
+            for package in &mut packages {
+                package.duplicate = backend::active().is_installed(&package.name).unwrap_or(false);
+            }
+            let mut state = state_clone.lock().unwrap();
+            state.language_packages = packages;
+            state.is_running = false;
+            state.progress = None;
+        });
+    }
+
+    fn update_language_packages(&self, state: &mut AppState, packages: Vec<LanguagePackage>) {
+        if state.is_running || packages.is_empty() {
+            return;
+        }
+        state.is_running = true;
+        state.error = None;
+        state.progress = Some(tr("Updating...").to_string());
+
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            for package in packages {
+                state_clone.lock().unwrap().log.begin_operation(&format!("Update {} with {}", package.name, package.tool.label()), Some(&package.name));
+                let result = run_logged(&mut package.tool.update_command(&package.name), &state_clone);
+                let mut state = state_clone.lock().unwrap();
+                match result {
+                    Ok(status) if status.success() => {
+                        state.log(&format!("{} updated.", package.name));
+                        let updated = state.language_packages.iter_mut().find(|p| p.tool == package.tool && p.name == package.name);
+                        if let (Some(updated), Some(latest)) = (updated, package.latest) {
+                            updated.version = latest;
+                        }
+                    }
+                    Ok(status) => {
+                        state.error = Some(format!("Updating {} failed: {} exited with {}", package.name, package.tool.label(), status));
+                        state.log_error(&format!("Update failed: {} exited with {}", package.tool.label(), status));
+                    }
+                    Err(e) => {
+                        state.error = Some(format!("Updating {} failed: {}", package.name, e));
+                        state.log_error(&format!("Update failed: {}", e));
+                    }
+                }
+            }
+            let mut state = state_clone.lock().unwrap();
+            state.is_running = false;
+            state.progress = None;
+        });
+    }
+
+    fn start_prebuilt_install(&self, state: &mut AppState, prebuilt: Prebuilt) {
+        if state.is_running {
+            return;
+        }
+        state.is_running = true;
+        state.error = None;
+        state.progress = Some(tr("Installing prebuilt package...").to_string());
+        state.log.begin_operation(&format!("Install {} from {}", prebuilt.package, prebuilt.repo), Some(&prebuilt.package));
+
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let config = state_clone.lock().unwrap().config.clone();
+            let result = install_prebuilt(&prebuilt, &config, &state_clone).await;
+            let mut state = state_clone.lock().unwrap();
+            state.is_running = false;
+            match result {
+                Ok(()) => {
+                    state.progress = Some(tr("Package installed successfully.").to_string());
+                    state.log("Package installation process completed.");
+                }
+                Err(e) => {
+                    state.error = Some(e.to_string());
+                    state.log_error(&format!("Install failed: {}", e));
+                }
+            }
+            notify_finished(&state, &prebuilt.package);
+        });
+    }
+
+    // `path` is None to ask for a file first
+    fn open_local_package(&self, path: Option<PathBuf>) {
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let result = tokio::task::spawn_blocking(move || {
+                let path = path.or_else(|| local_package::pick_file().map(PathBuf::from))?;
+                Some(local_package::read(&path).map_err(|e| e.to_string()))
+            })
+            .await
+            .ok()
+            .flatten();
+            let mut state = state_clone.lock().unwrap();
+            match result {
+                Some(Ok(package)) => state.local_package = Some(package),
+                Some(Err(e)) => state.error = Some(e),
+                None => {}
+            }
+        });
+    }
+
+    fn install_prerequisites(&self, state: &mut AppState, missing: Vec<String>) {
+        if state.is_running {
+            return;
+        }
+        state.is_running = true;
+        state.error = None;
+        state.progress = Some(fill(tr("Installing {}..."), &[&missing.join(tr(" and "))]));
+        state.log.begin_operation(&format!("Install {}", missing.join(" ")), None);
+
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let escalation_tool = state_clone.lock().unwrap().config.escalation_tool.clone();
+            let result = install_package(backend::repo(), &missing, &escalation_tool);
+            let mut state = state_clone.lock().unwrap();
+            state.is_running = false;
+            match result {
+                Ok(()) => {
+                    state.progress = Some(fill(tr("Installed {}."), &[&missing.join(tr(" and "))]));
+                    state.log("Build prerequisites installed; AUR packages can be built now.");
+                }
+                Err(e) => {
+                    state.error = Some(e.to_string());
+                    state.log_error(&format!("Could not install build prerequisites: {}", e));
+                }
+            }
+        });
+    }
+
+    fn start_local_install(&self, state: &mut AppState, package: LocalPackage) {
+        if state.is_running {
+            return;
+        }
+        state.is_running = true;
+        state.error = None;
+        state.progress = Some(tr("Installing package file...").to_string());
+        state.log.begin_operation(&format!("Install {}", package.path), Some(&package.name));
+
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let config = state_clone.lock().unwrap().config.clone();
+            let result = install_local_package(&package, &config, &state_clone);
+            let mut state = state_clone.lock().unwrap();
+            state.is_running = false;
+            match result {
+                Ok(()) => {
+                    state.progress = Some(tr("Package installed successfully.").to_string());
+                    state.log("Package installation process completed.");
+                }
+                Err(e) => {
+                    state.error = Some(e.to_string());
+                    state.log_error(&format!("Install failed: {}", e));
+                }
+            }
+            notify_finished(&state, &package.name);
+        });
+    }
+
+    fn open_details(&self, state: &mut AppState) {
+        let Some(package_name) = state.selected_package.clone() else {
+            return;
+        };
+        let from_aur = state.selected_backend().builds_from_source();
+        let cached = state.cached_metadata(&package_name).cloned();
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let lookup_name = package_name.clone();
+            let appstream = tokio::task::spawn_blocking(move || appstream::lookup(&lookup_name)).await.ok().flatten();
+            let (refreshed, aur, fetched_now) = match cached {
+                Some((fetched, package)) => (fetched, Some(Ok(package)), false),
+                None if from_aur => (Instant::now(), Some(fetch_metadata(&package_name).await.map_err(|e| e.to_string())), true),
+                None => (Instant::now(), None, false),
+            };
+            let mut state = state_clone.lock().unwrap();
+            match aur.transpose() {
+                Ok(aur) if aur.is_none() && appstream.is_none() => {
+                    state.progress = Some(fill(tr("No details available for {}."), &[&package_name]));
+                }
+                Ok(aur) => {
+                    if let Some(package) = &aur {
+                        state.metadata_cache.insert(package.name.clone(), (refreshed, package.clone()));
+                        if fetched_now {
+                            save_metadata(vec![package.clone()], &state.config);
+                        }
+                    }
+                    state.details = Some(Details {
+                        name: package_name,
+                        aur,
+                        refreshed,
+                        appstream,
+                    })
+                }
+                Err(e) => state.error = Some(format!("Could not load details for {}: {}", package_name, e)),
+            }
+        });
+    }
+
+    // Fetches the metadata of shown AUR results that are not cached yet in one go, so hovering
+    // and opening details need no request of their own
+    fn prefetch_metadata(&self, state: &mut AppState, rows: &[(&'static str, String, String)]) {
+        let names: Vec<String> = rows
+            .iter()
+            .filter(|(source, name, _)| {
+                backend::by_name(source).builds_from_source() && state.cached_metadata(name).is_none() && !state.metadata_pending.contains(name)
+            })
+            .map(|(_, name, _)| name.clone())
+            .collect();
+        if names.is_empty() {
+            return;
+        }
+        state.metadata_pending.extend(names.iter().cloned());
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let fetched = fetch_metadata_batch(&names).await.map_err(|e| e.to_string());
+            let mut state = state_clone.lock().unwrap();
+            // Names that stay pending are not asked for again; details fetch them one at a time
+            match fetched {
+                Ok(packages) => {
+                    save_metadata(packages.clone(), &state.config);
+                    for package in packages {
+                        state.metadata_pending.remove(&package.name);
+                        state.metadata_cache.insert(package.name.clone(), (Instant::now(), package));
+                    }
+                }
+                Err(e) => debug!("Could not prefetch metadata: {}", e),
+            }
+        });
+    }
+
+    // Fetches the AUR metadata in the open details window again, bypassing the cache
+    fn refresh_details(&self, state: &mut AppState) {
+        let Some(name) = state.details.as_ref().filter(|details| details.aur.is_some()).map(|details| details.name.clone()) else {
+            return;
+        };
+        let state_clone = Arc::clone(&self.state);
+        self.rt.spawn(async move {
+            let fetched = fetch_metadata(&name).await.map_err(|e| e.to_string());
+            let mut state = state_clone.lock().unwrap();
+            match fetched {
+                Ok(package) => {
+                    let refreshed = Instant::now();
+                    state.metadata_cache.insert(name.clone(), (refreshed, package.clone()));
+                    save_metadata(vec![package.clone()], &state.config);
+                    // The window may show another package by now
+                    if let Some(details) = state.details.as_mut().filter(|details| details.name == name) {
+                        details.aur = Some(package);
+                        details.refreshed = refreshed;
+                    }
+                }
+                Err(e) => state.error = Some(format!("Could not refresh metadata of {}: {}", name, e)),
+            }
+        });
+    }
+
+    // Forgets all cached metadata; shown results are fetched again on the next frame
+    fn refresh_metadata(&self, state: &mut AppState) {
+        state.metadata_cache.clear();
+        state.metadata_pending.clear();
+        if let Err(e) = state::update(|saved| saved.metadata.clear()) {
+            state.log_warning(&format!("Could not clear the saved metadata: {}", e));
+        }
+        self.refresh_details(state);
+    }
+
+    // j/k move, / focuses search, Enter opens details and dd asks to uninstall
+    fn handle_vim_keys(&self, ctx: &egui::Context, state: &mut AppState) {
+        if !state.config.vim_mode || state.modal_open() || ctx.wants_keyboard_input() {
+            return;
+        }
+        let pressed = |key| ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, key));
+
+        if pressed(egui::Key::J) {
+            state.move_selection(1);
+        }
+        if pressed(egui::Key::K) {
+            state.move_selection(-1);
+        }
+        if pressed(egui::Key::Slash) {
+            state.tab = Tab::Search;
+            self.run_action(ctx, state, Action::FocusSearch);
+        }
+        if pressed(egui::Key::Enter) {
+            self.open_details(state);
+        }
+        if pressed(egui::Key::D) {
+            if state.pending_delete {
+                state.pending_delete = false;
+                if let Some(package) = state.selected_package.clone() {
+                    if state.selected_backend().is_installed(&package).unwrap_or(false) {
+                        if state.config.confirms(ConfirmStep::Removal) {
+                            state.confirm_uninstall = Some((state.selected_source, package));
+                        } else {
+                            self.start_operation(state, true);
+                        }
+                    }
+                }
+            } else {
+                state.pending_delete = true;
+            }
+        } else if ctx.input(|i| i.events.iter().any(|event| matches!(event, egui::Event::Key { pressed: true, .. }))) {
+            state.pending_delete = false;
+        }
+    }
+
+    fn show_language_packages(&self, ui: &mut egui::Ui, state: &mut AppState) {
+        let outdated: Vec<LanguagePackage> = state.language_packages.iter().filter(|package| package.is_outdated()).cloned().collect();
+        ui.horizontal(|ui| {
+            if ui.button(tr("Refresh")).clicked() {
+                self.refresh_language_packages(state);
+            }
+            if ui.add_enabled(!outdated.is_empty() && !state.is_running, egui::Button::new(format!("Update all ({})", outdated.len()))).clicked() {
+                self.update_language_packages(state, outdated);
+            }
+        });
+
+        let mut update = None;
+        egui::Grid::new("language_packages").num_columns(5).striped(true).show(ui, |ui| {
+            for header in ["Tool", "Package", "Installed", "Latest", ""] {
+                ui.strong(header);
+            }
+            ui.end_row();
+            for package in &state.language_packages {
+                ui.label(package.tool.label());
+                ui.label(&package.name);
+                ui.label(&package.version);
+                match (&package.latest, package.local) {
+                    (_, true) => ui.weak("git/path"),
+                    (Some(latest), _) if package.is_outdated() => ui.colored_label(ui.visuals().warn_fg_color, latest),
+                    (Some(latest), _) => ui.label(latest),
+                    (None, _) => ui.weak(tr("unknown")),
+                };
+                ui.horizontal(|ui| {
+                    if package.is_outdated() && ui.add_enabled(!state.is_running, egui::Button::new(tr("Update"))).clicked() {
+                        update = Some(package.clone());
+                    }
+                    if package.duplicate {
+                        ui.colored_label(ui.visuals().warn_fg_color, format!("also in {}", backend::active().name()));
+                    }
+                });
+                ui.end_row();
+            }
+        });
+        if state.language_packages.is_empty() && !state.is_running {
+            ui.weak(tr("No packages installed with cargo install or pipx."));
+        }
+        if let Some(package) = update {
+            self.update_language_packages(state, vec![package]);
+        }
+    }
+
+    fn run_action(&self, ctx: &egui::Context, state: &mut AppState, action: Action) {
+        match action {
+            Action::FocusSearch => ctx.memory_mut(|m| m.request_focus(egui::Id::new(SEARCH_INPUT_ID))),
+            Action::Search => self.start_search(state),
+            Action::Refresh => match state.tab {
+                Tab::Search => self.start_search(state),
+                Tab::Installed => refresh_installed(state),
+                Tab::Languages => self.refresh_language_packages(state),
+                Tab::Browse => self.load_browse_entries(state),
+                Tab::Discover => self.load_discover(state, true),
+                Tab::DiskUsage => self.refresh_package_sizes(state),
+                Tab::Held => refresh_installed(state),
+                Tab::Updates => self.check_updates(state),
+                Tab::Keys => state.pgp_keys = pgp_keys::list(),
+            },
+            Action::ShowDetails => self.open_details(state),
+            Action::RefreshMetadata => self.refresh_metadata(state),
+            Action::ShowInstalled => {
+                state.tab = Tab::Installed;
+                refresh_installed(state);
+            }
+            Action::InstallSelected => {
+                if let Some(package) = &state.selected_package {
+                    if state.selected_backend().is_installed(package).unwrap_or(false) {
+                        state.progress = Some(tr("Package is already installed.").to_string());
+                    } else {
+                        self.start_operation(state, false);
+                    }
+                }
+            }
+            Action::ClearLog => state.clear_log(),
+            Action::OpenSettings => {
+                if state.settings.is_none() {
+                    state.settings = Some(SettingsDraft::new(&state.config));
+                }
+            }
+            Action::CommandPalette => state.command_palette = Some(CommandPalette::default()),
+        }
+    }
+}
+
+impl eframe::App for MyApp {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        // A background task that panicked while holding the state must not take the UI down too
+        if self.state.is_poisoned() {
+            self.state.clear_poison();
+        }
+        // Lock state for mutable access
+        let mut state = self.state.lock().unwrap();
+        if let Some(panic) = crash::take_background_panic() {
+            state.is_running = false;
+            state.progress = None;
+            state.error = Some(format!("Internal error: {}", panic));
+            state.log_error(&format!("A background task crashed: {}", panic));
+        }
+
+        if let Some(events) = &self.log_events {
+            for (level, message) in events.try_iter() {
+                state.log.push(level, &message);
+            }
+        }
+        if let Some(handoffs) = &self.handoffs {
+            for packages in handoffs.try_iter() {
+                state.log(&format!("Queued from the command line: {}", packages.join(" ")));
+                for package in packages {
+                    state.queue.push(package);
+                }
+                self.order_queue(&mut state);
+            }
+        }
+        if let Some(actions) = &self.notification_actions {
+            for action in actions.try_iter() {
+                match action {
+                    notifications::Action::ViewLog(package) => {
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                        if !state.log.reveal(&package) {
+                            state.log_warning(&format!("The log has no entries for {} any more", package));
+                        }
+                    }
+                    notifications::Action::Retry(package) => {
+                        state.retry_queued(&package);
+                        state.log(&format!("Queued {} again from its notification", package));
+                    }
+                }
+            }
+        }
+        poll_build_jobs(&mut state);
+        // Queued packages go through the usual review and install one after another, or with
+        // `concurrent_builds` build beside it once reviewed; a failure holds the queue until it is
+        // retried or skipped
+        if !state.is_running && !state.modal_open() {
+            if state.queue.running().is_some() {
+                let error = state.failure.as_ref().map(|failure| failure.error.clone()).or_else(|| state.error.clone());
+                state.queue.finish(error);
+                if state.queue.waiting() == 0 {
+                    state.build_order.clear();
+                    state.cycle_breaks.clear();
+                }
+            }
+            if state.failure.is_none() && state.queue.building() < state.config.concurrent_builds as usize {
+                if let Some(package) = state.queue.start_next() {
+                    state.select_package(Some((backend::active().name(), package)));
+                    self.start_operation(&mut state, false);
+                }
+            }
+        }
+        show_progress(ctx, &mut self.shown_progress, state.transaction_progress(), state.updates.as_ref().map_or(0, Vec::len));
+
+        // Re-applied whenever the settings or the system theme change
+        let visuals = theme::visuals(&state.config, frame.info().system_theme);
+        if self.applied_visuals.as_ref() != Some(&visuals) {
+            ctx.set_visuals(visuals.clone());
+            self.applied_visuals = Some(visuals);
+        }
+
+        // Ctrl+scroll zoom; egui's built-in Ctrl+plus/minus zoom is picked up from the context
+        let zoom_delta = ctx.input(|i| i.zoom_delta());
+        if zoom_delta != 1.0 {
+            state.config.ui_scale = (state.config.ui_scale * zoom_delta).clamp(theme::MIN_UI_SCALE, theme::MAX_UI_SCALE);
+            self.zoom_changed = true;
+        } else if self.applied_scale.is_some_and(|scale| scale != ctx.zoom_factor()) {
+            state.config.ui_scale = ctx.zoom_factor();
+            self.applied_scale = Some(ctx.zoom_factor());
+            self.zoom_changed = true;
+        }
+        if self.applied_scale != Some(state.config.ui_scale) {
+            ctx.set_zoom_factor(state.config.ui_scale);
+            self.applied_scale = Some(state.config.ui_scale);
+        }
+        if self.applied_font_size != Some(state.config.font_size) {
+            theme::apply_font_size(ctx, state.config.font_size);
+            self.applied_font_size = Some(state.config.font_size);
+        }
+
+        let mut actions = actions::triggered(ctx);
+        if let Some(palette) = &mut state.command_palette {
+            match actions::show_palette(ctx, palette) {
+                Some(PaletteResult::Run(action)) => {
+                    state.command_palette = None;
+                    actions.push(action);
+                }
+                Some(PaletteResult::Close) => state.command_palette = None,
+                None => {}
+            }
+        }
+        for action in actions {
+            self.run_action(ctx, &mut state, action);
+        }
+        self.handle_vim_keys(ctx, &mut state);
+
+        if let Some(draft) = &mut state.settings {
+            match settings::show(ctx, draft) {
+                Some(SettingsAction::Apply(mut config)) => {
+                    // Holds are changed outside the Settings window, possibly while it was open
+                    config.holds = state.config.holds.clone();
+                    config.holds_in_ignore_pkg = state.config.holds_in_ignore_pkg;
+                    if let Err(e) = config.save() {
+                        state.error = Some(format!("Could not save settings: {}", e));
+                    }
+                    state.config = *config;
+                    if let Err(e) = remote::set_active(&state.config).and_then(|_| backend::select(&state.config)) {
+                        state.error = Some(e.to_string());
+                    }
+                    http::configure(&state.config);
+                    state.log("Settings applied.");
+                }
+                Some(SettingsAction::Close) => state.settings = None,
+                None => {}
+            }
+        }
+
+        // Dropping a package file on the window works like `pacman -U`
+        let dropped = ctx.input(|i| i.raw.dropped_files.iter().filter_map(|file| file.path.clone()).find(|path| local_package::is_package_file(path)));
+        if let Some(path) = dropped.filter(|_| backend::active().name() == "pacman") {
+            self.open_local_package(Some(path));
+        }
+
+        if let Some(view) = &state.mirrors {
+            match mirrors::show(ctx, view) {
+                Some(MirrorsAction::CheckStatus) => self.check_mirror_status(state.mirrors.as_mut().unwrap()),
+                Some(MirrorsAction::TestSpeed) => self.test_mirror_speed(state.mirrors.as_mut().unwrap()),
+                Some(MirrorsAction::WriteMirrorlist) => self.write_mirrorlist(&mut state),
+                Some(MirrorsAction::Close) => state.mirrors = None,
+                None => {}
+            }
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            if let Some(release) = state.app_update.clone() {
+                ui.horizontal(|ui| {
+                    ui.label(fill(tr("Version {} of the app is available."), &[&release.version]));
+                    ui.hyperlink_to(tr("Changelog"), &release.url);
+                    if ui.small_button(tr("Dismiss")).clicked() {
+                        state.app_update = None;
+                    }
+                });
+            }
+            ui.horizontal(|ui| {
+                ui.label("Rust AUR Helper");
+                if ui.button(tr("Settings")).clicked() {
+                    self.run_action(ctx, &mut state, Action::OpenSettings);
+                }
+                if backend::active().name() == "pacman" && ui.button(tr("Install file...")).clicked() {
+                    self.open_local_package(None);
+                }
+                // Mirrorlists are a pacman concept
+                if backend::active().name() == "pacman" && ui.add_enabled(state.mirrors.is_none(), egui::Button::new(tr("Mirrors"))).clicked() {
+                    let mut view = MirrorsView::default();
+                    self.check_mirror_status(&mut view);
+                    state.mirrors = Some(view);
+                }
+                ui.separator();
+                ui.weak(format!("Backend: {}", backend::active().name()));
+                if runner::dry_run() {
+                    ui.colored_label(ui.visuals().warn_fg_color, "Dry run").on_hover_text(tr("Builds and package manager commands are logged instead of run"));
+                }
+
+                // Remote hosts are highlighted so operations are never run on the wrong machine
+                ui.label(tr("Host:"));
+                let mut selected = state.config.active_host.clone();
+                let text = egui::RichText::new(remote::describe_active());
+                let text = if selected.is_some() { text.color(ui.visuals().warn_fg_color).strong() } else { text };
+                ui.add_enabled_ui(!state.is_running, |ui| {
+                    egui::ComboBox::from_id_source("active_host").selected_text(text).show_ui(ui, |ui| {
+                        ui.selectable_value(&mut selected, None, "local");
+                        for (name, host) in &state.config.hosts {
+                            ui.selectable_value(&mut selected, Some(name.clone()), format!("{} ({})", name, host.address));
+                        }
+                    });
+                });
+                if selected != state.config.active_host {
+                    switch_host(&mut state, selected);
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut state.tab, Tab::Search, tr("Search"));
+                if ui.selectable_value(&mut state.tab, Tab::Installed, tr("Installed")).clicked() {
+                    refresh_installed(&mut state);
+                }
+                if ui.selectable_value(&mut state.tab, Tab::Discover, tr("Discover")).clicked() && state.discover.is_none() {
+                    self.load_discover(&mut state, false);
+                }
+                if ui.selectable_value(&mut state.tab, Tab::Browse, tr("Browse")).clicked() && state.browse_entries.is_none() {
+                    self.load_browse_entries(&mut state);
+                }
+                if ui.selectable_value(&mut state.tab, Tab::Languages, tr("Language packages")).clicked() && state.language_packages.is_empty() {
+                    self.refresh_language_packages(&mut state);
+                }
+                if ui.selectable_value(&mut state.tab, Tab::DiskUsage, tr("Disk usage")).clicked() && state.package_sizes.is_empty() {
+                    self.refresh_package_sizes(&mut state);
+                }
+                let held = fill(tr("Held ({})"), &[&state.config.holds.len()]);
+                if ui.selectable_value(&mut state.tab, Tab::Held, held).clicked() {
+                    refresh_installed(&mut state);
+                }
+                let updates = match state.updates.as_ref().map_or(0, Vec::len) {
+                    0 => tr("Updates").to_string(),
+                    count => fill(tr("Updates ({})"), &[&count]),
+                };
+                if ui.selectable_value(&mut state.tab, Tab::Updates, updates).clicked() && state.updates.is_none() {
+                    self.check_updates(&mut state);
+                }
+                if ui.selectable_value(&mut state.tab, Tab::Keys, tr("PGP keys")).clicked() {
+                    state.pgp_keys = pgp_keys::list();
+                }
+            });
+            ui.separator();
+
+            match state.tab {
+                Tab::Search => {
+                    // Input for package name
+                    ui.horizontal(|ui| {
+                        ui.label(if state.search_by == SearchBy::Maintainer { "Maintainer:" } else { "Package:" });
+                        self.search_input(ui, &mut state);
+                        egui::ComboBox::from_id_source("search_match").selected_text(state.search_match.label()).show_ui(ui, |ui| {
+                            for search_match in SearchMatch::ALL {
+                                ui.selectable_value(&mut state.search_match, search_match, search_match.label());
+                            }
+                        });
+                        if backend::active().builds_from_source() {
+                            egui::ComboBox::from_id_source("search_by").selected_text(state.search_by.label()).show_ui(ui, |ui| {
+                                for by in SearchBy::ALL {
+                                    ui.selectable_value(&mut state.search_by, by, by.label());
+                                }
+                            });
+                        }
+                    });
+
+                    if state.search_match == SearchMatch::Regex {
+                        if let Err(e) = regex::Regex::new(&state.package_name) {
+                            ui.colored_label(egui::Color32::RED, format!("Invalid regex: {}", e));
+                        } else if state.search_results.is_empty() {
+                            ui.weak(tr("Search first; the regex filters the results."));
+                        }
+                    } else {
+                        ui.horizontal(|ui| {
+                            if ui.button(tr("Search")).clicked() {
+                                self.start_search(&mut state);
+                            }
+                            if !state.favorites.is_empty() && ui.button(tr("Favorites")).on_hover_text(tr("List the packages marked as favorites")).clicked() {
+                                let favorites = state.favorites.iter().map(|favorite| (backend::by_name(&favorite.source).name(), favorite.name.clone())).collect();
+                                state.search_group = None;
+                                state.add_search_results(favorites);
+                            }
+                            if !state.search_results.is_empty() && ui.button(tr("Refresh metadata")).on_hover_text(tr("Fetch the details of the results again")).clicked() {
+                                self.refresh_metadata(&mut state);
+                            }
+                        });
+                        if let Some(group) = state.search_group.clone().filter(|group| group.name == state.package_name) {
+                            ui.horizontal(|ui| {
+                                ui.label(fill(tr("{} is a group of {} packages."), &[&group.name, &group.members.len()]));
+                                if ui.add_enabled(!state.is_running, egui::Button::new(tr("Install group..."))).clicked() {
+                                    state.group_install = Some(group);
+                                }
+                            });
+                        }
+                    }
+                }
+                Tab::Installed => {
+                    if ui.button(tr("Refresh")).clicked() {
+                        refresh_installed(&mut state);
+                    }
+                }
+                Tab::Languages => self.show_language_packages(ui, &mut state),
+                Tab::DiskUsage => self.show_package_sizes(ui, &mut state),
+                Tab::Held => self.show_holds(ui, &mut state),
+                Tab::Updates => self.show_updates(ui, &mut state),
+                Tab::Keys => self.show_pgp_keys(ui, &mut state),
+                Tab::Discover => {
+                    ui.horizontal(|ui| {
+                        for ranking in Ranking::ALL {
+                            ui.selectable_value(&mut state.discover_ranking, ranking, ranking.label());
+                        }
+                        if ui.button(tr("Refresh")).clicked() {
+                            self.load_discover(&mut state, true);
+                        }
+                        if let Some(fetched) = state.discover.as_ref().and_then(|discover| chrono::DateTime::from_timestamp(discover.fetched, 0)) {
+                            ui.weak(format!("Updated {}", fetched.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")));
+                        }
+                    });
+                    if !backend::active().builds_from_source() {
+                        ui.weak(tr("Rankings are only available for AUR packages."));
+                    }
+                }
+                Tab::Browse => {
+                    let categories = state.browse_entries.as_deref().map(browse::categories).unwrap_or_default();
+                    ui.horizontal(|ui| {
+                        ui.label(tr("Category:"));
+                        let selected = categories
+                            .iter()
+                            .find(|(key, _, _)| Some(key) == state.browse_category.as_ref())
+                            .map(|(_, label, _)| label.clone())
+                            .unwrap_or_default();
+                        egui::ComboBox::from_id_source("browse_category").selected_text(selected).show_ui(ui, |ui| {
+                            for (key, label, count) in &categories {
+                                ui.selectable_value(&mut state.browse_category, Some(key.clone()), format!("{} ({})", label, count));
+                            }
+                        });
+                        if ui.button(tr("Reload")).clicked() {
+                            self.load_browse_entries(&mut state);
+                        }
+                    });
+                }
+            }
+
+            // Display the current list and handle selection, with the source of each package
+            let rows: Vec<(&'static str, String, String)> = match state.tab {
+                Tab::Search => state.search_rows().into_iter().map(|(source, name)| (source, name.clone(), name)).collect(),
+                Tab::Installed => state
+                    .installed_packages
+                    .iter()
+                    .map(|(source, name, version)| (*source, name.clone(), format!("{} {}", name, version)))
+                    .collect(),
+                Tab::Browse => state.browse_rows(),
+                Tab::Discover => state.discover_rows(),
+                Tab::Languages | Tab::DiskUsage | Tab::Held | Tab::Updates | Tab::Keys => Vec::new(),
+            };
+            if state.tab == Tab::Search {
+                self.prefetch_metadata(&mut state, &rows);
+            }
+            // Split packages of one base get a foldable header row when more than one matched
+            let group_sizes: HashMap<String, usize> = if state.tab == Tab::Search {
+                rows.iter().fold(HashMap::new(), |mut sizes, (_, name, _)| {
+                    *sizes.entry(state.search_base(name).to_string()).or_insert(0) += 1;
+                    sizes
+                })
+            } else {
+                HashMap::new()
+            };
+            let keys: Vec<selection::Key> = rows.iter().map(|(source, name, _)| (*source, name.clone())).collect();
+            let mut current_base = None;
+            egui::Grid::new("package_list").num_columns(3).show(ui, |ui| {
+                for (source, name, label) in rows {
+                    let base = state.search_base(&name).to_string();
+                    let count = group_sizes.get(&base).copied().unwrap_or(1);
+                    if count > 1 && current_base.as_ref() != Some(&base) {
+                        let collapsed = state.collapsed_bases.contains(&base);
+                        let arrow = if collapsed { "⏵" } else { "⏷" };
+                        if ui.selectable_label(false, format!("{} {} ({} packages)", arrow, base, count)).clicked() && !state.collapsed_bases.remove(&base) {
+                            state.collapsed_bases.insert(base.clone());
+                        }
+                        ui.weak(tr("package base"));
+                        ui.end_row();
+                        current_base = Some(base.clone());
+                    }
+                    if count > 1 && state.collapsed_bases.contains(&base) {
+                        continue;
+                    }
+                    let selected = state.selection.contains(source, &name) || (state.selected_source == source && state.selected_package.as_deref() == Some(&name));
+                    let mut row = if count > 1 {
+                        ui.horizontal(|ui| {
+                            ui.add_space(18.0);
+                            ui.radio(selected, label)
+                        })
+                        .inner
+                    } else {
+                        ui.radio(selected, label)
+                    };
+                    if let Some((_, package)) = state.metadata_cache.get(&name) {
+                        let installed = state.search_versions.get(&(source, name.clone())).map(|version| version.installed.clone());
+                        row = row.on_hover_ui(|ui| metadata_tooltip(ui, package, installed.as_deref()));
+                    }
+                    if row.clicked() {
+                        let modifiers = ui.input(|input| input.modifiers);
+                        let mut selection = std::mem::take(&mut state.selection);
+                        selection.click(&keys, (source, name.clone()), modifiers, |key| state.knows_installed(key));
+                        state.select_package(Some((source, name.clone())));
+                        state.selection = selection;
+
+                        // Check if the selected package is installed
+                        if state.selected_backend().is_installed(&name).unwrap_or(false) {
+                            state.progress = Some(tr("Package is already installed.").to_string());
+                        } else {
+                            state.progress = None;
+                        }
+                    }
+                    ui.weak(backend::by_name(source).label());
+                    if state.tab == Tab::Search {
+                        if let Some(version) = state.search_versions.get(&(source, name.clone())) {
+                            let color = match version.state {
+                                VersionState::UpToDate => Some(egui::Color32::from_rgb(13, 188, 121)),
+                                VersionState::Outdated => Some(ui.visuals().warn_fg_color),
+                                VersionState::LocalNewer => Some(ui.visuals().error_fg_color),
+                                VersionState::Unknown => None,
+                            };
+                            let mut text = match &version.latest {
+                                Some(latest) if *latest != version.installed => format!("installed {}, AUR {}", version.installed, latest),
+                                _ => format!("installed {}", version.installed),
+                            };
+                            if state.config.is_held(&name) {
+                                text.push_str(", held");
+                            }
+                            match color {
+                                Some(color) => ui.colored_label(color, text),
+                                None => ui.weak(text),
+                            };
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+
+            // Actions on several packages picked with Ctrl or Shift. The queue installs from the
+            // active source, so picks from other sources are left out of Install.
+            if state.selection.len() > 1 {
+                if !state.is_running {
+                    let not_installed = state.selection.with_installed(false);
+                    let to_install: Vec<String> = not_installed.iter().filter(|(source, _)| *source == backend::active().name()).map(|(_, name)| name.clone()).collect();
+                    let left_out = not_installed.len() - to_install.len();
+                    let to_remove = state.selection.with_installed(true);
+                    ui.horizontal(|ui| {
+                        ui.label(fill(tr("{} selected"), &[&state.selection.len()]));
+                        let mut install = ui.add_enabled(!to_install.is_empty(), egui::Button::new(fill(tr("Install {}"), &[&to_install.len()])));
+                        if left_out > 0 {
+                            install = install.on_hover_text(fill(tr("{} from other sources than {} are left out"), &[&left_out, &backend::active().label()]));
+                        }
+                        if install.clicked() {
+                            state.log(&format!("Queued: {}", to_install.join(" ")));
+                            for package in to_install {
+                                state.queue.push(package);
+                            }
+                            self.order_queue(&mut state);
+                            state.select_package(None);
+                        }
+                        if ui.add_enabled(!to_remove.is_empty(), egui::Button::new(fill(tr("Uninstall {}"), &[&to_remove.len()]))).clicked() {
+                            if state.config.confirms(ConfirmStep::Removal) {
+                                state.confirm_bulk_uninstall = Some(to_remove);
+                            } else {
+                                self.start_bulk_uninstall(&mut state, to_remove);
+                            }
+                        }
+                        // makepkg resolves the dependencies with pacman, so Flatpak apps and snaps cannot be among them
+                        let pacman_packages: Vec<String> = state
+                            .selection
+                            .items()
+                            .iter()
+                            .filter(|(source, _)| backend::by_name(source).removes_with_pacman())
+                            .map(|(_, name)| name.clone())
+                            .collect();
+                        if backend::active().builds_from_source()
+                            && ui
+                                .add_enabled(!pacman_packages.is_empty(), egui::Button::new(tr("Metapackage...")))
+                                .on_hover_text(tr("Build a package that depends on these, to install and remove them as one"))
+                                .clicked()
+                        {
+                            state.metapackage_draft = Some(metapackage::Draft { packages: pacman_packages, mark_as_dependencies: true, ..Default::default() });
+                        }
+                        let selected: Vec<(&str, &str)> = state.selection.items().iter().map(|(source, name)| (*source, name.as_str())).collect();
+                        let all_favorites = selected.iter().all(|(source, name)| state.favorites.iter().any(|favorite| favorite.source == *source && favorite.name == *name));
+                        if ui.button(if all_favorites { tr("★ Favorite") } else { tr("☆ Favorite") }).on_hover_text(tr("Mark all of these as favorites, or unmark them when they all are")).clicked() {
+                            match state::toggle_favorites(&selected) {
+                                Ok(_) => state.favorites = state::read().favorites,
+                                Err(e) => state.log_warning(&format!("Could not save the favorites: {}", e)),
+                            }
+                        }
+                        if ui.button(tr("Clear selection")).clicked() {
+                            state.select_package(None);
+                        }
+                    });
+                }
+            } else if let Some(package) = state.selected_package.clone().filter(|_| !matches!(state.tab, Tab::Languages | Tab::DiskUsage | Tab::Held | Tab::Updates | Tab::Keys)) {
+                if !state.is_running {
+                    let source = state.selected_backend();
+                    let installed = source.is_installed(&package).unwrap_or(false);
+                    let button_text = if installed { "Uninstall" } else { "Install" };
+
+                    ui.horizontal(|ui| {
+                        if ui.button(button_text).clicked() {
+                            if installed && state.config.confirms(ConfirmStep::Removal) {
+                                state.confirm_uninstall = Some((state.selected_source, package.clone()));
+                            } else {
+                                self.start_operation(&mut state, installed);
+                            }
+                        }
+                        if ui.button(tr("Details")).clicked() {
+                            self.open_details(&mut state);
+                        }
+                        let favorite = state.favorites.iter().any(|favorite| favorite.source == source.name() && favorite.name == package);
+                        if ui.button(if favorite { tr("★ Favorite") } else { tr("☆ Favorite") }).on_hover_text(tr("Favorites are listed with the Favorites button of the search")).clicked() {
+                            match state::toggle_favorite(source.name(), &package) {
+                                Ok(_) => state.favorites = state::read().favorites,
+                                Err(e) => state.log_warning(&format!("Could not save the favorites: {}", e)),
+                            }
+                        }
+                        if installed && state.config.is_held(&package) {
+                            if ui.button(tr("Release hold")).clicked() {
+                                self.release_hold(&mut state, &package);
+                            }
+                        } else if installed && ui.button(tr("Hold")).on_hover_text(tr("Keep this version; updates skip it")).clicked() {
+                            let version = source.list_installed().ok().and_then(|installed| installed.into_iter().find(|(name, _)| *name == package)).map(|(_, version)| version);
+                            state.hold_draft = Some((package.clone(), Hold { version: version.unwrap_or_default(), reason: String::new() }));
+                        }
+                    });
+
+                    if !installed && source.builds_from_source() {
+                        ui.checkbox(&mut state.run_namcap, tr("Run namcap after build"));
+                        if ui.checkbox(&mut state.config.background_builds, tr("Build in the background (low priority)")).changed() {
+                            if let Err(e) = state.config.save() {
+                                state.log_warning(&format!("Could not save settings: {}", e));
+                            }
+                        }
+                    }
+                }
+            }
+
+            if let Some(details) = &state.details {
+                let mut open = true;
+                let mut search_maintainer = None;
+                let mut refresh = false;
+                egui::Window::new(format!("Details: {}", details.name)).open(&mut open).show(ctx, |ui| {
+                    if let Some(app) = &details.appstream {
+                        ui.horizontal(|ui| {
+                            if let Some(icon) = &app.icon {
+                                ui.add(egui::Image::new(icon.as_str()).fit_to_exact_size(egui::vec2(64.0, 64.0)));
+                            }
+                            ui.vertical(|ui| {
+                                ui.heading(&app.name);
+                                ui.label(&app.summary);
+                                ui.weak(&app.id);
+                            });
+                        });
+                    }
+                    if let Some(package) = &details.aur {
+                        egui::Grid::new("details_grid").num_columns(2).show(ui, |ui| {
+                            ui.label(tr("Version:"));
+                            ui.label(&package.version);
+                            ui.end_row();
+                            ui.label(tr("Package base:"));
+                            ui.label(&package.package_base);
+                            ui.end_row();
+                            ui.label(tr("Description:"));
+                            ui.label(&package.description);
+                            ui.end_row();
+                            ui.label(tr("Votes:"));
+                            ui.label(format!("{} (popularity {:.2})", package.votes, package.popularity));
+                            ui.end_row();
+                            ui.label(tr("Maintainer:"));
+                            match &package.maintainer {
+                                Some(maintainer) => {
+                                    if ui.link(maintainer).on_hover_text(tr("List packages by this maintainer")).clicked() {
+                                        search_maintainer = Some(maintainer.clone());
+                                    }
+                                }
+                                None => {
+                                    ui.colored_label(ui.visuals().warn_fg_color, "orphaned");
+                                }
+                            }
+                            ui.end_row();
+                        });
+                        ui.horizontal(|ui| {
+                            ui.weak(format!("Last refreshed {}", minutes_ago(details.refreshed)));
+                            if ui.small_button(tr("Refresh metadata")).clicked() {
+                                refresh = true;
+                            }
+                        });
+                    }
+                    // Screenshots are loaded from the network by egui_extras as they scroll into view
+                    if let Some(app) = details.appstream.as_ref().filter(|app| !app.screenshots.is_empty()) {
+                        ui.separator();
+                        egui::ScrollArea::horizontal().max_height(240.0).show(ui, |ui| {
+                            ui.horizontal(|ui| {
+                                for screenshot in &app.screenshots {
+                                    ui.add(egui::Image::new(screenshot.as_str()).max_height(220.0));
+                                }
+                            });
+                        });
+                    }
+                });
+                if !open {
+                    state.details = None;
+                }
+                if refresh {
+                    self.refresh_details(&mut state);
+                }
+                if let Some(maintainer) = search_maintainer {
+                    state.details = None;
+                    state.tab = Tab::Search;
+                    state.search_by = SearchBy::Maintainer;
+                    state.package_name = maintainer;
+                    self.start_search(&mut state);
+                }
+            }
+
+            if let Some((source, package)) = state.confirm_uninstall.clone() {
+                egui::Window::new(tr("Confirm uninstall")).collapsible(false).resizable(false).show(ctx, |ui| {
+                    let backend = backend::by_name(source);
+                    ui.label(fill(tr("Uninstall {} ({})?"), &[&package, &backend.label()]));
+                    if backend.removes_with_pacman() {
+                        let mut mode = state.remove_mode.unwrap_or(state.config.remove_mode);
+                        for option in RemoveMode::ALL {
+                            ui.radio_value(&mut mode, option, format!("{} ({})", option.label(), option.flag()));
+                            ui.indent(option.name(), |ui| ui.weak(option.explanation()));
+                        }
+                        state.remove_mode = Some(mode);
+                        ui.separator();
+                        ui.label(tr("Runs:"));
+                        ui.code(removal_command(backend, &package, mode, &state.config.escalation_tool));
+                        ui.separator();
+                        self.show_removal_impact(ui, &mut state, &package, mode);
+                    }
+                    // The impact has to be known before a pacman removal can be confirmed
+                    let ready = !backend.removes_with_pacman() || state.removal_impact.as_ref().is_some_and(|(_, _, impact)| impact.is_some());
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(ready, egui::Button::new(tr("Uninstall"))).clicked() {
+                            state.confirm_uninstall = None;
+                            state.removal_impact = None;
+                            state.select_package(Some((source, package.clone())));
+                            self.start_operation(&mut state, true);
+                        }
+                        let cancel = ui.button(tr("Cancel"));
+                        accessibility::focus_when_shown(&cancel);
+                        if cancel.clicked() {
+                            state.confirm_uninstall = None;
+                            state.remove_mode = None;
+                            state.removal_impact = None;
+                        }
+                    });
+                });
+            }
+
+            if let Some(packages) = state.confirm_bulk_uninstall.clone() {
+                egui::Window::new(tr("Confirm uninstall")).collapsible(false).resizable(false).show(ctx, |ui| {
+                    ui.label(fill(tr("Uninstall these {} packages?"), &[&packages.len()]));
+                    egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                        for (source, package) in &packages {
+                            ui.label(format!("{} ({})", package, backend::by_name(source).label()));
+                        }
+                    });
+                    if packages.iter().any(|(source, _)| backend::by_name(source).removes_with_pacman()) {
+                        ui.separator();
+                        let mut mode = state.remove_mode.unwrap_or(state.config.remove_mode);
+                        for option in RemoveMode::ALL {
+                            ui.radio_value(&mut mode, option, format!("{} ({})", option.label(), option.flag()));
+                            ui.indent(option.name(), |ui| ui.weak(option.explanation()));
+                        }
+                        state.remove_mode = Some(mode);
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.button(tr("Uninstall")).clicked() {
+                            state.confirm_bulk_uninstall = None;
+                            self.start_bulk_uninstall(&mut state, packages.clone());
+                        }
+                        let cancel = ui.button(tr("Cancel"));
+                        accessibility::focus_when_shown(&cancel);
+                        if cancel.clicked() {
+                            state.confirm_bulk_uninstall = None;
+                            state.remove_mode = None;
+                        }
+                    });
+                });
+            }
+
+            if let Some(DependencyCycle { cycle, mut nodes }) = state.dependency_cycle.take() {
+                let mut decision = None;
+                egui::Window::new(tr("Dependency cycle")).collapsible(false).resizable(false).show(ctx, |ui| {
+                    ui.label(tr("These queued packages depend on each other, so none of them can be built first:"));
+                    ui.monospace(cycle.join(" → "));
+                    ui.label(tr("One of them can be built first as if the package after it were installed (makepkg --nodeps, pacman --assume-installed). It may not work until the others are installed too."));
+                    ui.horizontal_wrapped(|ui| {
+                        for package in &cycle[..cycle.len() - 1] {
+                            if ui.button(fill(tr("Build {} first"), &[package])).clicked() {
+                                decision = Some(Some(package.clone()));
+                            }
+                        }
+                        let skip = ui.button(tr("Skip these"));
+                        accessibility::focus_when_shown(&skip);
+                        if skip.clicked() {
+                            decision = Some(None);
+                        }
+                    });
+                });
+                match decision {
+                    Some(Some(package)) => {
+                        let assumed = build_order::break_cycle(&mut nodes, &cycle, &package);
+                        state.log(&format!("Building {} first, assuming {} installed", package, assumed.join(" ")));
+                        state.cycle_breaks.insert(package, assumed);
+                        apply_build_order(&mut state, nodes);
+                    }
+                    Some(None) => {
+                        for package in &cycle {
+                            state.queue.skip(package);
+                        }
+                        nodes.retain(|node| !cycle.contains(&node.name));
+                        apply_build_order(&mut state, nodes);
+                    }
+                    None => state.dependency_cycle = Some(DependencyCycle { cycle, nodes }),
+                }
+            }
+
+            if let Some(mut draft) = state.metapackage_draft.take() {
+                let mut keep = true;
+                egui::Window::new(tr("Make a metapackage")).collapsible(false).resizable(false).show(ctx, |ui| {
+                    ui.label(fill(tr("Builds and installs a package that depends on these {} packages:"), &[&draft.packages.len()]));
+                    ui.weak(draft.packages.join(" "));
+                    egui::Grid::new("metapackage_grid").num_columns(2).show(ui, |ui| {
+                        let label = ui.label(tr("Name:"));
+                        let name = ui.text_edit_singleline(&mut draft.name).labelled_by(label.id);
+                        accessibility::focus_when_shown(&name);
+                        ui.end_row();
+                        let label = ui.label(tr("Description:"));
+                        ui.text_edit_singleline(&mut draft.description).labelled_by(label.id);
+                        ui.end_row();
+                    });
+                    ui.checkbox(&mut draft.mark_as_dependencies, tr("Mark them as dependencies, so that removing the metapackage takes them along"));
+                    let checked = metapackage::check_name(&draft.name);
+                    if let (Err(e), false) = (&checked, draft.name.is_empty()) {
+                        ui.colored_label(ui.visuals().error_fg_color, e);
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(checked.is_ok(), egui::Button::new(tr("Build"))).clicked() {
+                            keep = false;
+                            self.start_metapackage(&mut state, std::mem::take(&mut draft));
+                        }
+                        if ui.button(tr("Cancel")).clicked() {
+                            keep = false;
+                        }
+                    });
+                });
+                if keep {
+                    state.metapackage_draft = Some(draft);
+                }
+            }
+
+            if let Some(mut group) = state.group_install.take() {
+                let mut keep = true;
+                egui::Window::new(fill(tr("Install group {}"), &[&group.name])).collapsible(false).resizable(false).show(ctx, |ui| {
+                    ui.label(tr("Untick the members to leave out. They are installed in one pacman transaction."));
+                    ui.horizontal(|ui| {
+                        if ui.button(tr("All")).clicked() {
+                            group.members.iter_mut().for_each(|member| member.picked = true);
+                        }
+                        if ui.button(tr("None")).clicked() {
+                            group.members.iter_mut().for_each(|member| member.picked = false);
+                        }
+                    });
+                    egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                        for member in &mut group.members {
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut member.picked, &member.name);
+                                if member.installed {
+                                    ui.weak(tr("installed"));
+                                }
+                            });
+                        }
+                    });
+                    let picked = group.picked();
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(!picked.is_empty(), egui::Button::new(fill(tr("Install {}"), &[&picked.len()]))).clicked() {
+                            keep = false;
+                            self.start_group_install(&mut state, &group.name, picked);
+                        }
+                        let cancel = ui.button(tr("Cancel"));
+                        accessibility::focus_when_shown(&cancel);
+                        if cancel.clicked() {
+                            keep = false;
+                        }
+                    });
+                });
+                if keep {
+                    state.group_install = Some(group);
+                }
+            }
+
+            if let Some((package, mut hold)) = state.hold_draft.take() {
+                let mut keep = true;
+                egui::Window::new(fill(tr("Hold {}"), &[&package])).collapsible(false).resizable(false).show(ctx, |ui| {
+                    ui.label(fill(tr("Keep {} at {}. Update checks and \"Update all\" skip it until the hold is released."), &[&package, &hold.version]));
+                    ui.horizontal(|ui| {
+                        let label = ui.label(tr("Reason:"));
+                        let reason = ui.text_edit_singleline(&mut hold.reason).labelled_by(label.id);
+                        accessibility::focus_when_shown(&reason);
+                    });
+                    if state.config.holds_in_ignore_pkg && backend::active().removes_with_pacman() {
+                        ui.weak(fill(tr("It is also added to IgnorePkg in {}."), &[&pacman_conf::PATH]));
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.button(tr("Hold")).clicked() {
+                            keep = false;
+                            self.hold(&mut state, &package, hold.clone());
+                        }
+                        if ui.button(tr("Cancel")).clicked() {
+                            keep = false;
+                        }
+                    });
+                });
+                if keep {
+                    state.hold_draft = Some((package, hold));
+                }
+            }
+
+            if let Some(prebuilt) = state.prebuilt_offer.clone() {
+                egui::Window::new(fill(tr("Install {}"), &[&prebuilt.package])).collapsible(false).resizable(false).show(ctx, |ui| {
+                    ui.label(fill(tr("{} {} is available prebuilt from {}."), &[&prebuilt.package, &prebuilt.version, &prebuilt.repo]));
+                    ui.label(tr("Installing it skips downloading, reviewing and building the PKGBUILD."));
+                    ui.horizontal(|ui| {
+                        if ui.button(tr("Install prebuilt")).clicked() {
+                            state.prebuilt_offer = None;
+                            self.start_prebuilt_install(&mut state, prebuilt.clone());
+                        }
+                        if ui.button(tr("Build from source")).clicked() {
+                            state.prebuilt_offer = None;
+                            self.start_operation_with(&mut state, false, false);
+                        }
+                        let cancel = ui.button(tr("Cancel"));
+                        accessibility::focus_when_shown(&cancel);
+                        if cancel.clicked() {
+                            state.prebuilt_offer = None;
+                            state.progress = None;
+                        }
+                    });
+                });
+            }
+
+            if let Some(path) = state.crash_report.clone() {
+                egui::Window::new(tr("The last session crashed")).collapsible(false).resizable(false).show(ctx, |ui| {
+                    ui.label(tr("A crash report with a backtrace and the last operations was saved to:"));
+                    ui.label(egui::RichText::new(path.display().to_string()).monospace());
+                    ui.weak(tr("Attaching it to a bug report helps find the cause."));
+                    ui.horizontal(|ui| {
+                        if ui.button(tr("Open report")).clicked() {
+                            if let Err(e) = StdCommand::new("xdg-open").arg(&path).spawn() {
+                                state.log_warning(&format!("Could not open {}: {}", path.display(), e));
+                            }
+                        }
+                        if ui.button(tr("Copy path")).clicked() {
+                            ctx.copy_text(path.display().to_string());
+                        }
+                        let close = ui.button(tr("Close"));
+                        accessibility::focus_when_shown(&close);
+                        if close.clicked() {
+                            state.crash_report = None;
+                        }
+                    });
+                });
+            }
+
+            if !state.resumable.is_empty() {
+                let mut resume = None;
+                let mut discard = None;
+                let active_host = remote::active().map(|(name, _)| name);
+                egui::Window::new(tr("Unfinished installs")).collapsible(false).resizable(false).show(ctx, |ui| {
+                    ui.label(tr("These installs were interrupted when the app was last closed:"));
+                    egui::Grid::new("resumable").num_columns(3).show(ui, |ui| {
+                        for (index, checkpoint) in state.resumable.iter().enumerate() {
+                            ui.label(format!("{} {}", checkpoint.package, checkpoint.version));
+                            match &checkpoint.host {
+                                Some(host) => ui.weak(format!("{} on {}", checkpoint.stage.label(), host)),
+                                None => ui.weak(checkpoint.stage.label()),
+                            };
+                            ui.horizontal(|ui| {
+                                let same_host = checkpoint.host == active_host;
+                                let button = ui.add_enabled(same_host && !state.is_running, egui::Button::new(tr("Resume")));
+                                if button.on_disabled_hover_text(tr("Switch to the host it was built on first")).clicked() {
+                                    resume = Some(index);
+                                }
+                                if ui.button(tr("Discard")).clicked() {
+                                    discard = Some(index);
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+                    if ui.button(tr("Discard all")).clicked() {
+                        for checkpoint in state.resumable.drain(..) {
+                            pipeline::clear(&checkpoint.package);
+                        }
+                    }
+                });
+                if let Some(index) = resume {
+                    let checkpoint = state.resumable.remove(index);
+                    self.resume(&mut state, checkpoint);
+                } else if let Some(index) = discard {
+                    pipeline::clear(&state.resumable.remove(index).package);
+                }
+            }
+
+            if let Some(missing) = state.missing_prerequisites.clone() {
+                egui::Window::new(tr("Build tools missing")).collapsible(false).resizable(false).show(ctx, |ui| {
+                    ui.label(fill(tr("Building AUR packages on {} needs packages that are not installed:"), &[&remote::describe_active()]));
+                    for name in &missing {
+                        ui.horizontal_wrapped(|ui| {
+                            ui.strong(name);
+                            ui.label(prerequisites::purpose(name));
+                        });
+                    }
+                    ui.weak(tr("Without them makepkg fails with errors about fakeroot, strip or git that are hard to trace back."));
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(!state.is_running, egui::Button::new(tr("Install"))).clicked() {
+                            state.missing_prerequisites = None;
+                            self.install_prerequisites(&mut state, missing.clone());
+                        }
+                        let not_now = ui.button(tr("Not now"));
+                        accessibility::focus_when_shown(&not_now);
+                        if not_now.clicked() {
+                            state.missing_prerequisites = None;
+                        }
+                    });
+                });
+            }
+
+            if let Some(package) = state.local_package.clone() {
+                egui::Window::new(format!("Install {}", package.name)).collapsible(false).resizable(false).show(ctx, |ui| {
+                    ui.label(format!("{} {} ({})", package.name, package.version, package.arch));
+                    if !package.description.is_empty() {
+                        ui.label(&package.description);
+                    }
+                    ui.weak(format!("Installed size: {}", format_size(package.size)));
+                    if package.depends.is_empty() {
+                        ui.weak(tr("No dependencies"));
+                    } else {
+                        ui.weak(format!("Depends on: {}", package.depends.join(", ")));
+                    }
+                    ui.weak(&package.path);
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(!state.is_running, egui::Button::new(tr("Install"))).clicked() {
+                            state.local_package = None;
+                            self.start_local_install(&mut state, package.clone());
+                        }
+                        let cancel = ui.button(tr("Cancel"));
+                        accessibility::focus_when_shown(&cancel);
+                        if cancel.clicked() {
+                            state.local_package = None;
+                        }
+                    });
+                });
+            }
+
+            if let Some(pending) = &state.pending_dependencies {
+                let mut decision = None;
+                egui::Window::new(fill(tr("Dependencies of {}"), &[&pending.tree.package])).collapsible(false).resizable(false).show(ctx, |ui| {
+                    let tree = &pending.tree;
+                    ui.label(fill(
+                        tr("Installing it pulls in {} packages from the repositories and builds {} from the AUR; {} are installed already."),
+                        &[&tree.count(dep_tree::Origin::Repo), &tree.count(dep_tree::Origin::Aur), &tree.count(dep_tree::Origin::Installed)],
+                    ));
+                    if tree.count(dep_tree::Origin::Missing) > 0 {
+                        ui.colored_label(ui.visuals().error_fg_color, tr("Some dependencies were not found in the repositories or the AUR, so the build may fail."));
+                    }
+                    egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                        for row in &tree.rows {
+                            ui.horizontal(|ui| {
+                                ui.add_space(16.0 * row.depth as f32);
+                                ui.label(&row.name);
+                                if !row.version.is_empty() {
+                                    ui.weak(&row.version);
+                                }
+                                match row.origin {
+                                    dep_tree::Origin::Installed => ui.weak(row.origin.label()),
+                                    dep_tree::Origin::Repo => ui.label(row.origin.label()),
+                                    dep_tree::Origin::Aur => ui.colored_label(ui.visuals().warn_fg_color, row.origin.label()),
+                                    dep_tree::Origin::Missing => ui.colored_label(ui.visuals().error_fg_color, row.origin.label()),
+                                };
+                            });
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.button(tr("Continue")).clicked() {
+                            decision = Some(true);
+                        }
+                        let cancel = ui.button(tr("Cancel"));
+                        accessibility::focus_when_shown(&cancel);
+                        if cancel.clicked() {
+                            decision = Some(false);
+                        }
+                    });
+                });
+                if let Some(decision) = decision {
+                    let pending = state.pending_dependencies.take().unwrap();
+                    let _ = pending.reply.send(decision);
+                }
+            }
+
+            if let Some(((installed, available), _)) = &state.pending_keyring {
+                let mut decision = None;
+                egui::Window::new(tr("Outdated keyring")).collapsible(false).resizable(false).show(ctx, |ui| {
+                    ui.label(fill(
+                        tr("archlinux-keyring {} is installed, but {} is available. Packages signed with newer keys fail to install with signature errors until it is updated."),
+                        &[installed, available],
+                    ));
+                    ui.horizontal(|ui| {
+                        if ui.button(tr("Update keyring first")).clicked() {
+                            decision = Some(true);
+                        }
+                        let skip = ui.button(tr("Skip"));
+                        accessibility::focus_when_shown(&skip);
+                        if skip.clicked() {
+                            decision = Some(false);
+                        }
+                    });
+                });
+                if let Some(decision) = decision {
+                    let (_, reply) = state.pending_keyring.take().unwrap();
+                    let _ = reply.send(decision);
+                }
+            }
+
+            if let Some(pending) = &state.pending_partial_upgrade {
+                let mut decision = None;
+                egui::Window::new(tr("Partial upgrade")).collapsible(false).resizable(false).show(ctx, |ui| {
+                    ui.label(fill(
+                        tr("The package databases were synced without upgrading: {} installed packages have newer versions. Installing now may pull in packages built against those newer versions, which can break programs that use the installed ones."),
+                        &[&pending.outdated.len()],
+                    ));
+                    egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                        for (name, installed, available) in &pending.outdated {
+                            ui.label(format!("{} {} → {}", name, installed, available));
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.button(tr("Upgrade everything first")).clicked() {
+                            decision = Some(partial_upgrade::Choice::UpgradeFirst);
+                        }
+                        if ui.button(tr("Continue anyway")).clicked() {
+                            decision = Some(partial_upgrade::Choice::Continue);
+                        }
+                        let cancel = ui.button(tr("Cancel"));
+                        accessibility::focus_when_shown(&cancel);
+                        if cancel.clicked() {
+                            decision = Some(partial_upgrade::Choice::Cancel);
+                        }
+                    });
+                });
+                if let Some(decision) = decision {
+                    let pending = state.pending_partial_upgrade.take().unwrap();
+                    let _ = pending.reply.send(decision);
+                }
+            }
+
+            if let Some(transaction) = &state.pending_transaction {
+                let mut decision = None;
+                egui::Window::new(format!("Install {}", transaction.package)).collapsible(false).resizable(false).show(ctx, |ui| {
+                    show_transaction_summary(ui, &transaction.summary);
+                    ui.horizontal(|ui| {
+                        if ui.button(tr("Install")).clicked() {
+                            decision = Some(true);
+                        }
+                        let cancel = ui.button(tr("Cancel"));
+                        accessibility::focus_when_shown(&cancel);
+                        if cancel.clicked() {
+                            decision = Some(false);
+                        }
+                    });
+                });
+                if let Some(decision) = decision {
+                    let transaction = state.pending_transaction.take().unwrap();
+                    let _ = transaction.reply.send(decision);
+                }
+            }
+
+            // Review dialog: the build only starts once the user accepts the PKGBUILD changes,
+            // unless review confirmation is turned off
+            let skip_review = state.pending_review.is_some() && !state.config.confirms(ConfirmStep::Review);
+            let mut review_decision = skip_review.then_some(true);
+            let mut terminal_dir = None;
+            if let Some(review) = state.pending_review.as_mut().filter(|_| !skip_review) {
+                egui::Window::new(format!("Review {} {}", review.package, review.version))
+                    .collapsible(false)
+                    .default_size([640.0, 480.0])
+                    .show(ctx, |ui| {
+                        if review.has_integrity_problems() {
+                            ui.colored_label(egui::Color32::RED, tr("Some sources do not match the PKGBUILD's checksums or could not be downloaded; makepkg will stop at them."));
+                        }
+                        if review.has_changes() {
+                            ui.label(tr("Review the changes since the last build before continuing."));
+                        } else {
+                            ui.label(tr("No changes since the last reviewed version."));
+                        }
+
+                        egui::ScrollArea::vertical().max_height(380.0).show(ui, |ui| {
+                            if !review.findings.is_empty() {
+                                ui.collapsing(format!("Security scan ({} findings)", review.findings.len()), |ui| {
+                                    for finding in &review.findings {
+                                        let color = match finding.severity {
+                                            Severity::High => egui::Color32::RED,
+                                            Severity::Medium => egui::Color32::YELLOW,
+                                            Severity::Low => egui::Color32::LIGHT_BLUE,
+                                        };
+                                        ui.horizontal_wrapped(|ui| {
+                                            ui.colored_label(color, finding.severity.label());
+                                            ui.label(format!("{}:{}: {}", finding.file, finding.line, finding.message));
+                                        });
+                                        ui.label(egui::RichText::new(&finding.snippet).monospace().weak());
+                                    }
+                                });
+                            }
+
+                            if !review.integrity.is_empty() {
+                                egui::CollapsingHeader::new(format!("Source integrity ({})", source_hashes::summary(&review.integrity)))
+                                    .default_open(review.has_integrity_problems())
+                                    .show(ui, |ui| {
+                                        for entry in &review.integrity {
+                                            let color = match entry.status {
+                                                source_hashes::Status::Verified(_) => egui::Color32::GREEN,
+                                                _ if entry.status.is_problem() => egui::Color32::RED,
+                                                _ => egui::Color32::YELLOW,
+                                            };
+                                            ui.horizontal_wrapped(|ui| {
+                                                ui.label(&entry.name).on_hover_text(&entry.source);
+                                                ui.colored_label(color, entry.status.describe());
+                                            });
+                                        }
+                                    });
+                            }
+
+                            for file in review.files.iter().filter(|file| file.is_scriptlet) {
+                                egui::CollapsingHeader::new(format!("{} (install scriptlet)", file.name))
+                                    .default_open(true)
+                                    .show(ui, |ui| {
+                                        ui.colored_label(egui::Color32::YELLOW, "This script runs as root when the package is installed, upgraded or removed.");
+                                        ui.label(egui::RichText::new(&file.current).monospace());
+                                    });
+                            }
+
+                            if let Some(pkgbuild) = review.files.iter().find(|file| !file.is_scriptlet) {
+                                ui.collapsing(tr("Full PKGBUILD"), |ui| {
+                                    ui.label(egui::RichText::new(&pkgbuild.current).monospace());
+                                });
+                            }
+
+                            for file in &review.files {
+                                ui.collapsing(format!("Changes: {}", file.name), |ui| {
+                                    if !file.is_changed() {
+                                        ui.label(tr("Unchanged since last review."));
+                                        return;
+                                    }
+                                    for line in file.diff() {
+                                        let (prefix, color) = match line.kind {
+                                            DiffKind::Hunk => ("", egui::Color32::LIGHT_BLUE),
+                                            DiffKind::Context => (" ", ui.visuals().text_color()),
+                                            DiffKind::Added => ("+", egui::Color32::GREEN),
+                                            DiffKind::Removed => ("-", egui::Color32::RED),
+                                        };
+                                        ui.label(egui::RichText::new(format!("{}{}", prefix, line.text)).monospace().color(color));
+                                    }
+                                });
+                            }
+                        });
+
+                        if review.is_split() {
+                            ui.separator();
+                            ui.label(fill(tr("{} builds several packages. Choose which to install:"), &[&review.package_base]));
+                            for name in review.split_packages.clone() {
+                                let mut selected = review.selected_packages.contains(&name);
+                                if ui.checkbox(&mut selected, &name).changed() {
+                                    review.toggle_package(&name, selected);
+                                }
+                            }
+                        }
+
+                        ui.horizontal(|ui| {
+                            let can_build = !review.selected_packages.is_empty();
+                            if ui.add_enabled(can_build, egui::Button::new(tr("Accept and build"))).clicked() {
+                                review_decision = Some(true);
+                            }
+                            let cancel = ui.button(tr("Cancel"));
+                            accessibility::focus_when_shown(&cancel);
+                            if cancel.clicked() {
+                                review_decision = Some(false);
+                            }
+                            // Sources are extracted and reviewed locally, even for a remote host
+                            if ui.button(tr("Open terminal here")).on_hover_text(&review.build_dir).clicked() {
+                                terminal_dir = Some(review.build_dir.clone());
+                            }
+                        });
+                    });
+            }
+
+            if let Some(dir) = terminal_dir {
+                self.open_terminal(&mut state, &dir, None);
+            }
+
+            match review_decision {
+                Some(true) => {
+                    let review = state.pending_review.take().unwrap();
+                    if let Err(e) = review::save_reviewed(&review) {
+                        state.log_warning(&format!("Could not save reviewed files: {}", e));
+                    }
+                    let beside = state.config.concurrent_builds > 1 && state.queue.running() == Some(review.package.as_str());
+                    if beside {
+                        self.start_build_job(&mut state, review);
+                    } else {
+                        self.build_and_install(&mut state, review, None);
+                    }
+                }
+                Some(false) => {
+                    if let Some(review) = state.pending_review.take() {
+                        pipeline::clear(&review.package);
+                    }
+                    state.step = None;
+                    state.queue.cancel();
+                    state.progress = None;
+                    state.log("Build cancelled during review.");
+                }
+                None => {}
+            }
+
+            // Display progress or error; a failed install says which step failed and offers a retry
+            if state.failure.is_some() {
+                self.show_failure(ui, &mut state);
+            } else if let Some(error) = &state.error {
+                ui.colored_label(egui::Color32::RED, error);
+                for hint in troubleshoot::diagnose(error, []) {
+                    show_hint(ui, &hint.problem, &hint.fix);
+                }
+            }
+
+            if let Some(progress) = &state.progress {
+                ui.label(progress);
+            }
+            if let Some((started, estimate)) = state.build_started {
+                ui.weak(build_times::describe(started.elapsed(), estimate));
+            }
+
+            if !state.queue.is_empty() {
+                let running_status = match (state.step, &state.progress) {
+                    (_, Some(progress)) if state.pending_review.is_some() => progress.clone(),
+                    (Some(step), _) => step.label().to_string(),
+                    (None, progress) => progress.clone().unwrap_or_default(),
+                };
+                ui.group(|ui| {
+                    if let Some(package) = queue::show(ui, &mut state.queue, &running_status) {
+                        state.retry_queued(&package);
+                    }
+                });
+            }
+
+            // Spinner if running
+            if state.is_running {
+                ui.spinner();
+            } else if ui.button(tr("Clear Log")).clicked() {
+                state.clear_log();
+            }
+
+            ui.group(|ui| {
+                logging::show(ui, &mut state.log);
+            });
+
+            if !state.namcap_messages.is_empty() {
+                let state = &mut *state;
+                ui.collapsing(format!("namcap ({})", state.namcap_messages.len()), |ui| {
+                    ui.horizontal(|ui| {
+                        for (level, name) in [(NamcapLevel::Error, "Errors"), (NamcapLevel::Warning, "Warnings"), (NamcapLevel::Info, "Info")] {
+                            let mut shown = !state.hidden_namcap_levels.contains(&level);
+                            if ui.checkbox(&mut shown, name).changed() {
+                                if shown {
+                                    state.hidden_namcap_levels.retain(|hidden| *hidden != level);
+                                } else {
+                                    state.hidden_namcap_levels.push(level);
+                                }
+                            }
+                        }
+                    });
+                    for message in state.namcap_messages.iter().filter(|m| !state.hidden_namcap_levels.contains(&m.level)) {
+                        let color = match message.level {
+                            NamcapLevel::Error => egui::Color32::RED,
+                            NamcapLevel::Warning => egui::Color32::YELLOW,
+                            NamcapLevel::Info => ui.visuals().text_color(),
+                        };
+                        ui.colored_label(color, format!("{} {}: {}", message.level.label(), message.target, message.text));
+                    }
+                });
+            }
+        });
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        let state = self.state.lock().unwrap();
+        let ui_state = UiState {
+            tab: state.tab,
+            package_name: state.package_name.clone(),
+            run_namcap: state.run_namcap,
+            search_by: state.search_by,
+            search_match: state.search_match,
+            discover_ranking: state.discover_ranking,
+            log_level: state.log.min_level,
+            log_auto_scroll: state.log.auto_scroll,
+        };
+        eframe::set_value(storage, eframe::APP_KEY, &ui_state);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if self.zoom_changed {
+            let state = self.state.lock().unwrap();
+            if let Err(e) = state.config.save() {
+                error!("Could not save settings: {}", e);
+            }
+        }
+    }
+}
+
+
+
+async fn search_aur_package(package_name: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    search_aur(package_name, SearchBy::NameDesc).await
+}
+
+async fn search_aur(query: &str, by: SearchBy) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    Ok(search_aur_with_bases(query, by).await?.into_iter().map(|(name, _)| name).collect())
+}
+
+// Matches as (name, package base)
+async fn search_aur_with_bases(query: &str, by: SearchBy) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
+    let url = http::rpc_url(&[("type", "search"), ("by", by.rpc_field()), ("arg", query)]);
+    let response = http::get_json::<serde_json::Value>(&url).await?;
+    
+    let packages = response["results"].as_array().unwrap_or(&vec![]).iter()
+        .map(|pkg| (pkg["Name"].as_str().unwrap_or("").to_string(), pkg["PackageBase"].as_str().unwrap_or("").to_string()))
+        .collect::<Vec<(String, String)>>();
+    let names: Vec<String> = packages.iter().map(|(name, _)| name.clone()).collect();
+    if let Err(e) = completions::remember_aur_names(&names) {
+        debug!("Could not cache package names for completion: {}", e);
+    }
+
+    Ok(packages)
+}
+
+// Names starting with `prefix`; the RPC returns at most 20
+async fn suggest_aur(prefix: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let url = http::rpc_url(&[("type", "suggest"), ("arg", prefix)]);
+    http::get_json::<Vec<String>>(&url).await
+}
+
+// Keeps fetched metadata in the state file for the next session, in the background
+fn save_metadata(packages: Vec<Package>, config: &Config) {
+    let ttl = i64::from(config.metadata_ttl_minutes) * 60;
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = state::cache_metadata(&packages, ttl) {
+            debug!("Could not save metadata: {}", e);
+        }
+    });
+}
+
+async fn fetch_metadata(package_name: &str) -> Result<Package, Box<dyn Error>> {
+    let url = http::rpc_url(&[("type", "info"), ("arg", package_name)]);
+    debug!("Fetching metadata from URL: {}", url);
+
+    let client = http::client()?;
+    let response = http::send(client.get(&url)).await?;
+    
+    let content_type = response.headers().get(CONTENT_TYPE)
+        .ok_or("Missing content-type header")?
+        .to_str()?;
+    if !content_type.contains("application/json") {
+        return Err("Unexpected content type".into());
+    }
+
+    let body = response.text().await.map_err(http::readable)?;
+    debug!("Response body: {}", body);
+
+    let json_response = serde_json::from_str::<serde_json::Value>(&body)?;
+
+    let package = json_response["results"].as_array().unwrap_or(&vec![]).iter().map(Package::from_rpc).next();
+
+    match package {
+        Some(package) => Ok(package),
+        None => {
+            let close = did_you_mean(package_name).await;
+            let message = if close.is_empty() {
+                format!("Package {} not found in the AUR", package_name)
+            } else {
+                format!("Package {} not found in the AUR. Did you mean {}?", package_name, close.join(", "))
+            };
+            Err(exit_codes::coded(ExitCode::NotFound, message))
+        }
+    }
+}
+
+// A troubleshooting hint below an error
+fn show_hint(ui: &mut egui::Ui, problem: &str, fix: &str) {
+    ui.horizontal_wrapped(|ui| {
+        ui.colored_label(ui.visuals().warn_fg_color, problem);
+        ui.label(fix);
+    });
+}
+
+fn minutes_ago(instant: Instant) -> String {
+    match instant.elapsed().as_secs() / 60 {
+        0 => "just now".to_string(),
+        1 => "1 minute ago".to_string(),
+        minutes => format!("{} minutes ago", minutes),
+    }
+}
+
+// Hover text of a search result whose metadata was prefetched
+fn metadata_tooltip(ui: &mut egui::Ui, package: &Package, installed: Option<&str>) {
+    ui.strong(format!("{} {}", package.name, package.version));
+    if !package.description.is_empty() {
+        ui.label(&package.description);
+    }
+    ui.label(format!("{} votes, popularity {:.2}", package.votes, package.popularity));
+    match &package.maintainer {
+        Some(maintainer) => ui.weak(format!("maintained by {}", maintainer)),
+        None => ui.colored_label(ui.visuals().warn_fg_color, "orphaned"),
+    };
+    if let Some(installed) = installed {
+        ui.label(format!("installed {}", installed));
+    }
+}
+
+// Metadata of many packages in as few requests as the RPC's URL length allows; unknown names
+// are left out
+async fn fetch_metadata_batch(names: &[String]) -> Result<Vec<Package>, Box<dyn Error>> {
+    let mut packages = Vec::new();
+    for chunk in names.chunks(METADATA_BATCH) {
+        let params: Vec<(&str, &str)> = std::iter::once(("type", "info")).chain(chunk.iter().map(|name| ("arg[]", name.as_str()))).collect();
+        let url = http::rpc_url(&params);
+        debug!("Prefetching metadata: {}", url);
+        let response = http::get_json::<serde_json::Value>(&url).await?;
+        packages.extend(response["results"].as_array().unwrap_or(&vec![]).iter().map(Package::from_rpc));
+    }
+    Ok(packages)
+}
+
+// `packages` with the dependencies their AUR metadata lists, for working out the build order
+async fn build_nodes(packages: &[String]) -> Result<Vec<build_order::Node>, Box<dyn Error>> {
+    let found = fetch_metadata_batch(packages).await?;
+    Ok(packages
+        .iter()
+        .map(|name| {
+            let package = found.iter().find(|package| package.name == *name);
+            build_order::Node {
+                name: name.clone(),
+                version: package.map(|package| package.version.clone()).unwrap_or_default(),
+                depends: package.map(|package| package.depends.clone()).unwrap_or_default(),
+                provides: package.map(|package| package.provides.clone()).unwrap_or_default(),
+            }
+        })
+        .collect())
+}
+
+// Sorts the waiting queue items by `nodes`, or holds the queue and asks where to break the first
+// dependency cycle among them
+fn apply_build_order(state: &mut AppState, nodes: Vec<build_order::Node>) {
+    if let Some(cycle) = build_order::cycle(&nodes) {
+        state.log_warning(&format!("Dependency cycle: {}", cycle.join(" -> ")));
+        state.dependency_cycle = Some(DependencyCycle { cycle, nodes });
+        return;
+    }
+    state.queue.ordering = false;
+    let order = build_order::order(&nodes);
+    if nodes.iter().map(|node| &node.name).ne(order.iter()) {
+        state.log(&format!("Build order: {}", order.join(" -> ")));
+    }
+    state.queue.reorder(&order);
+    state.build_order = order;
+}
+
+// Closest AUR names to a misspelled one, from names seen in earlier searches, names sharing its
+// first letters and a search for it; lookup failures only mean fewer candidates
+async fn did_you_mean(package_name: &str) -> Vec<String> {
+    let mut candidates = completions::cached_aur_names();
+    let prefix: String = package_name.chars().take(2).collect();
+    candidates.extend(suggest_aur(&prefix).await.unwrap_or_default());
+    candidates.extend(search_aur(package_name, SearchBy::NameDesc).await.unwrap_or_default());
+    candidates.sort();
+    candidates.dedup();
+
+    // Allow about one typo per three letters
+    let max_distance = (package_name.chars().count() / 3).max(1);
+    let mut close: Vec<(usize, String)> = candidates
+        .into_iter()
+        .map(|name| (strsim::damerau_levenshtein(package_name, &name), name))
+        .filter(|(distance, _)| (1..=max_distance).contains(distance))
+        .collect();
+    close.sort();
+    close.into_iter().take(3).map(|(_, name)| name).collect()
+}
+
+// Tarball compressions, told apart by their first bytes
+#[derive(Clone, Copy)]
+enum Compression {
+    Gzip,
+    // What pacman packages use, and what AUR snapshots may move to
+    Zstd,
+}
+
+impl Compression {
+    const MAGIC_LEN: usize = 4;
+
+    fn sniff(head: &[u8]) -> Option<Compression> {
+        if head.starts_with(&[0x1f, 0x8b]) {
+            Some(Compression::Gzip)
+        } else if head.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Compression::Zstd)
+        } else {
+            None
+        }
+    }
+}
+
+// `progress` is called with the bytes received so far and the total size, when the server sends one.
+// When the AUR's snapshot cannot be downloaded, `mirrors` are tried in turn. Returns the address
+// the build files came from.
+async fn download_and_extract_package(
+    urlpath: &str,
+    package_base: &str,
+    dest: &str,
+    mirrors: &[String],
+    progress: impl Fn(u64, Option<u64>),
+) -> Result<String, Box<dyn Error>> {
+    let client = http::client()?;
+
+    // The snapshot is written next to `dest` as it arrives, so memory use stays flat however large it is
+    let tarball_path = format!("{}.tar", dest.trim_end_matches('/'));
+    if let Some(parent) = Path::new(&tarball_path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Errors are kept as text with their exit code, since a `Box<dyn Error>` must not live across an await
+    let mut failures: Vec<(String, ExitCode, String)> = Vec::new();
+    for source in snapshot_mirrors::sources(&http::snapshot_url(urlpath), urlpath, mirrors) {
+        debug!("Downloading package from URL: {}", source.url());
+        let fetched = match &source {
+            snapshot_mirrors::Source::Snapshot(url) => {
+                download_to_file(&client, url, &tarball_path, &progress).await.map_err(|e| (exit_codes::classify(e.as_ref()), e.to_string()))
+            }
+            snapshot_mirrors::Source::Git(url) => {
+                let (url, package_base, dest) = (url.clone(), package_base.to_string(), dest.to_string());
+                tokio::task::spawn_blocking(move || snapshot_mirrors::clone(&url, &package_base, &dest).map_err(|e| e.to_string()))
+                    .await
+                    .unwrap_or_else(|e| Err(e.to_string()))
+                    .map_err(|e| (ExitCode::Network, e))
+            }
+        };
+        match fetched {
+            // A snapshot that arrived but cannot be unpacked safely is not looked for elsewhere
+            Ok(()) => {
+                if let snapshot_mirrors::Source::Snapshot(_) = source {
+                    let result = extract_tarball(&tarball_path, dest);
+                    let _ = fs::remove_file(&tarball_path);
+                    result?;
+                }
+                return Ok(source.url().to_string());
+            }
+            Err((code, message)) => {
+                warn!("Could not download {}: {}", source.url(), message);
+                let _ = fs::remove_file(&tarball_path);
+                failures.push((source.url().to_string(), code, message));
+            }
+        }
+    }
+    // Without mirrors the AUR's error is reported as it is
+    let code = failures.first().map_or(ExitCode::Network, |(_, code, _)| *code);
+    let message = match failures.as_slice() {
+        [(_, _, message)] => message.clone(),
+        _ => failures.iter().map(|(url, _, message)| format!("{}: {}", url, message)).collect::<Vec<_>>().join("; "),
+    };
+    Err(exit_codes::coded(code, message))
+}
+
+async fn download_to_file(client: &Client, url: &str, path: &str, progress: impl Fn(u64, Option<u64>)) -> Result<(), Box<dyn Error>> {
+    let mut response = http::send(client.get(url)).await?.error_for_status()?;
+    let total = response.content_length();
+    let mut file = tokio::fs::File::create(path).await?;
+    let mut received = 0u64;
+    let mut head: Vec<u8> = Vec::new();
+    progress(0, total);
+    loop {
+        // A `while let` would keep the non-Send error alive across the awaits below
+        let chunk = response.chunk().await.map_err(http::readable)?;
+        let Some(chunk) = chunk else {
+            break;
+        };
+        if head.len() < Compression::MAGIC_LEN {
+            head.extend(chunk.iter().copied().take(Compression::MAGIC_LEN - head.len()));
+        }
+        file.write_all(&chunk).await?;
+        received += chunk.len() as u64;
+        progress(received, total);
+    }
+    file.flush().await?;
+    debug!("Downloaded {} bytes", received);
+    if let Some(total) = total.filter(|total| *total != received) {
+        return Err(format!("Download of {} was cut short: got {} of {} bytes", url, received, total).into());
+    }
+    // Servers label snapshots application/x-gzip, application/gzip or application/octet-stream,
+    // so the content is checked instead of the header
+    if Compression::sniff(&head).is_none() {
+        return Err(format!("{} is neither a gzip nor a zstd archive", url).into());
+    }
+    Ok(())
+}
+
+// Decompresses while reading, so only the unpacked files take up space
+fn extract_tarball(path: &str, dest: &str) -> Result<(), Box<dyn Error>> {
+    let mut head = [0; Compression::MAGIC_LEN];
+    let read = fs::File::open(path)?.read(&mut head)?;
+    match Compression::sniff(&head[..read]).ok_or(format!("{} is neither a gzip nor a zstd archive", path))? {
+        Compression::Gzip => unpack_entries(Archive::new(GzDecoder::new(BufReader::new(fs::File::open(path)?))), dest),
+        // zstd ships with pacman, so it is decoded by the command rather than a library
+        Compression::Zstd => {
+            let mut zstd = StdCommand::new("zstd").args(["-dcq", path]).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+            let result = unpack_entries(Archive::new(zstd.stdout.take().ok_or("Could not read zstd's output")?), dest);
+            // An unpacking error closes the pipe early, which zstd then fails on as well
+            let output = zstd.wait_with_output()?;
+            if result.is_ok() && !output.status.success() {
+                return Err(format!("zstd could not decompress {}: {}", path, String::from_utf8_lossy(&output.stderr).trim()).into());
+            }
+            result
+        }
+    }
+}
+
+fn unpack_entries<R: Read>(mut archive: Archive<R>, dest: &str) -> Result<(), Box<dyn Error>> {
+    // Create destination directory if it doesn't exist
+    fs::create_dir_all(dest)?;
+
+    // Unpack the archive; the tarball comes from the network, so every entry has to stay inside `dest`
+    debug!("Extracting files to {}", dest);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.into_owned();
+        if !stays_inside(&entry_path) {
+            return Err(format!("Refusing to unpack the snapshot: entry {} points outside {}", entry_path.display(), dest).into());
+        }
+        if entry.header().entry_type().is_hard_link() {
+            if let Some(target) = entry.link_name()?.filter(|target| !stays_inside(target)) {
+                return Err(format!("Refusing to unpack the snapshot: entry {} links to {} outside {}", entry_path.display(), target.display(), dest).into());
+            }
+        }
+        // unpack_in also refuses to write through a symlink that leads out of `dest`
+        if !entry.unpack_in(dest)? {
+            return Err(format!("Refusing to unpack the snapshot: entry {} would be written outside {}", entry_path.display(), dest).into());
+        }
+    }
+
+    // Debug information
+    for entry in fs::read_dir(dest)? {
+        let entry = entry?;
+        let path = entry.path();
+        debug!("Extracted {}", path.display());
+    }
+
+    Ok(())
+}
+
+// Relative, with no `..`: the path cannot lead out of the directory it is joined to
+fn stays_inside(path: &Path) -> bool {
+    path.components().all(|component| matches!(component, std::path::Component::Normal(_) | std::path::Component::CurDir))
+}
+
+// Runs a command while streaming each stdout/stderr line into the log as it arrives
+fn run_logged(command: &mut StdCommand, state: &Arc<Mutex<AppState>>) -> Result<ExitStatus, Box<dyn Error>> {
+    if runner::simulate(command) {
+        simulate_progress(state);
+        return Ok(ExitStatus::from_raw(0));
+    }
+    let mut child = command.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let stdout = child.stdout.take().ok_or("Could not capture stdout")?;
+    let stderr = child.stderr.take().ok_or("Could not capture stderr")?;
+
+    let stderr_state = Arc::clone(state);
+    let stderr_reader = std::thread::spawn(move || {
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            info!(target: BUILD_OUTPUT_TARGET, "{}", line);
+            let mut stderr_state = stderr_state.lock().unwrap();
+            stderr_state.log(&line);
+            if let Some(bar) = &stderr_state.progress_bar {
+                bar.set_message(ansi::strip(&line));
+            }
+        }
+    });
+    for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+        info!(target: BUILD_OUTPUT_TARGET, "{}", line);
+        let mut state = state.lock().unwrap();
+        state.log(&line);
+        if let Some(bar) = &state.progress_bar {
+            bar.set_message(ansi::strip(&line));
+        }
+    }
+    let _ = stderr_reader.join();
+
+    Ok(child.wait()?)
+}
+
+// Stands in for a build's output in dry-run mode, so the log and progress bar move as they would
+fn simulate_progress(state: &Arc<Mutex<AppState>>) {
+    for step in ["Retrieving sources", "Extracting sources", "Starting build()", "Entering fakeroot environment", "Finished (dry run)"] {
+        std::thread::sleep(Duration::from_millis(400));
+        let mut state = state.lock().unwrap();
+        state.log(&format!("==> {}...", step));
+        if let Some(bar) = &state.progress_bar {
+            bar.set_message(step);
+        }
+    }
+}
+
+// `makeflags` replaces MAKEFLAGS from the environment when set
+fn build_package(build_dir: &str, config: &Config, makeflags: Option<&str>, state: &Arc<Mutex<AppState>>) -> Result<(), Box<dyn Error>> {
+    info!("Building package in directory: {}", build_dir);
+
+    let mut makepkg = vec!["env".to_string()];
+    // makepkg -s installs dependencies with the tool named in PACMAN_AUTH; `env` sets it on remote hosts too.
+    // Containers build as a user with passwordless sudo, makepkg's default.
+    if config.container.is_empty() {
+        let tool = match remote::active() {
+            Some((_, host)) => Some(host.escalation_tool).filter(|tool| !tool.is_empty()),
+            None => escalation::resolve(&config.escalation_tool).ok(),
+        };
+        makepkg.extend(tool.map(|tool| format!("PACMAN_AUTH={}", tool)));
+    }
+    makepkg.extend(makeflags.map(|flags| format!("MAKEFLAGS={}", flags)));
+    makepkg.push("makepkg".to_string());
+    makepkg.extend(config.makepkg_flags.iter().cloned());
+
+    let command = if config.container.is_empty() {
+        [background_prefix(config, true), makepkg].concat()
+    } else {
+        container::command(config, build_dir, &[background_prefix(config, false), makepkg].concat())?
+    };
+    let (program, args) = command.split_first().ok_or("Empty build command")?;
+    let status = run_logged(&mut remote::command_in(Some(build_dir), program, args), state)?;
+    if !status.success() {
+        error!("Failed to build package: makepkg exited with {}", status);
+        return Err(format!("makepkg exited with {}", status).into());
+    } else {
+        info!("Package built successfully.");
+    }
+    Ok(())
+}
+
+// Installs missing build prerequisites after asking on the terminal; the GUI offers them in a
+// dialog instead and the build fails, to be retried once they are installed
+fn ensure_prerequisites(config: &Config, state: &Arc<Mutex<AppState>>) -> Result<(), Box<dyn Error>> {
+    let missing = match prerequisites::missing() {
+        Ok(missing) => missing,
+        Err(e) => {
+            state.lock().unwrap().log_warning(&e.to_string());
+            return Ok(());
+        }
+    };
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let install = {
+        let mut state = state.lock().unwrap();
+        if state.cli_mode {
+            let ask = || config.no_confirm || confirm(&fill(tr("Building needs {}, which is not installed. Install it now?"), &[&missing.join(" and ")]));
+            match &state.progress_bar {
+                Some(bar) => bar.suspend(ask),
+                None => ask(),
+            }
+        } else {
+            state.missing_prerequisites = Some(missing.clone());
+            false
+        }
+    };
+    if !install {
+        return Err(exit_codes::coded(ExitCode::BuildFailure, format!("{} must be installed to build AUR packages", missing.join(" and "))));
+    }
+    state.lock().unwrap().log(&format!("Installing build prerequisites: {}", missing.join(" ")));
+    install_package(backend::repo(), &missing, &config.escalation_tool)
+}
+
+// Background builds run at the lowest CPU and IO priority, optionally in a systemd scope whose
+// CPUQuota caps them (100% is one core). Containers apply the cap themselves.
+fn background_prefix(config: &Config, cpu_quota: bool) -> Vec<String> {
+    if !config.background_builds {
+        return Vec::new();
+    }
+    let mut prefix = Vec::new();
+    if cpu_quota && config.background_cpu_quota > 0 {
+        prefix.extend(["systemd-run", "--user", "--scope", "--quiet", "-p"].map(str::to_string));
+        prefix.push(format!("CPUQuota={}%", config.background_cpu_quota));
+    }
+    prefix.extend(["nice", "-n", "19", "ionice", "-c", "3"].map(str::to_string));
+    prefix
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..=59 => format!("{} s", seconds),
+        60..=3599 => format!("{} min {:02} s", seconds / 60, seconds % 60),
+        _ => format!("{} h {:02} min", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+// Binary units, as pacman prints them
+fn format_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    for unit in ["B", "KiB", "MiB", "GiB"] {
+        if size < 1024.0 {
+            return format!("{:.1} {}", size, unit);
+        }
+        size /= 1024.0;
+    }
+    format!("{:.1} TiB", size)
+}
+
+// Machine architecture of the active host, as makepkg sees it
+fn host_arch() -> Result<String, Box<dyn Error>> {
+    let output = remote::command("uname", ["-m"]).run()?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string().into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn command_exists(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+// Passes the confirmations of builds beside the queue on to the window, and takes over the log and
+// any failure of those that are done
+fn poll_build_jobs(state: &mut AppState) {
+    let state = &mut *state;
+    for job in &state.build_jobs {
+        let mut job_state = job.state.lock().unwrap();
+        if state.pending_transaction.is_none() {
+            state.pending_transaction = job_state.pending_transaction.take();
+        }
+        if let Some(line) = job_state.log.current_messages().last() {
+            state.queue.set_activity(&job.package, &ansi::strip(line));
+        }
+    }
+    let (done, running) = std::mem::take(&mut state.build_jobs).into_iter().partition(|job| job.task.is_finished());
+    state.build_jobs = running;
+    for job in done {
+        let mut job_state = job.state.lock().unwrap();
+        state.log.append(std::mem::take(&mut job_state.log));
+        state.queue.finish_build(&job.package, job_state.failure.as_ref().map(|failure| failure.error.clone()));
+        match job_state.failure.take() {
+            Some(failure) if state.failure.is_none() => state.failure = Some(failure),
+            Some(failure) => state.log_error(&format!("Build and install {} failed: {}", job.package, failure.error)),
+            None => state.log(&format!("{} built and installed", job.package)),
+        }
+    }
+}
+
+// The packages of a transaction in a table like pacman's, with the totals and commands below
+fn show_transaction_summary(ui: &mut egui::Ui, summary: &transaction::Summary) {
+    let size = |size: Option<String>| size.unwrap_or_else(|| "?".to_string());
+    egui::Grid::new("transaction_summary").num_columns(5).striped(true).show(ui, |ui| {
+        for header in ["Package", "Old version", "New version", "Download", "Net change"] {
+            ui.strong(header);
+        }
+        ui.end_row();
+        for change in &summary.changes {
+            ui.label(&change.name);
+            ui.label(change.old_version.as_deref().unwrap_or("-"));
+            ui.label(format!("{} ({})", change.new_version, change.action.label()));
+            ui.label(size(change.download_size.map(format_size)));
+            ui.label(size(change.size_change.map(transaction::format_size_change)));
+            ui.end_row();
+        }
+    });
+    ui.separator();
+    ui.label(format!("Total download size: {}", format_size(summary.download_size())));
+    ui.label(format!("Net installed size: {}", transaction::format_size_change(summary.size_change())));
+    if summary.incomplete() {
+        ui.weak(tr("Some sizes are unknown and left out of the totals."));
+    }
+    ui.label(tr("Commands to be run:"));
+    for command in &summary.commands {
+        ui.monospace(command);
+    }
+    if !summary.build_order.is_empty() {
+        ui.separator();
+        ui.label(fill(tr("Build order: {}"), &[&summary.build_order.join(" → ")]));
+    }
+}
+
+// Puts the overall install progress and the number of updates found in the window title and on
+// the launcher entry, which stay visible while the window is minimized; only sent when either
+// changes from `shown`
+fn show_progress(ctx: &egui::Context, shown: &mut (Option<u32>, usize), progress: Option<f32>, updates: usize) {
+    let percent = progress.map(launcher::percent);
+    if (percent, updates) == *shown {
+        return;
+    }
+    if percent != shown.0 {
+        launcher::show(progress);
+    }
+    if updates != shown.1 {
+        launcher::show_count(updates);
+    }
+    *shown = (percent, updates);
+    let base = match updates {
+        0 => WINDOW_TITLE.to_string(),
+        updates => format!("{} ({})", WINDOW_TITLE, fill(tr("{} updates"), &[&updates])),
+    };
+    ctx.send_viewport_cmd(egui::ViewportCommand::Title(launcher::title(&base, progress)));
+}
+
+fn notify_finished(state: &AppState, package_name: &str) {
+    if !state.config.notifications {
+        return;
+    }
+    let (summary, body) = match &state.error {
+        Some(error) => (format!("{} failed", package_name), error.clone()),
+        None => (format!("{} finished", package_name), state.progress.clone().unwrap_or_default()),
+    };
+    // Only failed installs can be retried; they are the ones recorded as failures
+    let retry = state.failure.as_ref().is_some_and(|failure| failure.package == package_name);
+    notifications::send(&summary, &body, package_name, retry);
+}
+
+// Versions of the installed packages among search results; only the AUR reports newest versions
+async fn result_versions(results: &[(&'static str, String)]) -> HashMap<(&'static str, String), ResultVersion> {
+    let mut installed: Vec<(&'static str, String, String)> = Vec::new();
+    for source in backend::sources() {
+        let Ok(packages) = source.list_installed() else {
+            continue;
+        };
+        installed.extend(
+            packages
+                .into_iter()
+                .filter(|(name, _)| results.iter().any(|(result_source, result)| *result_source == source.name() && result == name))
+                .map(|(name, version)| (source.name(), name, version)),
+        );
+    }
+    let aur_names: Vec<String> = installed
+        .iter()
+        .filter(|(source, _, _)| backend::by_name(source).builds_from_source())
+        .map(|(_, name, _)| name.clone())
+        .collect();
+    let latest = if aur_names.is_empty() { Vec::new() } else { aur_versions(&aur_names).await.unwrap_or_default() };
+
+    installed
+        .into_iter()
+        .map(|(source, name, version)| {
+            let latest = latest.iter().find(|(latest_name, _)| *latest_name == name).map(|(_, latest)| latest.clone());
+            let state = match &latest {
+                None => VersionState::Unknown,
+                Some(_) if VCS_SUFFIXES.iter().any(|suffix| name.ends_with(suffix)) => VersionState::LocalNewer,
+                Some(latest) => match backend::vercmp(&version, latest) {
+                    Some(std::cmp::Ordering::Equal) => VersionState::UpToDate,
+                    Some(std::cmp::Ordering::Less) => VersionState::Outdated,
+                    Some(std::cmp::Ordering::Greater) => VersionState::LocalNewer,
+                    None if version == *latest => VersionState::UpToDate,
+                    None => VersionState::Unknown,
+                },
+            };
+            ((source, name), ResultVersion { installed: version, latest, state })
+        })
+        .collect()
+}
+
+// Current AUR versions of the given packages, as (name, version)
+// (name, version) in the AUR of those of `names` it has, batched like any other metadata
+async fn aur_versions(names: &[String]) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    debug!("Checking for updates of {} packages", names.len());
+    Ok(fetch_metadata_batch(names).await?.into_iter().map(|package| (package.name, package.version)).collect())
+}
+
+fn switch_host(state: &mut AppState, host: Option<String>) {
+    let previous = std::mem::replace(&mut state.config.active_host, host);
+    if let Err(e) = remote::set_active(&state.config) {
+        state.config.active_host = previous;
+        state.error = Some(e.to_string());
+        return;
+    }
+    // The new host may run a different package manager
+    if let Err(e) = backend::select(&state.config) {
+        state.error = Some(e.to_string());
+    }
+    if let Err(e) = state.config.save() {
+        state.log_warning(&format!("Could not save the active host: {}", e));
+    }
+    state.select_package(None);
+    state.log(&format!("Now managing {}", remote::describe_active()));
+    refresh_installed(state);
+}
+
+fn refresh_installed(state: &mut AppState) {
+    let mut packages = Vec::new();
+    for source in backend::sources() {
+        match source.list_installed() {
+            Ok(installed) => packages.extend(installed.into_iter().map(|(name, version)| (source.name(), name, version))),
+            Err(e) => state.error = Some(format!("Could not list installed {} packages: {}", source.label(), e)),
+        }
+    }
+    state.installed_packages = packages;
+}
+
+// The root helper only speaks pacman and only runs on this machine. Dry runs escalate per call,
+// which the runner simulates, instead of starting it.
+fn uses_helper(source: &dyn backend::PackageBackend, escalation_tool: &str) -> bool {
+    escalation_tool == helper::ESCALATION_TOOL && remote::active().is_none() && source.name() == "pacman" && !runner::dry_run()
+}
+
+// `targets` are package files for the pacman backend and package names otherwise
+fn install_package(source: &dyn backend::PackageBackend, targets: &[String], escalation_tool: &str) -> Result<(), Box<dyn Error>> {
+    info!("Installing with {}: {}", source.name(), targets.join(" "));
+    let _lock = instance::lock_transactions()?;
+    let result = if uses_helper(source, escalation_tool) {
+        helper::install(targets)
+    } else {
+        source.install(targets, escalation_tool)
+    };
+    match &result {
+        Ok(()) => info!("Package installed successfully."),
+        Err(e) => error!("Failed to install package: {}", e),
+    }
+    record_transaction(state::Action::Install, source.name(), targets, &result);
+    result
+}
+
+// Keeps the outcome of every call into the package manager, including upgrades and the keyring,
+// in the state file; dry runs change nothing worth keeping. `source` is a backend name.
+fn record_transaction<T>(action: state::Action, source: &str, targets: &[String], result: &Result<T, Box<dyn Error>>) {
+    if runner::dry_run() {
+        return;
+    }
+    if let Err(e) = state::record_transaction(action, source, targets, result.as_ref().err().map(|e| e.to_string())) {
+        warn!("Could not record the transaction: {}", e);
+    }
+}
+
+// Installs built package files as if `assumed` ("name=version") were installed, for a package built
+// first to break a dependency cycle. The root helper only accepts package files, so this goes
+// through the escalation tool.
+fn install_assuming(package_files: &[String], assumed: &[String], escalation_tool: &str) -> Result<(), Box<dyn Error>> {
+    info!("Installing {} assuming {} installed", package_files.join(" "), assumed.join(" "));
+    let _lock = instance::lock_transactions()?;
+    let mut args = vec!["-U".to_string(), "--noconfirm".to_string()];
+    args.extend(package_files.iter().cloned());
+    for assumed in assumed {
+        args.extend(["--assume-installed".to_string(), assumed.clone()]);
+    }
+    let result = escalation::privileged(escalation::direct(escalation_tool), "pacman", args).and_then(|mut command| {
+        let output = command.run()?;
+        if !output.status.success() {
+            return Err(format!("pacman -U failed: {}", String::from_utf8_lossy(&output.stderr).trim()).into());
+        }
+        Ok(())
+    });
+    record_transaction(state::Action::Install, backend::repo().name(), package_files, &result);
+    result
+}
+
+// The pacman command line a removal runs, for the confirmation to show
+fn removal_command(source: &dyn backend::PackageBackend, package: &str, mode: RemoveMode, escalation_tool: &str) -> String {
+    pacman_command(source, &mode.pacman_args(package).join(" "), escalation_tool)
+}
+
+// How `pacman <args>` is run for `source`, with the escalation tool or host it goes through
+fn pacman_command(source: &dyn backend::PackageBackend, args: &str, escalation_tool: &str) -> String {
+    let pacman = format!("pacman {}", args);
+    if uses_helper(source, escalation_tool) {
+        return format!("{} (through the privileged helper)", pacman);
+    }
+    match remote::active() {
+        Some((name, host)) if host.escalation_tool.is_empty() => format!("{} (on {})", pacman, name),
+        Some((name, host)) => format!("{} {} (on {})", host.escalation_tool, pacman, name),
+        None => match escalation::resolve(escalation::direct(escalation_tool)) {
+            Ok(tool) => format!("{} {}", tool, pacman),
+            Err(_) => pacman,
+        },
+    }
+}
+
+fn uninstall_package(source: &dyn backend::PackageBackend, package_name: &str, mode: RemoveMode, escalation_tool: &str) -> Result<(), Box<dyn Error>> {
+    info!("Uninstalling package: {}", package_name);
+    let _lock = instance::lock_transactions()?;
+    let result = if uses_helper(source, escalation_tool) {
+        helper::remove(package_name, mode)
+    } else {
+        source.remove(package_name, mode, escalation_tool)
+    };
+    match &result {
+        Ok(()) => info!("Package uninstalled successfully."),
+        Err(e) => error!("Failed to uninstall package: {}", e),
+    }
+    record_transaction(state::Action::Remove, source.name(), &[package_name.to_string()], &result);
+    result
+}
+
+// Installs a package from a binary repository, running the same hooks as a build
+async fn install_prebuilt(prebuilt: &Prebuilt, config: &Config, state: &Arc<Mutex<AppState>>) -> Result<(), Box<dyn Error>> {
+    confirm_sync_install(&[format!("{}/{}", prebuilt.repo, prebuilt.package)], &prebuilt.package, config, state).await?;
+    let _lock = instance::lock_transactions()?;
+    let was_installed = backend::active().is_installed(&prebuilt.package).unwrap_or(false);
+    hooks::run_hook("pre_install", config.hooks.pre_install.as_deref(), &prebuilt.package)?;
+    let result = binary_repo::install(prebuilt, &config.escalation_tool);
+    record_transaction(state::Action::Install, backend::repo().name(), &[format!("{}/{}", prebuilt.repo, prebuilt.package)], &result);
+    result?;
+    let (hook_name, hook) = if was_installed {
+        ("post_update", config.hooks.post_update.as_deref())
+    } else {
+        ("post_install", config.hooks.post_install.as_deref())
+    };
+    if let Err(e) = hooks::run_hook(hook_name, hook, &prebuilt.package) {
+        state.lock().unwrap().log_warning(&e.to_string());
+    }
+    Ok(())
+}
+
+// `members` of a pacman group in one transaction, after the same confirmation as a repository package
+async fn install_group(group: &str, members: &[String], config: &Config, state: &Arc<Mutex<AppState>>) -> Result<(), Box<dyn Error>> {
+    if members.is_empty() {
+        state.lock().unwrap().log(&format!("Every member of {} is installed already.", group));
+        return Ok(());
+    }
+    confirm_sync_install(members, group, config, state).await?;
+    install_package(backend::repo(), members, &config.escalation_tool)
+}
+
+// Installs a package file with pacman -U, refusing files built for another architecture
+fn install_local_package(package: &LocalPackage, config: &Config, state: &Arc<Mutex<AppState>>) -> Result<(), Box<dyn Error>> {
+    let arch = host_arch()?;
+    if package.arch != "any" && package.arch != arch {
+        return Err(format!("{} is built for {}, but {} runs {}", package.path, package.arch, remote::describe_active(), arch).into());
+    }
+    let was_installed = backend::active().is_installed(&package.name).unwrap_or(false);
+    hooks::run_hook("pre_install", config.hooks.pre_install.as_deref(), &package.name)?;
+    let path = local_package::stage(package)?;
+    install_package(backend::active(), &[path], &config.escalation_tool)?;
+    let (hook_name, hook) = if was_installed {
+        ("post_update", config.hooks.post_update.as_deref())
+    } else {
+        ("post_install", config.hooks.post_install.as_deref())
+    };
+    if let Err(e) = hooks::run_hook(hook_name, hook, &package.name) {
+        state.lock().unwrap().log_warning(&e.to_string());
+    }
+    Ok(())
+}
+
+fn find_package_file(package_dir: &str, package_name: &str, pkgext: &str) -> Option<String> {
+    // Check PKGDEST or the build directory (on the active host) for package files
+    let file_names = remote::list_dir(package_dir).ok()?;
+    for file_name in file_names {
+        // Files are named <pkgname>-<pkgver>-<pkgrel>-<arch><PKGEXT>, so compare the full name
+        // to avoid picking up e.g. foo-docs when looking for foo
+        let Some(stem) = file_name.strip_suffix(pkgext) else {
+            continue;
+        };
+        if stem.rsplitn(4, '-').nth(3) == Some(package_name) {
+            return Some(format!("{}/{}", package_dir.trim_end_matches('/'), file_name));
+        }
+    }
+
+    None
+}
+#[allow(dead_code)]
+fn list_package_dependencies(package_name: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let output = StdCommand::new("pacman")
+        .args(["-Qi", package_name])
+        .output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut dependencies = Vec::new();
+
+    for line in stdout.lines() {
+        if line.starts_with("Depends On") {
+            dependencies.push(line.split(':').nth(1).unwrap_or("").trim().to_string());
+        }
+    }
+    Ok(dependencies)
+}
+
+async fn prepare_package(package_name: &str, state: &Arc<Mutex<AppState>>) -> Result<PendingReview, Box<dyn std::error::Error>> {
+    state.lock().unwrap().step = Some(Step::Fetch);
+    let package = fetch_metadata(package_name).await?;
+    // makepkg -s installs the build dependencies from the sync databases
+    check_keyring(state).await?;
+    guard_partial_upgrade(state).await?;
+    if state.lock().unwrap().config.confirms(ConfirmStep::Dependencies) && !confirm_dependencies(&package, state).await {
+        return Err("Installation cancelled".into());
+    }
+    state.lock().unwrap().step = Some(Step::Download);
+
+    let build_root = state.lock().unwrap().config.build_dir.clone();
+    let clone_path = format!("{}/{}", build_root, package.name);
+    if let Err(e) = build_dirs::track(&package.name, &clone_path) {
+        state.lock().unwrap().log_warning(&format!("Could not record the build directory {}: {}", clone_path, e));
+    }
+    let bar = state.lock().unwrap().progress_bar.clone();
+    let mirrors = state.lock().unwrap().config.snapshot_mirrors.clone();
+    let source = download_and_extract_package(&package.urlpath, &package.package_base, &clone_path, &mirrors, |downloaded, total| {
+        if let Some(bar) = &bar {
+            if downloaded == 0 {
+                cli_output::start_download(bar, total);
+                bar.set_message(format!("Downloading {}", package.name));
+            }
+            bar.set_position(downloaded);
+        }
+        state.lock().unwrap().progress = Some(match total {
+            Some(total) => fill(tr("Downloading... {} / {} KiB"), &[&(downloaded / 1024), &(total / 1024)]),
+            None => fill(tr("Downloading... {} KiB"), &[&(downloaded / 1024)]),
+        });
+    })
+    .await?;
+    state.lock().unwrap().log(&format!("Downloaded the build files of {} from {}", package.name, source));
+    state.lock().unwrap().progress = Some(tr("Package downloaded and extracted.").to_string());
+
+    // The AUR snapshot unpacks into a directory named after the package base
+    let build_dir = format!("{}/{}", clone_path, package.package_base);
+    if !Path::new(&build_dir).join("PKGBUILD").is_file() {
+        return Err(format!("The snapshot of {} has no PKGBUILD in {}", package.name, build_dir).into());
+    }
+    state.lock().unwrap().step = Some(Step::Review);
+    let mut review = review::load_review(&package.name, &package.package_base, &package.version, &build_dir)?;
+    if state.lock().unwrap().config.check_sources {
+        review.findings.extend(source_check::check(&build_dir).await);
+        review.findings.sort_by(|a, b| b.severity.partial_cmp(&a.severity).unwrap());
+    }
+    if state.lock().unwrap().config.verify_sources {
+        // Architecture specific sources are those of the host the package is built on
+        let arch = host_arch().unwrap_or_else(|_| std::env::consts::ARCH.to_string());
+        review.integrity = source_hashes::verify(&build_dir, &arch).await;
+    }
+    Ok(review)
+}
+
+// `built` skips the build and installs those package files, for retrying a failed install
+async fn build_and_install_package(review: &PendingReview, built: Option<&[String]>, state: &Arc<Mutex<AppState>>) -> Result<(), Box<dyn std::error::Error>> {
+    state.lock().unwrap().step = Some(if built.is_some() { Step::Install } else { Step::Build });
+    // makepkg -s installs build dependencies, so the whole build counts as a transaction
+    let _lock = instance::lock_transactions()?;
+    let package_files = match built {
+        Some(package_files) => package_files.to_vec(),
+        None => {
+            checkpoint(review, pipeline::Stage::Reviewed, &[], state);
+            let _turn = build_turn(review, state).await;
+            match build_reviewed(review, state).await? {
+                Some(package_files) => package_files,
+                None => return Ok(()),
+            }
+        }
+    };
+    checkpoint(review, pipeline::Stage::Built, &package_files, state);
+    let _turn = PACMAN_TURN.lock().await;
+    install_built(review, &package_files, state).await
+}
+
+// Records how the install of `package` ended, deleting the build directories the cleanup policy
+// no longer keeps
+fn clean_up_build_dirs(package: &str, installed: bool, state: &mut AppState) {
+    match build_dirs::finish(package, installed, &state.config.cleanup) {
+        Ok(removed) => {
+            for dir in removed {
+                state.log(&format!("Deleted build directory {}", dir));
+            }
+        }
+        Err(e) => state.log_warning(&format!("Could not clean up build directories: {}", e)),
+    }
+}
+
+// Saves how far the install of `review` got, so a later session can resume it. Only the GUI's
+// installs are saved; the command line starts over when run again.
+fn checkpoint(review: &PendingReview, stage: pipeline::Stage, built_files: &[String], state: &Arc<Mutex<AppState>>) {
+    let mut state = state.lock().unwrap();
+    if state.cli_mode || runner::dry_run() {
+        return;
+    }
+    if let Err(e) = pipeline::save(review, stage, built_files) {
+        state.log_warning(&format!("Could not save the progress of {}: {}", review.package, e));
+    }
+}
+
+// pacman runs one transaction at a time; builds of this process take turns for theirs
+static PACMAN_TURN: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+// Builds wait for their turn as a whole, since makepkg -s installs missing dependencies through
+// pacman. With `concurrent_builds`, builds that need none, or have a container of their own, run
+// side by side instead.
+async fn build_turn(review: &PendingReview, state: &Arc<Mutex<AppState>>) -> Option<tokio::sync::MutexGuard<'static, ()>> {
+    let config = state.lock().unwrap().config.clone();
+    let side_by_side = config.concurrent_builds > 1
+        && (!config.container.is_empty()
+            || srcinfo::SrcInfo::read(&review.build_dir)
+                .and_then(|srcinfo| prerequisites::missing_dependencies(&srcinfo))
+                .is_ok_and(|missing| missing.is_empty()));
+    if side_by_side {
+        return None;
+    }
+    if PACMAN_TURN.try_lock().is_err() {
+        state.lock().unwrap().log(&format!("Waiting for another build or install to finish before building {}", review.package));
+    }
+    Some(PACMAN_TURN.lock().await)
+}
+
+// Builds the reviewed package and returns the package files to install; None when the build failed,
+// which is recorded in the state
+async fn build_reviewed(review: &PendingReview, state: &Arc<Mutex<AppState>>) -> Result<Option<Vec<String>>, Box<dyn std::error::Error>> {
+    let mut config = state.lock().unwrap().config.clone();
+    // Built first to break a dependency cycle, so the dependency it waits for is not installed yet
+    if state.lock().unwrap().cycle_breaks.contains_key(&review.package) {
+        config.makepkg_flags.push("--nodeps".to_string());
+    }
+    let estimate = build_times::estimate(&review.package);
+    if let Some(bar) = &state.lock().unwrap().progress_bar {
+        let usually = estimate.map(|estimate| format!(" (usually {})", format_duration(estimate))).unwrap_or_default();
+        cli_output::start_spinner(bar, &format!("Building {}{}", review.package, usually));
+    }
+    // Without them makepkg fails with errors about fakeroot or strip; containers bring their own
+    if config.container.is_empty() {
+        ensure_prerequisites(&config, state)?;
+    }
+    // makepkg would refuse as well, but only after installing the build dependencies
+    let arch = match host_arch() {
+        Ok(arch) => Some(arch),
+        Err(e) => {
+            state.lock().unwrap().log_warning(&format!("Could not determine the architecture: {}", e));
+            None
+        }
+    };
+    if let (Some(arch), Ok(srcinfo)) = (&arch, srcinfo::SrcInfo::read(&review.build_dir)) {
+        if !srcinfo.supports_arch(arch) {
+            return Err(exit_codes::coded(
+                ExitCode::BuildFailure,
+                format!("{} does not support {}; its PKGBUILD lists arch=({})", review.package, arch, srcinfo.all_values("arch").join(" ")),
+            ));
+        }
+    }
+    // makepkg checks signed sources against validpgpkeys but leaves importing the keys to the user
+    if let Ok(srcinfo) = srcinfo::SrcInfo::read(&review.build_dir) {
+        let mut fingerprints = srcinfo.all_values("validpgpkeys");
+        fingerprints.sort();
+        fingerprints.dedup();
+        if !fingerprints.is_empty() {
+            let imported = pgp_keys::import_missing(&fingerprints, &review.package, &config.keyserver).map_err(|e| exit_codes::coded(ExitCode::BuildFailure, e.to_string()))?;
+            for fingerprint in imported {
+                state.lock().unwrap().log(&format!("Imported the PGP key {} from {}", fingerprint, config.keyserver));
+            }
+        }
+    }
+    // Sources are reviewed locally, then copied to a remote host to be built there
+    let build_dir = match remote::active() {
+        Some((name, host)) => {
+            let remote_dir = format!("{}/{}/{}", host.build_dir, review.package, review.package_base);
+            state.lock().unwrap().log(&format!("Uploading sources to {}:{}", name, remote_dir));
+            remote::upload(&review.build_dir, &remote_dir, &host)?;
+            remote_dir
+        }
+        None => review.build_dir.clone(),
+    };
+    state.lock().unwrap().build_dir = Some((build_dir.clone(), remote::active().map(|(_, host)| host)));
+    // makepkg reads its own config; this copy only tells where the packages will end up
+    let makepkg_conf = match makepkg_conf::load() {
+        Ok(makepkg_conf) => {
+            state.lock().unwrap().log(&makepkg_conf.describe());
+            makepkg_conf
+        }
+        Err(e) => {
+            state.lock().unwrap().log_warning(&format!("{}; looking for packages in the build directory", e));
+            MakepkgConf::default()
+        }
+    };
+    // The image's stock makepkg.conf applies inside a container
+    let makepkg_conf = if config.container.is_empty() {
+        makepkg_conf
+    } else {
+        state.lock().unwrap().log(&format!("Building in a {} container from {}", config.container, config.container_image));
+        makepkg_conf.in_container()
+    };
+    let makeflags = makepkg_conf.parallel_makeflags(config.max_build_jobs).filter(|_| config.parallel_builds);
+    {
+        let mut state = state.lock().unwrap();
+        match &makeflags {
+            Some(flags) => state.log(&format!("Building with MAKEFLAGS={} ({} cores)", flags, makepkg_conf.cores)),
+            None if makepkg_conf.sets_jobs() => state.log("Building with the -j setting from makepkg.conf"),
+            None if makepkg_conf.makeflags_in_conf => state.log("MAKEFLAGS in makepkg.conf has no -j; builds run one make job at a time"),
+            None => state.log("Building with one make job at a time"),
+        }
+        if config.background_builds {
+            state.log("Building in the background at low CPU and IO priority");
+        }
+    }
+    {
+        let mut state = state.lock().unwrap();
+        state.build_started = Some((Instant::now(), estimate));
+        if let Some(estimate) = estimate {
+            state.log(&format!("Builds of {} usually take {}", review.package, format_duration(estimate)));
+        }
+    }
+    let started = Instant::now();
+    // Kept as a string: the boxed error is not Send and must not live across the awaits below
+    let build_error = build_package(&build_dir, &config, makeflags.as_deref(), state).err().map(|e| e.to_string());
+    {
+        let mut state = state.lock().unwrap();
+        state.build_started = None;
+        if let Some(e) = build_error {
+            state.error = Some(e.clone());
+            state.error_code = Some(ExitCode::BuildFailure);
+            state.is_running = false;
+            state.log_error(&format!("Build failed: {}", e));
+            // makepkg -s fails this way when a dependency is missing from the enabled repositories
+            let missing = pacman_conf::missing_targets(state.log.current_messages().map(ansi::strip));
+            if !missing.is_empty() {
+                match pacman_conf::load() {
+                    Ok(conf) => {
+                        for hint in missing.iter().filter_map(|target| conf.explain_missing(target)) {
+                            state.log_warning(&hint);
+                        }
+                    }
+                    Err(e) => debug!("{}", e),
+                }
+            }
+            match state.log.save_failed_build() {
+                Ok(path) => state.log(&format!("Build log saved to {}", path.display())),
+                Err(e) => state.log_warning(&format!("Could not save build log: {}", e)),
+            }
+            clean_up_build_dirs(&review.package, false, &mut state);
+            return Ok(None);
+        }
+        state.progress = Some(tr("Package built successfully.").to_string());
+        state.log(&format!("Built {} in {}", review.package, format_duration(started.elapsed())));
+        // A simulated build says nothing about how long a real one takes
+        if !runner::dry_run() {
+            if let Err(e) = build_times::record(&review.package, &review.version, started.elapsed()) {
+                state.log_warning(&format!("Could not record the build time: {}", e));
+            }
+        }
+    }
 
-    // Create destination directory if it doesn't exist
-    fs::create_dir_all(dest)?;
+    // Only install the outputs of a split package the user picked during review
+    let mut package_files = Vec::new();
+    for name in &review.selected_packages {
+        let package_dir = makepkg_conf.package_dir(&build_dir);
+        let package_file = match find_package_file(package_dir, name, &makepkg_conf.pkgext) {
+            Some(package_file) => package_file,
+            // Nothing was built; install what makepkg would have made
+            None if runner::dry_run() => format!("{}/{}-{}-{}{}", package_dir.trim_end_matches('/'), name, review.version, arch.as_deref().unwrap_or("any"), makepkg_conf.pkgext),
+            None => return Err(format!("Package file for {} not found in {}", name, package_dir).into()),
+        };
+        // An older package of another architecture can be left behind in a shared PKGDEST
+        let file_arch = package_file.strip_suffix(makepkg_conf.pkgext.as_str()).and_then(|stem| stem.rsplit('-').next()).unwrap_or("any");
+        if let Some(arch) = arch.as_deref().filter(|arch| file_arch != "any" && file_arch != *arch) {
+            return Err(format!("{} is built for {}, but {} runs {}", package_file, file_arch, remote::describe_active(), arch).into());
+        }
+        package_files.push(package_file);
+    }
 
-    // Unpack the archive
-    println!("Extracting files to {}", dest);
-    archive.unpack(dest)?;
+    state.lock().unwrap().built_files = package_files.clone();
 
-    // Debug information
-    println!("Files in {}:", dest);
-    for entry in fs::read_dir(dest)? {
-        let entry = entry?;
-        let path = entry.path();
-        println!("{}", path.display());
+    let run_namcap = state.lock().unwrap().run_namcap;
+    if run_namcap && runner::dry_run() {
+        state.lock().unwrap().log("Nothing was built in a dry run; skipping namcap.");
+    } else if run_namcap && remote::active().is_some() {
+        state.lock().unwrap().log_warning("namcap only checks local builds; skipping.");
+    } else if run_namcap {
+        run_namcap_checks(review, &package_files, state);
     }
 
-    Ok(())
+    Ok(Some(package_files))
 }
 
-fn build_package(path: &str) -> Result<(), Box<dyn Error>> {
-    // Ensure the correct path where PKGBUILD is located
-    let build_dir = format!("{}/yay", path);
-    println!("Building package in directory: {}", build_dir);
+// Confirms and installs built package files, running the install hooks around them
+async fn install_built(review: &PendingReview, package_files: &[String], state: &Arc<Mutex<AppState>>) -> Result<(), Box<dyn std::error::Error>> {
+    let config = state.lock().unwrap().config.clone();
+    state.lock().unwrap().step = Some(Step::Install);
+    let updates: Vec<bool> = review.selected_packages.iter().map(|name| backend::active().is_installed(name).unwrap_or(false)).collect();
+    let assumed = state.lock().unwrap().cycle_breaks.get(&review.package).cloned().unwrap_or_default();
+    if config.confirms(ConfirmStep::Transaction) {
+        let packages: Vec<(String, String, String)> =
+            review.selected_packages.iter().zip(package_files).map(|(name, file)| (name.clone(), review.version.clone(), file.clone())).collect();
+        let assume: String = assumed.iter().map(|assumed| format!(" --assume-installed {}", assumed)).collect();
+        let command = pacman_command(backend::active(), &format!("-U --noconfirm {}{}", package_files.join(" "), assume), &config.escalation_tool);
+        let mut summary = transaction::for_files(&packages, vec![command]);
+        let build_order = state.lock().unwrap().build_order.clone();
+        if build_order.contains(&review.package) {
+            summary.build_order = build_order;
+        }
+        if !confirm_transaction(&review.package, summary, state).await {
+            return Err("Installation cancelled".into());
+        }
+    }
+
+    let hooks = &config.hooks;
+    for name in &review.selected_packages {
+        hooks::run_hook("pre_install", hooks.pre_install.as_deref(), name)?;
+    }
 
-    let output = StdCommand::new("makepkg")
-        .args(&["-si", "--noconfirm"])
-        .current_dir(&build_dir)
-        .output()?;
-    if !output.status.success() {
-        eprintln!("Failed to build package: {}", String::from_utf8_lossy(&output.stderr));
+    let install_result = if assumed.is_empty() {
+        install_package(backend::active(), package_files, &config.escalation_tool)
     } else {
-        println!("Package built successfully.");
+        install_assuming(package_files, &assumed, &config.escalation_tool)
+    };
+    if install_result.is_ok() {
+        for (name, was_installed) in review.selected_packages.iter().zip(updates) {
+            let (hook_name, hook) = if was_installed {
+                ("post_update", hooks.post_update.as_deref())
+            } else {
+                ("post_install", hooks.post_install.as_deref())
+            };
+            if let Err(e) = hooks::run_hook(hook_name, hook, name) {
+                state.lock().unwrap().log_warning(&e.to_string());
+            }
+        }
+    }
+    {
+        let mut state = state.lock().unwrap();
+        if let Err(e) = install_result {
+            state.error = Some(e.to_string());
+            state.error_code = Some(exit_codes::classify(e.as_ref()));
+            state.is_running = false;
+            state.log_error(&format!("Install failed: {}", e));
+            clean_up_build_dirs(&review.package, false, &mut state);
+            return Ok(());
+        }
+        state.progress = Some(tr("Package installed successfully.").to_string());
+        state.is_running = false;
+        state.step = None;
+        state.log("Package installation process completed.");
+        pipeline::clear(&review.package);
+        clean_up_build_dirs(&review.package, true, &mut state);
+        if runner::dry_run() {
+            state.log("Dry run: nothing was built or installed.");
+        }
     }
+
     Ok(())
 }
-fn is_package_installed(package_name: &str) -> Result<bool, Box<dyn Error>> {
-    let output = StdCommand::new("pacman")
-        .args(&["-Q", package_name])
-        .output()?;
-    Ok(output.status.success())
-}
 
-fn install_package(package_file: &str) -> Result<(), Box<dyn Error>> {
-    println!("Installing package from file: {}", package_file);
-    let output = StdCommand::new("pkexec")
-        .args(&["pacman", "-U", package_file, "--noconfirm"])
-        .output()?;
-    if !output.status.success() {
-        eprintln!("Failed to install package: {}", String::from_utf8_lossy(&output.stderr));
-    } else {
-        println!("Package installed successfully.");
+// Confirms installing repository packages with pacman -S, unless transactions are not confirmed,
+// after the keyring and partial upgrade checks
+async fn confirm_sync_install(targets: &[String], package: &str, config: &Config, state: &Arc<Mutex<AppState>>) -> Result<(), Box<dyn Error>> {
+    check_keyring(state).await?;
+    guard_partial_upgrade(state).await?;
+    if !config.confirms(ConfirmStep::Transaction) {
+        return Ok(());
     }
-    Ok(())
-}
-fn uninstall_package(package_name: &str) -> Result<(), Box<dyn Error>> {
-    println!("Uninstalling package: {}", package_name);
-    let output = StdCommand::new("pkexec")
-        .args(&["pacman", "-Rns", package_name, "--noconfirm"])
-        .output()?;
-    if !output.status.success() {
-        eprintln!("Failed to uninstall package: {}", String::from_utf8_lossy(&output.stderr));
-    } else {
-        println!("Package uninstalled successfully.");
+    let command = pacman_command(backend::repo(), &format!("-S --needed --noconfirm {}", targets.join(" ")), &config.escalation_tool);
+    if !confirm_transaction(package, transaction::for_targets(targets, vec![command]), state).await {
+        return Err("Installation cancelled".into());
     }
     Ok(())
 }
 
-fn find_package_file(base_directory: &str, package_name: &str) -> Option<String> {
-    // Construct the path where the package file should be located
-    let package_directory = format!("{}/{}", base_directory, package_name);
-
-    // Check the directory for package files
-    let entries = fs::read_dir(package_directory).ok()?;
-    for entry in entries {
-        let entry = entry.ok()?;
-        let path = entry.path();
-        if path.is_file() {
-            let file_name = path.file_name()?.to_string_lossy().to_string();
-            if file_name.starts_with(package_name) && file_name.ends_with(".pkg.tar.zst") {
-                return Some(path.to_string_lossy().to_string());
+// An outdated archlinux-keyring is the usual cause of signature errors when installing from the
+// repositories, so updating it first is offered; without confirmations it is updated right away
+async fn check_keyring(state: &Arc<Mutex<AppState>>) -> Result<(), Box<dyn Error>> {
+    let config = state.lock().unwrap().config.clone();
+    if !backend::active().builds_from_source() || config.is_held(keyring::PACKAGE) {
+        return Ok(());
+    }
+    let Some(versions) = keyring::outdated() else {
+        return Ok(());
+    };
+    let receiver = {
+        let mut state = state.lock().unwrap();
+        state.log_warning(&format!("{} {} is installed, {} is available", keyring::PACKAGE, versions.0, versions.1));
+        if config.no_confirm {
+            None
+        } else if state.cli_mode {
+            let ask = || confirm(&fill(tr("archlinux-keyring {} is out of date ({} is available). Update it first?"), &[&versions.0, &versions.1]));
+            let update = match &state.progress_bar {
+                Some(bar) => bar.suspend(ask),
+                None => ask(),
+            };
+            if !update {
+                return Ok(());
             }
+            None
+        } else {
+            let (reply, receiver) = tokio::sync::oneshot::channel();
+            state.progress = Some(tr("Waiting for confirmation.").to_string());
+            state.pending_keyring = Some((versions, reply));
+            Some(receiver)
+        }
+    };
+    if let Some(receiver) = receiver {
+        if !receiver.await.unwrap_or(false) {
+            return Ok(());
         }
     }
-    
-    None
+    state.lock().unwrap().progress = Some(fill(tr("Updating {}..."), &[&keyring::PACKAGE]));
+    let _lock = instance::lock_transactions()?;
+    let result = keyring::update(&config.escalation_tool);
+    record_transaction(state::Action::Update, backend::repo().name(), &[keyring::PACKAGE.to_string()], &result);
+    result?;
+    state.lock().unwrap().log(&format!("{} updated.", keyring::PACKAGE));
+    Ok(())
 }
-fn list_package_dependencies(package_name: &str) -> Result<Vec<String>, Box<dyn Error>> {
-    let output = StdCommand::new("pacman")
-        .args(&["-Qi", package_name])
-        .output()?;
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut dependencies = Vec::new();
 
-    for line in stdout.lines() {
-        if line.starts_with("Depends On") {
-            dependencies.push(line.split(':').nth(1).unwrap_or("").trim().to_string());
+// Installing from sync databases that are ahead of the installed packages is a partial upgrade,
+// which Arch does not support. Offers to upgrade everything first; Err when the user cancels.
+async fn guard_partial_upgrade(state: &Arc<Mutex<AppState>>) -> Result<(), Box<dyn Error>> {
+    let config = state.lock().unwrap().config.clone();
+    if !backend::active().builds_from_source() || partial_upgrade::accepted() {
+        return Ok(());
+    }
+    let outdated = partial_upgrade::pending(|name| config.is_held(name));
+    if outdated.is_empty() {
+        return Ok(());
+    }
+    state.lock().unwrap().log_warning(&format!("The package databases have newer versions of {} installed packages; installing now is a partial upgrade", outdated.len()));
+    let choice = if config.confirms(ConfirmStep::PartialUpgrade) { ask_partial_upgrade(outdated, state).await } else { partial_upgrade::Choice::Continue };
+    match choice {
+        partial_upgrade::Choice::Cancel => Err("Installation cancelled".into()),
+        partial_upgrade::Choice::Continue => {
+            partial_upgrade::accept();
+            Ok(())
+        }
+        partial_upgrade::Choice::UpgradeFirst => {
+            state.lock().unwrap().progress = Some(tr("Upgrading the system...").to_string());
+            let held: Vec<String> = config.holds.keys().cloned().collect();
+            let _lock = instance::lock_transactions()?;
+            let result = backend::system_upgrade(&held, &config.escalation_tool);
+            record_transaction(state::Action::Upgrade, backend::repo().name(), &[], &result);
+            result?;
+            state.lock().unwrap().log("System upgraded.");
+            Ok(())
         }
     }
-    Ok(dependencies)
 }
 
-async fn run_package_management_logic(package_name: &str, state: &Arc<Mutex<AppState>>) -> Result<(), Box<dyn std::error::Error>> {
-    let package = fetch_metadata(package_name).await?;
+// Asks on the terminal in CLI mode, where declining the upgrade goes on without it; the GUI shows
+// a dialog and answers through the channel
+async fn ask_partial_upgrade(outdated: Vec<backend::Update>, state: &Arc<Mutex<AppState>>) -> partial_upgrade::Choice {
+    let receiver = {
+        let mut state = state.lock().unwrap();
+        if state.cli_mode {
+            let ask = || {
+                println!("{}", fill(tr("The package databases were synced without upgrading {} installed packages:"), &[&outdated.len()]));
+                for (name, installed, available) in &outdated {
+                    println!("    {} {} -> {}", name, installed, available);
+                }
+                if confirm(tr("Upgrade everything first with pacman -Syu?")) {
+                    partial_upgrade::Choice::UpgradeFirst
+                } else {
+                    partial_upgrade::Choice::Continue
+                }
+            };
+            return match &state.progress_bar {
+                Some(bar) => bar.suspend(ask),
+                None => ask(),
+            };
+        }
+        let (reply, receiver) = tokio::sync::oneshot::channel();
+        state.progress = Some(tr("Waiting for confirmation.").to_string());
+        state.pending_partial_upgrade = Some(PendingPartialUpgrade { outdated, reply });
+        receiver
+    };
+    receiver.await.unwrap_or(partial_upgrade::Choice::Cancel)
+}
 
-    let clone_path = format!("/tmp/{}", package.name);
-    let download_result = download_and_extract_package(&package.urlpath, &clone_path).await;
-    {
+// Shows what installing `package` pulls in and asks whether to go on; nothing is asked when every
+// dependency is installed already or the tree cannot be worked out
+async fn confirm_dependencies(package: &Package, state: &Arc<Mutex<AppState>>) -> bool {
+    state.lock().unwrap().progress = Some(tr("Resolving dependencies...").to_string());
+    let tree = match dep_tree::resolve(&package.name, &package.depends).await {
+        Ok(tree) if tree.is_satisfied() => return true,
+        Ok(tree) => tree,
+        Err(e) => {
+            state.lock().unwrap().log_warning(&format!("Could not resolve the dependencies of {}: {}", package.name, e));
+            return true;
+        }
+    };
+    let receiver = {
         let mut state = state.lock().unwrap();
-        if let Err(e) = download_result {
-            state.error = Some(e.to_string());
-            state.is_running = false;
-            return Ok(());
+        if state.cli_mode {
+            let ask = || {
+                println!("{}", fill(tr("Dependencies of {}:"), &[&tree.package]));
+                for line in tree.lines() {
+                    println!("    {}", line);
+                }
+                confirm(tr("Continue with the download?"))
+            };
+            return match &state.progress_bar {
+                Some(bar) => bar.suspend(ask),
+                None => ask(),
+            };
         }
-        state.progress = Some("Package downloaded and extracted.".to_string());
-    }
+        let (reply, receiver) = tokio::sync::oneshot::channel();
+        state.progress = Some(tr("Waiting for confirmation.").to_string());
+        state.pending_dependencies = Some(PendingDependencies { tree, reply });
+        receiver
+    };
+    receiver.await.unwrap_or(false)
+}
 
-    let build_result = build_package(&clone_path);
-    {
+// Asks on the terminal in CLI mode; the GUI shows a dialog and answers through the channel
+async fn confirm_transaction(package: &str, summary: transaction::Summary, state: &Arc<Mutex<AppState>>) -> bool {
+    let receiver = {
         let mut state = state.lock().unwrap();
-        if let Err(e) = build_result {
-            state.error = Some(e.to_string());
-            state.is_running = false;
-            return Ok(());
+        if state.cli_mode {
+            let ask = || {
+                println!("{}", tr("Packages to install:"));
+                for line in summary.lines() {
+                    println!("    {}", line);
+                }
+                confirm(tr("Proceed with installation?"))
+            };
+            return match &state.progress_bar {
+                Some(bar) => bar.suspend(ask),
+                None => ask(),
+            };
         }
-        state.progress = Some("Package built successfully.".to_string());
+        let (reply, receiver) = tokio::sync::oneshot::channel();
+        state.progress = Some(tr("Waiting for confirmation.").to_string());
+        state.pending_transaction = Some(PendingTransaction {
+            package: package.to_string(),
+            summary,
+            reply,
+        });
+        receiver
+    };
+    receiver.await.unwrap_or(false)
+}
+
+fn run_namcap_checks(review: &PendingReview, package_files: &[String], state: &Arc<Mutex<AppState>>) {
+    if !namcap::is_available() {
+        state.lock().unwrap().log_warning("namcap is not installed; skipping package checks.");
+        return;
     }
+    state.lock().unwrap().progress = Some(tr("Running namcap...").to_string());
 
-    // Use the correct directory and package name to find the package file
-    let package_file = find_package_file("/tmp/yay", &package.name).ok_or("Package file not found")?;
-    let install_result = install_package(&package_file);
-    {
-        let mut state = state.lock().unwrap();
-        if let Err(e) = install_result {
-            state.error = Some(e.to_string());
-            state.is_running = false;
-            return Ok(());
+    let mut messages = Vec::new();
+    for target in std::iter::once("PKGBUILD").chain(package_files.iter().map(String::as_str)) {
+        match namcap::run_namcap(target, &review.build_dir) {
+            Ok(found) => messages.extend(found),
+            Err(e) => state.lock().unwrap().log_warning(&format!("namcap failed on {}: {}", target, e)),
         }
-        state.progress = Some("Package installed successfully.".to_string());
-        state.is_running = false;
-        state.log.push("Package installation process completed.".to_string());
+    }
+    for message in &messages {
+        info!("namcap {} {}: {}", message.level.label(), message.target, message.text);
     }
 
-    Ok(())
+    let mut state = state.lock().unwrap();
+    let errors = messages.iter().filter(|m| m.level == NamcapLevel::Error).count();
+    let warnings = messages.iter().filter(|m| m.level == NamcapLevel::Warning).count();
+    state.log(&format!("namcap: {} error(s), {} warning(s) for {}", errors, warnings, review.package));
+    state.namcap_messages = messages;
+}
+
+fn confirm(prompt: &str) -> bool {
+    print!("{} [y/N] ", prompt);
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    // When the package list was piped in, stdin is used up, so ask on the terminal instead
+    let read = if io::stdin().is_terminal() {
+        io::stdin().read_line(&mut answer)
+    } else {
+        fs::File::open("/dev/tty").and_then(|tty| BufReader::new(tty).read_line(&mut answer))
+    };
+    if read.is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+async fn run_package_management_logic(package_name: &str, state: &Arc<Mutex<AppState>>, cli: &CliOutput) -> Result<(), Box<dyn std::error::Error>> {
+    state.lock().unwrap().progress_bar = cli.progress_bar();
+    let prepared = prepare_package(package_name, state).await;
+    // The review and prompts are printed without a bar drawing over them
+    if let Some(bar) = state.lock().unwrap().progress_bar.take() {
+        bar.finish_and_clear();
+    }
+    let review = prepared?;
+    cli.ok(&format!("Downloaded {} {}", review.package, review.version));
+
+    let mut review = review;
+    if state.lock().unwrap().config.confirms(ConfirmStep::Review) {
+        review::print_review(&review);
+        if (review.has_changes() || !review.findings.is_empty() || review.has_integrity_problems()) && !confirm(&fill(tr("Build {} {} with these files?"), &[&review.package, &review.version])) {
+            return Err("Build aborted after review".into());
+        }
+        for name in review.split_packages.clone() {
+            if name != review.package && confirm(&fill(tr("{} also builds {}. Install it too?"), &[&review.package_base, &name])) {
+                review.toggle_package(&name, true);
+            }
+        }
+    }
+    review::save_reviewed(&review)?;
+
+    state.lock().unwrap().progress_bar = cli.progress_bar();
+    let result = build_and_install_package(&review, None, state).await;
+    if let Some(bar) = state.lock().unwrap().progress_bar.take() {
+        bar.finish_and_clear();
+    }
+    result
 }
 
 
-fn run_cli() {
-    let matches = Command::new("AUR Helper")
-        .version("1.0")
+fn cli() -> Command {
+    Command::new("AUR Helper")
+        .version(env!("CARGO_PKG_VERSION"))
         .author("Author Name <author@example.com>")
         .about("Helps manage AUR packages")
+        // Replaced so that --check-update can go along with it
+        .disable_version_flag(true)
+        .arg(Arg::new("version")
+            .short('V')
+            .long("version")
+            .action(ArgAction::SetTrue)
+            .help("Prints the version"))
+        .arg(Arg::new("check-update")
+            .long("check-update")
+            .requires("version")
+            .action(ArgAction::SetTrue)
+            .help("With --version, asks the project's releases for a newer version; exits with 10 when there is one"))
         .arg(Arg::new("package")
             .short('p')
             .long("package")
             .value_name("PACKAGE")
-            .help("Specifies the package name"))
-        .get_matches();
-
-    if let Some(package) = matches.get_one::<String>("package") {
-        let rt = Runtime::new().unwrap();
-        let state = Arc::new(Mutex::new(AppState::default()));
-        rt.block_on(async {
-            let state_clone = state.clone();
-            let result = run_package_management_logic(package, &state_clone).await;
-            if let Err(e) = result {
-                eprintln!("Error: {}", e);
+            .help("Specifies the package name")
+            .add(ArgValueCandidates::new(completions::aur_packages)))
+        .arg(Arg::new("namcap")
+            .long("namcap")
+            .global(true)
+            .action(ArgAction::SetTrue)
+            .help("Runs namcap on the PKGBUILD and built package before installing"))
+        .arg(Arg::new("noconfirm")
+            .long("noconfirm")
+            .global(true)
+            .action(ArgAction::SetTrue)
+            .help("Skips the review, transaction and removal prompts"))
+        .arg(Arg::new("background")
+            .long("background")
+            .global(true)
+            .action(ArgAction::SetTrue)
+            .help("Builds at low CPU and IO priority so the desktop stays responsive"))
+        .arg(Arg::new("host")
+            .long("host")
+            .global(true)
+            .value_name("NAME")
+            .help("Manages a host from the [hosts] config section over SSH; `local` for this machine"))
+        .arg(Arg::new("aur-url")
+            .long("aur-url")
+            .global(true)
+            .value_name("URL")
+            .help("Uses another AUR, e.g. a private aurweb instance, instead of the configured one"))
+        .arg(Arg::new("dry-run")
+            .long("dry-run")
+            .global(true)
+            .action(ArgAction::SetTrue)
+            .help("Logs the builds and package manager commands a transaction would run instead of running them; alone, opens the GUI"))
+        .arg(Arg::new("verbose")
+            .short('v')
+            .long("verbose")
+            .global(true)
+            .action(ArgAction::Count)
+            .help("Shows more output: -v for RPC and download details, -vv for makepkg output"))
+        .arg(Arg::new("quiet")
+            .short('q')
+            .long("quiet")
+            .global(true)
+            .action(ArgAction::SetTrue)
+            .conflicts_with("verbose")
+            .help("Only prints errors"))
+        .subcommand(Command::new("install")
+            .about("Reviews, builds and installs AUR packages")
+            .arg(Arg::new("package")
+                .num_args(1..)
+                .required_unless_present("file")
+                .value_name("PACKAGE")
+                .help("Package names; `-` reads newline-separated names from stdin")
+                .add(ArgValueCandidates::new(completions::aur_packages)))
+            .arg(Arg::new("file")
+                .long("file")
+                .value_name("FILE")
+                .value_hint(clap::ValueHint::FilePath)
+                .help("Reads newline-separated package names from a file"))
+            .arg(Arg::new("here")
+                .long("here")
+                .action(ArgAction::SetTrue)
+                .help("Installs from this terminal even when the GUI is running"))
+            .arg(Arg::new("exclude")
+                .long("exclude")
+                .value_name("PACKAGE")
+                .value_delimiter(',')
+                .action(ArgAction::Append)
+                .help("Leaves these members out when a name is a pacman group")))
+        .subcommand(Command::new("remove")
+            .about("Uninstalls a package")
+            .arg(Arg::new("package")
+                .required(true)
+                .value_name("PACKAGE")
+                .add(ArgValueCandidates::new(completions::installed_packages)))
+            .arg(Arg::new("mode")
+                .long("mode")
+                .value_name("MODE")
+                .value_parser(RemoveMode::ALL.map(RemoveMode::name))
+                .help("What pacman removes along with the package; defaults to remove_mode from the config")))
+        .subcommand(Command::new("update")
+            .about("Rebuilds installed AUR packages that have a newer version; asks only on a terminal and skips held and ignored packages")
+            .arg(Arg::new("check")
+                .long("check")
+                .action(ArgAction::SetTrue)
+                .help("Only lists outdated packages; exits with 10 when there are any"))
+            .arg(Arg::new("notify")
+                .long("notify")
+                .requires("check")
+                .action(ArgAction::SetTrue)
+                .help("With --check, also shows a desktop notification when there are updates")))
+        .subcommand(Command::new("serve")
+            .about("Serves a JSON API for search, updates and transactions")
+            .arg(Arg::new("listen")
+                .long("listen")
+                .value_name("ADDR")
+                .default_value("127.0.0.1:8700")
+                .value_parser(clap::value_parser!(std::net::SocketAddr))))
+        .subcommand(Command::new("helper")
+            .hide(true)
+            .about("Runs pacman transactions as root for the GUI; started through pkexec")
+            .arg(Arg::new("socket")
+                .long("socket")
+                .required(true)
+                .value_name("PATH")))
+        .subcommand(Command::new("enable-timer")
+            .about("Checks for updates on a systemd user timer and notifies when there are some; installs nothing")
+            .arg(Arg::new("interval")
+                .long("interval")
+                .value_name("INTERVAL")
+                .help("Time between checks, e.g. 30min, 6h or 1d; defaults to update_interval_hours from the config")))
+        .subcommand(Command::new("history")
+            .about("Lists past installs, updates, upgrades and removals, oldest first")
+            .arg(Arg::new("limit")
+                .long("limit")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("Only lists the last N")))
+        .subcommand(Command::new("install-desktop-entry")
+            .about("Adds the app to application launchers: writes a .desktop file and icons to ~/.local/share"))
+        .subcommand(Command::new("completions")
+            .about("Prints a shell completion script")
+            .arg(Arg::new("shell")
+                .required(true)
+                .value_parser(completions::SHELLS)))
+}
+
+fn run_cli(matches: clap::ArgMatches) -> ExitCode {
+    let config = load_cli_config(&matches);
+    if matches.get_flag("version") {
+        return print_version(matches.get_flag("check-update"), &config);
+    }
+    if let Some(("history", sub)) = matches.subcommand() {
+        return print_history(sub.get_one::<usize>("limit").copied());
+    }
+    if matches.subcommand_name() == Some("install-desktop-entry") {
+        return install_desktop_entry(&matches);
+    }
+    if let Some(("enable-timer", sub)) = matches.subcommand() {
+        let interval = sub.get_one::<String>("interval").cloned().unwrap_or_else(|| format!("{}h", config.update_interval_hours));
+        return enable_timer(&interval, &matches);
+    }
+    // Everything below queries pacman, so the host must be settled first
+    if let Err(e) = remote::set_active(&config).and_then(|_| backend::select(&config)) {
+        eprintln!("{} {}", tr("Error:"), e);
+        return ExitCode::Generic;
+    }
+    http::configure(&config);
+    match matches.subcommand() {
+        Some(("completions", sub)) => match completions::print_registration(sub.get_one::<String>("shell").unwrap()) {
+            Ok(()) => ExitCode::Success,
+            Err(e) => {
+                eprintln!("{} {}", tr("Error:"), e);
+                ExitCode::Generic
+            }
+        },
+        Some(("install", sub)) => match package_list(sub) {
+            // A running GUI would hold the transaction lock, so it takes the packages instead;
+            // --host targets a machine the GUI may not be managing, and its queue knows no exclusions
+            Ok(packages)
+                if !sub.get_flag("here") && matches.get_one::<String>("host").is_none() && sub.get_one::<String>("exclude").is_none() && !runner::dry_run() && instance::hand_off_install(&packages) =>
+            {
+                CliOutput::new(matches.get_flag("quiet")).ok(&format!("Queued {} in the running GUI", packages.join(" ")));
+                ExitCode::Success
+            }
+            Ok(packages) => install_packages(&packages, &config, &matches),
+            Err(e) => {
+                eprintln!("{} {}", tr("Error:"), e);
+                exit_codes::classify(e.as_ref())
+            }
+        },
+        Some(("remove", sub)) => {
+            let mode = sub.get_one::<String>("mode").and_then(|name| RemoveMode::from_name(name)).unwrap_or(config.remove_mode);
+            remove_cli(sub.get_one::<String>("package").unwrap(), mode, &config, &matches)
+        }
+        Some(("update", sub)) => update_cli(sub.get_flag("check"), &config, &matches),
+        Some(("serve", sub)) => {
+            let listen = *sub.get_one::<std::net::SocketAddr>("listen").unwrap();
+            let rt = Runtime::new().unwrap();
+            match rt.block_on(server::serve(listen, config)) {
+                Ok(()) => ExitCode::Success,
+                Err(e) => {
+                    eprintln!("{} {}", tr("Error:"), e);
+                    exit_codes::classify(e.as_ref())
+                }
+            }
+        }
+        Some(("helper", sub)) => match helper::serve(Path::new(sub.get_one::<String>("socket").unwrap())) {
+            Ok(()) => ExitCode::Success,
+            Err(e) => {
+                eprintln!("{} {}", tr("Error:"), e);
+                exit_codes::classify(e.as_ref())
+            }
+        },
+        _ => match matches.get_one::<String>("package") {
+            Some(package) => install_packages(std::slice::from_ref(package), &config, &matches),
+            None => ExitCode::Success,
+        },
+    }
+}
+
+fn install_desktop_entry(matches: &clap::ArgMatches) -> ExitCode {
+    match desktop_entry::install() {
+        Ok(files) => {
+            let output = CliOutput::new(matches.get_flag("quiet"));
+            for file in files {
+                output.ok(&format!("Wrote {}", file.display()));
+            }
+            ExitCode::Success
+        }
+        Err(e) => {
+            eprintln!("{} {}", tr("Error:"), e);
+            exit_codes::classify(e.as_ref())
+        }
+    }
+}
+
+fn enable_timer(interval: &str, matches: &clap::ArgMatches) -> ExitCode {
+    match timer::enable(interval) {
+        Ok(files) => {
+            let output = CliOutput::new(matches.get_flag("quiet"));
+            for file in files {
+                output.ok(&format!("Wrote {}", file.display()));
+            }
+            output.ok(&format!("Checking for updates every {}", interval));
+            ExitCode::Success
+        }
+        Err(e) => {
+            eprintln!("{} {}", tr("Error:"), e);
+            exit_codes::classify(e.as_ref())
+        }
+    }
+}
+
+fn print_history(limit: Option<usize>) -> ExitCode {
+    let transactions = match state::load() {
+        Ok(state) => state.transactions,
+        Err(e) => {
+            eprintln!("{} {}", tr("Error:"), e);
+            return ExitCode::Generic;
+        }
+    };
+    let skipped = limit.map_or(0, |limit| transactions.len().saturating_sub(limit));
+    for transaction in &transactions[skipped..] {
+        let finished = chrono::DateTime::from_timestamp(transaction.finished, 0).map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default();
+        let action = match transaction.action {
+            state::Action::Install => "install",
+            state::Action::Remove => "remove",
+            state::Action::Update => "update",
+            state::Action::Upgrade => "upgrade",
+        };
+        let outcome = transaction.error.as_ref().map_or_else(|| "ok".to_string(), |error| format!("failed: {}", error));
+        println!("{}  {:<7}  {:<7}  {}  ({})", finished, action, transaction.source, transaction.targets.join(" "), outcome);
+    }
+    ExitCode::Success
+}
+
+// With `check_update`, also looks for a newer release of the app itself
+fn print_version(check_update: bool, config: &Config) -> ExitCode {
+    println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+    if !check_update {
+        return ExitCode::Success;
+    }
+    http::configure(config);
+    let rt = Runtime::new().unwrap();
+    match rt.block_on(app_update::newer_release(app_update::RELEASES_URL)) {
+        Ok(Some(release)) => {
+            println!("{}", fill(tr("Version {} is available: {}"), &[&release.version, &release.url]));
+            ExitCode::UpdatesAvailable
+        }
+        Ok(None) => {
+            println!("{}", tr("This is the latest version."));
+            ExitCode::Success
+        }
+        Err(e) => {
+            eprintln!("{} {}", tr("Error:"), fill(tr("Could not check for a newer version: {}"), &[&e]));
+            exit_codes::classify(e.as_ref())
+        }
+    }
+}
+
+// Collects names from the arguments, `-` (stdin) and --file, in that order, without duplicates
+fn package_list(matches: &clap::ArgMatches) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut packages = Vec::new();
+    for arg in matches.get_many::<String>("package").into_iter().flatten() {
+        if arg == "-" {
+            packages.extend(parse_package_list(&io::read_to_string(io::stdin())?));
+        } else {
+            packages.push(arg.clone());
+        }
+    }
+    if let Some(file) = matches.get_one::<String>("file") {
+        let contents = fs::read_to_string(file).map_err(|e| format!("Could not read {}: {}", file, e))?;
+        packages.extend(parse_package_list(&contents));
+    }
+    let mut seen = std::collections::HashSet::new();
+    packages.retain(|package| seen.insert(package.clone()));
+    if packages.is_empty() {
+        return Err("No packages given".into());
+    }
+    Ok(packages)
+}
+
+// One package per line; blank lines and `#` comments are skipped, and only the first word
+// is used so `pacman -Qm` output can be piped in directly
+fn parse_package_list(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_string)
+        .collect()
+}
+
+fn load_cli_config(matches: &clap::ArgMatches) -> Config {
+    let mut config = Config::load().unwrap_or_else(|e| {
+        eprintln!("{} {}", tr("Error:"), e);
+        Config::default()
+    });
+    if matches.get_flag("noconfirm") {
+        config.no_confirm = true;
+    }
+    if matches.get_flag("background") {
+        config.background_builds = true;
+    }
+    if let Some(host) = matches.get_one::<String>("host") {
+        config.active_host = (host != "local").then(|| host.clone());
+    }
+    if let Some(aur_url) = matches.get_one::<String>("aur-url") {
+        config.aur_url = aur_url.clone();
+    }
+    config
+}
+
+// Lists what pacman would remove before asking
+fn confirm_removal(package: &str, mode: RemoveMode) -> bool {
+    if backend::active().removes_with_pacman() {
+        match removal::preview(package, mode) {
+            Ok(impact) => {
+                println!("{}", fill(tr("Removes {} package(s), freeing {}:"), &[&impact.packages.len(), &format_size(impact.total_size())]));
+                for (name, size) in &impact.packages {
+                    println!("  {} ({})", name, format_size(*size));
+                }
+                if !impact.orphans.is_empty() {
+                    println!("{}", fill(tr("Left installed but no longer needed: {}"), &[&impact.orphans.join(", ")]));
+                }
+            }
+            Err(e) => println!("{}", fill(tr("pacman would refuse this removal: {}"), &[&e])),
+        }
+    }
+    confirm(&fill(tr("Remove {} ({})?"), &[&package, &mode.label().to_lowercase()]))
+}
+
+fn remove_cli(package: &str, mode: RemoveMode, config: &Config, matches: &clap::ArgMatches) -> ExitCode {
+    let cli = CliOutput::new(matches.get_flag("quiet"));
+    let result = match backend::active().is_installed(package) {
+        Ok(false) => Err(exit_codes::coded(ExitCode::NotFound, format!("{} is not installed", package))),
+        _ if config.confirms(ConfirmStep::Removal) && !confirm_removal(package, mode) => Err("Removal cancelled".into()),
+        _ => hooks::run_hook("pre_remove", config.hooks.pre_remove.as_deref(), package)
+            .and_then(|_| uninstall_package(backend::active(), package, mode, &config.escalation_tool)),
+    };
+    match result {
+        Ok(()) => {
+            cli.ok(&format!("Removed {}", package));
+            ExitCode::Success
+        }
+        Err(e) => {
+            cli.fail(&format!("{}: {}", package, e));
+            cli.hints(&troubleshoot::diagnose(&e.to_string(), []));
+            exit_codes::classify(e.as_ref())
+        }
+    }
+}
+
+// Installs packages one after another; the exit code is that of the last failure
+fn install_packages(packages: &[String], config: &Config, matches: &clap::ArgMatches) -> ExitCode {
+    let rt = Runtime::new().unwrap();
+    let cli = CliOutput::new(matches.get_flag("quiet"));
+    let mut rows = Vec::new();
+    let mut code = ExitCode::Success;
+    let excluded: Vec<String> = matches.subcommand_matches("install").and_then(|sub| sub.get_many::<String>("exclude")).into_iter().flatten().cloned().collect();
+    // Packages that depend on others given are built after them
+    let mut build_order = Vec::new();
+    let mut cycle_breaks = HashMap::new();
+    if backend::active().builds_from_source() && packages.len() > 1 {
+        match rt.block_on(build_nodes(packages)) {
+            Ok(mut nodes) => {
+                while let Some(cycle) = build_order::cycle(&nodes) {
+                    eprintln!("{}", fill(tr("These packages depend on each other: {}"), &[&cycle.join(" -> ")]));
+                    let (first, next) = (cycle[0].clone(), &cycle[1]);
+                    if !config.no_confirm && !confirm(&fill(tr("Build {} first as if {} were installed?"), &[&first, next])) {
+                        cli.fail(tr("Not building packages that depend on each other"));
+                        return ExitCode::BuildFailure;
+                    }
+                    let assumed = build_order::break_cycle(&mut nodes, &cycle, &first);
+                    cycle_breaks.insert(first, assumed);
+                }
+                let order = build_order::order(&nodes);
+                if order != packages {
+                    cli.note(&format!("Build order: {}", order.join(" -> ")));
+                }
+                build_order = order;
+            }
+            Err(e) => eprintln!("{} {}", tr("Error:"), fill(tr("Could not work out the build order: {}"), &[&e])),
+        }
+    }
+    let packages = if build_order.is_empty() { packages } else { &build_order[..] };
+    for package in packages {
+        let state = Arc::new(Mutex::new(AppState {
+            config: config.clone(),
+            run_namcap: matches.get_flag("namcap"),
+            cli_mode: true,
+            build_order: build_order.clone(),
+            cycle_breaks: cycle_breaks.clone(),
+            ..Default::default()
+        }));
+        // Groups the package's output, which the failure hints are drawn from
+        state.lock().unwrap().log.begin_operation(&format!("Install {}", package), Some(package));
+        let started = Instant::now();
+        // A group name installs its members like `pacman -S <group>` would, not an AUR package
+        let group = backend::active().builds_from_source().then(|| groups::find(package)).flatten();
+        let prebuilt = (group.is_none() && backend::active().builds_from_source()).then(|| binary_repo::find(package, &config.binary_repos)).flatten();
+        // Without confirmations the prebuilt package is taken
+        let use_prebuilt = prebuilt.filter(|prebuilt| {
+            config.no_confirm
+                || confirm(&fill(tr("{} {} is available prebuilt from {}. Install it instead of building?"), &[&prebuilt.package, &prebuilt.version, &prebuilt.repo]))
+        });
+        let result = if let Some(group) = group {
+            let members: Vec<String> = group.picked().into_iter().filter(|member| !excluded.contains(member)).collect();
+            rt.block_on(install_group(&group.name, &members, config, &state))
+        } else if let Some(prebuilt) = use_prebuilt {
+            rt.block_on(install_prebuilt(&prebuilt, config, &state))
+        } else if backend::active().builds_from_source() {
+            rt.block_on(run_package_management_logic(package, &state, &cli))
+        } else {
+            install_package(backend::active(), std::slice::from_ref(package), &config.escalation_tool)
+        };
+        // Build and install failures are reported through the state rather than the result
+        let failure = match result {
+            Err(e) => Some((exit_codes::classify(e.as_ref()), e.to_string())),
+            Ok(()) => {
+                let state = state.lock().unwrap();
+                state.error.clone().map(|e| (state.error_code.unwrap_or(ExitCode::Generic), e))
             }
+        };
+        match &failure {
+            Some((failure_code, e)) => {
+                cli.fail(&format!("{}: {}", package, e));
+                let output: Vec<String> = state.lock().unwrap().log.current_messages().map(ansi::strip).collect();
+                cli.hints(&troubleshoot::diagnose(e, output.iter().map(String::as_str)));
+                code = *failure_code;
+            }
+            None => cli.ok(&format!("Installed {}", package)),
+        }
+        rows.push(SummaryRow {
+            package: package.clone(),
+            ok: failure.is_none(),
+            detail: failure.map(|(_, e)| e).unwrap_or_else(|| "installed".to_string()),
+            elapsed: started.elapsed(),
         });
     }
+    cli.summary(&rows);
+    code
+}
+
+// With `check`, only lists outdated packages and exits with UpdatesAvailable when there are any.
+// Packages of the active backend go through the install pipeline, extra sources update in one call each.
+// On a terminal the reviews and confirmations of an install are shown as usual. Without one,
+// e.g. from scripts and cron, nothing is asked, as with --noconfirm. Held packages and those
+// pacman.conf ignores are skipped.
+fn update_cli(check: bool, config: &Config, matches: &clap::ArgMatches) -> ExitCode {
+    let mut config = config.clone();
+    if !io::stdin().is_terminal() {
+        config.no_confirm = true;
+    }
+    let config = &config;
+    let ignored = if backend::active().removes_with_pacman() {
+        pacman_conf::load().map(|conf| pacman_conf::ignored_installed(&conf)).unwrap_or_else(|e| {
+            eprintln!("{} {}", tr("Error:"), e);
+            Vec::new()
+        })
+    } else {
+        Vec::new()
+    };
+    let mut updates = Vec::new();
+    for source in backend::sources() {
+        match source.check_updates() {
+            Ok(found) => {
+                let (held, found): (Vec<backend::Update>, Vec<backend::Update>) = found.into_iter().partition(|(name, _, _)| config.is_held(name));
+                for (name, installed, available) in held {
+                    eprintln!("{}", fill(tr("Skipping held {} {} ({} available)"), &[&name, &installed, &available]));
+                }
+                let (skipped, found): (Vec<backend::Update>, Vec<backend::Update>) =
+                    found.into_iter().partition(|(name, _, _)| source.removes_with_pacman() && pacman_conf::is_ignored(&ignored, name));
+                for (name, installed, available) in skipped {
+                    eprintln!("{}", fill(tr("Skipping ignored {} {} ({} available)"), &[&name, &installed, &available]));
+                }
+                updates.push((source, found));
+            }
+            Err(e) => {
+                eprintln!("{} {}", tr("Error:"), e);
+                return exit_codes::classify(e.as_ref());
+            }
+        }
+    }
+    if check {
+        for (_, found) in &updates {
+            for (name, installed, available) in found {
+                println!("{} {} -> {}", name, installed, available);
+            }
+        }
+        let any = updates.iter().any(|(_, found)| !found.is_empty());
+        if any && matches.subcommand_matches("update").is_some_and(|sub| sub.get_flag("notify")) {
+            let names: Vec<&str> = updates.iter().flat_map(|(_, found)| found.iter().map(|(name, _, _)| name.as_str())).collect();
+            notifications::send(&fill(tr("{} updates available"), &[&names.len()]), &names.join(", "), "", false);
+        }
+        return if any { ExitCode::UpdatesAvailable } else { ExitCode::Success };
+    }
+
+    let cli = CliOutput::new(matches.get_flag("quiet"));
+    let mut updates = updates.into_iter();
+    let (_, primary) = updates.next().expect("sources always include the active backend");
+    let names: Vec<String> = primary.into_iter().map(|(name, _, _)| name).collect();
+    let mut code = install_packages(&names, config, matches);
+    for (source, found) in updates.filter(|(_, found)| !found.is_empty()) {
+        let names: Vec<String> = found.into_iter().map(|(name, _, _)| name).collect();
+        let result = instance::lock_transactions().and_then(|_lock| source.update(&names, &config.escalation_tool));
+        record_transaction(state::Action::Update, source.name(), &names, &result);
+        match result {
+            Ok(()) => cli.ok(&format!("Updated {} {}", source.label(), names.join(" "))),
+            Err(e) => {
+                cli.fail(&format!("{}: {}", source.label(), e));
+                cli.hints(&troubleshoot::diagnose(&e.to_string(), []));
+                code = exit_codes::classify(e.as_ref());
+            }
+        }
+    }
+    code
 }
 
-fn run_gui() {
-    let state = Arc::new(Mutex::new(AppState::default()));
+fn run_gui(log_events: Option<Receiver<(LogLevel, String)>>) {
+    if instance::focus_running() {
+        info!("Already running; focusing the open window");
+        return;
+    }
+    let mut state = AppState::default();
+    match Config::load() {
+        Ok(config) => state.config = config,
+        Err(e) => state.error = Some(e.to_string()),
+    }
+    // An unknown host leaves this machine active, which the host selector shows
+    if let Err(e) = remote::set_active(&state.config).and_then(|_| backend::select(&state.config)) {
+        state.error = Some(e.to_string());
+    }
+    http::configure(&state.config);
+    state.crash_report = crash::take_pending();
+    state.resumable = pipeline::pending();
+    let saved = state::read();
+    state.favorites = saved.favorites;
+    state.search_history = saved.searches;
+    // Metadata fetched in an earlier session keeps its age, so the TTL still applies to it
+    let now = chrono::Utc::now().timestamp();
+    state.metadata_cache = saved
+        .metadata
+        .into_iter()
+        .filter_map(|(name, cached)| {
+            let age = Duration::from_secs(u64::try_from(now - cached.fetched).ok()?);
+            Some((name, (Instant::now().checked_sub(age)?, cached.package)))
+        })
+        .collect();
+    let check_prerequisites = backend::active().builds_from_source() && state.config.container.is_empty();
+    let check_app_updates = state.config.check_app_updates;
+    let state = Arc::new(Mutex::new(state));
     let rt = Runtime::new().unwrap();
+    // Checked again before every build; offered here so a first build does not fail on them
+    if check_prerequisites {
+        let state = Arc::clone(&state);
+        rt.spawn_blocking(move || match prerequisites::missing() {
+            Ok(missing) if !missing.is_empty() => state.lock().unwrap().missing_prerequisites = Some(missing),
+            Ok(_) => {}
+            Err(e) => debug!("{}", e),
+        });
+    }
+    // Quietly, so that a failed check does not greet the user with an error. Repeats every
+    // update_interval_hours, read again each time so a change in Settings applies to the next wait.
+    {
+        let state = Arc::clone(&state);
+        rt.spawn(async move {
+            loop {
+                let config = state.lock().unwrap().config.clone();
+                let checked = tokio::task::spawn_blocking(move || updates::check(&config).map_err(|e| e.to_string())).await;
+                match checked.unwrap_or_else(|e| Err(e.to_string())) {
+                    Ok(found) => {
+                        let mut state = state.lock().unwrap();
+                        let refreshed = match &state.updates {
+                            Some(previous) => updates::refresh(previous, found),
+                            None => found,
+                        };
+                        state.updates = Some(refreshed);
+                    }
+                    Err(e) => debug!("Could not check for updates in the background: {}", e),
+                }
+                let hours = state.lock().unwrap().config.update_interval_hours.max(1);
+                tokio::time::sleep(Duration::from_secs(u64::from(hours) * 3600)).await;
+            }
+        });
+    }
+    if check_app_updates {
+        let state = Arc::clone(&state);
+        rt.spawn(async move {
+            match app_update::newer_release(app_update::RELEASES_URL).await {
+                Ok(release) => state.lock().unwrap().app_update = release,
+                Err(e) => debug!("Could not check for a newer version of the app: {}", e),
+            }
+        });
+    }
+    // Matches the desktop file, which docks use to find the window and its launcher entry
+    let mut viewport = egui::ViewportBuilder::default().with_app_id(desktop_entry::APP_ID);
+    match eframe::icon_data::from_png_bytes(desktop_entry::ICON_PNG) {
+        Ok(icon) => viewport = viewport.with_icon(icon),
+        Err(e) => debug!("Could not load the window icon: {}", e),
+    }
     let _ = eframe::run_native(
-        "Rust AUR Helper GUI",
+        WINDOW_TITLE,
         eframe::NativeOptions {
+            viewport,
+            follow_system_theme: true,
+            persist_window: true,
             ..Default::default()
         },
         Box::new(move |cc| {
+            egui_extras::install_image_loaders(&cc.egui_ctx);
+            let handoffs = match instance::listen(cc.egui_ctx.clone()) {
+                Ok(handoffs) => Some(handoffs),
+                Err(e) => {
+                    debug!("Later instances cannot reach this one: {}", e);
+                    None
+                }
+            };
+            if let Some(ui_state) = cc.storage.and_then(|storage| eframe::get_value::<UiState>(storage, eframe::APP_KEY)) {
+                let mut state = state.lock().unwrap();
+                state.tab = ui_state.tab;
+                state.package_name = ui_state.package_name;
+                state.run_namcap = ui_state.run_namcap;
+                state.search_by = ui_state.search_by;
+                state.search_match = ui_state.search_match;
+                state.discover_ranking = ui_state.discover_ranking;
+                state.log.min_level = ui_state.log_level;
+                state.log.auto_scroll = ui_state.log_auto_scroll;
+                if state.tab == Tab::Installed {
+                    refresh_installed(&mut state);
+                }
+            }
             Ok(Box::new(MyApp {
                 state: state.clone(),
-                rt: rt,
+                rt,
+                applied_visuals: None,
+                applied_scale: None,
+                applied_font_size: None,
+                zoom_changed: false,
+                log_events,
+                handoffs,
+                notification_actions: Some(notifications::listen(cc.egui_ctx.clone())),
+                shown_progress: (None, 0),
             }))
         }),
     );
 }
 
 fn main() {
+    crash::install();
+    i18n::init();
+    // Answers completion requests from the scripts printed by `completions <shell>`
+    CompleteEnv::with_factory(cli).var(completions::COMPLETE_VAR).complete();
+
     let args: Vec<String> = std::env::args().collect();
-    if args.len() > 1 {
-        run_cli();
-    } else {
-        run_gui();
+    let matches = (args.len() > 1).then(|| cli().get_matches());
+    let dry_run = matches.as_ref().is_some_and(|matches| matches.get_flag("dry-run"));
+    runner::set_dry_run(dry_run);
+    // `--dry-run` with nothing to do opens the GUI in dry-run mode
+    let gui = matches.as_ref().is_none_or(|matches| dry_run && matches.subcommand().is_none() && !matches.contains_id("package"));
+    let stderr_filter = match &matches {
+        Some(matches) if !gui => diagnostics::cli_filter(matches.get_count("verbose"), matches.get_flag("quiet")),
+        _ => "info".to_string(),
+    };
+    let mut diagnostics = diagnostics::init(gui, &stderr_filter)
+        .map_err(|e| eprintln!("Could not set up logging: {}", e))
+        .ok();
+
+    match matches.filter(|_| !gui) {
+        Some(matches) => {
+            let code = run_cli(matches);
+            // process::exit skips destructors, so flush the log file first
+            drop(diagnostics);
+            std::process::exit(code as i32);
+        }
+        None => run_gui(diagnostics.as_mut().and_then(|d| d.gui_events.take())),
     }
 }
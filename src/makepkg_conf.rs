@@ -0,0 +1,111 @@
+use crate::remote;
+use crate::runner::Run;
+use std::error::Error;
+
+// makepkg.conf is a bash script, so bash reads it the way makepkg does: the system file, its
+// drop-ins, then the user's file, with variables from the environment taking precedence
+const SCRIPT: &str = r#"
+env_pkgdest=$PKGDEST env_srcdest=$SRCDEST env_packager=$PACKAGER env_pkgext=$PKGEXT env_makeflags=$MAKEFLAGS
+unset MAKEFLAGS
+for conf in /etc/makepkg.conf /etc/makepkg.conf.d/*.conf; do
+    [ -r "$conf" ] && source "$conf"
+done
+user_conf=${XDG_CONFIG_HOME:-$HOME/.config}/pacman/makepkg.conf
+[ -r "$user_conf" ] || user_conf=$HOME/.makepkg.conf
+[ -r "$user_conf" ] && source "$user_conf"
+printf 'PKGDEST=%s\n' "${env_pkgdest:-$PKGDEST}"
+printf 'SRCDEST=%s\n' "${env_srcdest:-$SRCDEST}"
+printf 'PACKAGER=%s\n' "${env_packager:-$PACKAGER}"
+printf 'PKGEXT=%s\n' "${env_pkgext:-$PKGEXT}"
+printf 'MAKEFLAGS=%s\n' "${MAKEFLAGS-$env_makeflags}"
+printf 'MAKEFLAGS_IN_CONF=%s\n' "${MAKEFLAGS+yes}"
+nproc
+"#;
+
+const DEFAULT_PKGEXT: &str = ".pkg.tar.zst";
+
+// Settings of the active host's makepkg that decide where builds end up
+#[derive(Clone, Default)]
+pub struct MakepkgConf {
+    // Where built packages are written; the build directory when unset
+    pub pkgdest: Option<String>,
+    // Where sources are downloaded; the build directory when unset
+    pub srcdest: Option<String>,
+    pub packager: Option<String>,
+    pub makeflags: Option<String>,
+    // makepkg.conf assigns MAKEFLAGS itself, replacing any value passed in the environment
+    pub makeflags_in_conf: bool,
+    pub pkgext: String,
+    // Processing units of the host that builds
+    pub cores: u32,
+}
+
+pub fn load() -> Result<MakepkgConf, Box<dyn Error>> {
+    let output = remote::command("bash", ["-c", SCRIPT]).run()?;
+    if !output.status.success() {
+        return Err(format!("Could not read makepkg.conf: {}", String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let value = |key: &str| {
+        stdout
+            .lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+    Ok(MakepkgConf {
+        pkgdest: value("PKGDEST"),
+        srcdest: value("SRCDEST"),
+        packager: value("PACKAGER"),
+        makeflags: value("MAKEFLAGS"),
+        makeflags_in_conf: value("MAKEFLAGS_IN_CONF").is_some(),
+        // nproc prints the last line on its own
+        cores: stdout.lines().last().and_then(|line| line.trim().parse().ok()).unwrap_or(1),
+        pkgext: value("PKGEXT").unwrap_or_else(|| DEFAULT_PKGEXT.to_string()),
+    })
+}
+
+impl MakepkgConf {
+    // Directory makepkg writes the packages built in `build_dir` to
+    pub fn package_dir<'a>(&'a self, build_dir: &'a str) -> &'a str {
+        self.pkgdest.as_deref().unwrap_or(build_dir)
+    }
+
+    pub fn sets_jobs(&self) -> bool {
+        self.makeflags.as_deref().is_some_and(|flags| flags.split_whitespace().any(|flag| flag.starts_with("-j") || flag.starts_with("--jobs")))
+    }
+
+    // MAKEFLAGS to pass to makepkg when it should run one job per core, up to `max_jobs` (0 for no cap).
+    // None when makepkg.conf already decides, or would overwrite the environment.
+    pub fn parallel_makeflags(&self, max_jobs: u32) -> Option<String> {
+        if self.sets_jobs() || self.makeflags_in_conf || self.cores == 0 {
+            return None;
+        }
+        let jobs = if max_jobs == 0 { self.cores } else { self.cores.min(max_jobs) };
+        Some(match &self.makeflags {
+            Some(flags) => format!("{} -j{}", flags, jobs),
+            None => format!("-j{}", jobs),
+        })
+    }
+
+    // Settings for a build in a stock archlinux container, on the same host
+    pub fn in_container(&self) -> MakepkgConf {
+        MakepkgConf {
+            pkgext: DEFAULT_PKGEXT.to_string(),
+            cores: self.cores,
+            ..Default::default()
+        }
+    }
+
+    // One line for the build log
+    pub fn describe(&self) -> String {
+        format!(
+            "makepkg: packages in {}, sources in {}, packager {}, MAKEFLAGS {}",
+            self.pkgdest.as_deref().unwrap_or("the build directory"),
+            self.srcdest.as_deref().unwrap_or("the build directory"),
+            self.packager.as_deref().unwrap_or("unset"),
+            self.makeflags.as_deref().unwrap_or("unset"),
+        )
+    }
+}
@@ -0,0 +1,91 @@
+use crate::escalation;
+use crate::runner::Run;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+// What the metapackage dialog asks for
+#[derive(Default)]
+pub struct Draft {
+    pub name: String,
+    pub description: String,
+    pub packages: Vec<String>,
+    pub mark_as_dependencies: bool,
+}
+
+// pacman accepts lowercase letters, digits and @._+- in names, not starting with a hyphen or dot
+pub fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("Enter a name for the metapackage".to_string());
+    }
+    if name.starts_with(['-', '.']) || !name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "@._+-".contains(c)) {
+        return Err(format!("{} is not a valid package name; use lowercase letters, digits and @._+-", name));
+    }
+    Ok(())
+}
+
+// A PKGBUILD that installs nothing itself and depends on `packages`. The version is the time it
+// was made, so that building it again with another set of packages upgrades it.
+pub fn pkgbuild(name: &str, description: &str, packages: &[String], version: &str) -> String {
+    let depends: Vec<String> = packages.iter().map(|package| quote(package)).collect();
+    format!(
+        "# Generated by cookin\npkgname={}\npkgver={}\npkgrel=1\npkgdesc={}\narch=('any')\ndepends=({})\n\npackage() {{\n    :\n}}\n",
+        name,
+        version,
+        quote(description),
+        depends.join(" ")
+    )
+}
+
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+// Writes the PKGBUILD to `build_root`/`name`/`name`, laid out like an AUR snapshot, and returns
+// that directory and the version
+pub fn create(build_root: &str, name: &str, description: &str, packages: &[String]) -> Result<(String, String), Box<dyn Error>> {
+    check_name(name)?;
+    let version = chrono::Local::now().format("%Y%m%d.%H%M%S").to_string();
+    let build_dir = format!("{}/{}/{}", build_root, name, name);
+    // Package files of an earlier version must not be picked up instead of the new one
+    if Path::new(&build_dir).exists() {
+        fs::remove_dir_all(&build_dir)?;
+    }
+    fs::create_dir_all(&build_dir)?;
+    fs::write(Path::new(&build_dir).join("PKGBUILD"), pkgbuild(name, description, packages, &version))?;
+    Ok((build_dir, version))
+}
+
+// Marks `packages` as installed as dependencies, so that removing the metapackage with -Rs takes
+// them along unless something else needs them
+pub fn mark_as_dependencies(packages: &[String], escalation_tool: &str) -> Result<(), Box<dyn Error>> {
+    let args = ["-D", "--asdeps"].iter().map(|arg| arg.to_string()).chain(packages.iter().cloned());
+    let output = escalation::privileged(escalation::direct(escalation_tool), "pacman", args)?.run()?;
+    if !output.status.success() {
+        return Err(format!("pacman -D failed: {}", String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn writes_a_pkgbuild_that_only_depends_on_the_packages() {
+        let root = testing::temp_dir("metapackage");
+        let root = root.to_str().unwrap();
+        assert!(check_name("desktop-essentials").is_ok());
+        assert!(check_name("Desktop").is_err());
+        assert!(create(root, "-rf", "", &[]).is_err());
+
+        let packages = ["firefox".to_string(), "gimp".to_string()];
+        let (build_dir, version) = create(root, "desktop-essentials", "My desktop's apps", &packages).unwrap();
+        assert_eq!(build_dir, format!("{}/desktop-essentials/desktop-essentials", root));
+        let pkgbuild = fs::read_to_string(Path::new(&build_dir).join("PKGBUILD")).unwrap();
+        assert!(pkgbuild.contains(&format!("\npkgver={}\n", version)));
+        assert!(pkgbuild.contains("\npkgdesc='My desktop'\\''s apps'\n"));
+        assert!(pkgbuild.contains("\ndepends=('firefox' 'gimp')\n"));
+    }
+}
@@ -0,0 +1,243 @@
+use crate::{escalation, http, remote};
+use crate::i18n::tr;
+use crate::runner::{self, Run};
+use chrono::Local;
+use eframe::egui;
+use std::error::Error;
+use std::io::Write;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+const STATUS_URL: &str = "https://archlinux.org/mirrors/status/json/";
+pub const MIRRORLIST: &str = "/etc/pacman.d/mirrorlist";
+// The best mirrors by status score are speed tested along with the ones already in use
+const CANDIDATES: usize = 20;
+const WRITTEN: usize = 10;
+// Below this the window suggests regenerating the mirrorlist
+const SLOW_BYTES_PER_SEC: f64 = 1024.0 * 1024.0;
+const TEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone)]
+pub struct Mirror {
+    // Base URL from the status page, e.g. https://mirror.example.org/archlinux/
+    pub url: String,
+    pub country: String,
+    // Lower is better; unset for mirrors that have not synced recently
+    pub score: Option<f64>,
+    pub completion: f64,
+    // Seconds behind the main mirror
+    pub delay: Option<i64>,
+    pub in_mirrorlist: bool,
+    // Bytes per second, once tested
+    pub speed: Option<f64>,
+}
+
+impl Mirror {
+    // Server line form used in the mirrorlist
+    pub fn server(&self) -> String {
+        format!("{}$repo/os/$arch", self.url)
+    }
+}
+
+#[derive(Default)]
+pub struct MirrorsView {
+    pub mirrors: Vec<Mirror>,
+    // Mirrors of the current mirrorlist missing from the status page
+    pub unknown_servers: Vec<String>,
+    pub busy: Option<String>,
+    pub error: Option<String>,
+}
+
+impl MirrorsView {
+    pub fn is_slow(&self) -> bool {
+        let speeds: Vec<f64> = self.mirrors.iter().filter(|mirror| mirror.in_mirrorlist).filter_map(|mirror| mirror.speed).collect();
+        !speeds.is_empty() && speeds.iter().all(|speed| *speed < SLOW_BYTES_PER_SEC)
+    }
+
+    // The mirrors already in use, then the best scored https mirrors that are fully synced
+    pub fn speed_test_candidates(&self) -> Vec<String> {
+        let mut candidates: Vec<&Mirror> = self.mirrors.iter().filter(|mirror| mirror.in_mirrorlist).collect();
+        let mut scored: Vec<&Mirror> = self
+            .mirrors
+            .iter()
+            .filter(|mirror| !mirror.in_mirrorlist && mirror.url.starts_with("https://") && mirror.completion >= 1.0)
+            .filter(|mirror| mirror.score.is_some())
+            .collect();
+        scored.sort_by(|a, b| a.score.unwrap_or(f64::MAX).total_cmp(&b.score.unwrap_or(f64::MAX)));
+        candidates.extend(scored.into_iter().take(CANDIDATES));
+        candidates.into_iter().map(|mirror| mirror.url.clone()).collect()
+    }
+
+    // Fastest tested mirrors first
+    pub fn ranked(&self) -> Vec<&Mirror> {
+        let mut tested: Vec<&Mirror> = self.mirrors.iter().filter(|mirror| mirror.speed.is_some()).collect();
+        tested.sort_by(|a, b| b.speed.unwrap_or(0.0).total_cmp(&a.speed.unwrap_or(0.0)));
+        tested.into_iter().take(WRITTEN).collect()
+    }
+}
+
+pub enum MirrorsAction {
+    CheckStatus,
+    TestSpeed,
+    WriteMirrorlist,
+    Close,
+}
+
+// `Server = ` lines of the active host's mirrorlist
+pub fn current_servers() -> Result<Vec<String>, Box<dyn Error>> {
+    let output = remote::command("cat", [MIRRORLIST]).run()?;
+    if !output.status.success() {
+        return Err(format!("Could not read {}: {}", MIRRORLIST, String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            (key.trim() == "Server").then(|| value.trim().to_string())
+        })
+        .collect())
+}
+
+pub async fn status() -> Result<MirrorsView, Box<dyn Error>> {
+    let servers = current_servers().map_err(|e| e.to_string())?;
+    let json = http::get_json::<serde_json::Value>(STATUS_URL).await?;
+    let mirrors: Vec<Mirror> = json["urls"]
+        .as_array()
+        .ok_or("Unexpected mirror status response")?
+        .iter()
+        .filter(|mirror| mirror["active"].as_bool().unwrap_or(false))
+        .filter(|mirror| matches!(mirror["protocol"].as_str(), Some("https" | "http")))
+        .map(|mirror| {
+            let url = mirror["url"].as_str().unwrap_or("").to_string();
+            Mirror {
+                in_mirrorlist: servers.iter().any(|server| server.starts_with(&url)),
+                url,
+                country: mirror["country"].as_str().unwrap_or("").to_string(),
+                score: mirror["score"].as_f64(),
+                completion: mirror["completion_pct"].as_f64().unwrap_or(0.0),
+                delay: mirror["delay"].as_i64(),
+                speed: None,
+            }
+        })
+        .collect();
+    let unknown_servers = servers.into_iter().filter(|server| !mirrors.iter().any(|mirror| server.starts_with(&mirror.url))).collect();
+    Ok(MirrorsView {
+        mirrors,
+        unknown_servers,
+        ..Default::default()
+    })
+}
+
+// Times a download of the core database, which every mirror carries; measured from this machine
+pub async fn measure(url: &str) -> Result<f64, Box<dyn Error>> {
+    let client = http::builder().timeout(TEST_TIMEOUT).build()?;
+    let started = Instant::now();
+    let bytes = client.get(format!("{}core/os/x86_64/core.db", url)).send().await?.error_for_status()?.bytes().await?;
+    Ok(bytes.len() as f64 / started.elapsed().as_secs_f64().max(0.001))
+}
+
+// Keeps a backup next to the old list; tee runs with escalation so it also works on remote hosts
+pub fn write_mirrorlist(servers: &[String], escalation_tool: &str) -> Result<(), Box<dyn Error>> {
+    let tool = escalation::direct(escalation_tool);
+    let backup = escalation::privileged(tool, "cp", [MIRRORLIST, &format!("{}.bak", MIRRORLIST)])?.run()?;
+    if !backup.status.success() {
+        return Err(format!("Could not back up {}: {}", MIRRORLIST, String::from_utf8_lossy(&backup.stderr).trim()).into());
+    }
+
+    let mut content = format!("# Ranked by speed with cookin on {}\n", Local::now().format("%Y-%m-%d %H:%M"));
+    for server in servers {
+        content.push_str(&format!("Server = {}\n", server));
+    }
+    let mut tee = escalation::privileged(tool, "tee", [MIRRORLIST])?;
+    if runner::simulate(&tee) {
+        return Ok(());
+    }
+    let mut tee = tee
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    tee.stdin.take().ok_or("Could not open tee's input")?.write_all(content.as_bytes())?;
+    let output = tee.wait_with_output()?;
+    if !output.status.success() {
+        return Err(format!("Could not write {}: {}", MIRRORLIST, String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(())
+}
+
+fn format_speed(bytes_per_sec: f64) -> String {
+    if bytes_per_sec >= 1024.0 * 1024.0 {
+        format!("{:.1} MiB/s", bytes_per_sec / 1024.0 / 1024.0)
+    } else {
+        format!("{:.0} KiB/s", bytes_per_sec / 1024.0)
+    }
+}
+
+pub fn show(ctx: &egui::Context, view: &MirrorsView) -> Option<MirrorsAction> {
+    let mut action = None;
+    let mut open = true;
+
+    egui::Window::new(tr("Mirrors")).open(&mut open).default_size([640.0, 420.0]).show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(view.busy.is_none(), |ui| {
+                if ui.button(tr("Check status")).clicked() {
+                    action = Some(MirrorsAction::CheckStatus);
+                }
+                if ui.add_enabled(!view.mirrors.is_empty(), egui::Button::new(tr("Test speed"))).clicked() {
+                    action = Some(MirrorsAction::TestSpeed);
+                }
+                let ranked = view.ranked().len();
+                if ui.add_enabled(ranked > 0, egui::Button::new(format!("Write {} fastest to mirrorlist", ranked))).clicked() {
+                    action = Some(MirrorsAction::WriteMirrorlist);
+                }
+            });
+            if let Some(busy) = &view.busy {
+                ui.spinner();
+                ui.label(busy);
+            }
+        });
+        if let Some(error) = &view.error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        if view.is_slow() {
+            ui.colored_label(ui.visuals().warn_fg_color, "Every mirror in your mirrorlist is slow. Consider writing the fastest tested ones.");
+        }
+        for server in &view.unknown_servers {
+            ui.weak(format!("{} is not on the status page.", server));
+        }
+
+        // Mirrors in use first, then by score
+        let mut rows: Vec<&Mirror> = view.mirrors.iter().filter(|mirror| mirror.in_mirrorlist || mirror.speed.is_some()).collect();
+        rows.sort_by(|a, b| b.in_mirrorlist.cmp(&a.in_mirrorlist).then(a.score.unwrap_or(f64::MAX).total_cmp(&b.score.unwrap_or(f64::MAX))));
+        egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+            egui::Grid::new("mirrors_grid").num_columns(6).striped(true).show(ui, |ui| {
+                for header in ["Mirror", "Country", "Score", "Behind", "Speed", ""] {
+                    ui.strong(header);
+                }
+                ui.end_row();
+                for mirror in rows {
+                    ui.label(&mirror.url);
+                    ui.label(&mirror.country);
+                    ui.label(mirror.score.map(|score| format!("{:.2}", score)).unwrap_or_else(|| "-".to_string()));
+                    match mirror.delay {
+                        Some(delay) if delay > 6 * 3600 => ui.colored_label(ui.visuals().warn_fg_color, format!("{} h", delay / 3600)),
+                        Some(delay) => ui.label(format!("{} min", delay / 60)),
+                        None => ui.weak(tr("never synced")),
+                    };
+                    ui.label(mirror.speed.map(format_speed).unwrap_or_else(|| "-".to_string()));
+                    if mirror.in_mirrorlist {
+                        ui.weak(tr("in use"));
+                    } else {
+                        ui.label("");
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    });
+
+    if !open {
+        action = Some(MirrorsAction::Close);
+    }
+    action
+}
@@ -0,0 +1,54 @@
+use crate::runner::Run;
+use std::error::Error;
+use std::process::Command as StdCommand;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum NamcapLevel {
+    Error,
+    Warning,
+    Info,
+}
+
+impl NamcapLevel {
+    pub fn label(&self) -> &'static str {
+        match self {
+            NamcapLevel::Error => "E",
+            NamcapLevel::Warning => "W",
+            NamcapLevel::Info => "I",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct NamcapMessage {
+    pub level: NamcapLevel,
+    pub target: String,
+    pub text: String,
+}
+
+pub fn is_available() -> bool {
+    StdCommand::new("namcap").arg("--version").run().is_ok()
+}
+
+// namcap prints lines like `PKGBUILD (foo) W: Missing Maintainer tag` or `foo E: Dependency bar detected and not included`
+fn parse_line(line: &str) -> Option<NamcapMessage> {
+    let (level, marker) = [(NamcapLevel::Error, " E: "), (NamcapLevel::Warning, " W: "), (NamcapLevel::Info, " I: ")]
+        .into_iter()
+        .find(|(_, marker)| line.contains(marker))?;
+    let (target, text) = line.split_once(marker)?;
+    Some(NamcapMessage {
+        level,
+        target: target.trim().to_string(),
+        text: text.trim().to_string(),
+    })
+}
+
+pub fn run_namcap(target: &str, cwd: &str) -> Result<Vec<NamcapMessage>, Box<dyn Error>> {
+    tracing::debug!("Running namcap on {}", target);
+    let output = StdCommand::new("namcap")
+        .args(["-i", target])
+        .current_dir(cwd)
+        .run()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.lines().filter_map(parse_line).collect())
+}
@@ -0,0 +1,57 @@
+use eframe::egui;
+use std::process::Command as StdCommand;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+
+// Clicked in a notification about `package`, for the GUI to act on
+pub enum Action {
+    ViewLog(String),
+    Retry(String),
+}
+
+// Where clicked actions go; only set while the GUI runs, so the CLI sends plain notifications
+static LISTENER: Mutex<Option<(Sender<Action>, egui::Context)>> = Mutex::new(None);
+
+pub fn listen(ctx: egui::Context) -> Receiver<Action> {
+    let (sender, receiver) = mpsc::channel();
+    *LISTENER.lock().unwrap() = Some((sender, ctx));
+    receiver
+}
+
+// Shows a notification about `package`; in the GUI it offers "View log", and "Retry" with `retry`
+pub fn send(summary: &str, body: &str, package: &str, retry: bool) {
+    let plain = |summary: &str, body: &str| StdCommand::new("notify-send").args(["--app-name=AUR Helper", summary, body]).spawn().map(drop);
+    let Some((sender, ctx)) = LISTENER.lock().unwrap().clone() else {
+        // Notifications are best-effort; notify-send may not be installed
+        let _ = plain(summary, body);
+        return;
+    };
+    let mut command = StdCommand::new("notify-send");
+    command.args(["--app-name=AUR Helper", "--action=log=View log"]);
+    if retry {
+        command.arg("--action=retry=Retry");
+    }
+    command.args([summary, body]);
+    let (summary, body, package) = (summary.to_string(), body.to_string(), package.to_string());
+    // notify-send waits for the notification to be clicked or closed, then prints the action
+    thread::spawn(move || {
+        let output = match command.output() {
+            Ok(output) => output,
+            Err(_) => return,
+        };
+        if !output.status.success() {
+            // Before libnotify 0.7.10, notify-send has no actions
+            let _ = plain(&summary, &body);
+            return;
+        }
+        let action = match String::from_utf8_lossy(&output.stdout).trim() {
+            "log" => Action::ViewLog(package),
+            "retry" => Action::Retry(package),
+            _ => return,
+        };
+        if sender.send(action).is_ok() {
+            ctx.request_repaint();
+        }
+    });
+}
@@ -0,0 +1,255 @@
+use crate::{escalation, remote};
+use crate::runner::{self, Run};
+use std::error::Error;
+use std::io::Write;
+use std::process::Stdio;
+
+pub const PATH: &str = "/etc/pacman.conf";
+
+// Repositories that official packages depend on, with what their packages look like
+const OPTIONAL_REPOS: [(&str, &str); 1] = [("multilib", "lib32-")];
+
+// The settings from pacman.conf that explain what pacman can see and install
+#[derive(Clone, Default)]
+pub struct PacmanConf {
+    // Enabled repositories in priority order
+    pub repos: Vec<String>,
+    pub ignore_pkg: Vec<String>,
+    pub ignore_group: Vec<String>,
+    pub parallel_downloads: Option<u32>,
+    // pacman falls back to /var/cache/pacman/pkg/ when unset
+    pub cache_dirs: Vec<String>,
+}
+
+// Reads the active host's pacman.conf; Include directives are not followed, as they only add servers
+pub fn load() -> Result<PacmanConf, Box<dyn Error>> {
+    let output = remote::command("cat", [PATH]).run()?;
+    if !output.status.success() {
+        return Err(format!("Could not read {}: {}", PATH, String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(parse(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse(content: &str) -> PacmanConf {
+    let mut conf = PacmanConf::default();
+    let mut section = String::new();
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            section = name.to_string();
+            if section != "options" {
+                conf.repos.push(section.clone());
+            }
+            continue;
+        }
+        if section != "options" {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        // List options may be repeated, each occurrence adding to the list
+        let values = value.split_whitespace().map(str::to_string);
+        match key.trim() {
+            "IgnorePkg" => conf.ignore_pkg.extend(values),
+            "IgnoreGroup" => conf.ignore_group.extend(values),
+            "CacheDir" => conf.cache_dirs.extend(values),
+            "ParallelDownloads" => conf.parallel_downloads = value.trim().parse().ok(),
+            _ => {}
+        }
+    }
+    conf
+}
+
+impl PacmanConf {
+    // Why pacman may not find `package`, when the configuration explains it
+    pub fn explain_missing(&self, package: &str) -> Option<String> {
+        if is_ignored(&self.ignore_pkg, package) {
+            return Some(format!("{} is listed in IgnorePkg in {}.", package, PATH));
+        }
+        OPTIONAL_REPOS.iter().find_map(|(repo, prefix)| {
+            (package.starts_with(prefix) && !self.repos.iter().any(|enabled| enabled == repo))
+                .then(|| format!("{} comes from the [{}] repository, which is not enabled in {}.", package, repo, PATH))
+        })
+    }
+}
+
+// Installed packages pacman leaves out of upgrades: those in IgnorePkg and the installed members
+// of the IgnoreGroup groups
+pub fn ignored_installed(conf: &PacmanConf) -> Vec<String> {
+    let mut ignored = conf.ignore_pkg.clone();
+    if !conf.ignore_group.is_empty() {
+        // Exits with an error when none of the groups has an installed member
+        let args = std::iter::once("-Qgq").chain(conf.ignore_group.iter().map(String::as_str));
+        if let Some(output) = remote::command("pacman", args).run().ok().filter(|output| output.status.success()) {
+            ignored.extend(String::from_utf8_lossy(&output.stdout).lines().map(str::to_string));
+        }
+    }
+    ignored
+}
+
+// IgnorePkg and IgnoreGroup take shell wildcards, e.g. "linux*"; `patterns` may mix them with
+// plain names
+pub fn is_ignored(patterns: &[String], package: &str) -> bool {
+    patterns.iter().any(|pattern| matches_glob(pattern.as_bytes(), package.as_bytes()))
+}
+
+// fnmatch as pacman uses it: `*`, `?` and bracket expressions such as [a-z] or [!0-9]
+fn matches_glob(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some(b'*') => (0..=name.len()).any(|skip| matches_glob(&pattern[1..], &name[skip..])),
+        Some(b'?') => !name.is_empty() && matches_glob(&pattern[1..], &name[1..]),
+        Some(b'[') => {
+            let Some(close) = pattern.iter().skip(2).position(|&byte| byte == b']').map(|position| position + 2) else {
+                return name.first() == Some(&b'[') && matches_glob(&pattern[1..], &name[1..]);
+            };
+            let Some(&byte) = name.first() else {
+                return false;
+            };
+            let (negated, set) = match pattern[1] {
+                b'!' | b'^' => (true, &pattern[2..close]),
+                _ => (false, &pattern[1..close]),
+            };
+            let mut found = false;
+            let mut index = 0;
+            while index < set.len() {
+                if index + 2 < set.len() && set[index + 1] == b'-' {
+                    found |= (set[index]..=set[index + 2]).contains(&byte);
+                    index += 3;
+                } else {
+                    found |= set[index] == byte;
+                    index += 1;
+                }
+            }
+            found != negated && matches_glob(&pattern[close + 1..], &name[1..])
+        }
+        Some(&literal) => name.first() == Some(&literal) && matches_glob(&pattern[1..], &name[1..]),
+    }
+}
+
+// Adds `packages` to IgnorePkg or takes them out, leaving the rest of the file as it is
+pub fn set_ignored(packages: &[String], ignored: bool, escalation_tool: &str) -> Result<(), Box<dyn Error>> {
+    let output = remote::command("cat", [PATH]).run()?;
+    if !output.status.success() {
+        return Err(format!("Could not read {}: {}", PATH, String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    let content = String::from_utf8_lossy(&output.stdout);
+    let updated = packages.iter().fold(content.to_string(), |content, package| with_ignored(&content, package, ignored));
+    if updated == content {
+        return Ok(());
+    }
+
+    let tool = escalation::direct(escalation_tool);
+    let backup = escalation::privileged(tool, "cp", [PATH, &format!("{}.bak", PATH)])?.run()?;
+    if !backup.status.success() {
+        return Err(format!("Could not back up {}: {}", PATH, String::from_utf8_lossy(&backup.stderr).trim()).into());
+    }
+    let mut tee = escalation::privileged(tool, "tee", [PATH])?;
+    if runner::simulate(&tee) {
+        return Ok(());
+    }
+    let mut tee = tee.stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::piped()).spawn()?;
+    tee.stdin.take().ok_or("Could not open tee's input")?.write_all(updated.as_bytes())?;
+    let output = tee.wait_with_output()?;
+    if !output.status.success() {
+        return Err(format!("Could not write {}: {}", PATH, String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(())
+}
+
+// Edits the IgnorePkg lines of [options]; a new one goes right below the section header
+fn with_ignored(content: &str, package: &str, ignored: bool) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut section = String::new();
+    let mut options_header = None;
+    let mut first_ignore_line = None;
+    let mut listed = false;
+    for line in content.lines() {
+        let (setting, comment) = match line.find('#') {
+            Some(index) => line.split_at(index),
+            None => (line, ""),
+        };
+        let trimmed = setting.trim();
+        if let Some(name) = trimmed.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            section = name.to_string();
+            if section == "options" {
+                options_header = Some(lines.len());
+            }
+        }
+        let values = trimmed.split_once('=').filter(|(key, _)| section == "options" && key.trim() == "IgnorePkg").map(|(_, values)| values);
+        let Some(values) = values else {
+            lines.push(line.to_string());
+            continue;
+        };
+        let mut packages: Vec<&str> = values.split_whitespace().collect();
+        first_ignore_line.get_or_insert(lines.len());
+        if !packages.contains(&package) {
+            lines.push(line.to_string());
+            continue;
+        }
+        listed = true;
+        if ignored {
+            lines.push(line.to_string());
+            continue;
+        }
+        packages.retain(|listed| *listed != package);
+        // A line only listing the package goes away with it
+        if packages.is_empty() && comment.is_empty() {
+            continue;
+        }
+        lines.push(format!("IgnorePkg = {}{}", packages.join(" "), if comment.is_empty() { String::new() } else { format!(" {}", comment) }));
+    }
+    if ignored && !listed {
+        match (first_ignore_line, options_header) {
+            (Some(index), _) => {
+                let line = &lines[index];
+                let (setting, comment) = line.split_at(line.find('#').unwrap_or(line.len()));
+                lines[index] = format!("{} {}{}", setting.trim_end(), package, if comment.is_empty() { String::new() } else { format!(" {}", comment) });
+            }
+            (None, Some(index)) => lines.insert(index + 1, format!("IgnorePkg = {}", package)),
+            (None, None) => {
+                lines.insert(0, "[options]".to_string());
+                lines.insert(1, format!("IgnorePkg = {}", package));
+            }
+        }
+    }
+    let mut updated = lines.join("\n");
+    if content.ends_with('\n') {
+        updated.push('\n');
+    }
+    updated
+}
+
+// Packages pacman reported as missing in build output, e.g. "error: target not found: lib32-glibc"
+pub fn missing_targets(lines: impl Iterator<Item = String>) -> Vec<String> {
+    let mut targets: Vec<String> = lines
+        .filter_map(|line| line.split_once("target not found:").map(|(_, target)| target.trim().to_string()))
+        .collect();
+    targets.dedup();
+    targets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Scripted};
+
+    #[test]
+    fn ignores_listed_packages_and_members_of_ignored_groups() {
+        let _isolated = testing::isolate_blocking();
+        Scripted::default().answer("pacman -Qgq kde-applications", "dolphin\nkate\n").install();
+
+        let conf = parse("[options]\nIgnorePkg = linux yay-bin\nIgnoreGroup = kde-applications # for now\n\n[core]\nIgnorePkg = not-options\n");
+        assert_eq!(ignored_installed(&conf), ["linux", "yay-bin", "dolphin", "kate"]);
+        assert_eq!(ignored_installed(&PacmanConf::default()), Vec::<String>::new());
+
+        let patterns = ["linux*".to_string(), "nvidia-[0-9]?0xx".to_string(), "yay-bin".to_string()];
+        assert!(is_ignored(&patterns, "linux-zen-headers"));
+        assert!(is_ignored(&patterns, "nvidia-470xx"));
+        assert!(is_ignored(&patterns, "yay-bin"));
+        assert!(!is_ignored(&patterns, "yay"));
+        assert!(!is_ignored(&patterns, "nvidia-utils"));
+        assert!(!is_ignored(&["[!l]*".to_string()], "linux"));
+    }
+}
@@ -0,0 +1,54 @@
+use crate::backend::{self, Update};
+use crate::remote;
+use crate::runner::Run;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// What to do when installing now would be a partial upgrade
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Choice {
+    UpgradeFirst,
+    Continue,
+    Cancel,
+}
+
+// Set once the user goes on without upgrading, so that every queued package does not ask again
+static ACCEPTED: AtomicBool = AtomicBool::new(false);
+
+pub fn accept() {
+    ACCEPTED.store(true, Ordering::Relaxed);
+}
+
+pub fn accepted() -> bool {
+    ACCEPTED.load(Ordering::Relaxed)
+}
+
+// Installed packages that the local sync databases have newer versions of, leaving out those
+// pacman.conf ignores and `held` ones. Any means the databases were synced, e.g. by `pacman -Sy`,
+// without upgrading, so installing from them now is a partial upgrade: a new package may need
+// libraries newer than the installed ones.
+pub fn pending(held: impl Fn(&str) -> bool) -> Vec<Update> {
+    // Exits with an error when nothing is outdated
+    let Ok(output) = remote::command("pacman", ["-Qu"]).run() else {
+        return Vec::new();
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().filter(|line| !line.ends_with("[ignored]")).collect();
+    backend::read_updates(&lines.join("\n")).into_iter().filter(|(name, _, _)| !held(name)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Scripted};
+
+    #[test]
+    fn finds_installed_packages_behind_the_sync_databases() {
+        let _isolated = testing::isolate_blocking();
+        Scripted::default().answer("pacman -Qu", "glibc 2.39-1 -> 2.40-1\nlinux 6.9.1-1 -> 6.9.2-1\nvim 9.1-1 -> 9.1-2 [ignored]\n").install();
+        let outdated = pending(|name| name == "linux");
+        assert_eq!(outdated, vec![("glibc".to_string(), "2.39-1".to_string(), "2.40-1".to_string())]);
+
+        Scripted::default().fail("pacman -Qu", "").install();
+        assert!(pending(|_| false).is_empty());
+    }
+}
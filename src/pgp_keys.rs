@@ -0,0 +1,185 @@
+use crate::remote;
+use crate::runner::Run;
+use crate::scan::{Finding, Severity};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+pub const DEFAULT_KEYSERVER: &str = "hkps://keyserver.ubuntu.com";
+
+// A key this app imported for verifying the sources of a PKGBUILD's validpgpkeys
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ImportedKey {
+    pub fingerprint: String,
+    // Packages whose builds needed it, in the order they did
+    pub packages: Vec<String>,
+    // Unix time of the import
+    pub imported: i64,
+    // Deleted from the keyring and never imported again, so builds needing it fail to verify
+    #[serde(default)]
+    pub revoked: bool,
+}
+
+fn registry_path() -> Result<PathBuf, Box<dyn Error>> {
+    let data_dir = dirs::data_dir().ok_or("Could not determine data directory")?;
+    Ok(data_dir.join("aur-helper").join("pgp-keys.json"))
+}
+
+pub fn list() -> Vec<ImportedKey> {
+    registry_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn store(keys: &[ImportedKey]) -> Result<(), Box<dyn Error>> {
+    let path = registry_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(keys)?)?;
+    Ok(())
+}
+
+// A full fingerprint or a long key ID. Anything else in validpgpkeys, such as a value starting with
+// `--`, would reach gpg's command line as something other than a key.
+pub fn is_key_id(value: &str) -> bool {
+    matches!(value.len(), 16 | 40) && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// The validpgpkeys entries of a .SRCINFO that are not keys; import_missing skips them
+pub fn check(srcinfo: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (index, line) in srcinfo.lines().enumerate() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        if key.trim() == "validpgpkeys" && !is_key_id(value) {
+            findings.push(Finding {
+                severity: Severity::High,
+                file: ".SRCINFO".to_string(),
+                line: index + 1,
+                message: "validpgpkeys entry is not a PGP fingerprint or key ID; it is not passed to gpg".to_string(),
+                snippet: line.trim().to_string(),
+            });
+        }
+    }
+    findings
+}
+
+fn in_keyring(fingerprint: &str) -> bool {
+    remote::command("gpg", ["--batch", "--list-keys", fingerprint]).run().is_ok_and(|output| output.status.success())
+}
+
+// Imports the keys of `fingerprints` that are not in the build user's keyring from `keyserver`,
+// recording that `package` needed them. Returns the fingerprints imported. Fails on a revoked key
+// rather than letting makepkg fail on it later. Values that are not keys are left to the review,
+// where `check` reports them.
+pub fn import_missing(fingerprints: &[&str], package: &str, keyserver: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut keys = list();
+    let mut imported = Vec::new();
+    for fingerprint in fingerprints.iter().filter(|fingerprint| is_key_id(fingerprint)) {
+        if let Some(key) = keys.iter().find(|key| key.fingerprint == *fingerprint && key.revoked) {
+            return Err(format!("{} needs the PGP key {}, which was revoked in the key list", package, key.fingerprint).into());
+        }
+        if !in_keyring(fingerprint) {
+            let output = remote::command("gpg", ["--batch", "--keyserver", keyserver, "--recv-keys", fingerprint]).run()?;
+            if !output.status.success() {
+                return Err(format!("Could not import the PGP key {} from {}: {}", fingerprint, keyserver, String::from_utf8_lossy(&output.stderr).trim()).into());
+            }
+            imported.push(fingerprint.to_string());
+            keys.retain(|key| key.fingerprint != *fingerprint);
+            keys.push(ImportedKey { fingerprint: fingerprint.to_string(), packages: Vec::new(), imported: chrono::Utc::now().timestamp(), revoked: false });
+        }
+        // Keys that were in the keyring before are not the app's to list
+        if let Some(key) = keys.iter_mut().find(|key| key.fingerprint == *fingerprint) {
+            if !key.packages.iter().any(|name| name == package) {
+                key.packages.push(package.to_string());
+            }
+        }
+    }
+    store(&keys)?;
+    Ok(imported)
+}
+
+fn delete_from_keyring(fingerprint: &str) -> Result<(), Box<dyn Error>> {
+    if !in_keyring(fingerprint) {
+        return Ok(());
+    }
+    let output = remote::command("gpg", ["--batch", "--yes", "--delete-keys", fingerprint]).run()?;
+    if !output.status.success() {
+        return Err(format!("Could not delete the PGP key {}: {}", fingerprint, String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(())
+}
+
+// Deletes the key and forgets it; a later build that needs it imports it again
+pub fn remove(fingerprint: &str) -> Result<(), Box<dyn Error>> {
+    delete_from_keyring(fingerprint)?;
+    let mut keys = list();
+    keys.retain(|key| key.fingerprint != fingerprint);
+    store(&keys)
+}
+
+// Deletes the key and keeps it from being imported again
+pub fn revoke(fingerprint: &str) -> Result<(), Box<dyn Error>> {
+    delete_from_keyring(fingerprint)?;
+    let mut keys = list();
+    for key in keys.iter_mut().filter(|key| key.fingerprint == fingerprint) {
+        key.revoked = true;
+    }
+    store(&keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Scripted};
+
+    const KEY: &str = "ABAF11C65A2970B130ABE3C479BE3E4300411886";
+    const OWN_KEY: &str = "6E3CCB4F4B6F4A1E2B0D2D5B1F1E0F0E0D0C0B0A";
+
+    #[test]
+    fn imports_missing_keys_and_refuses_revoked_ones() {
+        let _isolated = testing::isolate_blocking();
+        let _ = fs::remove_file(registry_path().unwrap());
+        let keyserver = "hkps://keys.example.org";
+        Scripted::default()
+            .fail(&format!("gpg --batch --list-keys {}", KEY), "gpg: error reading key: No public key")
+            .answer(&format!("gpg --batch --keyserver {} --recv-keys {}", keyserver, KEY), "")
+            .answer(&format!("gpg --batch --list-keys {}", OWN_KEY), "pub rsa4096")
+            .install();
+
+        assert_eq!(import_missing(&[KEY, OWN_KEY], "linux-mainline", keyserver).unwrap(), [KEY]);
+        let keys = list();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].packages, ["linux-mainline"]);
+
+        Scripted::default().fail(&format!("gpg --batch --list-keys {}", KEY), "").install();
+        revoke(KEY).unwrap();
+        assert!(list()[0].revoked);
+        assert!(import_missing(&[KEY], "linux-next", keyserver).is_err());
+        remove(KEY).unwrap();
+        assert!(list().is_empty());
+    }
+
+    #[test]
+    fn leaves_values_that_are_not_keys_to_the_review() {
+        let _isolated = testing::isolate_blocking();
+        let _ = fs::remove_file(registry_path().unwrap());
+        let gpg = Scripted::default().install();
+
+        let option = "--homedir=/tmp/evil";
+        assert!(import_missing(&[option, "79BE3E4300411886x"], "linux-mainline", DEFAULT_KEYSERVER).unwrap().is_empty());
+        assert!(list().is_empty());
+        assert!(gpg.calls().is_empty());
+
+        let findings = check(&format!("pkgbase = linux-mainline\n\tvalidpgpkeys = {}\n\tvalidpgpkeys = 79BE3E4300411886\n\tvalidpgpkeys = {}\n", KEY, option));
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].line, 4);
+        assert!(findings[0].snippet.contains(option));
+    }
+}
@@ -0,0 +1,126 @@
+use crate::diagnostics;
+use crate::remote;
+use crate::review::PendingReview;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// The last step an install got through, saved so that it can be resumed after the app was closed
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum Stage {
+    // Sources downloaded and extracted, waiting for the review
+    Extracted,
+    // Review accepted; the build had started
+    Reviewed,
+    // Package files built, waiting to be installed
+    Built,
+}
+
+impl Stage {
+    pub fn label(self) -> &'static str {
+        match self {
+            Stage::Extracted => "downloaded, not reviewed yet",
+            Stage::Reviewed => "reviewed, build not finished",
+            Stage::Built => "built, not installed yet",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Checkpoint {
+    pub package: String,
+    pub package_base: String,
+    pub version: String,
+    // Where the sources were extracted and reviewed, always on this machine
+    pub build_dir: String,
+    pub stage: Stage,
+    pub selected_packages: Vec<String>,
+    pub built_files: Vec<String>,
+    // Remote host the package is built on; None for this machine
+    pub host: Option<String>,
+}
+
+fn dir() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(diagnostics::state_dir()?.join("pipelines"))
+}
+
+fn path(package: &str) -> Result<PathBuf, Box<dyn Error>> {
+    Ok(dir()?.join(format!("{}.json", package)))
+}
+
+pub fn save(review: &PendingReview, stage: Stage, built_files: &[String]) -> Result<(), Box<dyn Error>> {
+    let checkpoint = Checkpoint {
+        package: review.package.clone(),
+        package_base: review.package_base.clone(),
+        version: review.version.clone(),
+        build_dir: review.build_dir.clone(),
+        stage,
+        selected_packages: review.selected_packages.clone(),
+        built_files: built_files.to_vec(),
+        host: remote::active().map(|(name, _)| name),
+    };
+    fs::create_dir_all(dir()?)?;
+    fs::write(path(&review.package)?, serde_json::to_string(&checkpoint)?)?;
+    Ok(())
+}
+
+// The install of `package` finished or was given up
+pub fn clear(package: &str) {
+    if let Ok(path) = path(package) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+// Installs that were interrupted and whose sources are still there; checkpoints of sources that
+// are gone, e.g. from a build directory in /tmp after a reboot, are dropped
+pub fn pending() -> Vec<Checkpoint> {
+    let Ok(entries) = dir().and_then(|dir| Ok(fs::read_dir(dir)?)) else {
+        return Vec::new();
+    };
+    let mut checkpoints: Vec<Checkpoint> = Vec::new();
+    for path in entries.flatten().map(|entry| entry.path()) {
+        match fs::read_to_string(&path).ok().and_then(|content| serde_json::from_str::<Checkpoint>(&content).ok()) {
+            Some(checkpoint) if Path::new(&checkpoint.build_dir).join("PKGBUILD").is_file() => checkpoints.push(checkpoint),
+            _ => {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+    checkpoints.sort_by(|a, b| a.package.cmp(&b.package));
+    checkpoints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn keeps_checkpoints_while_their_sources_exist() {
+        let _isolated = testing::isolate_blocking();
+        let build_dir = testing::temp_dir("pipeline").join("foo");
+        fs::create_dir_all(&build_dir).unwrap();
+        fs::write(build_dir.join("PKGBUILD"), "pkgname=foo\n").unwrap();
+        let review = PendingReview {
+            package: "foo".to_string(),
+            package_base: "foo".to_string(),
+            version: "1.0-1".to_string(),
+            build_dir: build_dir.to_string_lossy().into_owned(),
+            files: Vec::new(),
+            findings: Vec::new(),
+            integrity: Vec::new(),
+            split_packages: vec!["foo".to_string()],
+            selected_packages: vec!["foo".to_string()],
+        };
+
+        save(&review, Stage::Built, &["/tmp/foo-1.0-1-any.pkg.tar.zst".to_string()]).unwrap();
+        let checkpoints = pending();
+        assert_eq!(checkpoints.len(), 1);
+        assert!(checkpoints[0].stage == Stage::Built && checkpoints[0].built_files == ["/tmp/foo-1.0-1-any.pkg.tar.zst"]);
+
+        fs::remove_dir_all(&build_dir).unwrap();
+        assert!(pending().is_empty());
+        assert!(!path("foo").unwrap().exists());
+    }
+}
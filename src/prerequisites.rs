@@ -0,0 +1,63 @@
+use crate::remote;
+use crate::runner::Run;
+use crate::srcinfo::SrcInfo;
+use std::error::Error;
+
+// Packages every AUR build needs, with what they are needed for
+const PACKAGES: [(&str, &str); 2] = [
+    ("base-devel", "fakeroot, binutils, gcc, make and the other tools every PKGBUILD assumes"),
+    ("git", "fetches the sources of -git packages and of many others"),
+];
+
+// Prerequisites that are not installed on the active host
+pub fn missing() -> Result<Vec<String>, Box<dyn Error>> {
+    let names = PACKAGES.map(|(name, _)| name);
+    // -T prints the packages that are not installed and exits with 127 when there are any
+    let output = remote::command("pacman", std::iter::once("-T").chain(names)).run()?;
+    if !output.status.success() && output.status.code() != Some(127) {
+        return Err(format!("Could not check for {}: {}", names.join(" and "), String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines().map(str::to_string).collect())
+}
+
+// Dependencies of a package to build that are not installed, which makepkg -s would install
+pub fn missing_dependencies(srcinfo: &SrcInfo) -> Result<Vec<String>, Box<dyn Error>> {
+    let dependencies: Vec<&str> = ["depends", "makedepends", "checkdepends"].iter().flat_map(|key| srcinfo.all_values(key)).collect();
+    if dependencies.is_empty() {
+        return Ok(Vec::new());
+    }
+    let output = remote::command("pacman", std::iter::once("-T").chain(dependencies)).run()?;
+    if !output.status.success() && output.status.code() != Some(127) {
+        return Err(format!("Could not check the dependencies of {}: {}", srcinfo.pkgbase, String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines().map(str::to_string).collect())
+}
+
+pub fn purpose(name: &str) -> &'static str {
+    PACKAGES.iter().find(|(package, _)| *package == name).map_or("", |(_, purpose)| purpose)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Scripted};
+
+    #[test]
+    fn lists_what_pacman_finds_missing() {
+        let _isolated = testing::isolate_blocking();
+        Scripted::default().answer("pacman -T base-devel git", "git\n").install();
+        assert_eq!(missing().unwrap(), ["git"]);
+
+        Scripted::default().fail("pacman -T base-devel git", "error: could not open database").install();
+        assert_eq!(missing().unwrap_err().to_string(), "Could not check for base-devel and git: error: could not open database");
+    }
+
+    #[test]
+    fn checks_the_dependencies_of_a_build() {
+        let _isolated = testing::isolate_blocking();
+        let srcinfo = SrcInfo::parse("pkgbase = foo\n\tmakedepends = cmake\n\tdepends = glibc\n\npkgname = foo\n");
+        Scripted::default().answer("pacman -T glibc cmake", "cmake\n").install();
+        assert_eq!(missing_dependencies(&srcinfo).unwrap(), ["cmake"]);
+        assert!(missing_dependencies(&SrcInfo::parse("pkgbase = bar\n\npkgname = bar\n")).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,335 @@
+use crate::accessibility;
+use crate::i18n::tr;
+use eframe::egui;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, PartialEq)]
+pub enum Status {
+    Waiting,
+    Running,
+    // Reviewed and building beside the queue, which has moved on
+    Building,
+    Done,
+    // With the error
+    Failed(String),
+    Skipped,
+}
+
+pub struct Item {
+    pub package: String,
+    pub status: Status,
+    // Last line of output while building
+    pub activity: String,
+    started: Option<Instant>,
+    finished: Option<Instant>,
+}
+
+impl Item {
+    fn new(package: String) -> Item {
+        Item {
+            package,
+            status: Status::Waiting,
+            activity: String::new(),
+            started: None,
+            finished: None,
+        }
+    }
+
+    // Time spent on the item, still counting while it runs
+    fn elapsed(&self) -> Option<Duration> {
+        let started = self.started?;
+        Some(self.finished.unwrap_or_else(Instant::now).duration_since(started))
+    }
+}
+
+// Packages installed one after another through the usual review. Finished items stay listed, with
+// how they ended, until they are cleared.
+#[derive(Default)]
+pub struct Queue {
+    pub items: Vec<Item>,
+    // No further item starts while paused; the running one finishes
+    pub paused: bool,
+    // No item starts while the build order of the waiting ones is worked out
+    pub ordering: bool,
+    // Items started since the queue was last idle, for the overall progress
+    started: usize,
+}
+
+impl Queue {
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn waiting(&self) -> usize {
+        self.items.iter().filter(|item| item.status == Status::Waiting).count()
+    }
+
+    pub fn building(&self) -> usize {
+        self.items.iter().filter(|item| item.status == Status::Building).count()
+    }
+
+    // Adds `package` at the end unless it is already waiting
+    pub fn push(&mut self, package: String) {
+        if !self.items.iter().any(|item| item.package == package && item.status == Status::Waiting) {
+            self.items.push(Item::new(package));
+        }
+    }
+
+    // Queues `package` again ahead of the other waiting items, e.g. after it failed
+    pub fn retry(&mut self, package: &str) {
+        self.items.retain(|item| item.package != package || matches!(item.status, Status::Running | Status::Building));
+        let first_waiting = self.items.iter().position(|item| item.status == Status::Waiting).unwrap_or(self.items.len());
+        self.items.insert(first_waiting, Item::new(package.to_string()));
+    }
+
+    pub fn waiting_packages(&self) -> Vec<String> {
+        self.items.iter().filter(|item| item.status == Status::Waiting).map(|item| item.package.clone()).collect()
+    }
+
+    // Sorts the waiting items into `order`, in the places waiting items take up now; items missing
+    // from `order` go last
+    pub fn reorder(&mut self, order: &[String]) {
+        let slots: Vec<usize> = (0..self.items.len()).filter(|&index| self.items[index].status == Status::Waiting).collect();
+        let mut waiting: Vec<Item> = slots.iter().rev().map(|&index| self.items.remove(index)).collect();
+        waiting.reverse();
+        waiting.sort_by_key(|item| order.iter().position(|package| *package == item.package).unwrap_or(usize::MAX));
+        for (index, item) in slots.into_iter().zip(waiting) {
+            self.items.insert(index, item);
+        }
+    }
+
+    pub fn skip(&mut self, package: &str) {
+        if let Some(item) = self.items.iter_mut().find(|item| item.package == package && item.status == Status::Waiting) {
+            item.status = Status::Skipped;
+        }
+    }
+
+    // Marks the first waiting item running and returns its package; None while paused
+    pub fn start_next(&mut self) -> Option<String> {
+        if self.paused || self.ordering {
+            return None;
+        }
+        let Some(item) = self.items.iter_mut().find(|item| item.status == Status::Waiting) else {
+            self.started = 0;
+            return None;
+        };
+        item.status = Status::Running;
+        item.started = Some(Instant::now());
+        item.finished = None;
+        self.started += 1;
+        Some(item.package.clone())
+    }
+
+    pub fn running(&self) -> Option<&str> {
+        self.items.iter().find(|item| item.status == Status::Running).map(|item| item.package.as_str())
+    }
+
+    // Records how the running item ended: Done, or Failed with `error`
+    pub fn finish(&mut self, error: Option<String>) {
+        if let Some(item) = self.items.iter_mut().find(|item| item.status == Status::Running) {
+            item.status = error.map_or(Status::Done, Status::Failed);
+            item.finished = Some(Instant::now());
+        }
+    }
+
+    // The running item goes on building by itself
+    pub fn start_build(&mut self) {
+        if let Some(item) = self.items.iter_mut().find(|item| item.status == Status::Running) {
+            item.status = Status::Building;
+        }
+    }
+
+    pub fn set_activity(&mut self, package: &str, activity: &str) {
+        if let Some(item) = self.items.iter_mut().find(|item| item.package == package && item.status == Status::Building) {
+            item.activity = activity.to_string();
+        }
+    }
+
+    // Records how the build of `package` ended: Done, or Failed with `error`
+    pub fn finish_build(&mut self, package: &str, error: Option<String>) {
+        if let Some(item) = self.items.iter_mut().find(|item| item.package == package && item.status == Status::Building) {
+            item.status = error.map_or(Status::Done, Status::Failed);
+            item.finished = Some(Instant::now());
+        }
+    }
+
+    // The running item was cancelled, e.g. in the review
+    pub fn cancel(&mut self) {
+        if let Some(item) = self.items.iter_mut().find(|item| item.status == Status::Running) {
+            item.status = Status::Skipped;
+            item.finished = Some(Instant::now());
+        }
+    }
+
+    // A failed step of `package` runs again in place, from the failure panel
+    pub fn resume(&mut self, package: &str) {
+        if let Some(item) = self.items.iter_mut().find(|item| item.package == package && matches!(item.status, Status::Failed(_))) {
+            item.status = Status::Running;
+            item.finished = None;
+        }
+    }
+
+    // Share of the items started since the queue was idle, and the waiting ones, that is done
+    // when the running item is `step_progress` of the way through
+    pub fn progress(&self, step_progress: f32) -> f32 {
+        let done = self.started.saturating_sub(1);
+        let total = done + 1 + self.waiting();
+        (done as f32 + step_progress) / total as f32
+    }
+
+    // Moves the item at `from` to where the item at `to` is
+    fn move_item(&mut self, from: usize, to: usize) {
+        if from < self.items.len() && to < self.items.len() {
+            let item = self.items.remove(from);
+            self.items.insert(to, item);
+        }
+    }
+}
+
+// Lists the queue; waiting items are dragged by their handle to reorder them. `running_status`
+// describes what the running item is doing. Returns the package whose Retry was clicked.
+pub fn show(ui: &mut egui::Ui, queue: &mut Queue, running_status: &str) -> Option<String> {
+    let mut retry = None;
+    let mut skip = None;
+    let mut moved = None;
+    ui.horizontal(|ui| {
+        ui.strong(format!("Queue ({} waiting)", queue.waiting()));
+        let pause = if queue.paused { "Resume" } else { "Pause" };
+        if ui.button(pause).on_hover_text(tr("Pausing lets the running package finish")).clicked() {
+            queue.paused = !queue.paused;
+        }
+        if ui.button(tr("Clear finished")).clicked() {
+            queue.items.retain(|item| matches!(item.status, Status::Waiting | Status::Running | Status::Building));
+        }
+        if ui.button(tr("Clear queue")).clicked() {
+            queue.items.retain(|item| matches!(item.status, Status::Running | Status::Building));
+        }
+    });
+    for (index, item) in queue.items.iter().enumerate() {
+        let row = ui.horizontal(|ui| {
+            if item.status == Status::Waiting {
+                ui.dnd_drag_source(egui::Id::new(("queue_item", index)), index, |ui| {
+                    let handle = ui.label("☰");
+                    accessibility::name(&handle, tr("Drag to reorder"));
+                })
+                .response
+                .on_hover_text(tr("Drag to reorder"));
+            } else {
+                ui.add_space(ui.spacing().interact_size.y);
+            }
+            ui.label(&item.package);
+            let status = match &item.status {
+                Status::Waiting if queue.paused => "Paused".to_string(),
+                Status::Waiting => "Waiting".to_string(),
+                Status::Running => running_status.to_string(),
+                Status::Building if item.activity.is_empty() => "Building".to_string(),
+                Status::Building => format!("Building: {}", item.activity),
+                Status::Done => "Installed".to_string(),
+                Status::Failed(error) => format!("Failed: {}", error.lines().next().unwrap_or_default()),
+                Status::Skipped => "Skipped".to_string(),
+            };
+            match item.status {
+                Status::Failed(_) => ui.colored_label(egui::Color32::RED, status),
+                _ => ui.weak(status),
+            };
+            if let Some(elapsed) = item.elapsed() {
+                ui.weak(crate::format_duration(elapsed));
+            }
+            match item.status {
+                Status::Waiting => {
+                    if ui.small_button(tr("Skip")).clicked() {
+                        skip = Some(index);
+                    }
+                }
+                Status::Failed(_) | Status::Skipped => {
+                    if ui.small_button(tr("Retry")).clicked() {
+                        retry = Some(item.package.clone());
+                    }
+                }
+                Status::Running | Status::Building | Status::Done => {}
+            }
+        });
+        if item.status == Status::Waiting {
+            if let Some(from) = row.response.dnd_release_payload::<usize>() {
+                moved = Some((*from, index));
+            }
+        }
+    }
+    if let Some(index) = skip {
+        queue.items[index].status = Status::Skipped;
+    }
+    if let Some((from, to)) = moved {
+        queue.move_item(from, to);
+    }
+    if let Some(package) = &retry {
+        queue.retry(package);
+    }
+    retry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_items_in_order_and_keeps_how_they_ended() {
+        let mut queue = Queue::default();
+        for package in ["foo", "bar", "baz", "foo"] {
+            queue.push(package.to_string());
+        }
+        assert_eq!(queue.waiting(), 3);
+
+        assert_eq!(queue.start_next().as_deref(), Some("foo"));
+        queue.finish(Some("makepkg exited with exit status: 4".to_string()));
+        queue.move_item(2, 1);
+        queue.paused = true;
+        assert!(queue.start_next().is_none());
+        queue.paused = false;
+        assert_eq!(queue.start_next().as_deref(), Some("baz"));
+        // Two of three started, the second one halfway
+        assert_eq!(queue.progress(0.5), 1.5 / 3.0);
+        queue.finish(None);
+
+        queue.retry("foo");
+        let order: Vec<&str> = queue.items.iter().map(|item| item.package.as_str()).collect();
+        assert_eq!(order, ["baz", "foo", "bar"]);
+        assert!(queue.items[0].status == Status::Done && queue.items[1].status == Status::Waiting);
+    }
+
+    #[test]
+    fn moves_on_while_reviewed_packages_build() {
+        let mut queue = Queue::default();
+        queue.push("foo".to_string());
+        queue.push("bar".to_string());
+
+        queue.start_next();
+        queue.start_build();
+        assert!(queue.running().is_none());
+        assert_eq!(queue.start_next().as_deref(), Some("bar"));
+        assert_eq!(queue.building(), 1);
+
+        queue.set_activity("foo", "==> Starting build()...");
+        assert_eq!(queue.items[0].activity, "==> Starting build()...");
+        queue.finish_build("foo", Some("makepkg exited with exit status: 4".to_string()));
+        assert!(matches!(&queue.items[0].status, Status::Failed(error) if error.ends_with("status: 4")));
+        assert!(queue.items[1].status == Status::Running);
+    }
+
+    #[test]
+    fn sorts_only_waiting_items_into_build_order() {
+        let mut queue = Queue::default();
+        for package in ["done", "app", "other", "lib"] {
+            queue.push(package.to_string());
+        }
+        queue.start_next();
+        queue.finish(None);
+        queue.ordering = true;
+        assert!(queue.start_next().is_none());
+
+        queue.reorder(&["lib".to_string(), "app".to_string()]);
+        queue.ordering = false;
+        let order: Vec<&str> = queue.items.iter().map(|item| item.package.as_str()).collect();
+        assert_eq!(order, ["done", "lib", "app", "other"]);
+        assert_eq!(queue.start_next().as_deref(), Some("lib"));
+    }
+}
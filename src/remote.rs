@@ -0,0 +1,152 @@
+use crate::config::{Config, HostProfile};
+use crate::runner::Run;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::process::{Command as StdCommand, Stdio};
+use std::sync::RwLock;
+
+// The host every pacman and makepkg call is run on; None is this machine
+static ACTIVE: RwLock<Option<(String, HostProfile)>> = RwLock::new(None);
+
+pub fn set_active(config: &Config) -> Result<(), Box<dyn Error>> {
+    let active = match &config.active_host {
+        Some(name) => {
+            let host = config.hosts.get(name).ok_or(format!("Unknown host '{}'; add it under [hosts.{}]", name, name))?;
+            if host.address.is_empty() {
+                return Err(format!("Host '{}' has no address", name).into());
+            }
+            Some((name.clone(), host.clone()))
+        }
+        None => None,
+    };
+    *ACTIVE.write().unwrap() = active;
+    Ok(())
+}
+
+pub fn active() -> Option<(String, HostProfile)> {
+    ACTIVE.read().unwrap().clone()
+}
+
+// "local" or e.g. "pi (alarm@raspberrypi.local)"
+pub fn describe_active() -> String {
+    match active() {
+        Some((name, host)) => format!("{} ({})", name, host.address),
+        None => "local".to_string(),
+    }
+}
+
+// Single-quotes an argument for the remote shell
+fn quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+fn ssh(host: &HostProfile) -> StdCommand {
+    let mut ssh = StdCommand::new("ssh");
+    // Never prompt: there is no terminal in the GUI
+    ssh.args(["-o", "BatchMode=yes"]);
+    if let Some(port) = host.port {
+        ssh.args(["-p", &port.to_string()]);
+    }
+    if let Some(identity_file) = &host.identity_file {
+        ssh.args(["-i", identity_file]);
+    }
+    ssh.arg(&host.address).arg("--");
+    ssh
+}
+
+// Command line of an interactive shell in `dir` on `host`, or this machine, for a terminal
+// emulator to run
+pub fn shell_in(dir: &str, host: Option<&HostProfile>) -> Vec<String> {
+    let line = format!("cd {} && exec \"${{SHELL:-sh}}\" -l", quote(dir));
+    match host {
+        Some(host) => {
+            // Unlike `ssh`, this one has a terminal to ask for passwords and needs a tty
+            let mut command = vec!["ssh".to_string(), "-t".to_string()];
+            if let Some(port) = host.port {
+                command.extend(["-p".to_string(), port.to_string()]);
+            }
+            if let Some(identity_file) = &host.identity_file {
+                command.extend(["-i".to_string(), identity_file.clone()]);
+            }
+            command.extend([host.address.clone(), "--".to_string(), line]);
+            command
+        }
+        None => vec!["sh".to_string(), "-c".to_string(), line],
+    }
+}
+
+pub fn command<I, S>(program: &str, args: I) -> StdCommand
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    command_in(None, program, args)
+}
+
+// Runs `program` on the active host, in `dir` when given
+pub fn command_in<I, S>(dir: Option<&str>, program: &str, args: I) -> StdCommand
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    match active() {
+        Some((_, host)) => {
+            let mut line = dir.map(|dir| format!("cd {} && ", quote(dir))).unwrap_or_default();
+            line.push_str(&quote(program));
+            for arg in args {
+                line.push(' ');
+                line.push_str(&quote(arg.as_ref()));
+            }
+            let mut ssh = ssh(&host);
+            ssh.arg(line);
+            ssh
+        }
+        None => {
+            let mut command = StdCommand::new(program);
+            command.args(args.into_iter().map(|arg| arg.as_ref().to_string()));
+            if let Some(dir) = dir {
+                command.current_dir(dir);
+            }
+            command
+        }
+    }
+}
+
+pub fn list_dir(dir: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    if active().is_none() {
+        return Ok(fs::read_dir(dir)?
+            .filter_map(Result::ok)
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect());
+    }
+    let output = command("ls", ["-1", dir]).run()?;
+    if !output.status.success() {
+        return Err(format!("Could not list {}: {}", dir, String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines().map(str::to_string).collect())
+}
+
+// Copies a local directory to `remote_dir` on the active host by piping tar through ssh
+pub fn upload(local_dir: &str, remote_dir: &str, host: &HostProfile) -> Result<(), Box<dyn Error>> {
+    let local = Path::new(local_dir);
+    let remote_parent = Path::new(remote_dir).parent().ok_or("Remote build directory has no parent")?;
+    let mut tar = StdCommand::new("tar")
+        .arg("-C")
+        .arg(local)
+        .args(["-cf", "-", "."])
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let archive = tar.stdout.take().ok_or("Could not capture tar output")?;
+    let line = format!(
+        "rm -rf {dir} && mkdir -p {parent} {dir} && tar -C {dir} -xf -",
+        dir = quote(remote_dir),
+        parent = quote(&remote_parent.to_string_lossy())
+    );
+    let output = ssh(host).arg(line).stdin(archive).run()?;
+    tar.wait()?;
+    if !output.status.success() {
+        return Err(format!("Upload to {} failed: {}", host.address, String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,161 @@
+use crate::backend;
+use crate::config::RemoveMode;
+use crate::remote;
+use crate::runner::Run;
+use std::collections::HashSet;
+use std::error::Error;
+
+// What removing a package takes with it, worked out before anything is removed
+#[derive(Clone)]
+pub struct RemovalImpact {
+    // Every package pacman would remove, with its installed size in bytes
+    pub packages: Vec<(String, u64)>,
+    // Dependencies left installed that nothing would need any more
+    pub orphans: Vec<String>,
+}
+
+impl RemovalImpact {
+    pub fn total_size(&self) -> u64 {
+        self.packages.iter().map(|(_, size)| size).sum()
+    }
+}
+
+// (package, mode, impact) as shown in the uninstall confirmation; the impact is None while computing
+pub type Preview = (String, RemoveMode, Option<Result<RemovalImpact, String>>);
+
+// The fields of one `pacman -Qi` entry that decide what becomes an orphan
+struct Installed {
+    name: String,
+    size: u64,
+    depends: Vec<String>,
+    required_by: Vec<String>,
+    explicit: bool,
+}
+
+// `pacman -R -p` prints the targets without removing anything or needing root; a removal that
+// would break another package's dependencies fails here the same way it would for real
+pub fn preview(package: &str, mode: RemoveMode) -> Result<RemovalImpact, Box<dyn Error>> {
+    let output = remote::command("pacman", [&format!("{}p", mode.flag()), "--print-format", "%n", package]).run()?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string().into());
+    }
+    let targets: HashSet<String> = String::from_utf8_lossy(&output.stdout).lines().map(str::trim).filter(|line| !line.is_empty()).map(str::to_string).collect();
+
+    let output = remote::command("env", ["LC_ALL=C", "pacman", "-Qi"]).run()?;
+    if !output.status.success() {
+        return Err(format!("pacman -Qi failed: {}", String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    let installed = parse(&String::from_utf8_lossy(&output.stdout));
+
+    let mut packages: Vec<(String, u64)> = installed.iter().filter(|entry| targets.contains(&entry.name)).map(|entry| (entry.name.clone(), entry.size)).collect();
+    packages.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+    // Dependencies of the removed packages that only they require and that were not installed explicitly
+    let depended_on: HashSet<&str> = installed.iter().filter(|entry| targets.contains(&entry.name)).flat_map(|entry| entry.depends.iter().map(String::as_str)).collect();
+    let mut orphans: Vec<String> = installed
+        .iter()
+        .filter(|entry| !targets.contains(&entry.name) && !entry.explicit && depended_on.contains(entry.name.as_str()))
+        .filter(|entry| entry.required_by.iter().all(|dependent| targets.contains(dependent)))
+        .map(|entry| entry.name.clone())
+        .collect();
+    orphans.sort();
+    Ok(RemovalImpact { packages, orphans })
+}
+
+fn parse(output: &str) -> Vec<Installed> {
+    output
+        .split("\n\n")
+        .filter_map(|block| {
+            let field = |key: &str| {
+                block.lines().find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    (name.trim() == key).then(|| value.trim())
+                })
+            };
+            // "None" when empty; versioned dependencies like "glibc>=2.38" are matched by name
+            let names = |value: Option<&str>| -> Vec<String> {
+                value
+                    .unwrap_or("None")
+                    .split_whitespace()
+                    .filter(|name| *name != "None")
+                    .map(|name| name.split(['<', '>', '=']).next().unwrap_or(name).to_string())
+                    .collect()
+            };
+            Some(Installed {
+                name: field("Name")?.to_string(),
+                size: field("Installed Size").and_then(backend::parse_size).unwrap_or(0),
+                depends: names(field("Depends On")),
+                required_by: names(field("Required By")),
+                explicit: field("Install Reason").is_some_and(|reason| reason.starts_with("Explicitly")),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Scripted};
+
+    const INSTALLED: &str = "\
+Name            : foo
+Version         : 1.0-1
+Depends On      : libfoo>=1.2  glibc  python
+Required By     : None
+Installed Size  : 1.50 MiB
+Install Reason  : Explicitly installed
+
+Name            : libfoo
+Version         : 1.2-1
+Depends On      : glibc
+Required By     : foo
+Installed Size  : 512.00 KiB
+Install Reason  : Installed as a dependency for another package
+
+Name            : glibc
+Version         : 2.40-1
+Depends On      : None
+Required By     : bar  foo  libfoo
+Installed Size  : 40.00 MiB
+Install Reason  : Installed as a dependency for another package
+
+Name            : python
+Version         : 3.12.7-1
+Depends On      : glibc
+Required By     : foo
+Installed Size  : 60.00 MiB
+Install Reason  : Explicitly installed
+";
+
+    #[test]
+    fn lists_dependencies_only_the_removed_package_needed() {
+        let _isolated = testing::isolate_blocking();
+        Scripted::default().answer("pacman -Rp --print-format %n foo", "foo\n").answer("env LC_ALL=C pacman -Qi", INSTALLED).install();
+
+        let impact = preview("foo", RemoveMode::Package).unwrap();
+        assert_eq!(impact.packages, vec![("foo".to_string(), 1_572_864)]);
+        // glibc is still required by bar and python was installed explicitly
+        assert_eq!(impact.orphans, vec!["libfoo".to_string()]);
+    }
+
+    #[test]
+    fn counts_every_package_pacman_would_remove() {
+        let _isolated = testing::isolate_blocking();
+        Scripted::default().answer("pacman -Rnsp --print-format %n foo", "foo\nlibfoo\n").answer("env LC_ALL=C pacman -Qi", INSTALLED).install();
+
+        let impact = preview("foo", RemoveMode::Purge).unwrap();
+        assert_eq!(impact.packages, vec![("foo".to_string(), 1_572_864), ("libfoo".to_string(), 524_288)]);
+        assert_eq!(impact.total_size(), 2_097_152);
+        assert!(impact.orphans.is_empty());
+    }
+
+    #[test]
+    fn reports_why_pacman_refuses() {
+        let _isolated = testing::isolate_blocking();
+        Scripted::default().fail("pacman -Rp --print-format %n glibc", "error: failed to prepare transaction (could not satisfy dependencies)").install();
+
+        let Err(error) = preview("glibc", RemoveMode::Package) else {
+            panic!("pacman refused, so the preview must fail");
+        };
+        assert_eq!(error.to_string(), "error: failed to prepare transaction (could not satisfy dependencies)");
+    }
+}
@@ -0,0 +1,251 @@
+use crate::pgp_keys;
+use crate::scan::{self, Finding, Severity};
+use crate::source_hashes;
+use crate::srcinfo::{self, SrcInfo};
+use similar::{ChangeTag, TextDiff};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DiffKind {
+    Hunk,
+    Context,
+    Added,
+    Removed,
+}
+
+#[derive(Clone)]
+pub struct DiffLine {
+    pub kind: DiffKind,
+    pub text: String,
+}
+
+#[derive(Clone)]
+pub struct ReviewFile {
+    pub name: String,
+    // .install scriptlets run as root during pacman transactions
+    pub is_scriptlet: bool,
+    pub previous: Option<String>,
+    pub current: String,
+}
+
+impl ReviewFile {
+    pub fn is_changed(&self) -> bool {
+        self.previous.as_deref() != Some(self.current.as_str())
+    }
+
+    // Unified diff against the last reviewed copy; a first review shows every line as added.
+    pub fn diff(&self) -> Vec<DiffLine> {
+        let previous = self.previous.as_deref().unwrap_or("");
+        let diff = TextDiff::from_lines(previous, &self.current);
+        let mut lines = Vec::new();
+
+        for group in diff.grouped_ops(3) {
+            let (first, last) = (&group[0], &group[group.len() - 1]);
+            lines.push(DiffLine {
+                kind: DiffKind::Hunk,
+                text: format!(
+                    "@@ -{},{} +{},{} @@",
+                    first.old_range().start + 1,
+                    last.old_range().end - first.old_range().start,
+                    first.new_range().start + 1,
+                    last.new_range().end - first.new_range().start,
+                ),
+            });
+            for op in &group {
+                for change in diff.iter_changes(op) {
+                    let kind = match change.tag() {
+                        ChangeTag::Equal => DiffKind::Context,
+                        ChangeTag::Insert => DiffKind::Added,
+                        ChangeTag::Delete => DiffKind::Removed,
+                    };
+                    lines.push(DiffLine {
+                        kind,
+                        text: change.value().trim_end_matches('\n').to_string(),
+                    });
+                }
+            }
+        }
+        lines
+    }
+}
+
+#[derive(Clone)]
+pub struct PendingReview {
+    pub package: String,
+    pub package_base: String,
+    pub version: String,
+    pub build_dir: String,
+    pub files: Vec<ReviewFile>,
+    pub findings: Vec<Finding>,
+    // Checksums of the sources, when they were verified before the review
+    pub integrity: Vec<source_hashes::Entry>,
+    // Every package the base builds, and the subset the user wants installed
+    pub split_packages: Vec<String>,
+    pub selected_packages: Vec<String>,
+}
+
+impl PendingReview {
+    pub fn has_changes(&self) -> bool {
+        self.files.iter().any(|file| file.is_changed())
+    }
+
+    // A source that does not match its checksum or could not be fetched
+    pub fn has_integrity_problems(&self) -> bool {
+        self.integrity.iter().any(|entry| entry.status.is_problem())
+    }
+
+    pub fn is_split(&self) -> bool {
+        self.split_packages.len() > 1
+    }
+
+    pub fn toggle_package(&mut self, name: &str, selected: bool) {
+        self.selected_packages.retain(|selected_name| selected_name != name);
+        if selected {
+            self.selected_packages.push(name.to_string());
+        }
+    }
+}
+
+fn reviewed_dir(package_base: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let data_dir = dirs::data_dir().ok_or("Could not determine data directory")?;
+    Ok(data_dir.join("aur-helper").join("reviewed").join(package_base))
+}
+
+// PKGBUILD plus any .install scriptlets, since both run code on the user's machine.
+// Scriptlets are usually named *.install, but .SRCINFO records the exact `install=` names.
+fn review_file_names(build_dir: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut names = vec!["PKGBUILD".to_string()];
+    let mut scriptlets = Vec::new();
+    for entry in fs::read_dir(build_dir)? {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        if name.ends_with(".install") {
+            scriptlets.push(name);
+        }
+    }
+    if let Ok(srcinfo) = SrcInfo::read(&build_dir.to_string_lossy()) {
+        for name in srcinfo.all_values("install") {
+            // Read here and written under the reviewed directory, so it has to stay a file name
+            if !srcinfo::is_plain_file_name(name) {
+                return Err(format!("Refusing the install scriptlet {}: .SRCINFO has to name a file in the build directory", name).into());
+            }
+            if !scriptlets.iter().any(|known| known == name) && build_dir.join(name).is_file() {
+                scriptlets.push(name.to_string());
+            }
+        }
+    }
+    scriptlets.sort();
+    names.extend(scriptlets);
+    Ok(names)
+}
+
+pub fn load_review(
+    package: &str,
+    package_base: &str,
+    version: &str,
+    build_dir: &str,
+) -> Result<PendingReview, Box<dyn Error>> {
+    let reviewed = reviewed_dir(package_base)?;
+    let mut files = Vec::new();
+
+    for name in review_file_names(Path::new(build_dir))? {
+        let current = fs::read_to_string(Path::new(build_dir).join(&name))?;
+        let previous = fs::read_to_string(reviewed.join(&name)).ok();
+        let is_scriptlet = name != "PKGBUILD";
+        files.push(ReviewFile { name, is_scriptlet, previous, current });
+    }
+
+    let mut findings: Vec<Finding> = files.iter().flat_map(|file| scan::scan(&file.name, &file.current)).collect();
+    if let Ok(srcinfo) = fs::read_to_string(Path::new(build_dir).join(".SRCINFO")) {
+        findings.extend(pgp_keys::check(&srcinfo));
+    }
+    findings.sort_by(|a, b| b.severity.partial_cmp(&a.severity).unwrap());
+
+    let split_packages = match SrcInfo::read(build_dir) {
+        Ok(srcinfo) if !srcinfo.packages.is_empty() => srcinfo.package_names(),
+        _ => vec![package.to_string()],
+    };
+
+    Ok(PendingReview {
+        package: package.to_string(),
+        package_base: package_base.to_string(),
+        version: version.to_string(),
+        build_dir: build_dir.to_string(),
+        files,
+        findings,
+        integrity: Vec::new(),
+        split_packages,
+        selected_packages: vec![package.to_string()],
+    })
+}
+
+// Remember what the user just acknowledged so the next update only shows what changed.
+pub fn save_reviewed(review: &PendingReview) -> Result<(), Box<dyn Error>> {
+    let reviewed = reviewed_dir(&review.package_base)?;
+    if reviewed.exists() {
+        fs::remove_dir_all(&reviewed)?;
+    }
+    fs::create_dir_all(&reviewed)?;
+    for file in &review.files {
+        if !srcinfo::is_plain_file_name(&file.name) {
+            return Err(format!("Refusing to save {} outside {}", file.name, reviewed.display()).into());
+        }
+        fs::write(reviewed.join(&file.name), &file.current)?;
+    }
+    Ok(())
+}
+
+pub fn print_review(review: &PendingReview) {
+    if !review.findings.is_empty() {
+        println!("\x1b[1m==> Security scan: {} finding(s)\x1b[0m", review.findings.len());
+        for finding in &review.findings {
+            let color = match finding.severity {
+                Severity::High => "31",
+                Severity::Medium => "33",
+                Severity::Low => "36",
+            };
+            println!(
+                "\x1b[{}m[{}]\x1b[0m {}:{}: {}\n    {}",
+                color,
+                finding.severity.label(),
+                finding.file,
+                finding.line,
+                finding.message,
+                finding.snippet
+            );
+        }
+    }
+    if !review.integrity.is_empty() {
+        println!("\x1b[1m==> Source integrity: {}\x1b[0m", source_hashes::summary(&review.integrity));
+        for entry in &review.integrity {
+            let color = match entry.status {
+                source_hashes::Status::Verified(_) => "32",
+                _ if entry.status.is_problem() => "31",
+                _ => "33",
+            };
+            println!("    {}: \x1b[{}m{}\x1b[0m", entry.name, color, entry.status.describe());
+        }
+    }
+    for file in &review.files {
+        if file.is_scriptlet {
+            println!("\x1b[1;33m==> {} (install scriptlet, runs as root)\x1b[0m", file.name);
+            for line in file.current.lines() {
+                println!("    {}", line);
+            }
+        }
+        if !file.is_changed() {
+            println!("{}: unchanged since last review", file.name);
+            continue;
+        }
+        println!("\x1b[1m==> {}\x1b[0m", file.name);
+        for line in file.diff() {
+            match line.kind {
+                DiffKind::Hunk => println!("\x1b[36m{}\x1b[0m", line.text),
+                DiffKind::Context => println!(" {}", line.text),
+                DiffKind::Added => println!("\x1b[32m+{}\x1b[0m", line.text),
+                DiffKind::Removed => println!("\x1b[31m-{}\x1b[0m", line.text),
+            }
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::io;
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Command as StdCommand, ExitStatus, Output};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+// Runs commands whose output is captured: pacman queries, vercmp, makepkg.conf and the like.
+// Tests put a scripted runner in place of the system so no package manager is needed.
+pub trait CommandRunner: Send + Sync {
+    fn output(&self, command: &mut StdCommand) -> io::Result<Output>;
+}
+
+struct System;
+
+impl CommandRunner for System {
+    fn output(&self, command: &mut StdCommand) -> io::Result<Output> {
+        command.output()
+    }
+}
+
+static RUNNER: RwLock<&'static dyn CommandRunner> = RwLock::new(&System);
+// --dry-run: commands that change the system are logged instead of run
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+#[cfg(test)]
+pub fn set(runner: &'static dyn CommandRunner) {
+    *RUNNER.write().unwrap() = runner;
+}
+
+#[cfg(test)]
+pub fn reset() {
+    set(&System);
+    set_dry_run(false);
+}
+
+pub fn set_dry_run(enabled: bool) {
+    DRY_RUN.store(enabled, Ordering::Relaxed);
+}
+
+pub fn dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+// In dry-run mode, logs `command` instead of running it and returns true. Spawned commands call
+// this themselves; captured ones go through `run`, which only simulates what changes the system.
+pub fn simulate(command: &StdCommand) -> bool {
+    if !dry_run() {
+        return false;
+    }
+    tracing::info!("Would run: {}", command_line(command));
+    true
+}
+
+pub fn command_line(command: &StdCommand) -> String {
+    std::iter::once(command.get_program()).chain(command.get_args()).map(|arg| arg.to_string_lossy()).collect::<Vec<_>>().join(" ")
+}
+
+fn succeeded() -> Output {
+    Output {
+        status: ExitStatus::from_raw(0),
+        stdout: Vec::new(),
+        stderr: Vec::new(),
+    }
+}
+
+// Whether a command installs, removes, builds or writes something. Words are checked at every
+// position, so `sudo pacman -U` and the line ssh runs on a remote host are caught as well.
+fn changes_system(command: &StdCommand) -> bool {
+    let line = command_line(command);
+    let words: Vec<&str> = line.split_whitespace().map(|word| word.trim_matches('\'')).collect();
+    (0..words.len()).any(|start| changes_with(&words[start..]))
+}
+
+fn changes_with(words: &[&str]) -> bool {
+    let (program, args) = match words.split_first() {
+        Some((program, args)) => (program.rsplit('/').next().unwrap_or(program), args),
+        None => return false,
+    };
+    let first = args.first().copied().unwrap_or("");
+    match program {
+        "sudo" | "doas" | "pkexec" | "run0" | "makepkg" | "apt-get" | "cp" | "tee" => true,
+        // Queries: -Ss search, -Si info, -Sg groups, -Sl lists and -p prints the targets
+        "pacman" => match first.strip_prefix('-') {
+            Some(flags) if flags.starts_with('S') => !flags.contains(['s', 'i', 'g', 'l', 'p']),
+            Some(flags) if flags.starts_with('R') || flags.starts_with('U') => !flags.contains('p'),
+            _ => false,
+        },
+        "flatpak" => matches!(first, "install" | "uninstall" | "update"),
+        "snap" => matches!(first, "install" | "remove") || (first == "refresh" && !args.contains(&"--list")),
+        "cargo" => first == "install" && !args.contains(&"--list"),
+        "pipx" => matches!(first, "install" | "upgrade" | "uninstall"),
+        "gpg" => args.iter().any(|arg| matches!(*arg, "--recv-keys" | "--delete-keys")),
+        _ => false,
+    }
+}
+
+// `command.run()` in place of `command.output()`, so the call goes through the runner
+pub trait Run {
+    fn run(&mut self) -> io::Result<Output>;
+}
+
+impl Run for StdCommand {
+    fn run(&mut self) -> io::Result<Output> {
+        if changes_system(self) && simulate(self) {
+            return Ok(succeeded());
+        }
+        match RUNNER.read().unwrap().output(self) {
+            // So the GUI can be shown off where the package tools are missing, e.g. off Arch
+            Err(e) if e.kind() == io::ErrorKind::NotFound && dry_run() => Ok(succeeded()),
+            output => output,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Scripted};
+
+    fn command(line: &str) -> StdCommand {
+        let mut words = line.split(' ');
+        let mut command = StdCommand::new(words.next().unwrap());
+        command.args(words);
+        command
+    }
+
+    #[test]
+    fn tells_transactions_from_queries() {
+        let transactions = [
+            "sudo pacman -U /tmp/foo-1.0-1-any.pkg.tar.zst",
+            "pacman -Rns foo",
+            "pacman -Syu",
+            "env MAKEFLAGS=-j8 makepkg -s",
+            "flatpak install -y org.gnome.Maps",
+            "ssh -o BatchMode=yes pi -- 'doas' 'pacman' '-S' 'foo'",
+            "cargo install --locked ripgrep",
+            "gpg --batch --keyserver hkps://keyserver.ubuntu.com --recv-keys 79BE3E4300411886",
+        ];
+        for line in transactions {
+            assert!(changes_system(&command(line)), "{}", line);
+        }
+        let queries = [
+            "pacman -Qm",
+            "pacman -Ssq foo",
+            "pacman -Si core/bash",
+            "pacman -Rp --print-format %n foo",
+            "env LC_ALL=C pacman -Qi",
+            "snap refresh --list",
+            "cargo install --list",
+            "vercmp 1.0-1 1.1-1",
+            "gpg --batch --list-keys 79BE3E4300411886",
+        ];
+        for line in queries {
+            assert!(!changes_system(&command(line)), "{}", line);
+        }
+    }
+
+    #[test]
+    fn dry_runs_only_pass_queries_through() {
+        let _isolated = testing::isolate_blocking();
+        let scripted = Scripted::default().answer("pacman -Qm", "foo 1.0-1\n").install();
+        set_dry_run(true);
+
+        let installed = command("pacman -Qm").run().unwrap();
+        assert_eq!(String::from_utf8_lossy(&installed.stdout), "foo 1.0-1\n");
+        assert!(command("sudo pacman -U foo-1.0-1-any.pkg.tar.zst").run().unwrap().status.success());
+        // Missing from the script, so missing from the system: answered with nothing
+        assert!(command("pacman -Qi").run().unwrap().stdout.is_empty());
+        assert_eq!(scripted.calls(), ["pacman -Qm", "pacman -Qi"]);
+    }
+}
@@ -0,0 +1,106 @@
+use regex::Regex;
+
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl Severity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Severity::Low => "LOW",
+            Severity::Medium => "MEDIUM",
+            Severity::High => "HIGH",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+    pub snippet: String,
+}
+
+struct Rule {
+    severity: Severity,
+    message: &'static str,
+    pattern: Regex,
+    // Only match inside these shell functions; empty means anywhere in the file
+    functions: &'static [&'static str],
+}
+
+fn rules() -> Vec<Rule> {
+    let rule = |severity, message, pattern: &str, functions| Rule {
+        severity,
+        message,
+        pattern: Regex::new(pattern).unwrap(),
+        functions,
+    };
+    vec![
+        rule(Severity::High, "Downloads a script and pipes it into a shell", r"\b(curl|wget)\b[^|]*\|\s*(sudo\s+)?(ba|z|da)?sh\b", &[]),
+        rule(Severity::High, "Uses sudo inside a makepkg function", r"\bsudo\b", &["prepare", "pkgver", "build", "check", "package"]),
+        rule(Severity::High, "Decodes a base64 payload", r"\bbase64\s+(-d|--decode)\b", &[]),
+        rule(Severity::Medium, "Long base64-like blob embedded in the file", r"[A-Za-z0-9+/]{120,}={0,2}", &[]),
+        rule(Severity::Medium, "Evaluates dynamically built code", r"\beval\b", &[]),
+        rule(Severity::Medium, "Fetches from a raw IP address", r"https?://\d{1,3}(\.\d{1,3}){3}", &[]),
+        rule(Severity::Medium, "Uses a URL shortener or paste site", r"https?://(bit\.ly|tinyurl\.com|goo\.gl|is\.gd|t\.co|pastebin\.com|paste\.ee|transfer\.sh)/", &[]),
+        rule(Severity::Medium, "Network access during build", r"\b(curl|wget)\b", &["prepare", "pkgver", "build", "check", "package"]),
+        rule(Severity::Low, "Unencrypted http:// source", r"http://", &[]),
+    ]
+}
+
+// Writes to system paths in package() should always be prefixed with $pkgdir
+fn writes_outside_pkgdir(line: &str) -> bool {
+    let system_path = Regex::new(r#"(^|[\s"'=>])/(usr|etc|opt|bin|sbin|lib|lib64|var|boot|root|home|srv)(/|\s|$)"#).unwrap();
+    let writer = Regex::new(r"\b(install|cp|mv|ln|mkdir|touch|tee|rm|chmod|chown)\b|>").unwrap();
+    writer.is_match(line) && system_path.is_match(line) && !line.contains("pkgdir")
+}
+
+pub fn scan(file: &str, content: &str) -> Vec<Finding> {
+    let rules = rules();
+    let function_start = Regex::new(r"^\s*(?:function\s+)?([A-Za-z_][A-Za-z0-9_]*)\s*\(\s*\)").unwrap();
+    let mut current_function: Option<String> = None;
+    let mut findings = Vec::new();
+
+    for (index, raw_line) in content.lines().enumerate() {
+        let line = raw_line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        if let Some(captures) = function_start.captures(raw_line) {
+            current_function = Some(captures[1].to_string());
+        } else if raw_line.starts_with('}') {
+            current_function = None;
+        }
+
+        let mut push = |severity, message: &str| {
+            findings.push(Finding {
+                severity,
+                file: file.to_string(),
+                line: index + 1,
+                message: message.to_string(),
+                snippet: line.to_string(),
+            });
+        };
+
+        for rule in &rules {
+            let in_scope = rule.functions.is_empty()
+                || current_function.as_deref().is_some_and(|name| rule.functions.iter().any(|f| name.starts_with(f)));
+            if in_scope && rule.pattern.is_match(line) {
+                push(rule.severity, rule.message);
+            }
+        }
+
+        let in_package = current_function.as_deref().is_some_and(|name| name.starts_with("package"));
+        if in_package && writes_outside_pkgdir(line) {
+            push(Severity::High, "Writes outside $pkgdir in package()");
+        }
+    }
+
+    findings
+}
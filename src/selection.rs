@@ -0,0 +1,103 @@
+use eframe::egui::Modifiers;
+use std::collections::HashMap;
+
+pub type Key = (&'static str, String);
+
+// Packages picked in a list: a click picks one, Ctrl+click adds or removes one, and Shift+click
+// picks every row from the last clicked one. Whether each is installed is looked up once, when it
+// is picked.
+#[derive(Default)]
+pub struct Selection {
+    items: Vec<Key>,
+    installed: HashMap<Key, bool>,
+    // Row Shift+click counts from
+    anchor: Option<Key>,
+}
+
+impl Selection {
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    // In the order they were picked
+    pub fn items(&self) -> &[Key] {
+        &self.items
+    }
+
+    pub fn contains(&self, source: &str, name: &str) -> bool {
+        self.items.iter().any(|(item_source, item_name)| *item_source == source && item_name == name)
+    }
+
+    // Just `package`, e.g. after it was picked with the keyboard
+    pub fn reset(&mut self, package: Option<Key>) {
+        self.items = package.iter().cloned().collect();
+        self.installed.retain(|key, _| package.as_ref() == Some(key));
+        self.anchor = package;
+    }
+
+    // Updates the selection for a click on `clicked`, one of `rows` in the order they are listed
+    pub fn click(&mut self, rows: &[Key], clicked: Key, modifiers: Modifiers, is_installed: impl Fn(&Key) -> bool) {
+        if modifiers.shift {
+            let position = |key: &Key| rows.iter().position(|row| row == key);
+            let end = position(&clicked).unwrap_or_default();
+            let start = self.anchor.as_ref().and_then(position).unwrap_or(end);
+            if !modifiers.command {
+                self.items.clear();
+            }
+            for row in &rows[start.min(end)..=start.max(end)] {
+                if !self.items.contains(row) {
+                    self.items.push(row.clone());
+                }
+            }
+        } else if modifiers.command {
+            match self.items.iter().position(|item| *item == clicked) {
+                Some(index) => {
+                    self.items.remove(index);
+                }
+                None => self.items.push(clicked.clone()),
+            }
+            self.anchor = Some(clicked);
+        } else {
+            self.items = vec![clicked.clone()];
+            self.anchor = Some(clicked);
+        }
+        for item in &self.items {
+            if !self.installed.contains_key(item) {
+                self.installed.insert(item.clone(), is_installed(item));
+            }
+        }
+    }
+
+    // Selected packages that are installed (true) or not (false)
+    pub fn with_installed(&self, installed: bool) -> Vec<Key> {
+        self.items.iter().filter(|item| self.installed.get(*item) == Some(&installed)).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_ranges_and_toggles_rows() {
+        let rows: Vec<Key> = ["a", "b", "c", "d", "e"].iter().map(|name| ("aur", name.to_string())).collect();
+        let installed = |key: &Key| key.1 == "b";
+        let ctrl = Modifiers { command: true, ..Modifiers::NONE };
+        let mut selection = Selection::default();
+
+        selection.click(&rows, rows[1].clone(), Modifiers::NONE, installed);
+        selection.click(&rows, rows[3].clone(), Modifiers::SHIFT, installed);
+        assert_eq!(selection.len(), 3);
+        selection.click(&rows, rows[2].clone(), ctrl, installed);
+        assert!(!selection.contains("aur", "c"));
+        // Shift+click counts from the row clicked last; with Ctrl it keeps the rest
+        selection.click(&rows, rows[4].clone(), Modifiers::SHIFT | ctrl, installed);
+        assert_eq!(selection.len(), 4);
+        assert!(!selection.contains("aur", "a"));
+
+        assert_eq!(selection.with_installed(true), [rows[1].clone()]);
+        assert_eq!(selection.with_installed(false).len(), 3);
+        selection.click(&rows, rows[0].clone(), Modifiers::NONE, installed);
+        assert_eq!(selection.len(), 1);
+    }
+}
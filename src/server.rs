@@ -0,0 +1,403 @@
+use crate::config::Config;
+use crate::{AppState, Package};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use futures::stream::{self, Stream};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::error::Error;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::SocketAddr;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Action {
+    Install,
+    Remove,
+}
+
+impl Action {
+    fn label(&self) -> &'static str {
+        match self {
+            Action::Install => "Install",
+            Action::Remove => "Remove",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TransactionRequest {
+    action: Action,
+    packages: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase", tag = "status", content = "error")]
+enum Status {
+    Queued,
+    Running,
+    Succeeded,
+    Failed(String),
+}
+
+struct Transaction {
+    action: Action,
+    packages: Vec<String>,
+    status: Status,
+    // Shares the log with the pipeline, which is what the event stream reads from
+    state: Arc<Mutex<AppState>>,
+}
+
+#[derive(Serialize)]
+struct TransactionInfo {
+    id: usize,
+    action: Action,
+    packages: Vec<String>,
+    #[serde(flatten)]
+    status: Status,
+}
+
+#[derive(Clone)]
+struct Server {
+    config: Config,
+    transactions: Arc<Mutex<Vec<Transaction>>>,
+    // pacman holds a database lock, so transactions run one at a time
+    worker: Arc<tokio::sync::Mutex<()>>,
+}
+
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(HashMap::from([("error", self.1)]))).into_response()
+    }
+}
+
+impl From<Box<dyn Error>> for ApiError {
+    fn from(error: Box<dyn Error>) -> ApiError {
+        let status = match crate::exit_codes::classify(error.as_ref()) {
+            crate::ExitCode::NotFound => StatusCode::NOT_FOUND,
+            crate::ExitCode::Network => StatusCode::BAD_GATEWAY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        ApiError(status, error.to_string())
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    q: String,
+    // "maintainer" lists a maintainer's AUR packages; anything else searches names
+    by: Option<String>,
+}
+
+async fn search(Query(query): Query<SearchQuery>) -> Result<Json<Vec<String>>, ApiError> {
+    let results = match query.by.as_deref() {
+        Some("maintainer") => crate::search_aur(&query.q, crate::SearchBy::Maintainer).await.map_err(|e| e.to_string()),
+        _ => crate::backend::active().search(&query.q).map_err(|e| e.to_string()),
+    };
+    results.map(Json).map_err(|e| ApiError(StatusCode::BAD_GATEWAY, e))
+}
+
+async fn info(Path(name): Path<String>) -> Result<Json<Package>, ApiError> {
+    Ok(Json(crate::fetch_metadata(&name).await?))
+}
+
+#[derive(Serialize)]
+struct Update {
+    name: String,
+    installed: String,
+    available: String,
+}
+
+// Held packages are left out, as they are in `update`
+async fn updates(State(server): State<Server>) -> Result<Json<Vec<Update>>, ApiError> {
+    let updates = crate::backend::active().check_updates()?;
+    Ok(Json(
+        updates
+            .into_iter()
+            .filter(|(name, _, _)| !server.config.is_held(name))
+            .map(|(name, installed, available)| Update { name, installed, available })
+            .collect(),
+    ))
+}
+
+fn describe(id: usize, transaction: &Transaction) -> TransactionInfo {
+    TransactionInfo {
+        id,
+        action: transaction.action,
+        packages: transaction.packages.clone(),
+        status: transaction.status.clone(),
+    }
+}
+
+async fn list_transactions(State(server): State<Server>) -> Json<Vec<TransactionInfo>> {
+    let transactions = server.transactions.lock().unwrap();
+    Json(transactions.iter().enumerate().map(|(id, transaction)| describe(id, transaction)).collect())
+}
+
+async fn get_transaction(State(server): State<Server>, Path(id): Path<usize>) -> Result<Json<TransactionInfo>, ApiError> {
+    let transactions = server.transactions.lock().unwrap();
+    let transaction = transactions.get(id).ok_or(ApiError(StatusCode::NOT_FOUND, format!("No transaction {}", id)))?;
+    Ok(Json(describe(id, transaction)))
+}
+
+// There is nobody to answer prompts, so reviews and confirmations are skipped
+async fn submit(State(server): State<Server>, Json(request): Json<TransactionRequest>) -> Result<(StatusCode, Json<TransactionInfo>), ApiError> {
+    if request.packages.is_empty() {
+        return Err(ApiError(StatusCode::BAD_REQUEST, "No packages given".to_string()));
+    }
+    let mut config = server.config.clone();
+    config.no_confirm = true;
+    let state = Arc::new(Mutex::new(AppState {
+        config,
+        cli_mode: true,
+        ..Default::default()
+    }));
+    let (id, info) = {
+        let mut transactions = server.transactions.lock().unwrap();
+        transactions.push(Transaction {
+            action: request.action,
+            packages: request.packages.clone(),
+            status: Status::Queued,
+            state: Arc::clone(&state),
+        });
+        let id = transactions.len() - 1;
+        (id, describe(id, &transactions[id]))
+    };
+
+    tokio::spawn(async move {
+        let _worker = server.worker.lock().await;
+        set_status(&server, id, Status::Running);
+        let mut status = Status::Succeeded;
+        for package in &request.packages {
+            state.lock().unwrap().log.begin_operation(&format!("{} {}", request.action.label(), package), Some(package));
+            if let Err(e) = run(request.action, package, &state).await {
+                state.lock().unwrap().log_error(&e);
+                status = Status::Failed(e);
+                break;
+            }
+        }
+        set_status(&server, id, status);
+    });
+    Ok((StatusCode::ACCEPTED, Json(info)))
+}
+
+fn set_status(server: &Server, id: usize, status: Status) {
+    server.transactions.lock().unwrap()[id].status = status;
+}
+
+async fn run(action: Action, package: &str, state: &Arc<Mutex<AppState>>) -> Result<(), String> {
+    match action {
+        Action::Install if !crate::backend::active().builds_from_source() => {
+            let tool = state.lock().unwrap().config.escalation_tool.clone();
+            crate::install_package(crate::backend::active(), &[package.to_string()], &tool).map_err(|e| e.to_string())?;
+            state.lock().unwrap().log(&format!("Installed {}", package));
+            Ok(())
+        }
+        Action::Install => {
+            let review = crate::prepare_package(package, state).await.map_err(|e| e.to_string())?;
+            crate::review::save_reviewed(&review).map_err(|e| e.to_string())?;
+            crate::build_and_install_package(&review, None, state).await.map_err(|e| e.to_string())?;
+            // Build and install failures are reported through the state rather than the result
+            match state.lock().unwrap().error.take() {
+                Some(e) => Err(e),
+                None => Ok(()),
+            }
+        }
+        Action::Remove => {
+            let config = state.lock().unwrap().config.clone();
+            crate::hooks::run_hook("pre_remove", config.hooks.pre_remove.as_deref(), package)
+                .and_then(|_| crate::uninstall_package(crate::backend::active(), package, config.remove_mode, &config.escalation_tool))
+                .map_err(|e| e.to_string())?;
+            state.lock().unwrap().log(&format!("Removed {}", package));
+            Ok(())
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct LogEvent {
+    level: &'static str,
+    package: Option<String>,
+    message: String,
+}
+
+// Sends log entries as `log` events and finishes with one `status` event once the transaction is done
+async fn events(State(server): State<Server>, Path(id): Path<usize>) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let state = {
+        let transactions = server.transactions.lock().unwrap();
+        let transaction = transactions.get(id).ok_or(ApiError(StatusCode::NOT_FOUND, format!("No transaction {}", id)))?;
+        Arc::clone(&transaction.state)
+    };
+
+    let stream = stream::unfold((0, false), move |(sent, finished)| {
+        let server = server.clone();
+        let state = Arc::clone(&state);
+        async move {
+            if finished {
+                return None;
+            }
+            loop {
+                let status = server.transactions.lock().unwrap()[id].status.clone();
+                let done = matches!(status, Status::Succeeded | Status::Failed(_));
+                let new_entries: Vec<LogEvent> = state.lock().unwrap().log.entries[sent..]
+                    .iter()
+                    .map(|entry| LogEvent {
+                        level: entry.level.label(),
+                        package: entry.package.clone(),
+                        message: crate::ansi::strip(&entry.message),
+                    })
+                    .collect();
+                if !new_entries.is_empty() {
+                    let events: Vec<Result<Event, Infallible>> = new_entries
+                        .iter()
+                        .map(|entry| Ok(Event::default().event("log").json_data(entry).unwrap()))
+                        .collect();
+                    return Some((stream::iter(events), (sent + new_entries.len(), false)));
+                }
+                if done {
+                    let event = Event::default().event("status").json_data(&status).unwrap();
+                    return Some((stream::iter(vec![Ok(event)]), (sent, true)));
+                }
+                tokio::time::sleep(EVENT_POLL_INTERVAL).await;
+            }
+        }
+    });
+    Ok(Sse::new(futures::StreamExt::flatten(stream)).keep_alive(KeepAlive::default()))
+}
+
+// What every request has to show: the token, and a Host header naming the address the server
+// listens on, so a page that rebinds its own domain to the loopback address cannot use the API
+#[derive(Clone)]
+struct Access {
+    token: String,
+    hosts: Vec<String>,
+}
+
+impl Access {
+    fn new(listen: SocketAddr, token: String) -> Access {
+        let mut hosts = vec![listen.to_string()];
+        if listen.ip().is_loopback() {
+            hosts.push(format!("localhost:{}", listen.port()));
+        }
+        Access { token, hosts }
+    }
+}
+
+// Compares every byte, so the time taken does not tell how much of a guess was right
+fn same_token(given: &str, token: &str) -> bool {
+    given.len() == token.len() && given.bytes().zip(token.bytes()).fold(0, |differ, (a, b)| differ | (a ^ b)) == 0
+}
+
+async fn authorize(State(access): State<Access>, request: Request, next: Next) -> Result<Response, ApiError> {
+    let host = request.headers().get(header::HOST).and_then(|host| host.to_str().ok()).unwrap_or_default();
+    if !access.hosts.iter().any(|allowed| allowed == host) {
+        return Err(ApiError(StatusCode::FORBIDDEN, format!("Unexpected Host {:?}", host)));
+    }
+    let given = request.headers().get(header::AUTHORIZATION).and_then(|value| value.to_str().ok()).and_then(|value| value.strip_prefix("Bearer "));
+    if !given.is_some_and(|given| same_token(given, &access.token)) {
+        return Err(ApiError(StatusCode::UNAUTHORIZED, "Missing or wrong bearer token".to_string()));
+    }
+    Ok(next.run(request).await)
+}
+
+// Where a generated token is written for clients on this machine to read
+pub fn token_path() -> Result<PathBuf, Box<dyn Error>> {
+    let runtime_dir = dirs::runtime_dir().ok_or("XDG_RUNTIME_DIR is not set")?;
+    Ok(runtime_dir.join("aur-helper-api-token"))
+}
+
+// A new random token, readable by the user alone
+fn generate_token() -> Result<String, Box<dyn Error>> {
+    let mut bytes = [0u8; 32];
+    fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    let token: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let path = token_path()?;
+    let _ = fs::remove_file(&path);
+    let mut file = fs::OpenOptions::new().write(true).create_new(true).mode(0o600).open(&path)?;
+    file.write_all(token.as_bytes())?;
+    Ok(token)
+}
+
+fn router(config: Config, access: Access) -> Router {
+    let server = Server {
+        config,
+        transactions: Arc::new(Mutex::new(Vec::new())),
+        worker: Arc::new(tokio::sync::Mutex::new(())),
+    };
+    Router::new()
+        .route("/api/search", get(search))
+        .route("/api/info/:name", get(info))
+        .route("/api/updates", get(updates))
+        .route("/api/transactions", get(list_transactions).post(submit))
+        .route("/api/transactions/:id", get(get_transaction))
+        .route("/api/transactions/:id/events", get(events))
+        .layer(middleware::from_fn_with_state(access, authorize))
+        .with_state(server)
+}
+
+// Transactions skip every review, so the API is only served to whoever holds the token
+pub async fn serve(listen: SocketAddr, config: Config) -> Result<(), Box<dyn Error>> {
+    let token = if config.api_token.is_empty() {
+        if !listen.ip().is_loopback() {
+            return Err(format!("Set api_token in the config before serving the API on {}, which is not a loopback address", listen).into());
+        }
+        let token = generate_token()?;
+        tracing::info!("The API token is in {}", token_path()?.display());
+        token
+    } else {
+        config.api_token.clone()
+    };
+    let app = router(config, Access::new(listen, token));
+
+    let listener = tokio::net::TcpListener::bind(listen).await?;
+    tracing::info!("Listening on http://{}", listen);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[tokio::test]
+    async fn requires_the_token_and_the_listen_address() {
+        let _isolated = testing::isolate().await;
+        let listen: SocketAddr = "127.0.0.1:8700".parse().unwrap();
+        let url = testing::serve(router(Config::default(), Access::new(listen, "secret".to_string())));
+        let client = reqwest::Client::new();
+        let transactions = format!("{}/api/transactions", url);
+        let body = serde_json::json!({ "action": "install", "packages": ["yay"] });
+
+        let unauthenticated = client.post(&transactions).header(header::HOST, "127.0.0.1:8700").json(&body).send().await.unwrap();
+        assert_eq!(unauthenticated.status(), StatusCode::UNAUTHORIZED);
+        let wrong = client.post(&transactions).header(header::HOST, "127.0.0.1:8700").bearer_auth("guess").json(&body).send().await.unwrap();
+        assert_eq!(wrong.status(), StatusCode::UNAUTHORIZED);
+        let rebound = client.get(&transactions).header(header::HOST, "attacker.example:8700").bearer_auth("secret").send().await.unwrap();
+        assert_eq!(rebound.status(), StatusCode::FORBIDDEN);
+        let allowed = client.get(&transactions).header(header::HOST, "localhost:8700").bearer_auth("secret").send().await.unwrap();
+        assert_eq!(allowed.status(), StatusCode::OK);
+
+        let remote = Config::default();
+        assert!(serve("0.0.0.0:8700".parse().unwrap(), remote).await.is_err());
+        let token = generate_token().unwrap();
+        assert_eq!(fs::read_to_string(token_path().unwrap()).unwrap(), token);
+        assert_eq!(fs::metadata(token_path().unwrap()).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+}
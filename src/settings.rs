@@ -0,0 +1,394 @@
+use crate::backend;
+use crate::config::{Config, RemoveMode, ThemePreference};
+use crate::container;
+use crate::escalation;
+use crate::helper;
+use crate::i18n::tr;
+use crate::pacman_conf::{self, PacmanConf};
+use crate::remote;
+use crate::theme;
+use eframe::egui;
+use std::path::Path;
+
+// Edits are made on a copy of the config and only applied once they validate
+pub struct SettingsDraft {
+    pub config: Config,
+    makepkg_flags: String,
+    binary_repos: String,
+    snapshot_mirrors: String,
+    errors: Vec<String>,
+    // Read-only, shown for the pacman backend
+    pacman_conf: Option<Result<PacmanConf, String>>,
+}
+
+pub enum SettingsAction {
+    Apply(Box<Config>),
+    Close,
+}
+
+impl SettingsDraft {
+    pub fn new(config: &Config) -> SettingsDraft {
+        SettingsDraft {
+            config: config.clone(),
+            makepkg_flags: config.makepkg_flags.join(" "),
+            binary_repos: config.binary_repos.join(" "),
+            snapshot_mirrors: config.snapshot_mirrors.join(" "),
+            errors: Vec::new(),
+            pacman_conf: (backend::active().name() == "pacman").then(|| pacman_conf::load().map_err(|e| e.to_string())),
+        }
+    }
+
+    fn validate(&self) -> Result<Config, Vec<String>> {
+        let mut config = self.config.clone();
+        config.makepkg_flags = self.makepkg_flags.split_whitespace().map(str::to_string).collect();
+        config.binary_repos = self.binary_repos.split_whitespace().map(str::to_string).collect();
+        config.snapshot_mirrors = self.snapshot_mirrors.split_whitespace().map(str::to_string).collect();
+        let mut errors = Vec::new();
+
+        if !Path::new(&config.build_dir).is_absolute() {
+            errors.push("Build directory must be an absolute path.".to_string());
+        }
+        // Installation is done separately with pacman -U after the review step
+        if config.makepkg_flags.iter().any(|flag| flag == "--install" || (flag.starts_with('-') && !flag.starts_with("--") && flag.contains('i'))) {
+            errors.push("makepkg flags must not include -i/--install.".to_string());
+        }
+        let builtin = [escalation::AUTO, helper::ESCALATION_TOOL].contains(&config.escalation_tool.as_str());
+        if !builtin && !crate::command_exists(&config.escalation_tool) {
+            errors.push(format!("Escalation tool '{}' was not found in PATH.", config.escalation_tool));
+        }
+        if !config.container.is_empty() && remote::active().is_none() && !crate::command_exists(&config.container) {
+            errors.push(format!("{} was not found in PATH.", config.container));
+        }
+        if !config.container.is_empty() && config.container_image.trim().is_empty() {
+            errors.push("Container image must not be empty.".to_string());
+        }
+        for (name, color) in [("Accent", &config.accent_color), ("Background", &config.background_color)] {
+            if color.as_deref().is_some_and(|hex| theme::parse_hex_color(hex).is_none()) {
+                errors.push(format!("{} color must be a hex color like #3584e4.", name));
+            }
+        }
+        if !(theme::MIN_UI_SCALE..=theme::MAX_UI_SCALE).contains(&config.ui_scale) {
+            errors.push(format!("UI scale must be between {} and {}.", theme::MIN_UI_SCALE, theme::MAX_UI_SCALE));
+        }
+        if !(8.0..=32.0).contains(&config.font_size) {
+            errors.push("Font size must be between 8 and 32.".to_string());
+        }
+        if config.update_interval_hours == 0 {
+            errors.push("Update interval must be at least one hour.".to_string());
+        }
+        if config.metadata_ttl_minutes == 0 {
+            errors.push("Metadata cache must be kept for at least one minute.".to_string());
+        }
+        for (name, url) in [("AUR address", &config.aur_url), ("Snapshot address", &config.snapshot_url)] {
+            if !url.is_empty() && !url.starts_with("https://") && !url.starts_with("http://") {
+                errors.push(format!("{} must start with https:// or http://.", name));
+            }
+        }
+        if config.snapshot_mirrors.iter().any(|mirror| !mirror.starts_with("https://") && !mirror.starts_with("http://")) {
+            errors.push("Snapshot mirrors must start with https:// or http://.".to_string());
+        }
+        if config.aur_url.is_empty() {
+            errors.push("AUR address must not be empty.".to_string());
+        }
+        // gpg takes hkp://, hkps:// and ldap:// addresses, and plain host names
+        if config.keyserver.trim().is_empty() || config.keyserver.contains(char::is_whitespace) {
+            errors.push("Keyserver must be an address such as hkps://keyserver.ubuntu.com.".to_string());
+        }
+        if config.connect_timeout_secs == 0 || config.read_timeout_secs == 0 {
+            errors.push("Network timeouts must be at least one second.".to_string());
+        }
+
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+pub fn show(ctx: &egui::Context, draft: &mut SettingsDraft) -> Option<SettingsAction> {
+    let mut action = None;
+
+    egui::Window::new(tr("Settings")).collapsible(false).show(ctx, |ui| {
+        egui::Grid::new("settings_grid").num_columns(2).spacing([12.0, 6.0]).show(ui, |ui| {
+            let label = ui.label(tr("Build directory:"));
+            ui.text_edit_singleline(&mut draft.config.build_dir).labelled_by(label.id);
+            ui.end_row();
+
+            ui.label(tr("Clean up build directories:"));
+            ui.vertical(|ui| {
+                ui.checkbox(&mut draft.config.cleanup.after_success, tr("Delete after a successful install"));
+                ui.horizontal(|ui| {
+                    ui.add_enabled_ui(!draft.config.cleanup.after_success, |ui| {
+                        let keep = ui.label(tr("Otherwise keep the last"));
+                        ui.add(egui::DragValue::new(&mut draft.config.cleanup.keep_recent).range(0..=100)).labelled_by(keep.id);
+                    });
+                });
+                ui.horizontal(|ui| {
+                    let keep = ui.label(tr("Keep failed builds for"));
+                    ui.add(egui::DragValue::new(&mut draft.config.cleanup.keep_failed_days).range(0..=365).suffix(" days")).labelled_by(keep.id);
+                });
+            });
+            ui.end_row();
+
+            let label = ui.label(tr("makepkg flags:"));
+            ui.text_edit_singleline(&mut draft.makepkg_flags).labelled_by(label.id);
+            ui.end_row();
+
+            let label = ui.label(tr("Parallel builds:"));
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut draft.config.parallel_builds, tr("One make job per core when makepkg.conf sets no -j, at most"));
+                ui.add_enabled(draft.config.parallel_builds, egui::DragValue::new(&mut draft.config.max_build_jobs).range(0..=256)).labelled_by(label.id);
+                ui.weak(tr("(0 = no limit)"));
+            });
+            ui.end_row();
+
+            let label = ui.label(tr("Queued builds at once:"));
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut draft.config.concurrent_builds).range(1..=8)).labelled_by(label.id);
+                ui.weak(tr("(each in its own build directory; installs still run one at a time)"));
+            });
+            ui.end_row();
+
+            let label = ui.label(tr("Background builds:"));
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut draft.config.background_builds, tr("Low priority (nice/ionice), CPU limit"));
+                ui.add_enabled(draft.config.background_builds, egui::DragValue::new(&mut draft.config.background_cpu_quota).range(0..=6400).suffix("%")).labelled_by(label.id);
+                ui.weak(tr("(0 = none, 100% = one core)"));
+            });
+            ui.end_row();
+
+            let label = ui.label(tr("Build in container:"));
+            ui.horizontal(|ui| {
+                let selected = if draft.config.container.is_empty() { "off" } else { draft.config.container.as_str() };
+                egui::ComboBox::from_id_source("container")
+                    .selected_text(selected.to_string())
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut draft.config.container, String::new(), "off");
+                        for tool in container::TOOLS {
+                            ui.selectable_value(&mut draft.config.container, tool.to_string(), tool);
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id);
+                ui.add_enabled(!draft.config.container.is_empty(), egui::TextEdit::singleline(&mut draft.config.container_image)).labelled_by(label.id);
+            });
+            ui.end_row();
+
+            let label = ui.label(tr("Terminal:"));
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut draft.config.terminal).labelled_by(label.id);
+                ui.weak(tr("(empty = $TERMINAL or the first one found)"));
+            });
+            ui.end_row();
+
+            let label = ui.label(tr("Binary repositories:"));
+            ui.text_edit_singleline(&mut draft.binary_repos).labelled_by(label.id);
+            ui.end_row();
+
+            let label = ui.label(tr("Package manager:"));
+            egui::ComboBox::from_id_source("backend")
+                .selected_text(draft.config.backend.as_str())
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut draft.config.backend, backend::AUTO.to_string(), "auto");
+                    for name in backend::BACKENDS {
+                        ui.selectable_value(&mut draft.config.backend, name.to_string(), name);
+                    }
+                })
+                .response
+                .labelled_by(label.id);
+            ui.end_row();
+
+            ui.label(tr("Extra sources:"));
+            ui.vertical(|ui| {
+                ui.checkbox(&mut draft.config.flatpak, tr("Flatpak apps from Flathub"));
+                ui.checkbox(&mut draft.config.snap, tr("Snaps (requires snapd)"));
+            });
+            ui.end_row();
+
+            let label = ui.label(tr("Escalation tool:"));
+            egui::ComboBox::from_id_source("escalation_tool")
+                .selected_text(draft.config.escalation_tool.as_str())
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut draft.config.escalation_tool, escalation::AUTO.to_string(), "auto");
+                    for tool in escalation::available() {
+                        ui.selectable_value(&mut draft.config.escalation_tool, tool.to_string(), tool);
+                    }
+                    if crate::command_exists("pkexec") {
+                        ui.selectable_value(&mut draft.config.escalation_tool, helper::ESCALATION_TOOL.to_string(), "helper (asks once per session)");
+                    }
+                })
+                .response
+                .labelled_by(label.id);
+            ui.end_row();
+
+            let label = ui.label(tr("Uninstall:"));
+            egui::ComboBox::from_id_source("remove_mode")
+                .selected_text(format!("{} ({})", draft.config.remove_mode.label(), draft.config.remove_mode.flag()))
+                .show_ui(ui, |ui| {
+                    for mode in RemoveMode::ALL {
+                        ui.selectable_value(&mut draft.config.remove_mode, mode, format!("{} ({})", mode.label(), mode.flag()))
+                            .on_hover_text(mode.explanation());
+                    }
+                })
+                .response
+                .labelled_by(label.id);
+            ui.end_row();
+
+            ui.label(tr("Theme:"));
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut draft.config.theme, ThemePreference::System, tr("System"));
+                ui.radio_value(&mut draft.config.theme, ThemePreference::Dark, tr("Dark"));
+                ui.radio_value(&mut draft.config.theme, ThemePreference::Light, tr("Light"));
+            });
+            ui.end_row();
+
+            let label = ui.label(tr("Accent color:"));
+            theme::color_setting(ui, &mut draft.config.accent_color, ui.visuals().selection.bg_fill, label.id);
+            ui.end_row();
+
+            let label = ui.label(tr("Background color:"));
+            theme::color_setting(ui, &mut draft.config.background_color, ui.visuals().panel_fill, label.id);
+            ui.end_row();
+
+            let label = ui.label(tr("UI scale:"));
+            ui.add(egui::Slider::new(&mut draft.config.ui_scale, theme::MIN_UI_SCALE..=theme::MAX_UI_SCALE).step_by(0.05)).labelled_by(label.id);
+            ui.end_row();
+
+            let label = ui.label(tr("Font size:"));
+            ui.add(egui::Slider::new(&mut draft.config.font_size, 8.0..=32.0).step_by(0.5)).labelled_by(label.id);
+            ui.end_row();
+
+            ui.label(tr("Keyboard:"));
+            ui.checkbox(&mut draft.config.vim_mode, tr("Vim-style navigation (j/k, /, Enter, dd)"));
+            ui.end_row();
+
+            ui.label(tr("Notifications:"));
+            ui.checkbox(&mut draft.config.notifications, tr("Notify when operations finish"));
+            ui.end_row();
+
+            ui.label(tr("Confirmations:"));
+            ui.vertical(|ui| {
+                ui.checkbox(&mut draft.config.no_confirm, tr("Never ask (--noconfirm)"));
+                ui.add_enabled_ui(!draft.config.no_confirm, |ui| {
+                    ui.checkbox(&mut draft.config.confirm.dependencies, tr("Show dependencies before downloading"));
+                    ui.checkbox(&mut draft.config.confirm.partial_upgrade, tr("Offer a full upgrade before a partial one"));
+                    ui.checkbox(&mut draft.config.confirm.review, tr("Review PKGBUILD before building"));
+                    ui.checkbox(&mut draft.config.confirm.transaction, tr("Confirm packages before installing"));
+                    ui.checkbox(&mut draft.config.confirm.removal, tr("Confirm removals"));
+                });
+            });
+            ui.end_row();
+
+            let label = ui.label(tr("Update check interval:"));
+            ui.add(egui::DragValue::new(&mut draft.config.update_interval_hours).range(0..=168).suffix(" h")).labelled_by(label.id);
+            ui.end_row();
+
+            ui.label(tr("App updates:"));
+            ui.checkbox(&mut draft.config.check_app_updates, tr("Check for a newer version of this app at startup"));
+            ui.end_row();
+
+            let label = ui.label(tr("Keep AUR metadata for:"));
+            ui.add(egui::DragValue::new(&mut draft.config.metadata_ttl_minutes).range(0..=1440).suffix(" min")).labelled_by(label.id);
+            ui.end_row();
+
+            let label = ui.label(tr("AUR address:"));
+            ui.text_edit_singleline(&mut draft.config.aur_url).labelled_by(label.id);
+            ui.end_row();
+
+            let label = ui.label(tr("Snapshot address:"));
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut draft.config.snapshot_url).labelled_by(label.id);
+                ui.weak(tr("(empty = the AUR address)"));
+            });
+            ui.end_row();
+
+            let label = ui.label(tr("Snapshot mirrors:"));
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut draft.snapshot_mirrors).labelled_by(label.id);
+                ui.weak(tr("(tried when the AUR is unreachable)"));
+            });
+            ui.end_row();
+
+            let label = ui.label(tr("Keyserver:"));
+            ui.text_edit_singleline(&mut draft.config.keyserver).labelled_by(label.id);
+            ui.end_row();
+
+            ui.label(tr("Sources:"));
+            ui.vertical(|ui| {
+                ui.checkbox(&mut draft.config.check_sources, tr("Check source URLs before the review"));
+                ui.checkbox(&mut draft.config.verify_sources, tr("Download sources and verify their checksums before the review"));
+            });
+            ui.end_row();
+
+            ui.label(tr("Network timeouts:"));
+            ui.horizontal(|ui| {
+                let connect = ui.label(tr("connect"));
+                ui.add(egui::DragValue::new(&mut draft.config.connect_timeout_secs).range(0..=300).suffix(" s")).labelled_by(connect.id);
+                let read = ui.label(tr("read"));
+                ui.add(egui::DragValue::new(&mut draft.config.read_timeout_secs).range(0..=600).suffix(" s")).labelled_by(read.id);
+            });
+            ui.end_row();
+        });
+
+        match &draft.pacman_conf {
+            Some(Ok(conf)) => {
+                ui.collapsing(pacman_conf::PATH, |ui| show_pacman_conf(ui, conf));
+            }
+            Some(Err(e)) => {
+                ui.colored_label(ui.visuals().warn_fg_color, e);
+            }
+            None => {}
+        }
+
+        for error in &draft.errors {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button(tr("Apply")).clicked() {
+                match draft.validate() {
+                    Ok(config) => {
+                        draft.errors.clear();
+                        action = Some(SettingsAction::Apply(Box::new(config)));
+                    }
+                    Err(errors) => draft.errors = errors,
+                }
+            }
+            if ui.button(tr("Close")).clicked() {
+                action = Some(SettingsAction::Close);
+            }
+        });
+    });
+
+    action
+}
+
+fn show_pacman_conf(ui: &mut egui::Ui, conf: &PacmanConf) {
+    let list = |values: &[String]| if values.is_empty() { "none".to_string() } else { values.join(" ") };
+    egui::Grid::new("pacman_conf_grid").num_columns(2).spacing([12.0, 4.0]).show(ui, |ui| {
+        ui.label(tr("Repositories:"));
+        ui.label(list(&conf.repos));
+        ui.end_row();
+
+        ui.label("IgnorePkg:");
+        ui.label(list(&conf.ignore_pkg));
+        ui.end_row();
+
+        ui.label("IgnoreGroup:");
+        ui.label(list(&conf.ignore_group));
+        ui.end_row();
+
+        ui.label("ParallelDownloads:");
+        ui.label(conf.parallel_downloads.map(|count| count.to_string()).unwrap_or_else(|| "off".to_string()));
+        ui.end_row();
+
+        ui.label("CacheDir:");
+        ui.label(if conf.cache_dirs.is_empty() { "/var/cache/pacman/pkg/ (default)".to_string() } else { list(&conf.cache_dirs) });
+        ui.end_row();
+    });
+    if !conf.repos.iter().any(|repo| repo == "multilib") {
+        ui.weak(tr("[multilib] is disabled, so lib32- dependencies cannot be installed."));
+    }
+    ui.weak(tr("Edit pacman.conf as root to change these."));
+}
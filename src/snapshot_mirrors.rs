@@ -0,0 +1,100 @@
+use crate::runner::Run;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command as StdCommand;
+
+// Where a package's build files can be fetched from
+#[derive(Clone, Debug, PartialEq)]
+pub enum Source {
+    // A tarball, like the AUR's own snapshots
+    Snapshot(String),
+    // A git repository with a branch for every package base, like github.com/archlinux/aur
+    Git(String),
+}
+
+impl Source {
+    pub fn url(&self) -> &str {
+        match self {
+            Source::Snapshot(url) | Source::Git(url) => url,
+        }
+    }
+}
+
+// `aur_snapshot` first, then each of `mirrors` in the order configured. A mirror ending in .git is
+// cloned; any other address serves the snapshot paths the RPC reports, e.g. "/cgit/aur.git/snapshot/yay.tar.gz".
+pub fn sources(aur_snapshot: &str, urlpath: &str, mirrors: &[String]) -> Vec<Source> {
+    let mirrors = mirrors.iter().map(|mirror| mirror.trim_end_matches('/')).map(|mirror| {
+        if mirror.ends_with(".git") {
+            Source::Git(mirror.to_string())
+        } else {
+            Source::Snapshot(format!("{}{}", mirror, urlpath))
+        }
+    });
+    std::iter::once(Source::Snapshot(aur_snapshot.to_string())).chain(mirrors).collect()
+}
+
+// Clones the branch of `package_base` from the git mirror `url` and moves its files to
+// <dest>/<package_base>, where an extracted snapshot would be. Files of an earlier build there are
+// replaced and the rest kept, as with a snapshot.
+pub fn clone(url: &str, package_base: &str, dest: &str) -> Result<(), Box<dyn Error>> {
+    let checkout = PathBuf::from(format!("{}.git", dest.trim_end_matches('/')));
+    let _ = fs::remove_dir_all(&checkout);
+    let output = StdCommand::new("git")
+        .args(["clone", "--quiet", "--depth", "1", "--single-branch", "--branch", package_base, url])
+        .arg(&checkout)
+        .run()?;
+    if !output.status.success() {
+        let _ = fs::remove_dir_all(&checkout);
+        return Err(format!("git clone of {} from {} failed: {}", package_base, url, String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    let result = move_files(&checkout, &Path::new(dest).join(package_base));
+    let _ = fs::remove_dir_all(&checkout);
+    result
+}
+
+fn move_files(checkout: &Path, target: &Path) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(target)?;
+    for entry in fs::read_dir(checkout)? {
+        let entry = entry?;
+        if entry.file_name() == ".git" {
+            continue;
+        }
+        let to = target.join(entry.file_name());
+        if to.is_dir() {
+            fs::remove_dir_all(&to)?;
+        }
+        fs::rename(entry.path(), to)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Scripted};
+
+    #[test]
+    fn tries_the_aur_then_each_mirror() {
+        let mirrors = ["https://github.com/archlinux/aur.git".to_string(), "https://aur-mirror.example.org/".to_string()];
+        let path = "/cgit/aur.git/snapshot/yay.tar.gz";
+        assert_eq!(
+            sources("https://aur.archlinux.org/cgit/aur.git/snapshot/yay.tar.gz", path, &mirrors),
+            [
+                Source::Snapshot("https://aur.archlinux.org/cgit/aur.git/snapshot/yay.tar.gz".to_string()),
+                Source::Git("https://github.com/archlinux/aur.git".to_string()),
+                Source::Snapshot("https://aur-mirror.example.org/cgit/aur.git/snapshot/yay.tar.gz".to_string()),
+            ]
+        );
+
+        let _isolated = testing::isolate_blocking();
+        let dest = testing::temp_dir("git-mirror").join("yay");
+        let checkout = format!("{}.git", dest.display());
+        Scripted::default()
+            .fail(&format!("git clone --quiet --depth 1 --single-branch --branch yay https://github.com/archlinux/aur.git {}", checkout), "fatal: Remote branch yay not found")
+            .install();
+        let error = clone("https://github.com/archlinux/aur.git", "yay", dest.to_str().unwrap()).unwrap_err();
+        assert!(error.to_string().ends_with("Remote branch yay not found"), "{}", error);
+        assert!(!Path::new(&checkout).exists());
+    }
+}
@@ -0,0 +1,144 @@
+use crate::http;
+use crate::scan::{Finding, Severity};
+use crate::srcinfo::SrcInfo;
+use reqwest::header::LOCATION;
+use reqwest::{Client, Method, StatusCode, Url};
+
+// Redirects followed before a source counts as dead
+const MAX_REDIRECTS: usize = 10;
+
+// The http(s) downloads of the source arrays, including architecture specific ones, in the order
+// listed. Version control sources are cloned rather than downloaded and local files come with the
+// snapshot, so neither is checked.
+fn download_urls(srcinfo: &SrcInfo) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    let sources = srcinfo
+        .fields
+        .iter()
+        .chain(srcinfo.packages.iter().flat_map(|package| package.fields.iter()))
+        .filter(|(key, _)| key == "source" || key.starts_with("source_"));
+    for (_, source) in sources {
+        // "name::url" saves the download under another name
+        let url = source.split_once("::").map_or(source.as_str(), |(_, url)| url);
+        if (url.starts_with("https://") || url.starts_with("http://")) && !urls.iter().any(|listed| listed == url) {
+            urls.push(url.to_string());
+        }
+    }
+    urls
+}
+
+enum Outcome {
+    Reachable,
+    Moved(Url),
+    Dead(StatusCode),
+    Unreachable(String),
+}
+
+// Some servers refuse HEAD; those are asked for the file instead, without reading it
+async fn request(client: &Client, url: &Url) -> Result<reqwest::Response, reqwest::Error> {
+    let response = client.request(Method::HEAD, url.clone()).send().await?;
+    if matches!(response.status(), StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED | StatusCode::FORBIDDEN) {
+        return client.get(url.clone()).send().await;
+    }
+    Ok(response)
+}
+
+// Follows the redirects of `url` one at a time to see every host on the way
+async fn probe(client: &Client, url: &str) -> Outcome {
+    let Ok(original) = Url::parse(url) else {
+        return Outcome::Unreachable("not a valid address".to_string());
+    };
+    let mut current = original.clone();
+    for _ in 0..=MAX_REDIRECTS {
+        let response = match request(client, &current).await {
+            Ok(response) => response,
+            Err(e) => return Outcome::Unreachable(http::readable(e).to_string()),
+        };
+        let status = response.status();
+        if status.is_redirection() {
+            let next = response.headers().get(LOCATION).and_then(|location| location.to_str().ok()).and_then(|location| current.join(location).ok());
+            match next {
+                Some(next) => current = next,
+                None => return Outcome::Dead(status),
+            }
+            continue;
+        }
+        if status.is_client_error() || status.is_server_error() {
+            return Outcome::Dead(status);
+        }
+        if current.host_str() != original.host_str() {
+            return Outcome::Moved(current);
+        }
+        return Outcome::Reachable;
+    }
+    Outcome::Unreachable(format!("more than {} redirects", MAX_REDIRECTS))
+}
+
+// Asks the server of every source download of the package in `build_dir` whether it is there,
+// so a dead link is found before the build rather than at makepkg's download step. Sources that
+// redirect to another host are listed too, for the user to judge. Findings are numbered by their
+// place among the checked sources.
+pub async fn check(build_dir: &str) -> Vec<Finding> {
+    let Ok(srcinfo) = SrcInfo::read(build_dir) else {
+        return Vec::new();
+    };
+    let client = match http::builder().redirect(reqwest::redirect::Policy::none()).build() {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::warn!("Could not check the source URLs: {}", e);
+            return Vec::new();
+        }
+    };
+    let mut findings = Vec::new();
+    for (index, url) in download_urls(&srcinfo).into_iter().enumerate() {
+        let (severity, message, snippet) = match probe(&client, &url).await {
+            Outcome::Reachable => continue,
+            Outcome::Moved(to) => (
+                Severity::Medium,
+                format!("Source redirects to another host: {}", to.host_str().unwrap_or_default()),
+                format!("{} -> {}", url, to),
+            ),
+            Outcome::Dead(status) => (Severity::High, format!("Source is gone (HTTP {}); the download step will fail", status.as_u16()), url),
+            Outcome::Unreachable(reason) => (Severity::Medium, format!("Source could not be reached: {}", reason), url),
+        };
+        findings.push(Finding { severity, file: "source".to_string(), line: index + 1, message, snippet });
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use axum::http::{header, StatusCode as AxumStatus};
+    use axum::response::IntoResponse;
+    use axum::routing::get;
+    use std::fs;
+
+    #[tokio::test]
+    async fn flags_dead_and_moved_sources() {
+        let _isolated = testing::isolate().await;
+        let mirror = testing::serve(axum::Router::new().route("/moved.tar.gz", get(|| async { "tarball" })));
+        let moved = format!("{}/moved.tar.gz", mirror.replace("127.0.0.1", "localhost"));
+        let upstream = testing::serve(
+            axum::Router::new()
+                .route("/app-1.0.tar.gz", get(|| async { "tarball" }))
+                .route("/patch", get(|| async { (AxumStatus::FOUND, [(header::LOCATION, "/app-1.0.tar.gz")]).into_response() }))
+                .route("/old.tar.gz", get(move || async move { (AxumStatus::MOVED_PERMANENTLY, [(header::LOCATION, moved)]).into_response() })),
+        );
+        let dir = testing::temp_dir("source-check");
+        let srcinfo = format!(
+            "pkgbase = app\n\tsource = app-1.0.tar.gz::{0}/app-1.0.tar.gz\n\tsource = {0}/patch\n\tsource = app.install\n\tsource = git+https://example.org/app.git#tag=1.0\n\tsource_x86_64 = {0}/missing.tar.gz\n\tsource_x86_64 = {0}/old.tar.gz\n\npkgname = app\n",
+            upstream
+        );
+        fs::write(dir.join(".SRCINFO"), srcinfo).unwrap();
+
+        let findings = check(dir.to_str().unwrap()).await;
+        assert_eq!(findings.len(), 2);
+        assert!(findings[0].severity == Severity::High);
+        assert_eq!(findings[0].line, 3);
+        assert!(findings[0].message.contains("HTTP 404"));
+        assert!(findings[1].message.ends_with("another host: localhost"));
+        assert_eq!(findings[1].line, 4);
+    }
+}
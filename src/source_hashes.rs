@@ -0,0 +1,196 @@
+use crate::http;
+use crate::runner::Run;
+use crate::srcinfo::{self, SrcInfo};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::process::Command as StdCommand;
+use tokio::io::AsyncWriteExt;
+
+// Checksum arrays that are verified, with the coreutils command that computes each. md5sums and
+// sha1sums are too weak to vouch for a file, so sources with only those count as unchecked.
+const ALGORITHMS: [(&str, &str, &str); 3] = [("sha256", "sha256sums", "sha256sum"), ("sha512", "sha512sums", "sha512sum"), ("b2", "b2sums", "b2sum")];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Status {
+    // The algorithms whose sums matched
+    Verified(Vec<&'static str>),
+    Mismatch { algorithm: &'static str, expected: String, actual: String },
+    // SKIP in every checksum array, as for version control sources
+    Skipped,
+    Unchecked(String),
+    // Could not be downloaded or read, so makepkg would stop there as well
+    Failed(String),
+}
+
+impl Status {
+    // Worth rejecting the build over
+    pub fn is_problem(&self) -> bool {
+        matches!(self, Status::Mismatch { .. } | Status::Failed(_))
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Status::Verified(algorithms) => format!("verified ({})", algorithms.join(", ")),
+            Status::Mismatch { algorithm, expected, actual } => format!("{} MISMATCH: expected {}, got {}", algorithm, expected, actual),
+            Status::Skipped => "SKIP, not verified".to_string(),
+            Status::Unchecked(reason) => format!("not verified: {}", reason),
+            Status::Failed(reason) => format!("failed: {}", reason),
+        }
+    }
+}
+
+// One entry of the source array, under the name makepkg saves it as
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    pub source: String,
+    pub status: Status,
+}
+
+// e.g. "3 verified, 1 SKIP, 1 mismatched"
+pub fn summary(entries: &[Entry]) -> String {
+    let count = |matches: fn(&Status) -> bool| entries.iter().filter(|entry| matches(&entry.status)).count();
+    let parts = [
+        (count(|status| matches!(status, Status::Verified(_))), "verified"),
+        (count(|status| *status == Status::Skipped), "SKIP"),
+        (count(|status| matches!(status, Status::Unchecked(_))), "unchecked"),
+        (count(|status| matches!(status, Status::Mismatch { .. })), "mismatched"),
+        (count(|status| matches!(status, Status::Failed(_))), "failed"),
+    ];
+    parts.iter().filter(|(count, _)| *count > 0).map(|(count, label)| format!("{} {}", count, label)).collect::<Vec<_>>().join(", ")
+}
+
+// The file name makepkg gives a source: the part before "::", or the last part of the address
+fn file_name(source: &str) -> (&str, &str) {
+    if let Some((name, location)) = source.split_once("::") {
+        return (name, location);
+    }
+    let without_fragment = source.split('#').next().unwrap_or(source);
+    (without_fragment.rsplit('/').next().unwrap_or(without_fragment), source)
+}
+
+fn is_download(location: &str) -> bool {
+    location.starts_with("https://") || location.starts_with("http://")
+}
+
+fn hash(command: &str, path: &Path) -> Result<String, Box<dyn Error>> {
+    let output = StdCommand::new(command).arg(path).run()?;
+    if !output.status.success() {
+        return Err(format!("{} failed: {}", command, String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.split_whitespace().next().unwrap_or_default().to_string())
+}
+
+// Compares the file at `path` with each of `expected`, as (algorithm, command, sum)
+fn compare(path: &Path, expected: &[(&'static str, &'static str, String)]) -> Status {
+    let mut verified = Vec::new();
+    for (algorithm, command, sum) in expected {
+        match hash(command, path) {
+            Ok(actual) if actual.eq_ignore_ascii_case(sum) => verified.push(*algorithm),
+            Ok(actual) => return Status::Mismatch { algorithm, expected: sum.clone(), actual },
+            Err(e) => return Status::Failed(e.to_string()),
+        }
+    }
+    Status::Verified(verified)
+}
+
+// Streams `url` to `path`, through a partial file so an interrupted download is not taken for the source
+async fn download(url: &str, path: &Path) -> Result<(), String> {
+    let partial = path.with_extension("part");
+    let client = http::client().map_err(|e| e.to_string())?;
+    let mut response = http::send(client.get(url)).await.map_err(|e| e.to_string())?.error_for_status().map_err(|e| e.to_string())?;
+    let mut file = tokio::fs::File::create(&partial).await.map_err(|e| e.to_string())?;
+    while let Some(chunk) = response.chunk().await.map_err(|e| http::readable(e).to_string())? {
+        file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+    }
+    file.flush().await.map_err(|e| e.to_string())?;
+    fs::rename(&partial, path).map_err(|e| e.to_string())
+}
+
+// Downloads the http(s) sources of the package in `build_dir` and checks them, and the files that
+// come with the snapshot, against the PKGBUILD's sha256sums, sha512sums and b2sums. `arch` picks
+// the architecture specific arrays. Downloads are kept where makepkg looks for them, so it does not
+// fetch them again; a file from an earlier build is only downloaded again when it does not match.
+pub async fn verify(build_dir: &str, arch: &str) -> Vec<Entry> {
+    let Ok(srcinfo) = SrcInfo::read(build_dir) else {
+        return Vec::new();
+    };
+    // Sources belong to the package base; split packages cannot have their own
+    let values = |key: &str| srcinfo.fields.iter().filter(|(k, _)| k == key).map(|(_, v)| v.clone()).collect::<Vec<_>>();
+    let mut entries = Vec::new();
+    for suffix in [String::new(), format!("_{}", arch)] {
+        let sums: Vec<(&'static str, &'static str, Vec<String>)> =
+            ALGORITHMS.iter().map(|(algorithm, key, command)| (*algorithm, *command, values(&format!("{}{}", key, suffix)))).collect();
+        for (index, source) in values(&format!("source{}", suffix)).into_iter().enumerate() {
+            let (name, location) = file_name(&source);
+            let name = name.to_string();
+            let listed: Vec<&String> = sums.iter().filter_map(|(_, _, sums)| sums.get(index)).collect();
+            let expected: Vec<(&'static str, &'static str, String)> = sums
+                .iter()
+                .filter_map(|(algorithm, command, sums)| sums.get(index).filter(|sum| *sum != "SKIP").map(|sum| (*algorithm, *command, sum.clone())))
+                .collect();
+            let status = if !srcinfo::is_plain_file_name(&name) {
+                Status::Failed("the file name leaves the build directory".to_string())
+            } else if expected.is_empty() && !listed.is_empty() {
+                Status::Skipped
+            } else if expected.is_empty() {
+                Status::Unchecked("no sha256, sha512 or b2 sum".to_string())
+            } else if location.contains("://") && !is_download(location) {
+                Status::Unchecked("not an http(s) download".to_string())
+            } else {
+                let path = Path::new(build_dir).join(&name);
+                let mut status = if path.is_file() { compare(&path, &expected) } else { Status::Failed(format!("{} is missing", name)) };
+                if is_download(location) && !matches!(status, Status::Verified(_)) {
+                    status = match download(location, &path).await {
+                        Ok(()) => compare(&path, &expected),
+                        Err(e) => Status::Failed(e),
+                    };
+                }
+                status
+            };
+            entries.push(Entry { name, source, status });
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Scripted};
+    use axum::routing::get;
+
+    #[tokio::test]
+    async fn verifies_downloads_and_lists_skipped_sources() {
+        let _isolated = testing::isolate().await;
+        let server = testing::serve(axum::Router::new().route("/foo-1.0.tar.gz", get(|| async { "tarball" })).route("/fix.patch", get(|| async { "patch" })));
+        let dir = testing::temp_dir("source-hashes");
+        fs::write(dir.join("foo.install"), "post_install() { :; }\n").unwrap();
+        let srcinfo = format!(
+            "pkgbase = foo\n\tsource = foo.tar.gz::{0}/foo-1.0.tar.gz\n\tsource = {0}/fix.patch\n\tsource = git+https://example.org/foo.git#tag=1.0\n\tsource = foo.install\n\
+             \tsha256sums = aaaa\n\tsha256sums = bbbb\n\tsha256sums = SKIP\n\tsha256sums = SKIP\n\tb2sums = cccc\n\tb2sums = SKIP\n\tb2sums = SKIP\n\tb2sums = SKIP\n\
+             \tsource_x86_64 = {0}/gone.bin\n\tsha256sums_x86_64 = dddd\n\tsource_aarch64 = {0}/other.bin\n\tsha256sums_aarch64 = eeee\n\npkgname = foo\n",
+            server
+        );
+        fs::write(dir.join(".SRCINFO"), srcinfo).unwrap();
+        let path = |name: &str| dir.join(name).display().to_string();
+        Scripted::default()
+            .answer(&format!("sha256sum {}", path("foo.tar.gz")), &format!("AAAA  {}\n", path("foo.tar.gz")))
+            .answer(&format!("b2sum {}", path("foo.tar.gz")), &format!("cccc  {}\n", path("foo.tar.gz")))
+            .answer(&format!("sha256sum {}", path("fix.patch")), &format!("ffff  {}\n", path("fix.patch")))
+            .install();
+
+        let entries = verify(dir.to_str().unwrap(), "x86_64").await;
+        let statuses: Vec<(&str, &Status)> = entries.iter().map(|entry| (entry.name.as_str(), &entry.status)).collect();
+        assert_eq!(statuses[0], ("foo.tar.gz", &Status::Verified(vec!["sha256", "b2"])));
+        assert_eq!(statuses[1], ("fix.patch", &Status::Mismatch { algorithm: "sha256", expected: "bbbb".to_string(), actual: "ffff".to_string() }));
+        assert_eq!(statuses[2], ("foo.git", &Status::Skipped));
+        assert_eq!(statuses[3], ("foo.install", &Status::Skipped));
+        assert!(matches!(statuses[4], ("gone.bin", Status::Failed(_))));
+        assert_eq!(entries.len(), 5);
+        assert_eq!(fs::read_to_string(dir.join("foo.tar.gz")).unwrap(), "tarball");
+        assert_eq!(summary(&entries), "1 verified, 2 SKIP, 1 mismatched, 1 failed");
+    }
+}
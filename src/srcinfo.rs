@@ -0,0 +1,80 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Component, Path};
+
+// Values such as install= and the names of sources come from the package's author; one that is
+// not a single plain file name could point outside the build directory
+pub fn is_plain_file_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!((components.next(), components.next()), (Some(Component::Normal(_)), None)) && !name.ends_with('/')
+}
+
+pub struct SrcInfoPackage {
+    pub name: String,
+    pub fields: Vec<(String, String)>,
+}
+
+pub struct SrcInfo {
+    pub pkgbase: String,
+    pub fields: Vec<(String, String)>,
+    pub packages: Vec<SrcInfoPackage>,
+}
+
+impl SrcInfo {
+    pub fn parse(content: &str) -> SrcInfo {
+        let mut info = SrcInfo {
+            pkgbase: String::new(),
+            fields: Vec::new(),
+            packages: Vec::new(),
+        };
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (key, value) = (key.trim().to_string(), value.trim().to_string());
+
+            match key.as_str() {
+                "pkgbase" => info.pkgbase = value,
+                "pkgname" => info.packages.push(SrcInfoPackage {
+                    name: value,
+                    fields: Vec::new(),
+                }),
+                _ => match info.packages.last_mut() {
+                    Some(package) => package.fields.push((key, value)),
+                    None => info.fields.push((key, value)),
+                },
+            }
+        }
+        info
+    }
+
+    pub fn read(build_dir: &str) -> Result<SrcInfo, Box<dyn Error>> {
+        let content = fs::read_to_string(Path::new(build_dir).join(".SRCINFO"))?;
+        Ok(SrcInfo::parse(&content))
+    }
+
+    // Values for a key from the pkgbase section and every package section
+    pub fn all_values(&self, key: &str) -> Vec<&str> {
+        self.fields
+            .iter()
+            .chain(self.packages.iter().flat_map(|package| package.fields.iter()))
+            .filter(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+            .collect()
+    }
+
+    // `arch` entries of the pkgbase and of split packages; `any` builds everywhere
+    pub fn supports_arch(&self, arch: &str) -> bool {
+        let arches = self.all_values("arch");
+        arches.is_empty() || arches.iter().any(|supported| *supported == "any" || *supported == arch)
+    }
+
+    pub fn package_names(&self) -> Vec<String> {
+        self.packages.iter().map(|package| package.name.clone()).collect()
+    }
+}
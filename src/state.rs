@@ -0,0 +1,300 @@
+use crate::build_times::Build;
+use crate::Package;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+// Layout of state.json. Each change to it bumps this and adds the step from the one before to
+// MIGRATIONS, so files written by earlier releases are brought up to date when read.
+const VERSION: u64 = 1;
+
+type Migration = fn(&mut Value) -> Result<(), Box<dyn Error>>;
+
+// MIGRATIONS[n] turns a file of version n into one of version n + 1
+const MIGRATIONS: [Migration; 1] = [import_build_times];
+
+const KEPT_SEARCHES: usize = 20;
+const KEPT_TRANSACTIONS: usize = 500;
+
+// Saves of this process wait for each other here, and for other processes at the lock file
+static SAVING: Mutex<()> = Mutex::new(());
+
+// Numbers the temporary files of this process
+static SAVES: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Favorite {
+    // Backend name, e.g. "aur" or "flatpak"
+    pub source: String,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Install,
+    Remove,
+    Update,
+    // pacman -Syu, with the packages picked for it; none when it ran before an install
+    Upgrade,
+}
+
+// An install, update, upgrade or removal that reached the package manager, whether or not it succeeded
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Transaction {
+    // Unix time it finished
+    pub finished: i64,
+    pub action: Action,
+    pub source: String,
+    // Package names, or package files for pacman installs
+    pub targets: Vec<String>,
+    pub error: Option<String>,
+}
+
+// AUR metadata of a package with the Unix time it was fetched, so the TTL holds across sessions
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CachedMetadata {
+    pub fetched: i64,
+    pub package: Package,
+}
+
+// What the app remembers between sessions
+#[derive(Serialize, Deserialize, Default)]
+pub struct State {
+    #[serde(default)]
+    pub favorites: Vec<Favorite>,
+    // Newest first
+    #[serde(default)]
+    pub searches: Vec<String>,
+    // Durations of past successful builds by package name
+    #[serde(default)]
+    pub builds: HashMap<String, Vec<Build>>,
+    // Oldest first
+    #[serde(default)]
+    pub transactions: Vec<Transaction>,
+    // By package name
+    #[serde(default)]
+    pub metadata: HashMap<String, CachedMetadata>,
+}
+
+#[derive(Serialize)]
+struct StateFile<'a> {
+    version: u64,
+    #[serde(flatten)]
+    state: &'a State,
+}
+
+fn data_dir() -> Result<PathBuf, Box<dyn Error>> {
+    let data_dir = dirs::data_dir().ok_or("Could not determine data directory")?;
+    Ok(data_dir.join("aur-helper"))
+}
+
+fn path() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(data_dir()?.join("state.json"))
+}
+
+// Version 1 took over the build times from build-times.json, which had the same layout
+fn import_build_times(file: &mut Value) -> Result<(), Box<dyn Error>> {
+    let legacy = data_dir()?.join("build-times.json");
+    if let Some(builds) = fs::read_to_string(legacy).ok().and_then(|content| serde_json::from_str::<Value>(&content).ok()).and_then(|mut old| old.get_mut("builds").map(Value::take)) {
+        file["builds"] = builds;
+    }
+    Ok(())
+}
+
+// Brings `file` up to VERSION; a file without a version predates state.json
+fn migrate(file: &mut Value) -> Result<(), Box<dyn Error>> {
+    let version = file.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version > VERSION {
+        return Err(format!("The state file was written by a newer version of the app (format {}, this one reads up to {})", version, VERSION).into());
+    }
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(file)?;
+    }
+    file["version"] = VERSION.into();
+    Ok(())
+}
+
+pub fn load() -> Result<State, Box<dyn Error>> {
+    let path = path()?;
+    let mut file = match fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).map_err(|e| format!("Invalid state file {}: {}", path.display(), e))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Value::Object(Default::default()),
+        Err(e) => return Err(e.into()),
+    };
+    migrate(&mut file)?;
+    serde_json::from_value(file).map_err(|e| format!("Invalid state file {}: {}", path.display(), e).into())
+}
+
+// The state for reading only; what cannot be read counts as empty
+pub fn read() -> State {
+    load().unwrap_or_else(|e| {
+        tracing::warn!("Could not read the saved state: {}", e);
+        State::default()
+    })
+}
+
+// Written to a temporary file of its own and synced before it replaces the old one, so readers
+// and an interrupted write only ever see a whole file
+fn store(state: &State) -> Result<(), Box<dyn Error>> {
+    let path = path()?;
+    let partial = path.with_extension(format!("json.{}.{}.part", std::process::id(), SAVES.fetch_add(1, Ordering::Relaxed)));
+    let written = File::create(&partial).and_then(|mut file| {
+        file.write_all(serde_json::to_string(&StateFile { version: VERSION, state })?.as_bytes())?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|_| fs::rename(&partial, &path)) {
+        let _ = fs::remove_file(&partial);
+        return Err(e.into());
+    }
+    // Imported by the migration to version 1
+    let _ = fs::remove_file(data_dir()?.join("build-times.json"));
+    Ok(())
+}
+
+// Applies `change` to the saved state and saves it. Saves of this and other processes, such as the
+// CLI beside the GUI, take turns, so none is lost. A file of a newer release is left alone; one
+// that cannot be read at all is set aside as state.json.invalid and started over.
+pub fn update<T>(change: impl FnOnce(&mut State) -> T) -> Result<T, Box<dyn Error>> {
+    let _saving = SAVING.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let dir = data_dir()?;
+    fs::create_dir_all(&dir)?;
+    let lock = OpenOptions::new().write(true).create(true).truncate(false).open(dir.join("state.lock"))?;
+    lock.lock()?;
+    let mut state = match load() {
+        Ok(state) => state,
+        Err(e) if e.to_string().starts_with("Invalid state file") => {
+            tracing::warn!("{}; starting over", e);
+            fs::rename(path()?, dir.join("state.json.invalid"))?;
+            State::default()
+        }
+        Err(e) => return Err(e),
+    };
+    let result = change(&mut state);
+    store(&state)?;
+    Ok(result)
+}
+
+// Adds or removes a favorite; true when it is one afterwards
+pub fn toggle_favorite(source: &str, name: &str) -> Result<bool, Box<dyn Error>> {
+    toggle_favorites(&[(source, name)])
+}
+
+// Removes `packages` from the favorites when all of them are ones, and adds those that are not
+// otherwise, in one save; true when they are favorites afterwards. `packages` are (source, name).
+pub fn toggle_favorites(packages: &[(&str, &str)]) -> Result<bool, Box<dyn Error>> {
+    let packages: Vec<Favorite> = packages.iter().map(|(source, name)| Favorite { source: source.to_string(), name: name.to_string() }).collect();
+    update(|state| {
+        if packages.iter().all(|favorite| state.favorites.contains(favorite)) {
+            state.favorites.retain(|known| !packages.contains(known));
+            return false;
+        }
+        for favorite in packages {
+            if !state.favorites.contains(&favorite) {
+                state.favorites.push(favorite);
+            }
+        }
+        state.favorites.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.source.cmp(&b.source)));
+        true
+    })
+}
+
+// Moves `query` to the front of the search history; returns the history
+pub fn remember_search(query: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    update(|state| {
+        state.searches.retain(|search| search != query);
+        state.searches.insert(0, query.to_string());
+        state.searches.truncate(KEPT_SEARCHES);
+        state.searches.clone()
+    })
+}
+
+pub fn record_transaction(action: Action, source: &str, targets: &[String], error: Option<String>) -> Result<(), Box<dyn Error>> {
+    update(|state| {
+        state.transactions.push(Transaction {
+            finished: chrono::Utc::now().timestamp(),
+            action,
+            source: source.to_string(),
+            targets: targets.to_vec(),
+            error,
+        });
+        let excess = state.transactions.len().saturating_sub(KEPT_TRANSACTIONS);
+        state.transactions.drain(..excess);
+    })
+}
+
+// Keeps `packages` as fetched now and drops entries older than `ttl_seconds`
+pub fn cache_metadata(packages: &[Package], ttl_seconds: i64) -> Result<(), Box<dyn Error>> {
+    let now = chrono::Utc::now().timestamp();
+    update(|state| {
+        state.metadata.retain(|_, cached| now - cached.fetched < ttl_seconds);
+        for package in packages {
+            state.metadata.insert(package.name.clone(), CachedMetadata { fetched: now, package: package.clone() });
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn migrates_build_times_and_refuses_newer_files() {
+        let _isolated = testing::isolate_blocking();
+        let dir = data_dir().unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        let _ = fs::remove_file(path().unwrap());
+        fs::write(dir.join("build-times.json"), r#"{"builds":{"legacy":[{"version":"1.0-1","seconds":90,"finished":1700000000}]}}"#).unwrap();
+
+        assert!(toggle_favorite("aur", "foo").unwrap());
+        assert_eq!(remember_search("bar").unwrap(), ["bar"]);
+        assert_eq!(remember_search("foo").unwrap(), ["foo", "bar"]);
+        assert_eq!(remember_search("bar").unwrap(), ["bar", "foo"]);
+        record_transaction(Action::Remove, "aur", &["foo".to_string()], Some("pacman failed".to_string())).unwrap();
+
+        let state = load().unwrap();
+        assert_eq!(state.builds["legacy"].len(), 1);
+        assert_eq!(state.favorites, [Favorite { source: "aur".to_string(), name: "foo".to_string() }]);
+        assert_eq!(state.transactions[0].error.as_deref(), Some("pacman failed"));
+        assert!(!dir.join("build-times.json").exists());
+        let saved: Value = serde_json::from_str(&fs::read_to_string(path().unwrap()).unwrap()).unwrap();
+        assert_eq!(saved["version"], VERSION);
+        assert!(!toggle_favorite("aur", "foo").unwrap());
+        assert!(toggle_favorite("flatpak", "foo").unwrap());
+        assert!(toggle_favorites(&[("aur", "foo"), ("flatpak", "foo")]).unwrap());
+        assert_eq!(load().unwrap().favorites.len(), 2);
+        assert!(!toggle_favorites(&[("aur", "foo"), ("flatpak", "foo")]).unwrap());
+        assert!(load().unwrap().favorites.is_empty());
+
+        fs::write(path().unwrap(), r#"{"version":99,"favorites":[]}"#).unwrap();
+        assert!(remember_search("baz").is_err());
+        assert!(fs::read_to_string(path().unwrap()).unwrap().contains("99"));
+    }
+
+    #[test]
+    fn overlapping_saves_keep_every_change() {
+        let _isolated = testing::isolate_blocking();
+        let _ = fs::remove_file(path().unwrap());
+        let writers: Vec<_> = (0..8)
+            .map(|writer| std::thread::spawn(move || record_transaction(Action::Install, "aur", &[format!("package{}", writer)], None).unwrap()))
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(load().unwrap().transactions.len(), 8);
+        let leftovers = fs::read_dir(data_dir().unwrap()).unwrap().filter(|entry| entry.as_ref().unwrap().file_name().to_string_lossy().ends_with(".part")).count();
+        assert_eq!(leftovers, 0);
+
+        fs::write(path().unwrap(), "{\"version\":1,\"favorites\":").unwrap();
+        assert!(toggle_favorite("aur", "foo").unwrap());
+        assert_eq!(read().favorites.len(), 1);
+        assert!(data_dir().unwrap().join("state.json.invalid").exists());
+    }
+}
@@ -0,0 +1,46 @@
+use crate::config::HostProfile;
+use crate::remote;
+use std::error::Error;
+use std::process::{Command as StdCommand, Stdio};
+
+// Emulators tried in order when none is configured, with the arguments that come before the
+// command they should run
+const TERMINALS: [(&str, &[&str]); 9] = [
+    ("kitty", &[]),
+    ("alacritty", &["-e"]),
+    ("foot", &[]),
+    ("wezterm", &["start", "--"]),
+    ("konsole", &["-e"]),
+    ("gnome-terminal", &["--"]),
+    ("kgx", &["--"]),
+    ("xfce4-terminal", &["-x"]),
+    ("xterm", &["-e"]),
+];
+
+// `configured` first, then $TERMINAL, then the first installed entry of TERMINALS
+fn find(configured: &str) -> Option<String> {
+    if !configured.is_empty() {
+        return Some(configured.to_string());
+    }
+    if let Some(terminal) = std::env::var("TERMINAL").ok().filter(|terminal| !terminal.is_empty()) {
+        return Some(terminal);
+    }
+    TERMINALS.iter().map(|(name, _)| name.to_string()).find(|name| crate::command_exists(name))
+}
+
+// Opens a shell in `dir` on `host`, or this machine, in a new terminal window that outlives the app
+pub fn open(dir: &str, host: Option<&HostProfile>, configured: &str) -> Result<(), Box<dyn Error>> {
+    let terminal = find(configured).ok_or("No terminal emulator found; set `terminal` in the config or $TERMINAL")?;
+    let program = terminal.rsplit('/').next().unwrap_or(&terminal);
+    // Unknown emulators get -e, which most of them understand
+    let exec: &[&str] = TERMINALS.iter().find(|(name, _)| *name == program).map(|(_, exec)| *exec).unwrap_or(&["-e"]);
+    StdCommand::new(&terminal)
+        .args(exec)
+        .args(remote::shell_in(dir, host))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Could not start {}: {}", terminal, e))?;
+    Ok(())
+}
@@ -0,0 +1,132 @@
+// Support for tests: a scripted command runner, a local stand-in for the AUR and isolation of the
+// process-wide state both of them change
+use crate::config::Config;
+use crate::http;
+use crate::runner::{self, CommandRunner};
+use std::io;
+use std::os::unix::process::ExitStatusExt;
+use std::path::PathBuf;
+use std::process::{Command as StdCommand, ExitStatus, Output};
+use std::sync::{Mutex, Once};
+use std::{env, fs, thread};
+use tokio::sync::{Mutex as AsyncMutex, MutexGuard};
+
+// The runner and HTTP settings are global, so tests that set them run one at a time
+static SERIAL: AsyncMutex<()> = AsyncMutex::const_new(());
+static ENVIRONMENT: Once = Once::new();
+
+// Held for the length of a test; puts the real runner and default HTTP settings back when dropped
+pub struct Isolated {
+    _serial: MutexGuard<'static, ()>,
+}
+
+impl Drop for Isolated {
+    fn drop(&mut self) {
+        runner::reset();
+        http::configure(&Config::default());
+    }
+}
+
+// Caches, reviews and config go to a directory of this test run instead of the user's
+fn isolate_environment() {
+    ENVIRONMENT.call_once(|| {
+        let root = env::temp_dir().join(format!("cookin-tests-{}", std::process::id()));
+        for (var, dir) in [("XDG_CACHE_HOME", "cache"), ("XDG_DATA_HOME", "data"), ("XDG_CONFIG_HOME", "config"), ("XDG_RUNTIME_DIR", "runtime"), ("XDG_STATE_HOME", "state")] {
+            let path = root.join(dir);
+            fs::create_dir_all(&path).unwrap();
+            env::set_var(var, path);
+        }
+    });
+}
+
+pub async fn isolate() -> Isolated {
+    isolate_environment();
+    Isolated { _serial: SERIAL.lock().await }
+}
+
+// For tests that are not async
+pub fn isolate_blocking() -> Isolated {
+    isolate_environment();
+    Isolated { _serial: SERIAL.blocking_lock() }
+}
+
+// An empty directory for one test
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("cookin-tests-{}", std::process::id())).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// Serves `router` on a free local port from a thread of its own, so tests that block on a request
+// do not starve it; returns the server's address
+pub fn serve(router: axum::Router) -> String {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async move {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            sender.send(listener.local_addr().unwrap()).unwrap();
+            axum::serve(listener, router).await.unwrap();
+        });
+    });
+    format!("http://{}", receiver.recv().unwrap())
+}
+
+// Sends every AUR request to `url`, with `configure` adjusting the rest of the settings
+pub fn use_aur(url: &str, configure: impl FnOnce(&mut Config)) {
+    let mut config = Config {
+        aur_url: url.to_string(),
+        ..Default::default()
+    };
+    configure(&mut config);
+    http::configure(&config);
+}
+
+// Answers commands by their full command line, e.g. "pacman -Qm"; any other command fails as if
+// the program was missing
+#[derive(Default)]
+pub struct Scripted {
+    answers: Vec<(String, i32, String, String)>,
+    calls: Mutex<Vec<String>>,
+}
+
+impl Scripted {
+    pub fn answer(mut self, line: &str, stdout: &str) -> Scripted {
+        self.answers.push((line.to_string(), 0, stdout.to_string(), String::new()));
+        self
+    }
+
+    pub fn fail(mut self, line: &str, stderr: &str) -> Scripted {
+        self.answers.push((line.to_string(), 1, String::new(), stderr.to_string()));
+        self
+    }
+
+    // Runs every command through this script until the test's `Isolated` is dropped
+    pub fn install(self) -> &'static Scripted {
+        let scripted: &'static Scripted = Box::leak(Box::new(self));
+        runner::set(scripted);
+        scripted
+    }
+
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+impl CommandRunner for Scripted {
+    fn output(&self, command: &mut StdCommand) -> io::Result<Output> {
+        let line = runner::command_line(command);
+        self.calls.lock().unwrap().push(line.clone());
+        let (_, code, stdout, stderr) = self
+            .answers
+            .iter()
+            .find(|(scripted, ..)| *scripted == line)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} is not scripted", line)))?;
+        Ok(Output {
+            status: ExitStatus::from_raw(code << 8),
+            stdout: stdout.clone().into_bytes(),
+            stderr: stderr.clone().into_bytes(),
+        })
+    }
+}
@@ -0,0 +1,360 @@
+// Tests of the AUR client and the install pipeline against a local stand-in for the AUR
+use super::*;
+use crate::testing::{self, Scripted};
+use axum::extract::RawQuery;
+use flate2::write::GzEncoder;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const SNAPSHOT_PATH: &str = "/cgit/aur.git/snapshot/foo.tar.gz";
+
+// Decoded values of `key` in an RPC query string; `arg` also matches the `arg[]` of info requests
+fn query_values(query: &str, key: &str) -> Vec<String> {
+    reqwest::Url::parse(&format!("http://aur/rpc/?{}", query))
+        .unwrap()
+        .query_pairs()
+        .filter(|(name, _)| name.trim_end_matches("[]") == key)
+        .map(|(_, value)| value.into_owned())
+        .collect()
+}
+
+// Answers info requests from `packages`, searches with all of them and suggestions with their
+// names; `snapshot` is served at SNAPSHOT_PATH
+fn fake_aur(packages: serde_json::Value, snapshot: Vec<u8>) -> axum::Router {
+    let rpc = move |RawQuery(query): RawQuery| {
+        let packages = packages.as_array().cloned().unwrap_or_default();
+        async move {
+            let query = query.unwrap_or_default();
+            let args = query_values(&query, "arg");
+            let reply = match query_values(&query, "type").first().map(String::as_str) {
+                Some("info") => serde_json::json!({ "results": packages.into_iter().filter(|pkg| args.iter().any(|arg| pkg["Name"] == *arg)).collect::<Vec<_>>() }),
+                Some("search") => serde_json::json!({ "results": packages }),
+                Some("suggest") => serde_json::json!(packages.iter().map(|pkg| pkg["Name"].clone()).collect::<Vec<_>>()),
+                _ => serde_json::json!({ "error": "Incorrect request type specified." }),
+            };
+            axum::Json(reply)
+        }
+    };
+    axum::Router::new().route("/rpc/", axum::routing::get(rpc)).route(SNAPSHOT_PATH, axum::routing::get(move || async move { snapshot }))
+}
+
+// A gzipped tarball of (path, contents), like the snapshots the AUR hands out
+fn snapshot(files: &[(&str, &str)]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), flate2::Compression::default()));
+    for (path, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        // Set by hand, since the tar crate refuses to write the hostile paths some tests need
+        header.as_old_mut().name[..path.len()].copy_from_slice(path.as_bytes());
+        header.set_cksum();
+        builder.append(&header, contents.as_bytes()).unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+fn foo() -> serde_json::Value {
+    serde_json::json!({
+        "Name": "foo",
+        "PackageBase": "foo",
+        "Version": "1.0-1",
+        "Description": "Frobnicates widgets",
+        "URLPath": SNAPSHOT_PATH,
+        "Maintainer": "alice",
+        "NumVotes": 42,
+        "Popularity": 1.25,
+    })
+}
+
+const PKGBUILD: &str = "pkgbase=foo\npkgname=(foo foo-docs)\npkgver=1.0\npkgrel=1\narch=(any)\ninstall=foo.install\n";
+const SRCINFO: &str = "pkgbase = foo\n\tpkgver = 1.0\n\tpkgrel = 1\n\tarch = any\n\npkgname = foo\n\tinstall = foo.install\n\npkgname = foo-docs\n";
+
+fn build_state(name: &str) -> Arc<Mutex<AppState>> {
+    let build_dir = testing::temp_dir(name).to_string_lossy().into_owned();
+    Arc::new(Mutex::new(AppState {
+        config: Config { build_dir, ..Default::default() },
+        ..Default::default()
+    }))
+}
+
+#[test]
+fn reads_package_metadata_from_rpc_results() {
+    let package = Package::from_rpc(&foo());
+    assert_eq!((package.name.as_str(), package.package_base.as_str(), package.version.as_str()), ("foo", "foo", "1.0-1"));
+    assert_eq!(package.description, "Frobnicates widgets");
+    assert_eq!(package.urlpath, SNAPSHOT_PATH);
+    assert_eq!(package.maintainer.as_deref(), Some("alice"));
+    assert_eq!((package.votes, package.popularity), (42, 1.25));
+
+    // Orphans have a null maintainer
+    let orphan = Package::from_rpc(&serde_json::json!({ "Name": "bar", "Maintainer": null }));
+    assert_eq!(orphan.maintainer, None);
+    assert_eq!(orphan.votes, 0);
+}
+
+#[tokio::test]
+async fn searches_return_names_with_their_bases() {
+    let _isolated = testing::isolate().await;
+    let packages = serde_json::json!([
+        { "Name": "linux-zen", "PackageBase": "linux-zen" },
+        { "Name": "linux-zen-headers", "PackageBase": "linux-zen" },
+    ]);
+    testing::use_aur(&testing::serve(fake_aur(packages, Vec::new())), |_| {});
+
+    let found = search_aur_with_bases("zen", SearchBy::NameDesc).await.unwrap();
+    assert_eq!(
+        found,
+        [("linux-zen".to_string(), "linux-zen".to_string()), ("linux-zen-headers".to_string(), "linux-zen".to_string())]
+    );
+    assert_eq!(search_aur("zen", SearchBy::Maintainer).await.unwrap(), ["linux-zen", "linux-zen-headers"]);
+}
+
+#[tokio::test]
+async fn metadata_is_fetched_in_batches() {
+    let _isolated = testing::isolate().await;
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&requests);
+    let router = axum::Router::new().route(
+        "/rpc/",
+        axum::routing::get(move |RawQuery(query): RawQuery| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let results: Vec<serde_json::Value> = query_values(&query.unwrap_or_default(), "arg").into_iter().map(|name| serde_json::json!({ "Name": name })).collect();
+            async move { axum::Json(serde_json::json!({ "results": results })) }
+        }),
+    );
+    testing::use_aur(&testing::serve(router), |_| {});
+
+    let mut names: Vec<String> = (0..150).map(|index| format!("package-{}", index)).collect();
+    names.extend(["libc++".to_string(), "dvd+rw-tools".to_string()]);
+    let packages = fetch_metadata_batch(&names).await.unwrap();
+    assert_eq!(packages.iter().map(|package| package.name.clone()).collect::<Vec<_>>(), names);
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    // Update checks go through the same batches
+    let versions = aur_versions(&names).await.unwrap();
+    assert_eq!(versions.len(), names.len());
+    assert_eq!(versions[151].0, "dvd+rw-tools");
+    assert_eq!(requests.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn typed_prefixes_reach_the_rpc_as_typed() {
+    let _isolated = testing::isolate().await;
+    let router = axum::Router::new().route(
+        "/rpc/",
+        axum::routing::get(|RawQuery(query): RawQuery| async move { axum::Json(query_values(&query.unwrap_or_default(), "arg")) }),
+    );
+    testing::use_aur(&testing::serve(router), |_| {});
+
+    assert_eq!(suggest_aur("c++").await.unwrap(), ["c++"]);
+    assert_eq!(suggest_aur("a&b c").await.unwrap(), ["a&b c"]);
+}
+
+#[tokio::test]
+async fn unknown_packages_get_suggestions() {
+    let _isolated = testing::isolate().await;
+    let packages = serde_json::json!([{ "Name": "yay" }, { "Name": "yaourt" }]);
+    testing::use_aur(&testing::serve(fake_aur(packages, Vec::new())), |_| {});
+
+    let Err(error) = fetch_metadata("yya").await else {
+        panic!("yya is not in the fake AUR");
+    };
+    assert_eq!(error.to_string(), "Package yya not found in the AUR. Did you mean yay?");
+    assert_eq!(exit_codes::classify(error.as_ref()), ExitCode::NotFound);
+}
+
+#[tokio::test]
+async fn installed_results_are_compared_with_the_aur() {
+    let _isolated = testing::isolate().await;
+    let packages = serde_json::json!([
+        { "Name": "foo", "Version": "1.1-1" },
+        { "Name": "bar-git", "Version": "r5-1" },
+        { "Name": "baz", "Version": "2.0-1" },
+    ]);
+    testing::use_aur(&testing::serve(fake_aur(packages, Vec::new())), |_| {});
+    Scripted::default()
+        .answer("pacman -Qm", "foo 1.0-1\nbar-git r10-1\nbaz 2.0-1\nqux 1.0-1\n")
+        .answer("vercmp 1.0-1 1.1-1", "-1\n")
+        .answer("vercmp 2.0-1 2.0-1", "0\n")
+        .install();
+
+    let results: Vec<(&'static str, String)> = ["foo", "bar-git", "baz", "missing"].iter().map(|name| ("pacman", name.to_string())).collect();
+    let versions = result_versions(&results).await;
+    let state = |name: &str| versions.get(&("pacman", name.to_string())).map(|version| version.state);
+    assert!(state("foo") == Some(VersionState::Outdated));
+    // A VCS package's AUR version only records the last PKGBUILD change
+    assert!(state("bar-git") == Some(VersionState::LocalNewer));
+    assert!(state("baz") == Some(VersionState::UpToDate));
+    assert!(state("missing").is_none());
+    assert!(state("qux").is_none());
+}
+
+#[tokio::test]
+async fn prepares_a_review_of_the_downloaded_snapshot() {
+    let _isolated = testing::isolate().await;
+    let files = [("foo/PKGBUILD", PKGBUILD), ("foo/.SRCINFO", SRCINFO), ("foo/foo.install", "post_install() { :; }\n")];
+    testing::use_aur(&testing::serve(fake_aur(serde_json::json!([foo()]), snapshot(&files))), |_| {});
+    let state = build_state("prepare");
+
+    let review = prepare_package("foo", &state).await.unwrap();
+    assert_eq!((review.package.as_str(), review.package_base.as_str(), review.version.as_str()), ("foo", "foo", "1.0-1"));
+    assert!(review.build_dir.ends_with("/prepare/foo/foo"));
+    assert_eq!(review.files.iter().map(|file| (file.name.as_str(), file.is_scriptlet)).collect::<Vec<_>>(), [("PKGBUILD", false), ("foo.install", true)]);
+    assert_eq!(review.split_packages, ["foo", "foo-docs"]);
+    assert_eq!(review.selected_packages, ["foo"]);
+    assert_eq!(state.lock().unwrap().progress.as_deref(), Some("Package downloaded and extracted."));
+    assert!(state.lock().unwrap().step == Some(Step::Review));
+    // Nothing was reviewed before, so every file is new
+    assert!(review.has_changes());
+
+    // Once reviewed, the same snapshot has nothing new to show
+    review::save_reviewed(&review).unwrap();
+    assert!(!prepare_package("foo", &state).await.unwrap().has_changes());
+}
+
+#[tokio::test]
+async fn refuses_install_scriptlets_outside_the_build_directory() {
+    let _isolated = testing::isolate().await;
+    for install in ["../../../../etc/passwd", "/etc/passwd", "sub/foo.install"] {
+        let srcinfo = format!("pkgbase = foo\n\tpkgver = 1.0\n\tpkgrel = 1\n\npkgname = foo\n\tinstall = {}\n", install);
+        let files = [("foo/PKGBUILD", PKGBUILD), ("foo/.SRCINFO", srcinfo.as_str()), ("foo/sub/foo.install", "post_install() { :; }\n")];
+        testing::use_aur(&testing::serve(fake_aur(serde_json::json!([foo()]), snapshot(&files))), |_| {});
+        let state = build_state("install-outside");
+
+        let Err(error) = prepare_package("foo", &state).await else {
+            panic!("install = {} was accepted", install);
+        };
+        assert!(error.to_string().starts_with(&format!("Refusing the install scriptlet {}", install)), "{}", error);
+    }
+    assert!(srcinfo::is_plain_file_name("foo.install"));
+    assert!(!srcinfo::is_plain_file_name(".."));
+    assert!(!srcinfo::is_plain_file_name("./"));
+}
+
+#[tokio::test]
+async fn refuses_snapshots_that_escape_the_build_directory() {
+    let _isolated = testing::isolate().await;
+    let files = [("foo/PKGBUILD", PKGBUILD), ("../escaped", "gotcha\n")];
+    testing::use_aur(&testing::serve(fake_aur(serde_json::json!([foo()]), snapshot(&files))), |_| {});
+    let state = build_state("escape");
+
+    let Err(error) = prepare_package("foo", &state).await else {
+        panic!("the snapshot has an entry outside the build directory");
+    };
+    assert!(error.to_string().starts_with("Refusing to unpack the snapshot: entry ../escaped points outside"), "{}", error);
+    assert!(!testing::temp_dir("escape").join("escaped").exists());
+}
+
+#[tokio::test]
+async fn refuses_snapshots_without_a_pkgbuild() {
+    let _isolated = testing::isolate().await;
+    testing::use_aur(&testing::serve(fake_aur(serde_json::json!([foo()]), snapshot(&[("foo/README", "hello\n")]))), |_| {});
+    let state = build_state("no-pkgbuild");
+
+    let Err(error) = prepare_package("foo", &state).await else {
+        panic!("the snapshot has no PKGBUILD");
+    };
+    assert!(error.to_string().starts_with("The snapshot of foo has no PKGBUILD in "), "{}", error);
+}
+
+#[tokio::test]
+async fn refuses_downloads_that_are_not_archives() {
+    let _isolated = testing::isolate().await;
+    let page = b"<html>Maintenance</html>".to_vec();
+    testing::use_aur(&testing::serve(fake_aur(serde_json::json!([foo()]), page)), |_| {});
+    let state = build_state("not-an-archive");
+
+    let Err(error) = prepare_package("foo", &state).await else {
+        panic!("an HTML page is not a snapshot");
+    };
+    assert!(error.to_string().ends_with("is neither a gzip nor a zstd archive"), "{}", error);
+    assert!(state.lock().unwrap().step == Some(Step::Download));
+}
+
+#[tokio::test]
+async fn falls_back_to_snapshot_mirrors() {
+    let _isolated = testing::isolate().await;
+    let files = [("foo/PKGBUILD", PKGBUILD), ("foo/.SRCINFO", SRCINFO)];
+    let mirror = testing::serve(fake_aur(serde_json::json!([]), snapshot(&files)));
+    testing::use_aur(&testing::serve(fake_aur(serde_json::json!([foo()]), b"<html>Maintenance</html>".to_vec())), |_| {});
+    let state = build_state("mirrors");
+    let build_root = state.lock().unwrap().config.build_dir.clone();
+    let git_mirror = "https://git.example.org/aur.git".to_string();
+    state.lock().unwrap().config.snapshot_mirrors = vec![git_mirror.clone(), mirror.clone()];
+    Scripted::default()
+        .fail(&format!("git clone --quiet --depth 1 --single-branch --branch foo {} {}/foo.git", git_mirror, build_root), "fatal: unable to access")
+        .install();
+
+    let review = prepare_package("foo", &state).await.unwrap();
+    assert_eq!(review.split_packages, ["foo", "foo-docs"]);
+    let logged = state.lock().unwrap().log.entries.iter().map(|entry| entry.message.clone()).collect::<Vec<_>>();
+    assert!(logged.contains(&format!("Downloaded the build files of foo from {}{}", mirror, SNAPSHOT_PATH)), "{:?}", logged);
+
+    // With every mirror failing, each one's error is reported
+    state.lock().unwrap().config.snapshot_mirrors = vec![git_mirror];
+    let Err(error) = prepare_package("foo", &state).await else {
+        panic!("neither the AUR nor the mirror has a snapshot");
+    };
+    assert!(error.to_string().contains("is neither a gzip nor a zstd archive; https://git.example.org/aur.git: git clone of foo"), "{}", error);
+}
+
+#[test]
+fn dry_runs_with_the_root_helper_escalate_per_call() {
+    let _isolated = testing::isolate_blocking();
+    // The helper is not started for dry runs, so the call escalates with whatever tool is found
+    let tools = testing::temp_dir("escalation-tools");
+    fs::write(tools.join("sudo"), "").unwrap();
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    std::env::set_var("PATH", std::env::join_paths(std::iter::once(tools).chain(std::env::split_paths(&path))).unwrap());
+    let scripted = Scripted::default().answer("id -u", "1000\n").install();
+    runner::set_dry_run(true);
+
+    let installed = install_package(backend::by_name("pacman"), &["/tmp/foo-1.0-1-any.pkg.tar.zst".to_string()], helper::ESCALATION_TOOL);
+    let removed = uninstall_package(backend::by_name("pacman"), "foo", RemoveMode::Package, helper::ESCALATION_TOOL);
+    std::env::set_var("PATH", path);
+    installed.unwrap();
+    removed.unwrap();
+    assert_eq!(scripted.calls(), ["id -u", "id -u"]);
+}
+
+#[test]
+fn failures_keep_what_a_retry_needs() {
+    let mut state = AppState::default();
+    state.log.begin_operation("Build and install foo", Some("foo"));
+    for line in 0..30 {
+        state.log(&format!("\x1b[1mline {}\x1b[0m", line));
+    }
+    state.error = Some("makepkg exited with exit status: 4".to_string());
+    state.step = Some(Step::Build);
+    state.build_dir = Some(("/tmp/build/foo/foo".to_string(), None));
+
+    state.record_failure("pacman", "foo", None);
+    let failure = state.failure.as_ref().unwrap();
+    assert!(failure.step == Step::Build);
+    assert_eq!(failure.error, "makepkg exited with exit status: 4");
+    assert_eq!(failure.output.len(), FAILURE_OUTPUT_LINES);
+    assert_eq!((failure.output[0].as_str(), failure.output[19].as_str()), ("line 10", "line 29"));
+    assert_eq!(failure.build_dir.as_ref().map(|(dir, _)| dir.as_str()), Some("/tmp/build/foo/foo"));
+    // Taken over by the failure, so the next install starts afresh
+    assert!(state.step.is_none() && state.build_dir.is_none());
+}
+
+#[test]
+fn progress_covers_the_queued_installs() {
+    let mut state = AppState {
+        step: Some(Step::Build),
+        ..Default::default()
+    };
+    assert!(state.transaction_progress().is_none());
+
+    state.is_running = true;
+    assert_eq!(state.transaction_progress(), Some(0.15));
+    // The second of three queued packages is being installed
+    for package in ["foo", "bar", "baz"] {
+        state.queue.push(package.to_string());
+    }
+    state.queue.start_next();
+    state.queue.finish(None);
+    state.queue.start_next();
+    state.step = Some(Step::Install);
+    assert_eq!(state.transaction_progress(), Some(1.9 / 3.0));
+}
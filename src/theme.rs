@@ -0,0 +1,67 @@
+use crate::config::{Config, ThemePreference};
+use crate::i18n::tr;
+use eframe::egui::{self, Color32};
+
+pub fn parse_hex_color(hex: &str) -> Option<Color32> {
+    let hex = hex.trim().strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |range| u8::from_str_radix(hex.get(range)?, 16).ok();
+    Some(Color32::from_rgb(channel(0..2)?, channel(2..4)?, channel(4..6)?))
+}
+
+pub fn to_hex_color(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+// Base visuals follow the theme preference; custom colors from the config are layered on top
+pub fn visuals(config: &Config, system_theme: Option<eframe::Theme>) -> egui::Visuals {
+    let mut visuals = match config.theme {
+        ThemePreference::Dark => egui::Visuals::dark(),
+        ThemePreference::Light => egui::Visuals::light(),
+        ThemePreference::System => system_theme.unwrap_or(eframe::Theme::Dark).egui_visuals(),
+    };
+
+    if let Some(accent) = config.accent_color.as_deref().and_then(parse_hex_color) {
+        visuals.selection.bg_fill = accent;
+        visuals.hyperlink_color = accent;
+        visuals.widgets.hovered.bg_stroke.color = accent;
+        visuals.widgets.active.bg_fill = accent;
+    }
+    if let Some(background) = config.background_color.as_deref().and_then(parse_hex_color) {
+        visuals.panel_fill = background;
+        visuals.window_fill = background;
+    }
+    visuals
+}
+
+pub const MIN_UI_SCALE: f32 = 0.5;
+pub const MAX_UI_SCALE: f32 = 3.0;
+
+// Scales every text style relative to egui's defaults, where body text is 12.5 points
+pub fn apply_font_size(ctx: &egui::Context, font_size: f32) {
+    let factor = font_size / 12.5;
+    ctx.style_mut(|style| {
+        style.text_styles = egui::style::default_text_styles()
+            .into_iter()
+            .map(|(text_style, font)| (text_style, egui::FontId::new(font.size * factor, font.family)))
+            .collect();
+    });
+}
+
+// Checkbox to enable a custom color plus a picker, editing the config's hex string in place
+pub fn color_setting(ui: &mut egui::Ui, value: &mut Option<String>, default: Color32, label: egui::Id) {
+    ui.horizontal(|ui| {
+        let mut enabled = value.is_some();
+        if ui.checkbox(&mut enabled, tr("Custom")).changed() {
+            *value = enabled.then(|| to_hex_color(default));
+        }
+        if let Some(hex) = value {
+            let mut color = parse_hex_color(hex).unwrap_or(default);
+            if egui::color_picker::color_edit_button_srgba(ui, &mut color, egui::color_picker::Alpha::Opaque).labelled_by(label).changed() {
+                *hex = to_hex_color(color);
+            }
+        }
+    });
+}
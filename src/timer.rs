@@ -0,0 +1,92 @@
+use crate::runner::Run;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command as StdCommand;
+
+// Name of the service and the timer that starts it
+pub const UNIT: &str = "cookin-update-check";
+
+// systemd time spans such as "6h", "30min" or "1d"; the unit is required so that a bare number is
+// not taken as seconds by mistake
+pub fn check_interval(interval: &str) -> Result<(), String> {
+    let digits = interval.chars().take_while(char::is_ascii_digit).count();
+    let (number, unit) = interval.split_at(digits);
+    if number.parse::<u32>().map_or(true, |number| number == 0) || !["s", "m", "min", "h", "d", "w"].contains(&unit) {
+        return Err(format!("{} is not an interval; use a number followed by s, min, h, d or w, e.g. 6h", interval));
+    }
+    Ok(())
+}
+
+// The check exits with 10 when it finds updates, which is not a failure here
+fn service(exec: &Path) -> String {
+    format!(
+        "[Unit]\nDescription=Check for AUR package updates\n\n[Service]\nType=oneshot\nExecStart=\"{}\" update --check --notify --quiet\nSuccessExitStatus=10\n",
+        exec.display()
+    )
+}
+
+// The first check runs a few minutes after login rather than with everything else that starts then
+fn timer(interval: &str) -> String {
+    format!(
+        "[Unit]\nDescription=Check for AUR package updates every {}\n\n[Timer]\nOnStartupSec=5min\nOnUnitActiveSec={}\n\n[Install]\nWantedBy=timers.target\n",
+        interval, interval
+    )
+}
+
+// Writes the service and timer to `dir`, the service running `exec`. Returns the files written.
+fn write_units(dir: &Path, exec: &Path, interval: &str) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    check_interval(interval)?;
+    fs::create_dir_all(dir)?;
+    let files = [(dir.join(format!("{}.service", UNIT)), service(exec)), (dir.join(format!("{}.timer", UNIT)), timer(interval))];
+    for (path, content) in &files {
+        fs::write(path, content)?;
+    }
+    Ok(files.into_iter().map(|(path, _)| path).collect())
+}
+
+fn systemctl(args: &[&str]) -> Result<(), Box<dyn Error>> {
+    let output = StdCommand::new("systemctl").arg("--user").args(args).run()?;
+    if !output.status.success() {
+        return Err(format!("systemctl --user {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(())
+}
+
+// Writes the units to the user's systemd directory and starts the timer, which checks for updates
+// every `interval` and notifies when there are some; nothing is installed. Returns the files
+// written.
+pub fn enable(interval: &str) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let config_dir = dirs::config_dir().ok_or("Could not determine config directory")?;
+    let files = write_units(&config_dir.join("systemd").join("user"), &std::env::current_exe()?, interval)?;
+    systemctl(&["daemon-reload"])?;
+    systemctl(&["enable", "--now", &format!("{}.timer", UNIT)])?;
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Scripted};
+
+    #[test]
+    fn writes_a_timer_that_only_checks() {
+        let _isolated = testing::isolate_blocking();
+        let scripted = Scripted::default()
+            .answer("systemctl --user daemon-reload", "")
+            .answer("systemctl --user enable --now cookin-update-check.timer", "")
+            .install();
+        assert!(check_interval("30min").is_ok());
+        assert!(check_interval("6").is_err());
+        assert!(check_interval("0h").is_err());
+        assert!(enable("6 hours").is_err());
+
+        let files = enable("6h").unwrap();
+        let service = fs::read_to_string(&files[0]).unwrap();
+        let timer = fs::read_to_string(&files[1]).unwrap();
+        assert!(files[0].starts_with(dirs::config_dir().unwrap().join("systemd/user")));
+        assert!(service.contains(" update --check --notify --quiet\n"));
+        assert!(timer.contains("\nOnUnitActiveSec=6h\n"));
+        assert_eq!(scripted.calls().len(), 2);
+    }
+}
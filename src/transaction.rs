@@ -0,0 +1,193 @@
+use crate::backend;
+use crate::format_size;
+use crate::remote;
+use crate::runner::Run;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Action {
+    Install,
+    Upgrade,
+    Reinstall,
+}
+
+impl Action {
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::Install => "new",
+            Action::Upgrade => "upgrade",
+            Action::Reinstall => "reinstall",
+        }
+    }
+}
+
+// One package of a transaction; sizes are in bytes and None when pacman could not tell
+pub struct Change {
+    pub name: String,
+    pub old_version: Option<String>,
+    pub new_version: String,
+    pub action: Action,
+    pub download_size: Option<u64>,
+    // Installed size after the transaction minus the size before it
+    pub size_change: Option<i64>,
+}
+
+// What a transaction will do, as pacman would list it before asking, with the exact commands
+pub struct Summary {
+    pub changes: Vec<Change>,
+    pub commands: Vec<String>,
+    // The queued packages in the order they are built, when this is one of several
+    pub build_order: Vec<String>,
+}
+
+impl Summary {
+    pub fn download_size(&self) -> u64 {
+        self.changes.iter().filter_map(|change| change.download_size).sum()
+    }
+
+    pub fn size_change(&self) -> i64 {
+        self.changes.iter().filter_map(|change| change.size_change).sum()
+    }
+
+    // True when a size is missing from the totals
+    pub fn incomplete(&self) -> bool {
+        self.changes.iter().any(|change| change.download_size.is_none() || change.size_change.is_none())
+    }
+
+    // Plain-text form for the terminal, e.g. "foo 1.0-1 -> 1.1-1 (upgrade)"
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .changes
+            .iter()
+            .map(|change| {
+                let versions = match &change.old_version {
+                    Some(old) if change.action == Action::Upgrade => format!("{} -> {}", old, change.new_version),
+                    _ => change.new_version.clone(),
+                };
+                let size = change.size_change.map(|size| format!(", {}", format_size_change(size))).unwrap_or_default();
+                format!("{} {} ({}{})", change.name, versions, change.action.label(), size)
+            })
+            .collect();
+        lines.push(String::new());
+        lines.push(format!("Total download size: {}", format_size(self.download_size())));
+        lines.push(format!("Net installed size:  {}{}", format_size_change(self.size_change()), if self.incomplete() { " (some sizes unknown)" } else { "" }));
+        lines.push(String::new());
+        lines.push("Commands:".to_string());
+        lines.extend(self.commands.iter().map(|command| format!("    {}", command)));
+        if !self.build_order.is_empty() {
+            lines.push(format!("Build order: {}", self.build_order.join(" -> ")));
+        }
+        lines
+    }
+}
+
+// Built package files to install with pacman -U, as (name, version, file). Download sizes are 0,
+// since the files are already on the building host.
+pub fn for_files(packages: &[(String, String, String)], commands: Vec<String>) -> Summary {
+    let changes = packages
+        .iter()
+        .map(|(name, version, file)| {
+            let new_size = info(&["-Qip", file]).and_then(|fields| size_of(&fields));
+            change(name, version, Some(0), new_size)
+        })
+        .collect();
+    Summary { changes, commands, build_order: Vec::new() }
+}
+
+// Repository packages to install with pacman -S, e.g. "chaotic-aur/foo", with sizes from the sync
+// database
+pub fn for_targets(targets: &[String], commands: Vec<String>) -> Summary {
+    let changes = targets
+        .iter()
+        .map(|target| {
+            let name = target.rsplit('/').next().unwrap_or(target);
+            let fields = info(&["-Si", target]);
+            let field = |key: &str| fields.as_ref().and_then(|fields| value(fields, key));
+            let version = field("Version").unwrap_or_else(|| "?".to_string());
+            let download = field("Download Size").and_then(|size| backend::parse_size(&size));
+            change(name, &version, download, fields.as_ref().and_then(|fields| size_of(fields)))
+        })
+        .collect();
+    Summary { changes, commands, build_order: Vec::new() }
+}
+
+fn change(name: &str, new_version: &str, download_size: Option<u64>, new_size: Option<u64>) -> Change {
+    let installed = info(&["-Qi", name]);
+    let old_version = installed.as_ref().and_then(|fields| value(fields, "Version"));
+    let old_size = match &installed {
+        Some(fields) => size_of(fields),
+        None => Some(0),
+    };
+    let action = match &old_version {
+        None => Action::Install,
+        Some(old) if old == new_version => Action::Reinstall,
+        Some(_) => Action::Upgrade,
+    };
+    Change {
+        name: name.to_string(),
+        old_version,
+        new_version: new_version.to_string(),
+        action,
+        download_size,
+        size_change: new_size.zip(old_size).map(|(new, old)| new as i64 - old as i64),
+    }
+}
+
+// The fields of `pacman <args>` in the C locale; None when pacman fails, e.g. for a package that
+// is not installed
+fn info(args: &[&str]) -> Option<Vec<(String, String)>> {
+    let output = remote::command("env", ["LC_ALL=C", "pacman"].iter().chain(args)).run().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(parse(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse(output: &str) -> Vec<(String, String)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(" : ")?;
+            Some((key.trim().to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+fn value(fields: &[(String, String)], key: &str) -> Option<String> {
+    fields.iter().find(|(name, _)| name == key).map(|(_, value)| value.clone())
+}
+
+fn size_of(fields: &[(String, String)]) -> Option<u64> {
+    value(fields, "Installed Size").and_then(|size| backend::parse_size(&size))
+}
+
+pub fn format_size_change(bytes: i64) -> String {
+    let sign = if bytes < 0 { "-" } else { "+" };
+    format!("{}{}", sign, format_size(bytes.unsigned_abs()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Scripted};
+
+    #[test]
+    fn compares_built_files_with_the_installed_versions() {
+        let _isolated = testing::isolate_blocking();
+        Scripted::default()
+            .answer("env LC_ALL=C pacman -Qip /tmp/foo-1.1-1-x86_64.pkg.tar.zst", "Name            : foo\nVersion         : 1.1-1\nInstalled Size  : 3.00 MiB\n")
+            .answer("env LC_ALL=C pacman -Qi foo", "Name            : foo\nVersion         : 1.0-1\nInstalled Size  : 2.00 MiB\n")
+            .answer("env LC_ALL=C pacman -Qip /tmp/foo-docs-1.1-1-any.pkg.tar.zst", "Name            : foo-docs\nInstalled Size  : 512.00 KiB\n")
+            .fail("env LC_ALL=C pacman -Qi foo-docs", "error: package 'foo-docs' was not found")
+            .install();
+        let packages = [
+            ("foo".to_string(), "1.1-1".to_string(), "/tmp/foo-1.1-1-x86_64.pkg.tar.zst".to_string()),
+            ("foo-docs".to_string(), "1.1-1".to_string(), "/tmp/foo-docs-1.1-1-any.pkg.tar.zst".to_string()),
+        ];
+        let summary = for_files(&packages, vec!["sudo pacman -U --noconfirm ...".to_string()]);
+
+        assert!(summary.changes[0].action == Action::Upgrade && summary.changes[1].action == Action::Install);
+        assert_eq!(summary.size_change(), 1024 * 1024 + 512 * 1024);
+        assert!(!summary.incomplete());
+        assert_eq!(summary.lines()[0], "foo 1.0-1 -> 1.1-1 (upgrade, +1.0 MiB)");
+    }
+}
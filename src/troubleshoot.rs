@@ -0,0 +1,111 @@
+use regex::Regex;
+
+// A known cause of a failure, recognised in the error or the output that led up to it
+pub struct Hint {
+    pub problem: String,
+    pub fix: String,
+}
+
+struct Rule {
+    pattern: Regex,
+    // Gets the captures of `pattern`; returns (problem, fix)
+    explain: fn(&regex::Captures) -> (String, String),
+}
+
+fn rules() -> Vec<Rule> {
+    let rule = |pattern: &str, explain| Rule {
+        pattern: Regex::new(pattern).unwrap(),
+        explain,
+    };
+    vec![
+        rule(r"(?i)['‘’]?\b(makepkg|fakeroot)['‘’]?: (command not found|not found|no such file or directory)|cannot find the (fakeroot|strip|debugedit) binary", |_| {
+            (
+                "The build tools from base-devel are missing".to_string(),
+                "Install them with `sudo pacman -S --needed base-devel`, then retry".to_string(),
+            )
+        }),
+        rule(r"(?i)unknown public key ([0-9A-F]{8,40})|PGP signatures could not be verified", |captures| {
+            let key = captures.get(1).map_or("<key id>", |key| key.as_str());
+            (
+                "A source is signed with a PGP key that is not in your keyring".to_string(),
+                format!("Check that {} is listed in validpgpkeys in the PKGBUILD and by the upstream project, import it with `gpg --recv-keys {}` and retry", key, key),
+            )
+        }),
+        rule(r"(?i)did not pass the validity check", |_| {
+            (
+                "A downloaded source does not match its checksum in the PKGBUILD".to_string(),
+                "The download may be corrupt, or upstream replaced the file. Delete it from the build directory and retry; if it fails again, tell the maintainer on the package's AUR page".to_string(),
+            )
+        }),
+        rule(r"(?i)(\S+): (/\S+) exists in filesystem|conflicting files", |captures| {
+            let path = captures.get(2).map_or("<path>", |path| path.as_str());
+            (
+                "A file of the package is already on disk".to_string(),
+                format!("See which package owns it with `pacman -Qo {}`. If none does, move the file out of the way and retry", path),
+            )
+        }),
+        rule(r"(?i)target not found: (\S+)", |captures| {
+            (
+                format!("pacman cannot find {}", &captures[1]),
+                "It may be an AUR package, which has to be installed first, or come from a repository not enabled in pacman.conf. If it is new or was renamed, sync the databases with `sudo pacman -Syu`".to_string(),
+            )
+        }),
+        rule(
+            r"(?i)could not resolve host|failed to lookup address|temporary failure in name resolution|dns error|network is unreachable|connection refused|timed out connecting|error sending request",
+            |_| {
+                (
+                    "The network or the server cannot be reached".to_string(),
+                    "Check the connection and any proxy settings; https://status.archlinux.org shows whether the AUR is down. On slow links, raise connect_timeout_secs".to_string(),
+                )
+            },
+        ),
+    ]
+}
+
+// Hints for every known problem in `error` and `output`, at most one per kind. Of the lines a rule
+// matches, the one that tells the most, e.g. the key id or the conflicting path, is explained.
+pub fn diagnose<'a>(error: &'a str, output: impl IntoIterator<Item = &'a str>) -> Vec<Hint> {
+    let lines: Vec<&str> = error.lines().chain(output).collect();
+    rules()
+        .iter()
+        .filter_map(|rule| {
+            let captures = lines.iter().filter_map(|line| rule.pattern.captures(line)).max_by_key(|captures| captures.iter().flatten().count())?;
+            let (problem, fix) = (rule.explain)(&captures);
+            Some(Hint { problem, fix })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(error: &str, output: &[&str]) -> Vec<String> {
+        diagnose(error, output.iter().copied()).into_iter().map(|hint| hint.problem).collect()
+    }
+
+    #[test]
+    fn recognises_build_failures_in_the_output() {
+        let output = ["==> Verifying source file signatures with gpg...", "    foo-1.0.tar.gz ... FAILED (unknown public key 4AA4767BBC9C4B1D)", "==> ERROR: One or more PGP signatures could not be verified!"];
+        let hints = diagnose("makepkg exited with exit status: 1", output);
+        assert_eq!(hints.len(), 1);
+        assert!(hints[0].fix.contains("gpg --recv-keys 4AA4767BBC9C4B1D"), "{}", hints[0].fix);
+
+        assert_eq!(problems("makepkg exited with exit status: 127", &["env: ‘makepkg’: No such file or directory"]), ["The build tools from base-devel are missing"]);
+        assert_eq!(
+            problems("makepkg exited with exit status: 1", &["==> ERROR: One or more files did not pass the validity check!"]),
+            ["A downloaded source does not match its checksum in the PKGBUILD"]
+        );
+    }
+
+    #[test]
+    fn names_what_pacman_complained_about() {
+        let hints = diagnose("error: failed to commit transaction (conflicting files)\nfoo: /usr/bin/foo exists in filesystem", []);
+        assert_eq!(hints.len(), 1);
+        assert!(hints[0].fix.contains("pacman -Qo /usr/bin/foo"), "{}", hints[0].fix);
+
+        assert_eq!(problems("error: target not found: libfoo", &[]), ["pacman cannot find libfoo"]);
+        assert_eq!(problems("error sending request for url (https://aur.archlinux.org/rpc/)", &[]), ["The network or the server cannot be reached"]);
+        assert!(problems("Installation cancelled", &[]).is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+use crate::backend::{self, PackageBackend};
+use crate::config::Config;
+use std::error::Error;
+
+// A package with a newer version available, ticked when it is to be updated
+#[derive(Clone, Debug, PartialEq)]
+pub struct Outdated {
+    pub source: &'static str,
+    pub name: String,
+    pub installed: String,
+    pub available: String,
+    pub checked: bool,
+}
+
+// Where updates are looked for: the sync repositories first when the active backend is the AUR
+// one, then every source
+fn update_sources() -> Vec<&'static dyn PackageBackend> {
+    let repo = backend::active().builds_from_source().then(backend::repo);
+    repo.into_iter().chain(backend::sources()).collect()
+}
+
+// Outdated packages of every source, all ticked. Held packages are left out.
+pub fn check(config: &Config) -> Result<Vec<Outdated>, Box<dyn Error>> {
+    let mut found = Vec::new();
+    for source in update_sources() {
+        for (name, installed, available) in source.check_updates()? {
+            if !config.is_held(&name) {
+                found.push(Outdated { source: source.name(), name, installed, available, checked: true });
+            }
+        }
+    }
+    Ok(found)
+}
+
+// A newer check's results, keeping the ticks the user set on packages that were listed before
+pub fn refresh(previous: &[Outdated], found: Vec<Outdated>) -> Vec<Outdated> {
+    found
+        .into_iter()
+        .map(|mut update| {
+            if let Some(listed) = previous.iter().find(|listed| listed.source == update.source && listed.name == update.name) {
+                update.checked = listed.checked;
+            }
+            update
+        })
+        .collect()
+}
+
+pub fn check_all(updates: &mut [Outdated], checked: bool) {
+    for update in updates {
+        update.checked = checked;
+    }
+}
+
+// Ticks the packages of `source` alone, e.g. those built from the AUR
+pub fn check_only(updates: &mut [Outdated], source: &str) {
+    for update in updates {
+        update.checked = update.source == source;
+    }
+}
+
+// Names of the packages of `source` that are ticked (true) or not (false)
+pub fn names(updates: &[Outdated], source: &str, checked: bool) -> Vec<String> {
+    updates.iter().filter(|update| update.source == source && update.checked == checked).map(|update| update.name.clone()).collect()
+}
+
+// Sources with at least one ticked package, in the order they are listed
+pub fn checked_sources(updates: &[Outdated]) -> Vec<&'static str> {
+    let mut sources: Vec<&'static str> = Vec::new();
+    for update in updates.iter().filter(|update| update.checked) {
+        if !sources.contains(&update.source) {
+            sources.push(update.source);
+        }
+    }
+    sources
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outdated(source: &'static str, name: &str) -> Outdated {
+        Outdated { source, name: name.to_string(), installed: "1.0-1".to_string(), available: "1.1-1".to_string(), checked: true }
+    }
+
+    #[test]
+    fn ticks_all_or_one_source_and_lists_what_to_skip() {
+        let mut updates = vec![outdated("repo", "linux"), outdated("repo", "mesa"), outdated("pacman", "yay"), outdated("flatpak", "org.gimp.GIMP")];
+        check_only(&mut updates, "pacman");
+        assert_eq!(checked_sources(&updates), ["pacman"]);
+        assert_eq!(names(&updates, "repo", false), ["linux", "mesa"]);
+
+        check_all(&mut updates, true);
+        updates[0].checked = false;
+        assert_eq!(checked_sources(&updates), ["repo", "pacman", "flatpak"]);
+        assert_eq!(names(&updates, "repo", true), ["mesa"]);
+        assert_eq!(names(&updates, "repo", false), ["linux"]);
+
+        let found = vec![outdated("repo", "linux"), outdated("repo", "glibc")];
+        let refreshed = refresh(&updates, found);
+        assert_eq!(refreshed.iter().map(|update| (update.name.as_str(), update.checked)).collect::<Vec<_>>(), [("linux", false), ("glibc", true)]);
+    }
+}